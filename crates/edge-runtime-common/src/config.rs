@@ -113,6 +113,24 @@ pub struct ExecutionConfig {
     /// When enabled, CPU usage is tracked and limited by the `max_fuel` setting.
     #[serde(default = "defaults::fuel_metering")]
     pub fuel_metering: bool,
+
+    /// Maximum fuel for deferred work registered via `wait_until`.
+    ///
+    /// Deferred calls run after the response has been sent and are budgeted
+    /// separately from the request itself.
+    #[serde(default = "defaults::deferred_max_fuel")]
+    pub deferred_max_fuel: u64,
+
+    /// Timeout in milliseconds for deferred work registered via `wait_until`.
+    #[serde(default = "defaults::deferred_timeout_ms")]
+    pub deferred_timeout_ms: u64,
+
+    /// Maximum nesting depth of module-to-module service calls.
+    ///
+    /// A request entering the runtime runs at depth 0; each `invoke` from a
+    /// guest adds one level.
+    #[serde(default = "defaults::max_service_depth")]
+    pub max_service_depth: u32,
 }
 
 impl Default for ExecutionConfig {
@@ -122,6 +140,9 @@ impl Default for ExecutionConfig {
             timeout_ms: defaults::timeout_ms(),
             max_memory_mb: defaults::max_memory_mb(),
             fuel_metering: defaults::fuel_metering(),
+            deferred_max_fuel: defaults::deferred_max_fuel(),
+            deferred_timeout_ms: defaults::deferred_timeout_ms(),
+            max_service_depth: defaults::max_service_depth(),
         }
     }
 }
@@ -170,6 +191,18 @@ mod defaults {
     pub const fn fuel_metering() -> bool {
         true
     }
+
+    pub const fn deferred_max_fuel() -> u64 {
+        10_000_000
+    }
+
+    pub const fn deferred_timeout_ms() -> u64 {
+        1000
+    }
+
+    pub const fn max_service_depth() -> u32 {
+        4
+    }
 }
 
 #[cfg(test)]
@@ -190,6 +223,9 @@ mod tests {
         assert_eq!(config.execution.timeout_ms, 100);
         assert_eq!(config.execution.max_memory_mb, 128);
         assert!(config.execution.fuel_metering);
+        assert_eq!(config.execution.deferred_max_fuel, 10_000_000);
+        assert_eq!(config.execution.deferred_timeout_ms, 1000);
+        assert_eq!(config.execution.max_service_depth, 4);
     }
 
     #[test]
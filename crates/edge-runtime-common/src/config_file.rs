@@ -3,9 +3,28 @@
 //! This module defines structures for TOML configuration files:
 //! - [`ConfigFile`]: Top-level configuration file structure
 //! - [`ServerConfigFile`]: HTTP server settings
+//! - [`JobsConfig`]: Asynchronous invocation queue settings
+//! - [`KvConfig`]: Key-value store backend and quotas
+//! - [`CacheConfig`]: Response cache size limits
+//! - [`SqlConfig`]: Embedded SQL database location and limits
+//! - [`BlobConfig`]: Blob storage location and limits
+//! - [`SecretsConfig`]: Encrypted module secrets file
+//! - [`CryptoConfig`]: Host-held keys and fuel rates for crypto functions
+//! - [`SocketsConfig`]: Limits of outbound TCP connections
+//! - [`WebSocketConfig`]: Limits of outbound WebSocket connections
+//! - [`MetricsConfig`]: Limits of guest-emitted metrics
+//! - [`LoggingConfig`]: Limits, minimum level, sampling and sinks of guest logs
+//! - [`FileSinkConfig`], [`SyslogSinkConfig`], [`HttpSinkConfig`]: Guest log sinks
+//! - [`TracingConfig`]: OTLP export of the runtime's traces
+//! - [`AccessLogConfig`]: Access log of function invocations
 //! - [`AdminConfig`]: Admin API settings
 //! - [`ModuleEntry`]: Pre-loaded module definition
+//! - [`PipelineEntry`]: Middleware pipeline composed from modules
+//! - [`OverlapPolicy`]: Handling of overlapping scheduled runs
+//! - [`GuestLogLevel`]: Minimum level of guest logs
+//! - [`AccessLogFormat`]: Line format of the access log
 
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
@@ -32,6 +51,70 @@ use crate::RuntimeConfig;
 /// bind_addr = "0.0.0.0:8080"
 /// request_timeout_secs = 30
 ///
+/// [server.jobs]
+/// workers = 4
+/// max_queue_depth = 1000
+///
+/// [server.kv]
+/// backend = "file"
+/// path = "./data/kv.redb"
+///
+/// [server.cache]
+/// max_bytes_per_module = 16_777_216
+///
+/// [server.sql]
+/// directory = "./data/sql"
+/// max_database_bytes = 67_108_864
+///
+/// [server.blob]
+/// directory = "./data/blobs"
+/// default_quota_bytes = 1_073_741_824
+///
+/// [server.secrets]
+/// path = "./secrets.enc"
+/// key_env = "EDGE_SECRETS_KEY"
+///
+/// [server.crypto]
+/// fuel_per_byte = 2
+///
+/// [[server.crypto.keys]]
+/// name = "webhook-signing"
+/// algorithm = "ed25519"
+/// path = "./keys/webhook.pk8"
+///
+/// [server.sockets]
+/// max_connections = 4
+/// max_bytes = 16_777_216
+///
+/// [server.websocket]
+/// max_connections = 2
+/// max_messages = 1000
+///
+/// [server.metrics]
+/// max_series_per_module = 1000
+///
+/// [server.logging]
+/// max_entries_per_request = 1000
+/// max_bytes_per_request = 1_048_576
+/// min_level = "info"
+///
+/// [server.logging.file]
+/// directory = "./logs"
+/// max_file_bytes = 10_485_760
+/// max_files = 5
+///
+/// [server.logging.http]
+/// url = "http://127.0.0.1:9880/logs"
+///
+/// [server.tracing]
+/// otlp_endpoint = "http://127.0.0.1:4318"
+/// service_name = "edge-runtime"
+///
+/// [server.access_log]
+/// enabled = true
+/// format = "combined"
+/// path = "./logs/access.log"
+///
 /// [admin]
 /// enabled = true
 /// token = "your-secret-token"
@@ -40,6 +123,33 @@ use crate::RuntimeConfig;
 /// [[modules]]
 /// id = "hello"
 /// path = "./modules/hello.wasm"
+///
+/// [[modules]]
+/// id = "report"
+/// path = "./modules/report.wasm"
+/// schedule = "0 * * * *"
+/// overlap_policy = "skip"
+///
+/// [[modules]]
+/// id = "api"
+/// path = "./modules/api.wasm"
+/// services = ["auth", "pricing"]
+/// http_hosts = ["api.example.com", "*.stream.example.com"]
+/// kv_namespace = "api"
+/// sql_namespace = "api"
+/// blob_buckets = ["uploads"]
+/// crypto_keys = ["webhook-signing"]
+/// sockets = ["redis.internal.example.com:6379", "*.smtp.example.com:465"]
+/// log_level = "debug"
+/// log_sample_rate = 0.1
+///
+/// [modules.config]
+/// region = "eu-west"
+/// upstream_url = "https://backend.internal"
+///
+/// [[pipelines]]
+/// id = "shop"
+/// stages = ["auth", "rate-limit", "app", "compress"]
 /// ```
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ConfigFile {
@@ -58,6 +168,10 @@ pub struct ConfigFile {
     /// Modules to load at startup.
     #[serde(default)]
     pub modules: Vec<ModuleEntry>,
+
+    /// Middleware pipelines to register at startup.
+    #[serde(default)]
+    pub pipelines: Vec<PipelineEntry>,
 }
 
 impl ConfigFile {
@@ -105,6 +219,58 @@ pub struct ServerConfigFile {
     /// Enable graceful shutdown.
     #[serde(default = "defaults::graceful_shutdown")]
     pub graceful_shutdown: bool,
+
+    /// Asynchronous invocation queue settings.
+    #[serde(default)]
+    pub jobs: JobsConfig,
+
+    /// Key-value store settings.
+    #[serde(default)]
+    pub kv: KvConfig,
+
+    /// Response cache settings.
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    /// Embedded SQL database settings.
+    #[serde(default)]
+    pub sql: SqlConfig,
+
+    /// Blob storage settings.
+    #[serde(default)]
+    pub blob: BlobConfig,
+
+    /// Module secrets settings.
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+
+    /// Crypto host function settings.
+    #[serde(default)]
+    pub crypto: CryptoConfig,
+
+    /// Outbound TCP socket settings.
+    #[serde(default)]
+    pub sockets: SocketsConfig,
+
+    /// Outbound WebSocket settings.
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+
+    /// Guest metrics settings.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// Guest log settings.
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// Trace export settings.
+    #[serde(default)]
+    pub tracing: TracingConfig,
+
+    /// Access log settings.
+    #[serde(default)]
+    pub access_log: AccessLogConfig,
 }
 
 impl Default for ServerConfigFile {
@@ -113,167 +279,1457 @@ impl Default for ServerConfigFile {
             bind_addr: defaults::bind_addr(),
             request_timeout_secs: defaults::request_timeout_secs(),
             graceful_shutdown: defaults::graceful_shutdown(),
+            jobs: JobsConfig::default(),
+            kv: KvConfig::default(),
+            cache: CacheConfig::default(),
+            sql: SqlConfig::default(),
+            blob: BlobConfig::default(),
+            secrets: SecretsConfig::default(),
+            crypto: CryptoConfig::default(),
+            sockets: SocketsConfig::default(),
+            websocket: WebSocketConfig::default(),
+            metrics: MetricsConfig::default(),
+            logging: LoggingConfig::default(),
+            tracing: TracingConfig::default(),
+            access_log: AccessLogConfig::default(),
         }
     }
 }
 
-/// Admin API configuration.
+/// Asynchronous invocation queue configuration.
+///
+/// Jobs submitted via `POST /functions/:id/async` are executed by a bounded
+/// worker pool outside the HTTP request timeout.
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct AdminConfig {
-    /// Enable Admin API.
-    #[serde(default)]
-    pub enabled: bool,
+pub struct JobsConfig {
+    /// Number of jobs executed concurrently.
+    #[serde(default = "defaults::job_workers")]
+    pub workers: usize,
 
-    /// Authentication token (required when enabled).
+    /// Maximum number of jobs waiting for a worker.
     ///
-    /// Clients must include this token in the `X-Admin-Token` header.
-    pub token: Option<String>,
+    /// Submissions beyond this depth are rejected with `429`.
+    #[serde(default = "defaults::job_max_queue_depth")]
+    pub max_queue_depth: usize,
 
-    /// URL prefix for Admin API endpoints.
-    #[serde(default = "defaults::admin_prefix")]
-    pub prefix: String,
+    /// How long finished jobs are kept for status queries, in seconds.
+    #[serde(default = "defaults::job_retention_secs")]
+    pub retention_secs: u64,
+
+    /// Execution timeout for jobs in milliseconds.
+    ///
+    /// Overrides `runtime.execution.timeout_ms` for async invocations.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+
+    /// Number of times a trapped job is retried.
+    #[serde(default)]
+    pub max_retries: u32,
+
+    /// Initial retry backoff in milliseconds, doubled after each attempt.
+    #[serde(default = "defaults::job_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
 }
 
-impl Default for AdminConfig {
+impl Default for JobsConfig {
     fn default() -> Self {
         Self {
-            enabled: false,
-            token: None,
-            prefix: defaults::admin_prefix(),
+            workers: defaults::job_workers(),
+            max_queue_depth: defaults::job_max_queue_depth(),
+            retention_secs: defaults::job_retention_secs(),
+            timeout_ms: None,
+            max_retries: 0,
+            retry_backoff_ms: defaults::job_retry_backoff_ms(),
         }
     }
 }
 
-impl AdminConfig {
-    /// Check if Admin API is properly configured.
-    ///
-    /// Returns `true` if enabled and token is set.
-    pub fn is_configured(&self) -> bool {
-        self.enabled && self.token.is_some()
+/// Key-value store configuration.
+///
+/// Modules get access to the store through their `kv_namespace`; the
+/// quotas apply to every request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KvConfig {
+    /// Storage backend.
+    #[serde(default)]
+    pub backend: KvBackendKind,
+
+    /// Database file for the `file` backend.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Maximum number of KV operations per request.
+    #[serde(default = "defaults::kv_max_operations")]
+    pub max_operations: u32,
+
+    /// Maximum size of a single value in bytes.
+    #[serde(default = "defaults::kv_max_value_bytes")]
+    pub max_value_bytes: usize,
+
+    /// Maximum bytes of values written per request.
+    #[serde(default = "defaults::kv_max_bytes_per_request")]
+    pub max_bytes_per_request: u64,
+}
+
+impl Default for KvConfig {
+    fn default() -> Self {
+        Self {
+            backend: KvBackendKind::default(),
+            path: None,
+            max_operations: defaults::kv_max_operations(),
+            max_value_bytes: defaults::kv_max_value_bytes(),
+            max_bytes_per_request: defaults::kv_max_bytes_per_request(),
+        }
     }
 }
 
-/// A module entry to load at startup.
+/// Key-value store backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KvBackendKind {
+    /// In-process store, lost on restart.
+    #[default]
+    Memory,
+    /// Embedded database file at `path`.
+    File,
+}
+
+/// Response cache configuration.
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ModuleEntry {
-    /// Unique identifier for the module.
+pub struct CacheConfig {
+    /// Size budget of each module's cache partition in bytes.
     ///
-    /// This ID is used in the `/functions/:id` endpoint.
-    pub id: String,
+    /// Least recently used responses are evicted beyond this size.
+    #[serde(default = "defaults::cache_max_bytes_per_module")]
+    pub max_bytes_per_module: usize,
+}
 
-    /// Path to the WebAssembly module file.
-    pub path: String,
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_module: defaults::cache_max_bytes_per_module(),
+        }
+    }
 }
 
-/// Configuration file errors.
-#[derive(Debug, thiserror::Error)]
-pub enum ConfigFileError {
-    /// Failed to read configuration file.
-    #[error("Failed to read config file '{path}': {source}")]
-    Io {
-        path: String,
-        #[source]
-        source: std::io::Error,
-    },
+/// Embedded SQL database configuration.
+///
+/// Each SQL namespace is a separate `SQLite` database. Modules get access to
+/// a database through their `sql_namespace`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SqlConfig {
+    /// Directory holding one `<namespace>.sqlite` file per namespace.
+    ///
+    /// Databases are kept in memory and lost on restart if unset.
+    #[serde(default)]
+    pub directory: Option<String>,
 
-    /// Failed to parse configuration file.
-    #[error("Failed to parse config file: {message}")]
-    Parse { message: String },
+    /// Maximum size of each database in bytes.
+    #[serde(default = "defaults::sql_max_database_bytes")]
+    pub max_database_bytes: u64,
+
+    /// Maximum number of rows returned by a single query.
+    #[serde(default = "defaults::sql_max_rows")]
+    pub max_rows: usize,
+
+    /// Maximum number of statements per request.
+    #[serde(default = "defaults::sql_max_statements")]
+    pub max_statements: u32,
 }
 
-/// Default value functions for serde.
-mod defaults {
-    pub fn bind_addr() -> String {
-        "0.0.0.0:8080".to_string()
+impl Default for SqlConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            max_database_bytes: defaults::sql_max_database_bytes(),
+            max_rows: defaults::sql_max_rows(),
+            max_statements: defaults::sql_max_statements(),
+        }
     }
+}
 
-    pub const fn request_timeout_secs() -> u64 {
-        30
-    }
+/// Blob storage configuration.
+///
+/// Objects are stored once per distinct content in a content-addressed
+/// directory and referenced by bucket and key. Modules get access to
+/// buckets through their `blob_buckets`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlobConfig {
+    /// Directory holding the object files and their index.
+    ///
+    /// Blob storage is disabled if unset.
+    #[serde(default)]
+    pub directory: Option<String>,
 
-    pub const fn graceful_shutdown() -> bool {
-        true
-    }
+    /// Maximum size of a single object in bytes.
+    #[serde(default = "defaults::blob_max_object_bytes")]
+    pub max_object_bytes: u64,
 
-    pub fn admin_prefix() -> String {
-        "/admin".to_string()
+    /// Maximum number of blob operations per request.
+    #[serde(default = "defaults::blob_max_operations")]
+    pub max_operations: u32,
+
+    /// Size limit of each bucket a module writes to, in bytes.
+    ///
+    /// Modules may override this with `blob_quota_bytes`.
+    #[serde(default = "defaults::blob_default_quota_bytes")]
+    pub default_quota_bytes: u64,
+}
+
+impl Default for BlobConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            max_object_bytes: defaults::blob_max_object_bytes(),
+            max_operations: defaults::blob_max_operations(),
+            default_quota_bytes: defaults::blob_default_quota_bytes(),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Module secrets configuration.
+///
+/// Secrets are read from a file encrypted with AES-256-GCM. The key is
+/// never stored in the configuration file; it is read from an environment
+/// variable as 64 hex digits.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SecretsConfig {
+    /// Path to the encrypted secrets file.
+    ///
+    /// Secrets are disabled if unset.
+    #[serde(default)]
+    pub path: Option<String>,
 
-    #[test]
-    fn test_default_config_file() {
-        let config = ConfigFile::default();
+    /// Environment variable holding the decryption key.
+    #[serde(default = "defaults::secrets_key_env")]
+    pub key_env: String,
+}
 
-        assert_eq!(config.server.bind_addr, "0.0.0.0:8080");
-        assert_eq!(config.server.request_timeout_secs, 30);
-        assert!(config.server.graceful_shutdown);
-        assert!(!config.admin.enabled);
-        assert!(config.admin.token.is_none());
-        assert_eq!(config.admin.prefix, "/admin");
-        assert!(config.modules.is_empty());
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            key_env: defaults::secrets_key_env(),
+        }
     }
+}
 
-    #[test]
-    fn test_parse_minimal_config() {
-        let toml = r#"
-            [server]
-            bind_addr = "127.0.0.1:3000"
-        "#;
+/// Crypto host function configuration.
+///
+/// Host-side crypto work is charged to the calling module's fuel: each call
+/// costs `fuel_per_operation` plus `fuel_per_byte` for every input byte, and
+/// signing or verifying adds `fuel_per_signature`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CryptoConfig {
+    /// Fuel charged for every crypto call.
+    #[serde(default = "defaults::crypto_fuel_per_operation")]
+    pub fuel_per_operation: u64,
 
-        let config = ConfigFile::from_toml(toml).unwrap();
+    /// Fuel charged per input byte.
+    #[serde(default = "defaults::crypto_fuel_per_byte")]
+    pub fuel_per_byte: u64,
 
-        assert_eq!(config.server.bind_addr, "127.0.0.1:3000");
-        // Defaults applied
-        assert_eq!(config.server.request_timeout_secs, 30);
+    /// Additional fuel charged for signing and verifying.
+    #[serde(default = "defaults::crypto_fuel_per_signature")]
+    pub fuel_per_signature: u64,
+
+    /// Keys held by the host, referenced by name from guest code.
+    ///
+    /// Modules get access to keys through their `crypto_keys`.
+    #[serde(default)]
+    pub keys: Vec<CryptoKeyEntry>,
+}
+
+impl Default for CryptoConfig {
+    fn default() -> Self {
+        Self {
+            fuel_per_operation: defaults::crypto_fuel_per_operation(),
+            fuel_per_byte: defaults::crypto_fuel_per_byte(),
+            fuel_per_signature: defaults::crypto_fuel_per_signature(),
+            keys: Vec::new(),
+        }
     }
+}
 
-    #[test]
-    fn test_parse_full_config() {
-        let toml = r#"
-            [runtime.engine]
-            pooling_allocator = true
-            max_instances = 500
+/// A key held by the host for crypto functions.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CryptoKeyEntry {
+    /// Name guest code refers to the key by.
+    pub name: String,
 
-            [runtime.execution]
-            max_fuel = 5_000_000
-            timeout_ms = 50
+    /// Key algorithm.
+    pub algorithm: CryptoKeyAlgorithm,
 
-            [server]
-            bind_addr = "0.0.0.0:9000"
-            request_timeout_secs = 60
-            graceful_shutdown = false
+    /// Path to the key file, in DER or PEM form.
+    ///
+    /// Signing keys are PKCS#8 private keys, or public keys (raw or
+    /// `SubjectPublicKeyInfo`) if `public` is set. AES-GCM keys are the raw
+    /// 16 or 32 key bytes.
+    pub path: String,
 
-            [admin]
-            enabled = true
-            token = "secret-token"
-            prefix = "/api/admin"
+    /// The file holds only a public key; the key can verify but not sign.
+    #[serde(default)]
+    pub public: bool,
+}
 
-            [[modules]]
-            id = "hello"
-            path = "./hello.wasm"
+/// Algorithm of a host-held crypto key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CryptoKeyAlgorithm {
+    /// Ed25519 signatures.
+    Ed25519,
+    /// ECDSA signatures on P-256 with SHA-256.
+    EcdsaP256,
+    /// AES-GCM authenticated encryption (AES-128 or AES-256).
+    AesGcm,
+}
 
-            [[modules]]
-            id = "echo"
-            path = "./echo.wasm"
-        "#;
+/// Outbound TCP socket configuration.
+///
+/// Modules get access to sockets through their `sockets` allowlist; the
+/// limits apply to every request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SocketsConfig {
+    /// Maximum number of connections opened per request.
+    #[serde(default = "defaults::sockets_max_connections")]
+    pub max_connections: u32,
 
-        let config = ConfigFile::from_toml(toml).unwrap();
+    /// Maximum number of bytes sent and received per request.
+    #[serde(default = "defaults::sockets_max_bytes")]
+    pub max_bytes: u64,
 
-        assert_eq!(config.runtime.engine.max_instances, 500);
-        assert_eq!(config.runtime.execution.max_fuel, 5_000_000);
-        assert_eq!(config.server.bind_addr, "0.0.0.0:9000");
-        assert_eq!(config.server.request_timeout_secs, 60);
-        assert!(!config.server.graceful_shutdown);
-        assert!(config.admin.enabled);
-        assert_eq!(config.admin.token, Some("secret-token".to_string()));
-        assert_eq!(config.admin.prefix, "/api/admin");
-        assert_eq!(config.modules.len(), 2);
-        assert_eq!(config.modules[0].id, "hello");
-        assert_eq!(config.modules[1].path, "./echo.wasm");
+    /// Timeout for establishing a connection, in milliseconds.
+    #[serde(default = "defaults::sockets_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+}
+
+impl Default for SocketsConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: defaults::sockets_max_connections(),
+            max_bytes: defaults::sockets_max_bytes(),
+            connect_timeout_ms: defaults::sockets_connect_timeout_ms(),
+        }
+    }
+}
+
+/// Outbound WebSocket configuration.
+///
+/// Modules may open WebSocket connections to their `http_hosts`; the limits
+/// apply to every request. Connections are established with the
+/// `server.sockets` connect timeout.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebSocketConfig {
+    /// Maximum number of connections opened per request.
+    #[serde(default = "defaults::websocket_max_connections")]
+    pub max_connections: u32,
+
+    /// Maximum number of messages sent and received per request.
+    #[serde(default = "defaults::websocket_max_messages")]
+    pub max_messages: u32,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: defaults::websocket_max_connections(),
+            max_messages: defaults::websocket_max_messages(),
+        }
+    }
+}
+
+/// Guest metrics configuration.
+///
+/// Metrics recorded by modules are aggregated per module and exported on
+/// the server's `/metrics` endpoint.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MetricsConfig {
+    /// Maximum number of distinct series (metric name and label set) per
+    /// module. Recording a new series beyond the limit fails.
+    #[serde(default = "defaults::metrics_max_series_per_module")]
+    pub max_series_per_module: usize,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            max_series_per_module: defaults::metrics_max_series_per_module(),
+        }
+    }
+}
+
+/// Guest log configuration.
+///
+/// The limits apply to every request; entries beyond them are dropped and
+/// replaced by a single marker entry. The minimum level and sample rate
+/// are defaults that modules can override.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// Maximum number of log entries kept per request.
+    #[serde(default = "defaults::logging_max_entries_per_request")]
+    pub max_entries_per_request: usize,
+
+    /// Maximum total size of log messages and fields kept per request, in
+    /// bytes.
+    #[serde(default = "defaults::logging_max_bytes_per_request")]
+    pub max_bytes_per_request: usize,
+
+    /// Entries below this level are discarded.
+    #[serde(default)]
+    pub min_level: GuestLogLevel,
+
+    /// Fraction of requests, from 0 to 1, whose debug and info entries are
+    /// kept. Warnings and errors are always kept.
+    #[serde(default = "defaults::logging_sample_rate")]
+    pub sample_rate: f64,
+
+    /// Number of requests' logs buffered per sink. Logs of further
+    /// requests are dropped until the sink catches up.
+    #[serde(default = "defaults::logging_sink_buffer")]
+    pub sink_buffer: usize,
+
+    /// Rotating JSON-lines files, one per module.
+    #[serde(default)]
+    pub file: Option<FileSinkConfig>,
+
+    /// Syslog over a local datagram socket.
+    #[serde(default)]
+    pub syslog: Option<SyslogSinkConfig>,
+
+    /// Batches of JSON lines posted to an HTTP collector.
+    #[serde(default)]
+    pub http: Option<HttpSinkConfig>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            max_entries_per_request: defaults::logging_max_entries_per_request(),
+            max_bytes_per_request: defaults::logging_max_bytes_per_request(),
+            min_level: GuestLogLevel::default(),
+            sample_rate: defaults::logging_sample_rate(),
+            sink_buffer: defaults::logging_sink_buffer(),
+            file: None,
+            syslog: None,
+            http: None,
+        }
+    }
+}
+
+/// Rotating JSON-lines file sink of guest logs.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FileSinkConfig {
+    /// Directory of the log files, created if missing.
+    pub directory: String,
+
+    /// Size at which a module's file is rotated, in bytes.
+    #[serde(default = "defaults::file_sink_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Number of rotated files kept per module.
+    #[serde(default = "defaults::file_sink_max_files")]
+    pub max_files: u32,
+}
+
+/// Syslog sink of guest logs.
+///
+/// Entries are sent as RFC 5424 messages over a Unix datagram socket.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SyslogSinkConfig {
+    /// Path of the syslog socket.
+    #[serde(default = "defaults::syslog_sink_socket")]
+    pub socket: String,
+
+    /// Syslog facility code (1 is `user`, 16 to 23 are `local0` to
+    /// `local7`).
+    #[serde(default = "defaults::syslog_sink_facility")]
+    pub facility: u8,
+
+    /// Application name in the messages.
+    #[serde(default = "defaults::syslog_sink_app_name")]
+    pub app_name: String,
+}
+
+/// HTTP batch exporter of guest logs.
+///
+/// Entries are posted as newline-delimited JSON.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpSinkConfig {
+    /// Collector URL.
+    pub url: String,
+
+    /// Number of entries that triggers a post.
+    #[serde(default = "defaults::http_sink_batch_size")]
+    pub batch_size: usize,
+
+    /// Interval at which pending entries are posted, in milliseconds.
+    #[serde(default = "defaults::http_sink_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+
+    /// Timeout of a post, in milliseconds.
+    #[serde(default = "defaults::http_sink_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// Trace export configuration.
+///
+/// Without an OTLP endpoint, trace context is still propagated from
+/// inbound to outbound requests but no span is exported.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TracingConfig {
+    /// Base URL of an OTLP/HTTP collector; spans are posted as JSON to
+    /// `<otlp_endpoint>/v1/traces`.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// `service.name` resource attribute of the exported spans.
+    #[serde(default = "defaults::tracing_service_name")]
+    pub service_name: String,
+
+    /// Number of spans that triggers an export.
+    #[serde(default = "defaults::tracing_batch_size")]
+    pub batch_size: usize,
+
+    /// Interval at which pending spans are exported, in milliseconds.
+    #[serde(default = "defaults::tracing_export_interval_ms")]
+    pub export_interval_ms: u64,
+
+    /// Number of finished spans buffered for export. Further spans are
+    /// dropped until the exporter catches up.
+    #[serde(default = "defaults::tracing_queue_size")]
+    pub queue_size: usize,
+
+    /// Timeout of an export, in milliseconds.
+    #[serde(default = "defaults::tracing_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: defaults::tracing_service_name(),
+            batch_size: defaults::tracing_batch_size(),
+            export_interval_ms: defaults::tracing_export_interval_ms(),
+            queue_size: defaults::tracing_queue_size(),
+            timeout_ms: defaults::tracing_timeout_ms(),
+        }
+    }
+}
+
+/// Access log configuration.
+///
+/// Each function invocation is logged as one line once its response is
+/// produced.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccessLogConfig {
+    /// Enable the access log.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Line format.
+    #[serde(default)]
+    pub format: AccessLogFormat,
+
+    /// Log file, rotated by size; standard output if unset.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Size at which the file is rotated, in bytes.
+    #[serde(default = "defaults::access_log_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Number of rotated files kept.
+    #[serde(default = "defaults::access_log_max_files")]
+    pub max_files: u32,
+
+    /// Number of lines buffered for the writer. Further lines are dropped
+    /// until it catches up.
+    #[serde(default = "defaults::access_log_buffer")]
+    pub buffer: usize,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: AccessLogFormat::default(),
+            path: None,
+            max_file_bytes: defaults::access_log_max_file_bytes(),
+            max_files: defaults::access_log_max_files(),
+            buffer: defaults::access_log_buffer(),
+        }
+    }
+}
+
+/// Admin API configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AdminConfig {
+    /// Enable Admin API.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Authentication token (required when enabled).
+    ///
+    /// Clients must include this token in the `X-Admin-Token` header.
+    pub token: Option<String>,
+
+    /// URL prefix for Admin API endpoints.
+    #[serde(default = "defaults::admin_prefix")]
+    pub prefix: String,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token: None,
+            prefix: defaults::admin_prefix(),
+        }
+    }
+}
+
+impl AdminConfig {
+    /// Check if Admin API is properly configured.
+    ///
+    /// Returns `true` if enabled and token is set.
+    pub fn is_configured(&self) -> bool {
+        self.enabled && self.token.is_some()
+    }
+}
+
+/// A module entry to load at startup.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModuleEntry {
+    /// Unique identifier for the module.
+    ///
+    /// This ID is used in the `/functions/:id` endpoint.
+    pub id: String,
+
+    /// Path to the WebAssembly module file.
+    pub path: String,
+
+    /// Cron expression for scheduled invocation (optional).
+    ///
+    /// Accepts the standard five-field form (`"*/5 * * * *"`) or the
+    /// six/seven-field form with seconds (and years).
+    #[serde(default)]
+    pub schedule: Option<String>,
+
+    /// What to do when a scheduled run fires while the previous one is
+    /// still executing.
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+
+    /// Modules this module may invoke via service bindings.
+    ///
+    /// Use `"*"` to allow calling any loaded module.
+    #[serde(default)]
+    pub services: Vec<String>,
+
+    /// Hosts this module may reach over outbound HTTP and WebSocket.
+    ///
+    /// Entries are exact hosts or `*.example.com` wildcards.
+    #[serde(default)]
+    pub http_hosts: Vec<String>,
+
+    /// Key-value store namespace this module reads and writes.
+    ///
+    /// Modules without a namespace have no KV access. Modules sharing a
+    /// namespace share data.
+    #[serde(default)]
+    pub kv_namespace: Option<String>,
+
+    /// SQL database namespace this module uses.
+    ///
+    /// Modules without a namespace have no SQL access. Modules sharing a
+    /// namespace share the database.
+    #[serde(default)]
+    pub sql_namespace: Option<String>,
+
+    /// Only allow statements that do not modify the SQL database.
+    #[serde(default)]
+    pub sql_read_only: bool,
+
+    /// Blob storage buckets this module reads and writes.
+    ///
+    /// Use `"*"` to allow any bucket.
+    #[serde(default)]
+    pub blob_buckets: Vec<String>,
+
+    /// Size limit of the buckets this module writes to, in bytes.
+    ///
+    /// Defaults to `server.blob.default_quota_bytes`.
+    #[serde(default)]
+    pub blob_quota_bytes: Option<u64>,
+
+    /// Host-held crypto keys this module may use.
+    ///
+    /// Use `"*"` to allow any key.
+    #[serde(default)]
+    pub crypto_keys: Vec<String>,
+
+    /// Addresses this module may open TCP connections to, as `host:port`.
+    ///
+    /// The host may be a `*.example.com` wildcard and the port `*`.
+    #[serde(default)]
+    pub sockets: Vec<String>,
+
+    /// Configuration values readable by the module at runtime.
+    ///
+    /// These are the initial values; the Admin API can replace them without
+    /// reloading the module.
+    #[serde(default)]
+    pub config: BTreeMap<String, String>,
+
+    /// Minimum level of the module's logs.
+    ///
+    /// Defaults to `server.logging.min_level`; the Admin API can change it
+    /// at runtime.
+    #[serde(default)]
+    pub log_level: Option<GuestLogLevel>,
+
+    /// Fraction of the module's requests whose debug and info logs are
+    /// kept.
+    ///
+    /// Defaults to `server.logging.sample_rate`.
+    #[serde(default)]
+    pub log_sample_rate: Option<f64>,
+}
+
+/// Middleware pipeline definition.
+///
+/// A pipeline is served at `/functions/:id` and runs its stages in order.
+/// Each stage is a loaded module that can modify the request, edit the
+/// response produced by an earlier stage, or end the pipeline early by
+/// sending its response.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PipelineEntry {
+    /// Unique identifier for the pipeline.
+    ///
+    /// Takes precedence over a module with the same ID.
+    pub id: String,
+
+    /// Module IDs of the stages, in execution order.
+    pub stages: Vec<String>,
+}
+
+/// Policy for scheduled runs that overlap a run still in progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Drop the new run.
+    #[default]
+    Skip,
+    /// Start the new run once the current one finishes.
+    ///
+    /// At most one run is queued; further overlapping runs are dropped.
+    Queue,
+}
+
+/// Severity of guest log entries, for minimum level settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuestLogLevel {
+    /// Debug-level messages.
+    #[default]
+    Debug,
+    /// Informational messages.
+    Info,
+    /// Warning messages.
+    Warn,
+    /// Error messages.
+    Error,
+}
+
+/// Line format of the access log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogFormat {
+    /// One JSON object per line.
+    #[default]
+    Json,
+    /// Common Log Format, followed by the runtime's fields.
+    Common,
+    /// Combined Log Format (Common with referer and user agent), followed
+    /// by the runtime's fields.
+    Combined,
+}
+
+/// Configuration file errors.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigFileError {
+    /// Failed to read configuration file.
+    #[error("Failed to read config file '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Failed to parse configuration file.
+    #[error("Failed to parse config file: {message}")]
+    Parse { message: String },
+}
+
+/// Default value functions for serde.
+mod defaults {
+    pub fn bind_addr() -> String {
+        "0.0.0.0:8080".to_string()
+    }
+
+    pub const fn request_timeout_secs() -> u64 {
+        30
+    }
+
+    pub const fn graceful_shutdown() -> bool {
+        true
+    }
+
+    pub fn admin_prefix() -> String {
+        "/admin".to_string()
+    }
+
+    pub const fn job_workers() -> usize {
+        4
+    }
+
+    pub const fn job_max_queue_depth() -> usize {
+        1000
+    }
+
+    pub const fn job_retention_secs() -> u64 {
+        3600
+    }
+
+    pub const fn job_retry_backoff_ms() -> u64 {
+        100
+    }
+
+    pub const fn kv_max_operations() -> u32 {
+        1000
+    }
+
+    pub const fn kv_max_value_bytes() -> usize {
+        1024 * 1024
+    }
+
+    pub const fn kv_max_bytes_per_request() -> u64 {
+        16 * 1024 * 1024
+    }
+
+    pub const fn cache_max_bytes_per_module() -> usize {
+        16 * 1024 * 1024
+    }
+
+    pub const fn sql_max_database_bytes() -> u64 {
+        64 * 1024 * 1024
+    }
+
+    pub const fn sql_max_rows() -> usize {
+        1000
+    }
+
+    pub const fn sql_max_statements() -> u32 {
+        100
+    }
+
+    pub const fn blob_max_object_bytes() -> u64 {
+        64 * 1024 * 1024
+    }
+
+    pub const fn blob_max_operations() -> u32 {
+        1000
+    }
+
+    pub const fn blob_default_quota_bytes() -> u64 {
+        1024 * 1024 * 1024
+    }
+
+    pub const fn crypto_fuel_per_operation() -> u64 {
+        1_000
+    }
+
+    pub const fn crypto_fuel_per_byte() -> u64 {
+        2
+    }
+
+    pub const fn crypto_fuel_per_signature() -> u64 {
+        100_000
+    }
+
+    pub const fn sockets_max_connections() -> u32 {
+        4
+    }
+
+    pub const fn sockets_max_bytes() -> u64 {
+        16 * 1024 * 1024
+    }
+
+    pub const fn sockets_connect_timeout_ms() -> u64 {
+        5_000
+    }
+
+    pub const fn websocket_max_connections() -> u32 {
+        2
+    }
+
+    pub const fn websocket_max_messages() -> u32 {
+        1000
+    }
+
+    pub const fn metrics_max_series_per_module() -> usize {
+        1000
+    }
+
+    pub const fn logging_max_entries_per_request() -> usize {
+        1000
+    }
+
+    pub const fn logging_max_bytes_per_request() -> usize {
+        1024 * 1024
+    }
+
+    pub const fn logging_sample_rate() -> f64 {
+        1.0
+    }
+
+    pub const fn logging_sink_buffer() -> usize {
+        1024
+    }
+
+    pub const fn file_sink_max_file_bytes() -> u64 {
+        10 * 1024 * 1024
+    }
+
+    pub const fn file_sink_max_files() -> u32 {
+        5
+    }
+
+    pub fn syslog_sink_socket() -> String {
+        "/dev/log".to_string()
+    }
+
+    pub const fn syslog_sink_facility() -> u8 {
+        1
+    }
+
+    pub fn syslog_sink_app_name() -> String {
+        "edge-runtime".to_string()
+    }
+
+    pub const fn http_sink_batch_size() -> usize {
+        500
+    }
+
+    pub const fn http_sink_flush_interval_ms() -> u64 {
+        1000
+    }
+
+    pub const fn http_sink_timeout_ms() -> u64 {
+        5000
+    }
+
+    pub fn tracing_service_name() -> String {
+        "edge-runtime".to_string()
+    }
+
+    pub const fn tracing_batch_size() -> usize {
+        512
+    }
+
+    pub const fn tracing_export_interval_ms() -> u64 {
+        1000
+    }
+
+    pub const fn tracing_queue_size() -> usize {
+        2048
+    }
+
+    pub const fn tracing_timeout_ms() -> u64 {
+        5000
+    }
+
+    pub const fn access_log_max_file_bytes() -> u64 {
+        100 * 1024 * 1024
+    }
+
+    pub const fn access_log_max_files() -> u32 {
+        5
+    }
+
+    pub const fn access_log_buffer() -> usize {
+        4096
+    }
+
+    pub fn secrets_key_env() -> String {
+        "EDGE_SECRETS_KEY".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_file() {
+        let config = ConfigFile::default();
+
+        assert_eq!(config.server.bind_addr, "0.0.0.0:8080");
+        assert_eq!(config.server.request_timeout_secs, 30);
+        assert!(config.server.graceful_shutdown);
+        assert!(!config.admin.enabled);
+        assert!(config.admin.token.is_none());
+        assert_eq!(config.admin.prefix, "/admin");
+        assert!(config.modules.is_empty());
+    }
+
+    #[test]
+    fn test_parse_minimal_config() {
+        let toml = r#"
+            [server]
+            bind_addr = "127.0.0.1:3000"
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+
+        assert_eq!(config.server.bind_addr, "127.0.0.1:3000");
+        // Defaults applied
+        assert_eq!(config.server.request_timeout_secs, 30);
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let toml = r#"
+            [runtime.engine]
+            pooling_allocator = true
+            max_instances = 500
+
+            [runtime.execution]
+            max_fuel = 5_000_000
+            timeout_ms = 50
+
+            [server]
+            bind_addr = "0.0.0.0:9000"
+            request_timeout_secs = 60
+            graceful_shutdown = false
+
+            [admin]
+            enabled = true
+            token = "secret-token"
+            prefix = "/api/admin"
+
+            [[modules]]
+            id = "hello"
+            path = "./hello.wasm"
+
+            [[modules]]
+            id = "echo"
+            path = "./echo.wasm"
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+
+        assert_eq!(config.runtime.engine.max_instances, 500);
+        assert_eq!(config.runtime.execution.max_fuel, 5_000_000);
+        assert_eq!(config.server.bind_addr, "0.0.0.0:9000");
+        assert_eq!(config.server.request_timeout_secs, 60);
+        assert!(!config.server.graceful_shutdown);
+        assert!(config.admin.enabled);
+        assert_eq!(config.admin.token, Some("secret-token".to_string()));
+        assert_eq!(config.admin.prefix, "/api/admin");
+        assert_eq!(config.modules.len(), 2);
+        assert_eq!(config.modules[0].id, "hello");
+        assert_eq!(config.modules[1].path, "./echo.wasm");
+        assert!(config.modules[0].schedule.is_none());
+        assert_eq!(config.modules[0].overlap_policy, OverlapPolicy::Skip);
+    }
+
+    #[test]
+    fn test_parse_module_schedule() {
+        let toml = r#"
+            [[modules]]
+            id = "report"
+            path = "./report.wasm"
+            schedule = "*/5 * * * *"
+            overlap_policy = "queue"
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+
+        assert_eq!(config.modules[0].schedule.as_deref(), Some("*/5 * * * *"));
+        assert_eq!(config.modules[0].overlap_policy, OverlapPolicy::Queue);
+        assert!(config.modules[0].services.is_empty());
+    }
+
+    #[test]
+    fn test_parse_module_services() {
+        let toml = r#"
+            [[modules]]
+            id = "api"
+            path = "./api.wasm"
+            services = ["auth", "pricing"]
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+
+        assert_eq!(config.modules[0].services, vec!["auth", "pricing"]);
+    }
+
+    #[test]
+    fn test_parse_pipelines() {
+        let toml = r#"
+            [[pipelines]]
+            id = "shop"
+            stages = ["auth", "app", "compress"]
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+
+        assert_eq!(config.pipelines.len(), 1);
+        assert_eq!(config.pipelines[0].id, "shop");
+        assert_eq!(config.pipelines[0].stages, vec!["auth", "app", "compress"]);
+    }
+
+    #[test]
+    fn test_parse_jobs_config() {
+        let toml = "
+            [server.jobs]
+            workers = 2
+            max_queue_depth = 10
+            timeout_ms = 60000
+            max_retries = 3
+        ";
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let jobs = &config.server.jobs;
+
+        assert_eq!(jobs.workers, 2);
+        assert_eq!(jobs.max_queue_depth, 10);
+        assert_eq!(jobs.timeout_ms, Some(60_000));
+        assert_eq!(jobs.max_retries, 3);
+        // Defaults applied
+        assert_eq!(jobs.retention_secs, 3600);
+        assert_eq!(jobs.retry_backoff_ms, 100);
+    }
+
+    #[test]
+    fn test_parse_kv_config() {
+        let toml = r#"
+            [server.kv]
+            backend = "file"
+            path = "./kv.redb"
+            max_operations = 50
+
+            [[modules]]
+            id = "api"
+            path = "./api.wasm"
+            kv_namespace = "shared"
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let kv = &config.server.kv;
+
+        assert_eq!(kv.backend, KvBackendKind::File);
+        assert_eq!(kv.path.as_deref(), Some("./kv.redb"));
+        assert_eq!(kv.max_operations, 50);
+        // Defaults applied
+        assert_eq!(kv.max_value_bytes, 1024 * 1024);
+        assert_eq!(kv.max_bytes_per_request, 16 * 1024 * 1024);
+        assert_eq!(config.modules[0].kv_namespace.as_deref(), Some("shared"));
+    }
+
+    #[test]
+    fn test_parse_cache_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert_eq!(config.server.cache.max_bytes_per_module, 16 * 1024 * 1024);
+
+        let toml = "
+            [server.cache]
+            max_bytes_per_module = 1024
+        ";
+        let config = ConfigFile::from_toml(toml).unwrap();
+        assert_eq!(config.server.cache.max_bytes_per_module, 1024);
+    }
+
+    #[test]
+    fn test_parse_sql_config() {
+        let toml = r#"
+            [server.sql]
+            directory = "./sql"
+            max_rows = 10
+
+            [[modules]]
+            id = "report"
+            path = "./report.wasm"
+            sql_namespace = "analytics"
+            sql_read_only = true
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let sql = &config.server.sql;
+        assert_eq!(sql.directory.as_deref(), Some("./sql"));
+        assert_eq!(sql.max_rows, 10);
+        assert_eq!(sql.max_database_bytes, 64 * 1024 * 1024);
+        assert_eq!(sql.max_statements, 100);
+        assert_eq!(
+            config.modules[0].sql_namespace.as_deref(),
+            Some("analytics")
+        );
+        assert!(config.modules[0].sql_read_only);
+    }
+
+    #[test]
+    fn test_parse_blob_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert!(config.server.blob.directory.is_none());
+
+        let toml = r#"
+            [server.blob]
+            directory = "./blobs"
+            max_object_bytes = 1024
+
+            [[modules]]
+            id = "uploads"
+            path = "./uploads.wasm"
+            blob_buckets = ["avatars", "documents"]
+            blob_quota_bytes = 4096
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let blob = &config.server.blob;
+        assert_eq!(blob.directory.as_deref(), Some("./blobs"));
+        assert_eq!(blob.max_object_bytes, 1024);
+        assert_eq!(blob.default_quota_bytes, 1024 * 1024 * 1024);
+        assert_eq!(config.modules[0].blob_buckets, ["avatars", "documents"]);
+        assert_eq!(config.modules[0].blob_quota_bytes, Some(4096));
+    }
+
+    #[test]
+    fn test_parse_secrets_and_module_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert!(config.server.secrets.path.is_none());
+        assert_eq!(config.server.secrets.key_env, "EDGE_SECRETS_KEY");
+
+        let toml = r#"
+            [server.secrets]
+            path = "./secrets.enc"
+            key_env = "MY_KEY"
+
+            [[modules]]
+            id = "api"
+            path = "./api.wasm"
+
+            [modules.config]
+            region = "eu-west"
+            retries = "3"
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        assert_eq!(config.server.secrets.path.as_deref(), Some("./secrets.enc"));
+        assert_eq!(config.server.secrets.key_env, "MY_KEY");
+        assert_eq!(config.modules[0].config["region"], "eu-west");
+        assert_eq!(config.modules[0].config.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_crypto_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert!(config.server.crypto.keys.is_empty());
+        assert_eq!(config.server.crypto.fuel_per_byte, 2);
+
+        let toml = r#"
+            [server.crypto]
+            fuel_per_signature = 5000
+
+            [[server.crypto.keys]]
+            name = "signing"
+            algorithm = "ecdsa_p256"
+            path = "./signing.pem"
+
+            [[server.crypto.keys]]
+            name = "partner"
+            algorithm = "ed25519"
+            path = "./partner.pub"
+            public = true
+
+            [[modules]]
+            id = "webhooks"
+            path = "./webhooks.wasm"
+            crypto_keys = ["signing", "partner"]
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let crypto = &config.server.crypto;
+        assert_eq!(crypto.fuel_per_signature, 5000);
+        assert_eq!(crypto.fuel_per_operation, 1_000);
+        assert_eq!(crypto.keys[0].algorithm, CryptoKeyAlgorithm::EcdsaP256);
+        assert!(!crypto.keys[0].public);
+        assert!(crypto.keys[1].public);
+        assert_eq!(config.modules[0].crypto_keys, ["signing", "partner"]);
+    }
+
+    #[test]
+    fn test_parse_sockets_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert_eq!(config.server.sockets.max_connections, 4);
+
+        let toml = r#"
+            [server.sockets]
+            max_bytes = 1024
+
+            [[modules]]
+            id = "mailer"
+            path = "./mailer.wasm"
+            sockets = ["smtp.example.com:465", "*.redis.example.com:*"]
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        assert_eq!(config.server.sockets.max_bytes, 1024);
+        assert_eq!(config.server.sockets.connect_timeout_ms, 5_000);
+        assert_eq!(
+            config.modules[0].sockets,
+            ["smtp.example.com:465", "*.redis.example.com:*"]
+        );
+    }
+
+    #[test]
+    fn test_parse_websocket_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert_eq!(config.server.websocket.max_connections, 2);
+
+        let toml = r#"
+            [server.websocket]
+            max_messages = 50
+
+            [[modules]]
+            id = "ticker"
+            path = "./ticker.wasm"
+            http_hosts = ["*.stream.example.com"]
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        assert_eq!(config.server.websocket.max_messages, 50);
+        assert_eq!(config.modules[0].http_hosts, ["*.stream.example.com"]);
+    }
+
+    #[test]
+    fn test_parse_logging_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert_eq!(config.server.logging.max_entries_per_request, 1000);
+        assert_eq!(config.server.logging.min_level, GuestLogLevel::Debug);
+
+        let toml = r#"
+            [server.logging]
+            max_bytes_per_request = 4096
+            min_level = "warn"
+
+            [[modules]]
+            id = "noisy"
+            path = "./noisy.wasm"
+            log_level = "error"
+            log_sample_rate = 0.25
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        assert_eq!(config.server.logging.max_bytes_per_request, 4096);
+        assert_eq!(config.server.logging.min_level, GuestLogLevel::Warn);
+        assert_eq!(config.modules[0].log_level, Some(GuestLogLevel::Error));
+        assert_eq!(config.modules[0].log_sample_rate, Some(0.25));
+    }
+
+    #[test]
+    fn test_parse_log_sinks() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert!(config.server.logging.file.is_none());
+        assert!(config.server.logging.http.is_none());
+
+        let toml = r#"
+            [server.logging.file]
+            directory = "/var/log/edge"
+            max_files = 3
+
+            [server.logging.syslog]
+            facility = 16
+
+            [server.logging.http]
+            url = "http://127.0.0.1:9880/logs"
+            batch_size = 50
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let logging = &config.server.logging;
+        let file = logging.file.as_ref().unwrap();
+        assert_eq!(file.directory, "/var/log/edge");
+        assert_eq!(file.max_files, 3);
+        assert_eq!(file.max_file_bytes, 10 * 1024 * 1024);
+        let syslog = logging.syslog.as_ref().unwrap();
+        assert_eq!(syslog.socket, "/dev/log");
+        assert_eq!(syslog.facility, 16);
+        let http = logging.http.as_ref().unwrap();
+        assert_eq!(http.batch_size, 50);
+        assert_eq!(http.flush_interval_ms, 1000);
+    }
+
+    #[test]
+    fn test_parse_tracing_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert!(config.server.tracing.otlp_endpoint.is_none());
+        assert_eq!(config.server.tracing.service_name, "edge-runtime");
+
+        let toml = r#"
+            [server.tracing]
+            otlp_endpoint = "http://collector:4318"
+            service_name = "edge-eu"
+            batch_size = 64
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let tracing = &config.server.tracing;
+        assert_eq!(
+            tracing.otlp_endpoint.as_deref(),
+            Some("http://collector:4318")
+        );
+        assert_eq!(tracing.service_name, "edge-eu");
+        assert_eq!(tracing.batch_size, 64);
+        assert_eq!(tracing.export_interval_ms, 1000);
+    }
+
+    #[test]
+    fn test_parse_access_log_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert!(!config.server.access_log.enabled);
+        assert_eq!(config.server.access_log.format, AccessLogFormat::Json);
+
+        let toml = r#"
+            [server.access_log]
+            enabled = true
+            format = "combined"
+            path = "/var/log/edge/access.log"
+            max_files = 10
+        "#;
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        let access_log = &config.server.access_log;
+        assert!(access_log.enabled);
+        assert_eq!(access_log.format, AccessLogFormat::Combined);
+        assert_eq!(access_log.path.as_deref(), Some("/var/log/edge/access.log"));
+        assert_eq!(access_log.max_files, 10);
+        assert_eq!(access_log.max_file_bytes, 100 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_parse_metrics_config() {
+        let config = ConfigFile::from_toml("").unwrap();
+        assert_eq!(config.server.metrics.max_series_per_module, 1000);
+
+        let toml = r"
+            [server.metrics]
+            max_series_per_module = 50
+        ";
+
+        let config = ConfigFile::from_toml(toml).unwrap();
+        assert_eq!(config.server.metrics.max_series_per_module, 50);
     }
 
     #[test]
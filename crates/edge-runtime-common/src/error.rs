@@ -103,6 +103,34 @@ pub enum HostFunctionError {
     #[error("KV store error: {0}")]
     KvStore(String),
 
+    /// SQL database operation failed.
+    #[error("SQL error: {0}")]
+    Sql(String),
+
+    /// Blob storage operation failed.
+    #[error("Blob storage error: {0}")]
+    Blob(String),
+
+    /// Module configuration or secrets could not be loaded.
+    #[error("Config error: {0}")]
+    Config(String),
+
+    /// Cryptographic operation failed.
+    #[error("Crypto error: {0}")]
+    Crypto(String),
+
+    /// Socket connection or transfer failed.
+    #[error("Socket error: {0}")]
+    Socket(String),
+
+    /// WebSocket connection or message exchange failed.
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
+    /// Guest metric could not be recorded.
+    #[error("Metrics error: {0}")]
+    Metrics(String),
+
     /// Rate limit for host function calls was exceeded.
     #[error("Rate limit exceeded: {operation}")]
     RateLimitExceeded {
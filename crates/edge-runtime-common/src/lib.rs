@@ -11,5 +11,11 @@ pub mod config_file;
 pub mod error;
 
 pub use config::{EngineConfig, ExecutionConfig, RuntimeConfig};
-pub use config_file::{AdminConfig, ConfigFile, ConfigFileError, ModuleEntry, ServerConfigFile};
+pub use config_file::{
+    AccessLogConfig, AccessLogFormat, AdminConfig, BlobConfig, CacheConfig, ConfigFile,
+    ConfigFileError, CryptoConfig, CryptoKeyAlgorithm, CryptoKeyEntry, FileSinkConfig,
+    GuestLogLevel, HttpSinkConfig, JobsConfig, KvBackendKind, KvConfig, LoggingConfig,
+    MetricsConfig, ModuleEntry, OverlapPolicy, PipelineEntry, SecretsConfig, ServerConfigFile,
+    SocketsConfig, SqlConfig, SyslogSinkConfig, TracingConfig, WebSocketConfig,
+};
 pub use error::{HostFunctionError, RuntimeError, WasiError};
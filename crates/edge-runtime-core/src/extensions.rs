@@ -0,0 +1,93 @@
+//! Type-keyed per-request extensions.
+//!
+//! [`Extensions`] lets crates layered on top of the core (host functions,
+//! the server) attach their own per-request state to a [`WorkerContext`]
+//! without the core needing to know about their types.
+//!
+//! [`WorkerContext`]: crate::WorkerContext
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A map holding at most one value per type.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send>>,
+}
+
+impl Extensions {
+    /// Create an empty extension map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, returning the previous value of the same type.
+    pub fn insert<T: Send + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|prev| prev.downcast().ok().map(|boxed| *boxed))
+    }
+
+    /// Get a reference to the value of type `T`.
+    pub fn get<T: Send + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Get a mutable reference to the value of type `T`.
+    pub fn get_mut<T: Send + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Remove and return the value of type `T`.
+    pub fn remove<T: Send + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|boxed| *boxed))
+    }
+
+    /// Returns `true` if no extensions are set.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get() {
+        let mut ext = Extensions::new();
+        assert!(ext.is_empty());
+
+        assert!(ext.insert(42u32).is_none());
+        assert!(ext.insert(String::from("hello")).is_none());
+
+        assert_eq!(ext.get::<u32>(), Some(&42));
+        assert_eq!(ext.get::<String>().map(String::as_str), Some("hello"));
+        assert!(ext.get::<u64>().is_none());
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let mut ext = Extensions::new();
+        ext.insert(1u32);
+
+        assert_eq!(ext.insert(2u32), Some(1));
+        *ext.get_mut::<u32>().unwrap() += 1;
+        assert_eq!(ext.remove::<u32>(), Some(3));
+        assert!(ext.is_empty());
+    }
+}
@@ -7,16 +7,19 @@
 //! 2. Instantiate the module with a fresh store
 //! 3. Execute the entry point function
 //! 4. Collect results and metrics
+//! 5. Optionally run deferred work registered by the guest
 
 use std::sync::Arc;
 use std::time::Instant;
 
 use tracing::{debug, error, info, instrument, warn};
-use wasmtime::component::Linker as ComponentLinker;
-use wasmtime::{Engine, Linker, Store, Trap};
+use wasmtime::component::{Instance as ComponentInstance, Linker as ComponentLinker};
+use wasmtime::{Engine, Instance, Linker, Store, Trap};
 
 use crate::CompiledModule;
-use crate::store::{WorkerContext, calculate_fuel_consumed, get_remaining_fuel};
+use crate::store::{
+    MAX_DEFERRED_CALLS, WorkerContext, calculate_fuel_consumed, get_remaining_fuel,
+};
 use edge_runtime_common::RuntimeError;
 
 /// Result of executing a WebAssembly module.
@@ -46,6 +49,17 @@ impl ExecutionResult {
     }
 }
 
+/// Outcome of a deferred call registered via `wait_until`.
+#[derive(Debug)]
+pub struct DeferredOutcome {
+    /// Name of the exported function that was called.
+    pub export: String,
+    /// Result of the call.
+    pub result: Result<ExecutionResult, RuntimeError>,
+    /// Fuel consumed by the call.
+    pub fuel_consumed: u64,
+}
+
 /// Instance lifecycle manager.
 ///
 /// This struct manages the execution of WebAssembly modules, including:
@@ -109,13 +123,37 @@ impl InstanceRunner {
     /// - Instantiation fails
     /// - Entry point is not found
     /// - Fuel is exhausted
-    #[instrument(skip(self, module, store), fields(entry_point = %entry_point))]
     pub async fn execute_core(
         &self,
         module: &CompiledModule,
         store: &mut Store<WorkerContext>,
         entry_point: &str,
     ) -> Result<ExecutionResult, RuntimeError> {
+        self.execute_core_instance(module, store, entry_point)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// Execute a core WebAssembly module and keep its instance.
+    ///
+    /// This behaves like [`execute_core`](Self::execute_core), but also
+    /// returns the instance so that deferred work can be run against it with
+    /// [`run_deferred`](Self::run_deferred).
+    ///
+    /// # Errors
+    ///
+    /// See [`execute_core`](Self::execute_core).
+    #[instrument(
+        name = "execute_core",
+        skip(self, module, store),
+        fields(entry_point = %entry_point)
+    )]
+    pub async fn execute_core_instance(
+        &self,
+        module: &CompiledModule,
+        store: &mut Store<WorkerContext>,
+        entry_point: &str,
+    ) -> Result<(ExecutionResult, Instance), RuntimeError> {
         let start = Instant::now();
         let initial_fuel = get_remaining_fuel(store).unwrap_or(0);
 
@@ -127,6 +165,7 @@ impl InstanceRunner {
             .instantiate_async(&mut *store, module.as_core_module())
             .await
             .map_err(|e| RuntimeError::compilation_failed(format!("Instantiation failed: {e}")))?;
+        store.data_mut().metrics.instantiation_duration = start.elapsed();
 
         debug!("Module instantiated, looking for entry point");
 
@@ -145,6 +184,11 @@ impl InstanceRunner {
         // Calculate metrics
         let fuel_consumed = calculate_fuel_consumed(initial_fuel, store);
         store.data_mut().metrics.fuel_consumed = fuel_consumed;
+        // Linear memory never shrinks, so its final size is its peak
+        let memory_used_bytes = instance
+            .get_memory(&mut *store, "memory")
+            .map_or(0, |memory| memory.data_size(&*store));
+        store.data_mut().metrics.memory_used_bytes = memory_used_bytes;
         store.data_mut().finalize_metrics();
 
         let duration = start.elapsed();
@@ -156,7 +200,7 @@ impl InstanceRunner {
                     fuel_consumed = fuel_consumed,
                     "Execution completed successfully"
                 );
-                Ok(ExecutionResult::Success)
+                Ok((ExecutionResult::Success, instance))
             }
             Err(trap) => {
                 let trap_info = extract_trap_info(&trap);
@@ -178,10 +222,13 @@ impl InstanceRunner {
                     "Execution trapped"
                 );
 
-                Ok(ExecutionResult::Trap {
-                    message: trap_info.0,
-                    code: trap_info.1,
-                })
+                Ok((
+                    ExecutionResult::Trap {
+                        message: trap_info.0,
+                        code: trap_info.1,
+                    },
+                    instance,
+                ))
             }
         }
     }
@@ -189,25 +236,45 @@ impl InstanceRunner {
     /// Execute a WebAssembly component.
     ///
     /// This is the preferred execution method for Component Model modules.
-    #[instrument(skip(self, component, store))]
     pub async fn execute_component(
         &self,
         component: &CompiledModule,
         store: &mut Store<WorkerContext>,
     ) -> Result<ExecutionResult, RuntimeError> {
+        self.execute_component_instance(component, store)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// Execute a WebAssembly component and keep its instance.
+    ///
+    /// This behaves like [`execute_component`](Self::execute_component), but
+    /// also returns the instance so that deferred work can be run against it
+    /// with [`run_deferred_component`](Self::run_deferred_component).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if instantiation fails.
+    #[instrument(skip(self, component, store))]
+    pub async fn execute_component_instance(
+        &self,
+        component: &CompiledModule,
+        store: &mut Store<WorkerContext>,
+    ) -> Result<(ExecutionResult, ComponentInstance), RuntimeError> {
         let start = Instant::now();
         let initial_fuel = get_remaining_fuel(store).unwrap_or(0);
 
         debug!("Instantiating component");
 
         // Instantiate the component
-        let _instance = self
+        let instance = self
             .component_linker
             .instantiate_async(&mut *store, component.as_component())
             .await
             .map_err(|e| {
                 RuntimeError::compilation_failed(format!("Component instantiation failed: {e}"))
             })?;
+        store.data_mut().metrics.instantiation_duration = start.elapsed();
 
         // Calculate metrics
         let fuel_consumed = calculate_fuel_consumed(initial_fuel, store);
@@ -224,7 +291,72 @@ impl InstanceRunner {
 
         // Note: Actual component execution would depend on the specific interface
         // This is a placeholder for the basic instantiation
-        Ok(ExecutionResult::Success)
+        Ok((ExecutionResult::Success, instance))
+    }
+
+    /// Run deferred work registered by a core module via `wait_until`.
+    ///
+    /// Each registered export is called in registration order. Calls may
+    /// register further deferred work, up to the per-request limit. The
+    /// caller is responsible for resetting the store's budget first (see
+    /// [`reset_for_deferred`](crate::store::reset_for_deferred)).
+    ///
+    /// Fuel consumed is accumulated in
+    /// [`ExecutionMetrics::deferred_fuel_consumed`](crate::ExecutionMetrics).
+    #[instrument(skip(self, instance, store))]
+    pub async fn run_deferred(
+        &self,
+        instance: &Instance,
+        store: &mut Store<WorkerContext>,
+    ) -> Vec<DeferredOutcome> {
+        let mut outcomes = Vec::new();
+
+        while let Some(export) = next_deferred(store, outcomes.len()) {
+            let initial_fuel = get_remaining_fuel(store).unwrap_or(0);
+
+            let result = match instance.get_typed_func::<(), ()>(&mut *store, &export) {
+                Ok(func) => call_result(func.call_async(&mut *store, ()).await),
+                Err(_) => Err(RuntimeError::module_not_found(format!(
+                    "Deferred export '{export}' not found"
+                ))),
+            };
+
+            outcomes.push(record_deferred(store, export, result, initial_fuel));
+        }
+
+        outcomes
+    }
+
+    /// Run deferred work registered by a component via `wait-until`.
+    ///
+    /// This is the component counterpart of
+    /// [`run_deferred`](Self::run_deferred). Registered exports must have the
+    /// signature `func()`.
+    #[instrument(skip(self, instance, store))]
+    pub async fn run_deferred_component(
+        &self,
+        instance: &ComponentInstance,
+        store: &mut Store<WorkerContext>,
+    ) -> Vec<DeferredOutcome> {
+        let mut outcomes = Vec::new();
+
+        while let Some(export) = next_deferred(store, outcomes.len()) {
+            let initial_fuel = get_remaining_fuel(store).unwrap_or(0);
+
+            let result = match instance.get_typed_func::<(), ()>(&mut *store, &export) {
+                Ok(func) => match func.call_async(&mut *store, ()).await {
+                    Ok(()) => call_result(func.post_return_async(&mut *store).await),
+                    Err(e) => call_result(Err(e)),
+                },
+                Err(_) => Err(RuntimeError::module_not_found(format!(
+                    "Deferred export '{export}' not found"
+                ))),
+            };
+
+            outcomes.push(record_deferred(store, export, result, initial_fuel));
+        }
+
+        outcomes
     }
 
     /// Get the engine reference.
@@ -233,6 +365,64 @@ impl InstanceRunner {
     }
 }
 
+/// Pop the next deferred export, honoring the per-request call limit.
+fn next_deferred(store: &mut Store<WorkerContext>, completed: usize) -> Option<String> {
+    if completed >= MAX_DEFERRED_CALLS {
+        let dropped = store.data_mut().take_deferred();
+        if !dropped.is_empty() {
+            warn!(
+                dropped = dropped.len(),
+                "Deferred call limit reached, dropping remaining work"
+            );
+        }
+        return None;
+    }
+
+    store.data_mut().pop_deferred()
+}
+
+/// Convert the result of a guest call to an [`ExecutionResult`].
+fn call_result(result: wasmtime::Result<()>) -> Result<ExecutionResult, RuntimeError> {
+    match result {
+        Ok(()) => Ok(ExecutionResult::Success),
+        Err(trap) if is_out_of_fuel(&trap) => Err(RuntimeError::FuelExhausted),
+        Err(trap) => {
+            let (message, code) = extract_trap_info(&trap);
+            Ok(ExecutionResult::Trap { message, code })
+        }
+    }
+}
+
+/// Account for a finished deferred call and log its outcome.
+fn record_deferred(
+    store: &mut Store<WorkerContext>,
+    export: String,
+    result: Result<ExecutionResult, RuntimeError>,
+    initial_fuel: u64,
+) -> DeferredOutcome {
+    let fuel_consumed = calculate_fuel_consumed(initial_fuel, store);
+    store.data_mut().metrics.deferred_fuel_consumed += fuel_consumed;
+
+    let request_id = &store.data().request_id;
+    match &result {
+        Ok(ExecutionResult::Success) => {
+            info!(request_id = %request_id, export = %export, fuel_consumed, "Deferred call completed");
+        }
+        Ok(ExecutionResult::Trap { message, .. }) => {
+            error!(request_id = %request_id, export = %export, trap_message = %message, "Deferred call trapped");
+        }
+        Err(e) => {
+            error!(request_id = %request_id, export = %export, error = %e, "Deferred call failed");
+        }
+    }
+
+    DeferredOutcome {
+        export,
+        result,
+        fuel_consumed,
+    }
+}
+
 /// Extract human-readable trap information.
 fn extract_trap_info(error: &wasmtime::Error) -> (String, Option<String>) {
     let message = error.to_string();
@@ -3,6 +3,7 @@
 //! This crate provides the fundamental WebAssembly execution capabilities:
 //! - [`WasmEngine`]: Configured Wasmtime engine with pooling allocator
 //! - [`WorkerContext`]: Per-request execution context
+//! - [`Extensions`]: Per-request state attached by higher layers
 //! - [`CompiledModule`]: Compiled WebAssembly module wrapper
 //! - [`InstanceRunner`]: Instance lifecycle management
 //!
@@ -34,11 +35,13 @@
 //! ```
 
 pub mod engine;
+pub mod extensions;
 pub mod instance;
 pub mod module;
 pub mod store;
 
 pub use engine::WasmEngine;
-pub use instance::{ExecutionResult, InstanceRunner};
+pub use extensions::Extensions;
+pub use instance::{DeferredOutcome, ExecutionResult, InstanceRunner};
 pub use module::CompiledModule;
 pub use store::{ExecutionMetrics, LogEntry, LogLevel, WorkerContext};
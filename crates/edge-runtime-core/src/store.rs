@@ -4,15 +4,19 @@
 //! - [`WorkerContext`]: Per-request state accessible from host functions
 //! - [`LogEntry`] and [`LogLevel`]: Structured logging from guest code
 //! - [`ExecutionMetrics`]: Performance metrics for each execution
+//! - Deferred work registered by the guest to run after the response
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use wasmtime::Store;
 use wasmtime::component::ResourceTable;
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
 
-use crate::WasmEngine;
-use edge_runtime_common::{ExecutionConfig, RuntimeError};
+use crate::{Extensions, WasmEngine};
+use edge_runtime_common::{ExecutionConfig, GuestLogLevel, RuntimeError};
+
+/// Maximum number of deferred calls a single request may register.
+pub const MAX_DEFERRED_CALLS: usize = 16;
 
 /// Per-request execution context.
 ///
@@ -26,8 +30,11 @@ use edge_runtime_common::{ExecutionConfig, RuntimeError};
 /// - `wasi`: WASI context for system interface calls
 /// - `table`: Resource table for component model resources
 /// - `request_id`: Unique identifier for tracing
+/// - `module_id`: Module being executed, if known
 /// - `logs`: Collected log entries from guest code
 /// - `metrics`: Execution performance metrics
+/// - `deferred`: Exports to call after the response has been sent
+/// - `extensions`: Per-request state attached by the host and server layers
 pub struct WorkerContext {
     /// WASI context for system interface.
     wasi: WasiCtx,
@@ -38,14 +45,26 @@ pub struct WorkerContext {
     /// Unique request identifier for tracing.
     pub request_id: String,
 
+    /// Identifier of the module being executed.
+    pub module_id: Option<String>,
+
     /// Logs collected from guest code.
     pub logs: Vec<LogEntry>,
 
     /// Execution metrics.
     pub metrics: ExecutionMetrics,
 
+    /// Exported functions registered via `wait_until`.
+    deferred: Vec<String>,
+
+    /// Per-request state attached by higher layers.
+    extensions: Extensions,
+
     /// Execution start time.
     start_time: Instant,
+
+    /// Wall-clock time at which the epoch deadline interrupts execution.
+    deadline: Option<Instant>,
 }
 
 /// A single log entry from guest code.
@@ -57,12 +76,33 @@ pub struct LogEntry {
     /// Log message content.
     pub message: String,
 
+    /// Structured key-value fields attached by the guest.
+    pub fields: Vec<(String, String)>,
+
     /// Timestamp when the log was recorded.
     pub timestamp: Instant,
+
+    /// Wall-clock time when the log was recorded.
+    pub time: SystemTime,
+}
+
+impl LogEntry {
+    /// Create a log entry recorded now.
+    pub fn new(level: LogLevel, message: String, fields: Vec<(String, String)>) -> Self {
+        Self {
+            level,
+            message,
+            fields,
+            timestamp: Instant::now(),
+            time: SystemTime::now(),
+        }
+    }
 }
 
 /// Log level for guest logs.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Levels are ordered by severity, from `Debug` to `Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
     /// Debug-level messages.
     Debug,
@@ -85,17 +125,117 @@ impl std::fmt::Display for LogLevel {
     }
 }
 
+impl From<GuestLogLevel> for LogLevel {
+    fn from(level: GuestLogLevel) -> Self {
+        match level {
+            GuestLogLevel::Debug => Self::Debug,
+            GuestLogLevel::Info => Self::Info,
+            GuestLogLevel::Warn => Self::Warn,
+            GuestLogLevel::Error => Self::Error,
+        }
+    }
+}
+
+impl From<LogLevel> for GuestLogLevel {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Debug => Self::Debug,
+            LogLevel::Info => Self::Info,
+            LogLevel::Warn => Self::Warn,
+            LogLevel::Error => Self::Error,
+        }
+    }
+}
+
 /// Execution performance metrics.
 #[derive(Debug, Clone, Default)]
 pub struct ExecutionMetrics {
     /// Fuel consumed during execution.
     pub fuel_consumed: u64,
 
-    /// Memory used in bytes.
+    /// Peak size of the exported linear memory in bytes (core modules
+    /// only).
     pub memory_used_bytes: usize,
 
     /// Total execution duration.
     pub duration: Option<Duration>,
+
+    /// Wall-clock time spent in `sleep`, included in `duration`.
+    pub sleep_duration: Duration,
+
+    /// Time spent instantiating the module, included in `duration`.
+    pub instantiation_duration: Duration,
+
+    /// Fuel consumed by deferred work after the response.
+    pub deferred_fuel_consumed: u64,
+
+    /// Number of module-to-module service calls made.
+    pub service_calls: u32,
+
+    /// Number of key-value store operations performed.
+    pub kv_operations: u32,
+
+    /// Bytes of values written to the key-value store.
+    pub kv_bytes_written: u64,
+
+    /// Number of cache lookups that found a fresh response.
+    pub cache_hits: u32,
+
+    /// Number of cache lookups that found nothing.
+    pub cache_misses: u32,
+
+    /// Number of SQL statements executed.
+    pub sql_statements: u32,
+
+    /// Number of blob storage operations performed.
+    pub blob_operations: u32,
+
+    /// Number of TCP connections opened.
+    pub socket_connections: u32,
+
+    /// Bytes sent and received over TCP connections.
+    pub socket_bytes: u64,
+
+    /// Number of WebSocket connections opened.
+    pub websocket_connections: u32,
+
+    /// Number of WebSocket messages sent and received.
+    pub websocket_messages: u32,
+}
+
+impl ExecutionMetrics {
+    /// Execution duration excluding time spent in `sleep`.
+    pub fn active_duration(&self) -> Option<Duration> {
+        self.duration
+            .map(|duration| duration.saturating_sub(self.sleep_duration))
+    }
+
+    /// Merge the metrics of another execution into these.
+    ///
+    /// Fuel, durations, call counts and byte counts are summed; memory is the
+    /// peak of both executions.
+    pub fn merge(&mut self, other: &Self) {
+        self.fuel_consumed += other.fuel_consumed;
+        self.memory_used_bytes = self.memory_used_bytes.max(other.memory_used_bytes);
+        self.duration = match (self.duration, other.duration) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+        };
+        self.sleep_duration += other.sleep_duration;
+        self.instantiation_duration += other.instantiation_duration;
+        self.deferred_fuel_consumed += other.deferred_fuel_consumed;
+        self.service_calls += other.service_calls;
+        self.kv_operations += other.kv_operations;
+        self.kv_bytes_written += other.kv_bytes_written;
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+        self.sql_statements += other.sql_statements;
+        self.blob_operations += other.blob_operations;
+        self.socket_connections += other.socket_connections;
+        self.socket_bytes += other.socket_bytes;
+        self.websocket_connections += other.websocket_connections;
+        self.websocket_messages += other.websocket_messages;
+    }
 }
 
 impl WorkerContext {
@@ -119,19 +259,60 @@ impl WorkerContext {
             wasi,
             table,
             request_id,
+            module_id: None,
             logs: Vec::new(),
             metrics: ExecutionMetrics::default(),
+            deferred: Vec::new(),
+            extensions: Extensions::new(),
             start_time: Instant::now(),
+            deadline: None,
         }
     }
 
     /// Add a log entry.
     pub fn log(&mut self, level: LogLevel, message: String) {
-        self.logs.push(LogEntry {
-            level,
-            message,
-            timestamp: Instant::now(),
-        });
+        self.logs.push(LogEntry::new(level, message, Vec::new()));
+    }
+
+    /// Register an exported function to call after the response is sent.
+    ///
+    /// Returns `false` if the per-request limit of [`MAX_DEFERRED_CALLS`]
+    /// has been reached.
+    pub fn defer(&mut self, export: String) -> bool {
+        if self.deferred.len() >= MAX_DEFERRED_CALLS {
+            return false;
+        }
+        self.deferred.push(export);
+        true
+    }
+
+    /// Check whether any deferred work is registered.
+    pub fn has_deferred(&self) -> bool {
+        !self.deferred.is_empty()
+    }
+
+    /// Remove and return the oldest registered deferred call.
+    pub fn pop_deferred(&mut self) -> Option<String> {
+        if self.deferred.is_empty() {
+            None
+        } else {
+            Some(self.deferred.remove(0))
+        }
+    }
+
+    /// Take the registered deferred work, leaving none behind.
+    pub fn take_deferred(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    /// Get the per-request extensions.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get the per-request extensions mutably.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
     }
 
     /// Get elapsed time since execution started.
@@ -139,6 +320,20 @@ impl WorkerContext {
         self.start_time.elapsed()
     }
 
+    /// Get the wall-clock time at which execution will be interrupted.
+    ///
+    /// Set alongside the epoch deadline; `None` if epoch interruption is
+    /// disabled. Host functions that block outside of Wasm use this to
+    /// bound their own work.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Set the wall-clock deadline to `timeout` from now.
+    pub fn set_deadline(&mut self, timeout: Duration) {
+        self.deadline = Some(Instant::now() + timeout);
+    }
+
     /// Finalize metrics after execution.
     pub fn finalize_metrics(&mut self) {
         self.metrics.duration = Some(self.start_time.elapsed());
@@ -187,11 +382,45 @@ pub fn create_store(
     // (assuming 1 epoch increment per millisecond from background task)
     if engine.config().epoch_interruption {
         store.set_epoch_deadline(config.timeout_ms);
+        store
+            .data_mut()
+            .set_deadline(Duration::from_millis(config.timeout_ms));
     }
 
     Ok(store)
 }
 
+/// Reset a store's limits to the deferred work budget.
+///
+/// Called once the response has been sent, before running calls registered
+/// via `wait_until`. Fuel and the epoch deadline are replaced with
+/// [`ExecutionConfig::deferred_max_fuel`] and
+/// [`ExecutionConfig::deferred_timeout_ms`].
+///
+/// # Errors
+///
+/// Returns an error if fuel cannot be set on the store.
+pub fn reset_for_deferred(
+    store: &mut Store<WorkerContext>,
+    engine: &WasmEngine,
+    config: &ExecutionConfig,
+) -> Result<(), RuntimeError> {
+    if config.fuel_metering {
+        store
+            .set_fuel(config.deferred_max_fuel)
+            .map_err(|e| RuntimeError::invalid_config(format!("Failed to set fuel: {e}")))?;
+    }
+
+    if engine.config().epoch_interruption {
+        store.set_epoch_deadline(config.deferred_timeout_ms);
+        store
+            .data_mut()
+            .set_deadline(Duration::from_millis(config.deferred_timeout_ms));
+    }
+
+    Ok(())
+}
+
 /// Get remaining fuel from a store.
 pub fn get_remaining_fuel(store: &Store<WorkerContext>) -> Option<u64> {
     store.get_fuel().ok()
@@ -230,6 +459,30 @@ mod tests {
         assert_eq!(ctx.logs[1].level, LogLevel::Error);
     }
 
+    #[test]
+    fn test_worker_context_deferred() {
+        let mut ctx = WorkerContext::new("test".into());
+        assert!(!ctx.has_deferred());
+
+        assert!(ctx.defer("flush".into()));
+        assert!(ctx.defer("report".into()));
+        assert!(ctx.has_deferred());
+
+        assert_eq!(ctx.pop_deferred().as_deref(), Some("flush"));
+        assert_eq!(ctx.take_deferred(), vec!["report".to_string()]);
+        assert!(!ctx.has_deferred());
+    }
+
+    #[test]
+    fn test_worker_context_deferred_limit() {
+        let mut ctx = WorkerContext::new("test".into());
+
+        for _ in 0..MAX_DEFERRED_CALLS {
+            assert!(ctx.defer("flush".into()));
+        }
+        assert!(!ctx.defer("flush".into()));
+    }
+
     #[test]
     fn test_log_level_display() {
         assert_eq!(LogLevel::Debug.to_string(), "DEBUG");
@@ -238,6 +491,34 @@ mod tests {
         assert_eq!(LogLevel::Error.to_string(), "ERROR");
     }
 
+    #[test]
+    fn test_execution_metrics_merge() {
+        let mut merged = ExecutionMetrics {
+            fuel_consumed: 10,
+            memory_used_bytes: 4096,
+            kv_operations: 1,
+            ..Default::default()
+        };
+        merged.merge(&ExecutionMetrics {
+            fuel_consumed: 5,
+            memory_used_bytes: 1024,
+            duration: Some(Duration::from_millis(3)),
+            kv_operations: 2,
+            cache_misses: 1,
+            socket_bytes: 512,
+            websocket_messages: 4,
+            ..Default::default()
+        });
+
+        assert_eq!(merged.fuel_consumed, 15);
+        assert_eq!(merged.memory_used_bytes, 4096);
+        assert_eq!(merged.duration, Some(Duration::from_millis(3)));
+        assert_eq!(merged.kv_operations, 3);
+        assert_eq!(merged.cache_misses, 1);
+        assert_eq!(merged.socket_bytes, 512);
+        assert_eq!(merged.websocket_messages, 4);
+    }
+
     #[test]
     fn test_store_creation() {
         let engine_config = EngineConfig {
@@ -247,8 +528,8 @@ mod tests {
         let engine = WasmEngine::new(&engine_config).unwrap();
         let exec_config = ExecutionConfig::default();
 
-        let store = create_store(&engine, &exec_config, "test-123".into());
-        assert!(store.is_ok());
+        let store = create_store(&engine, &exec_config, "test-123".into()).unwrap();
+        assert!(store.data().deadline().is_some());
     }
 
     #[test]
@@ -269,4 +550,24 @@ mod tests {
 
         assert_eq!(remaining, Some(1000));
     }
+
+    #[test]
+    fn test_reset_for_deferred() {
+        let engine_config = EngineConfig {
+            pooling_allocator: false,
+            ..Default::default()
+        };
+        let engine = WasmEngine::new(&engine_config).unwrap();
+        let exec_config = ExecutionConfig {
+            max_fuel: 1000,
+            deferred_max_fuel: 500,
+            fuel_metering: true,
+            ..Default::default()
+        };
+
+        let mut store = create_store(&engine, &exec_config, "test".into()).unwrap();
+        reset_for_deferred(&mut store, &engine, &exec_config).unwrap();
+
+        assert_eq!(get_remaining_fuel(&store), Some(500));
+    }
 }
@@ -10,7 +10,7 @@
 use std::sync::Arc;
 
 use edge_runtime_common::{EngineConfig, ExecutionConfig};
-use edge_runtime_core::store::{LogLevel, create_store};
+use edge_runtime_core::store::{LogLevel, create_store, reset_for_deferred};
 use edge_runtime_core::{CompiledModule, ExecutionResult, InstanceRunner, WasmEngine};
 use edge_runtime_host::linker::register_all;
 
@@ -290,3 +290,110 @@ async fn test_multiple_logs() {
     assert_eq!(logs[2].message, "Error message");
     assert_eq!(logs[2].level, LogLevel::Error);
 }
+
+// ============================================================================
+// Test: Deferred Work (wait_until)
+// ============================================================================
+
+#[tokio::test]
+async fn test_wait_until_deferred() {
+    let wat = r#"
+        (module
+            (import "env" "log" (func $log (param i32 i32 i32)))
+            (import "env" "wait_until" (func $wait_until (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "flush")
+            (data (i32.const 16) "Deferred ran")
+
+            (func (export "_start")
+                (drop (call $wait_until (i32.const 0) (i32.const 5)))
+            )
+
+            (func (export "flush")
+                (call $log (i32.const 1) (i32.const 16) (i32.const 12))
+            )
+        )
+    "#;
+
+    let engine_config = EngineConfig {
+        pooling_allocator: false,
+        epoch_interruption: false,
+        ..Default::default()
+    };
+    let engine = WasmEngine::new(&engine_config).unwrap();
+    let mut runner = InstanceRunner::new(Arc::new(engine.inner().clone()));
+    register_all(runner.linker_mut()).unwrap();
+
+    let compiled = CompiledModule::from_wat(engine.inner(), wat).unwrap();
+
+    let exec_config = ExecutionConfig {
+        deferred_max_fuel: 10_000,
+        ..Default::default()
+    };
+    let mut store = create_store(&engine, &exec_config, "test-deferred".into()).unwrap();
+
+    let (result, instance) = runner
+        .execute_core_instance(&compiled, &mut store, "_start")
+        .await
+        .unwrap();
+
+    assert!(result.is_success());
+    assert!(store.data().logs.is_empty());
+    assert!(store.data().has_deferred());
+
+    reset_for_deferred(&mut store, &engine, &exec_config).unwrap();
+    let outcomes = runner.run_deferred(&instance, &mut store).await;
+
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].export, "flush");
+    assert!(matches!(outcomes[0].result, Ok(ExecutionResult::Success)));
+    assert!(outcomes[0].fuel_consumed > 0);
+
+    let data = store.data();
+    assert_eq!(data.logs.len(), 1);
+    assert_eq!(data.logs[0].message, "Deferred ran");
+    assert_eq!(
+        data.metrics.deferred_fuel_consumed,
+        outcomes[0].fuel_consumed
+    );
+}
+
+#[tokio::test]
+async fn test_wait_until_missing_export() {
+    let wat = r#"
+        (module
+            (import "env" "wait_until" (func $wait_until (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "missing")
+
+            (func (export "_start")
+                (drop (call $wait_until (i32.const 0) (i32.const 7)))
+            )
+        )
+    "#;
+
+    let engine_config = EngineConfig {
+        pooling_allocator: false,
+        epoch_interruption: false,
+        ..Default::default()
+    };
+    let engine = WasmEngine::new(&engine_config).unwrap();
+    let mut runner = InstanceRunner::new(Arc::new(engine.inner().clone()));
+    register_all(runner.linker_mut()).unwrap();
+
+    let compiled = CompiledModule::from_wat(engine.inner(), wat).unwrap();
+
+    let exec_config = ExecutionConfig::default();
+    let mut store = create_store(&engine, &exec_config, "test-deferred-missing".into()).unwrap();
+
+    let (_, instance) = runner
+        .execute_core_instance(&compiled, &mut store, "_start")
+        .await
+        .unwrap();
+
+    reset_for_deferred(&mut store, &engine, &exec_config).unwrap();
+    let outcomes = runner.run_deferred(&instance, &mut store).await;
+
+    assert_eq!(outcomes.len(), 1);
+    assert!(outcomes[0].result.is_err());
+}
@@ -0,0 +1,1167 @@
+//! Blob storage host function implementation.
+//!
+//! This module provides the host-side implementation of the blob interface,
+//! which lets guest code store objects too large for the KV store.
+//!
+//! Objects are addressed by bucket and key. Their content is stored once
+//! per distinct SHA-256 digest under `objects/` in the configured
+//! directory, and an embedded index (redb) maps keys to digests and tracks
+//! how many keys reference each object and how many bytes each bucket
+//! holds.
+//!
+//! Uploads and downloads are streamed through per-request handles, so an
+//! object never has to fit in guest memory at once. Access is controlled
+//! per module through [`Permissions`]: a module can only use its
+//! `blob_buckets`, and writes fail once a bucket would exceed the module's
+//! quota.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use edge_runtime_common::{BlobConfig, HostFunctionError, RuntimeError};
+use edge_runtime_core::store::WorkerContext;
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, warn};
+
+use crate::Permissions;
+
+/// Maximum size of a bucket name in bytes.
+pub const MAX_BLOB_BUCKET_BYTES: usize = 64;
+
+/// Maximum size of a key in bytes.
+pub const MAX_BLOB_KEY_BYTES: usize = 1024;
+
+/// Maximum number of objects returned by a single `list` call.
+pub const MAX_BLOB_LIST_OBJECTS: usize = 1000;
+
+/// Maximum number of uploads and downloads a request may have open.
+pub const MAX_OPEN_BLOB_STREAMS: usize = 16;
+
+/// Maximum number of bytes returned by a single read.
+pub const MAX_BLOB_READ_BYTES: usize = 1024 * 1024;
+
+/// Index of keys: `(bucket, key)` to serialized [`BlobMeta`].
+const OBJECTS: TableDefinition<(&str, &str), &[u8]> = TableDefinition::new("objects");
+
+/// Number of keys referencing each object digest.
+const REFS: TableDefinition<&str, u64> = TableDefinition::new("refs");
+
+/// Total size of the objects in each bucket.
+const USAGE: TableDefinition<&str, u64> = TableDefinition::new("usage");
+
+/// Metadata of a stored object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobMeta {
+    /// Object key within its bucket.
+    pub key: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Hex-encoded SHA-256 digest of the content.
+    pub digest: String,
+    /// Content type given when the object was stored.
+    pub content_type: Option<String>,
+    /// When the object was stored, in milliseconds since the Unix epoch.
+    pub created_ms: u64,
+}
+
+/// Size and object count of a bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BucketUsage {
+    /// Bucket name.
+    pub bucket: String,
+    /// Number of objects.
+    pub objects: u64,
+    /// Total size of the objects in bytes.
+    pub bytes: u64,
+}
+
+/// Shared handle to the blob store.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime.
+#[derive(Clone)]
+pub struct BlobStore {
+    inner: Arc<BlobStoreInner>,
+}
+
+struct BlobStoreInner {
+    root: PathBuf,
+    db: Database,
+    max_object_bytes: u64,
+    /// Serializes index updates with the object files they add or remove.
+    write_lock: Mutex<()>,
+    temp_files: AtomicU64,
+}
+
+impl BlobStore {
+    /// Open the blob store described by `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no directory is configured, or the directory or
+    /// index cannot be created.
+    pub fn open(config: &BlobConfig) -> Result<Self, RuntimeError> {
+        let root = config
+            .directory
+            .as_ref()
+            .map(PathBuf::from)
+            .ok_or_else(|| RuntimeError::invalid_config("Blob storage requires a directory"))?;
+
+        for dir in [root.join("objects"), root.join("tmp")] {
+            std::fs::create_dir_all(&dir).map_err(|e| {
+                RuntimeError::invalid_config(format!(
+                    "Failed to create blob directory '{}': {e}",
+                    dir.display()
+                ))
+            })?;
+        }
+
+        let db = Database::create(root.join("index.redb"))
+            .map_err(|e| RuntimeError::invalid_config(format!("Failed to open blob index: {e}")))?;
+        let txn = db.begin_write().map_err(open_error)?;
+        txn.open_table(OBJECTS).map_err(open_error)?;
+        txn.open_table(REFS).map_err(open_error)?;
+        txn.open_table(USAGE).map_err(open_error)?;
+        txn.commit().map_err(open_error)?;
+
+        Ok(Self {
+            inner: Arc::new(BlobStoreInner {
+                root,
+                db,
+                max_object_bytes: config.max_object_bytes,
+                write_lock: Mutex::new(()),
+                temp_files: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// Get the maximum size of a single object in bytes.
+    pub fn max_object_bytes(&self) -> u64 {
+        self.inner.max_object_bytes
+    }
+
+    /// Start an upload.
+    ///
+    /// The content is written to a scratch file until it is committed with
+    /// [`BlobStore::commit`]; dropping the writer discards it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scratch file cannot be created.
+    pub async fn writer(&self) -> Result<BlobWriter, HostFunctionError> {
+        let n = self.inner.temp_files.fetch_add(1, Ordering::Relaxed);
+        let path = self
+            .inner
+            .root
+            .join("tmp")
+            .join(format!("{}.{n}", std::process::id()));
+        let file = tokio::fs::File::create(&path).await.map_err(blob_error)?;
+
+        Ok(BlobWriter {
+            file: Some(file),
+            path,
+            hasher: Sha256::new(),
+            size: 0,
+            max_size: self.inner.max_object_bytes,
+        })
+    }
+
+    /// Store an upload under `bucket` and `key`, replacing any existing
+    /// object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket would exceed `quota` bytes, or the
+    /// object cannot be stored.
+    pub async fn commit(
+        &self,
+        mut writer: BlobWriter,
+        bucket: &str,
+        key: &str,
+        content_type: Option<String>,
+        quota: u64,
+    ) -> Result<BlobMeta, HostFunctionError> {
+        if let Some(mut file) = writer.file.take() {
+            file.flush().await.map_err(blob_error)?;
+            file.sync_all().await.map_err(blob_error)?;
+        }
+
+        let meta = BlobMeta {
+            key: key.to_string(),
+            size: writer.size,
+            digest: hex(&std::mem::take(&mut writer.hasher).finalize()),
+            content_type,
+            created_ms: now_ms(),
+        };
+        let store = self.clone();
+        let bucket = bucket.to_string();
+        blocking(move || store.commit_blocking(&writer.path, &bucket, meta, quota)).await
+    }
+
+    /// Get the metadata of an object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be read.
+    pub async fn head(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<BlobMeta>, HostFunctionError> {
+        let store = self.clone();
+        let (bucket, key) = (bucket.to_string(), key.to_string());
+        blocking(move || store.head_blocking(&bucket, &key)).await
+    }
+
+    /// Open an object for reading.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index or the object file cannot be read.
+    pub async fn reader(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<(BlobMeta, BlobReader)>, HostFunctionError> {
+        let Some(meta) = self.head(bucket, key).await? else {
+            return Ok(None);
+        };
+        let file = tokio::fs::File::open(self.object_path(&meta.digest))
+            .await
+            .map_err(blob_error)?;
+        Ok(Some((meta, BlobReader { file })))
+    }
+
+    /// Read a whole object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index or the object file cannot be read.
+    pub async fn read(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<(BlobMeta, Vec<u8>)>, HostFunctionError> {
+        let Some(meta) = self.head(bucket, key).await? else {
+            return Ok(None);
+        };
+        let data = tokio::fs::read(self.object_path(&meta.digest))
+            .await
+            .map_err(blob_error)?;
+        Ok(Some((meta, data)))
+    }
+
+    /// Delete an object, returning `true` if it existed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be updated.
+    pub async fn delete(&self, bucket: &str, key: &str) -> Result<bool, HostFunctionError> {
+        let store = self.clone();
+        let (bucket, key) = (bucket.to_string(), key.to_string());
+        blocking(move || store.delete_blocking(&bucket, &key)).await
+    }
+
+    /// List up to `limit` objects whose keys start with `prefix`, in
+    /// lexicographic order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be read.
+    pub async fn list(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<BlobMeta>, HostFunctionError> {
+        let store = self.clone();
+        let (bucket, prefix) = (bucket.to_string(), prefix.to_string());
+        blocking(move || store.list_blocking(&bucket, &prefix, limit)).await
+    }
+
+    /// List all buckets with their usage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be read.
+    pub async fn buckets(&self) -> Result<Vec<BucketUsage>, HostFunctionError> {
+        let store = self.clone();
+        blocking(move || store.buckets_blocking()).await
+    }
+
+    fn commit_blocking(
+        &self,
+        upload: &Path,
+        bucket: &str,
+        meta: BlobMeta,
+        quota: u64,
+    ) -> Result<BlobMeta, HostFunctionError> {
+        let _guard = self
+            .inner
+            .write_lock
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        // Object file moved into place by this commit, removed again if the
+        // index update fails so it is not left unreferenced.
+        let mut stored = None;
+        let result = self.commit_index(upload, bucket, &meta, quota, &mut stored);
+        let released = match result {
+            Ok(released) => released,
+            Err(e) => {
+                if let Some(path) = stored {
+                    let _ = std::fs::remove_file(path);
+                }
+                return Err(e);
+            }
+        };
+
+        if let Some(digest) = released {
+            let _ = std::fs::remove_file(self.object_path(&digest));
+        }
+        debug!(bucket, key = %meta.key, size = meta.size, "Stored blob");
+        Ok(meta)
+    }
+
+    /// Record a blob in the index, moving the upload into `objects/` if its
+    /// content is new.
+    ///
+    /// Returns the digest of an object no longer referenced.
+    fn commit_index(
+        &self,
+        upload: &Path,
+        bucket: &str,
+        meta: &BlobMeta,
+        quota: u64,
+        stored: &mut Option<PathBuf>,
+    ) -> Result<Option<String>, HostFunctionError> {
+        let txn = self.inner.db.begin_write().map_err(blob_error)?;
+        let released = {
+            let mut objects = txn.open_table(OBJECTS).map_err(blob_error)?;
+            let mut refs = txn.open_table(REFS).map_err(blob_error)?;
+            let mut usage = txn.open_table(USAGE).map_err(blob_error)?;
+
+            let previous = objects
+                .get((bucket, meta.key.as_str()))
+                .map_err(blob_error)?
+                .map(|v| decode_meta(v.value()))
+                .transpose()?;
+            let used = usage
+                .get(bucket)
+                .map_err(blob_error)?
+                .map_or(0, |v| v.value());
+            let used = used.saturating_sub(previous.as_ref().map_or(0, |p| p.size));
+            if used + meta.size > quota {
+                return Err(HostFunctionError::RateLimitExceeded {
+                    operation: format!("blob bucket '{bucket}' quota"),
+                });
+            }
+
+            let count = refs
+                .get(meta.digest.as_str())
+                .map_err(blob_error)?
+                .map_or(0, |v| v.value());
+            if count == 0 {
+                let path = self.object_path(&meta.digest);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(blob_error)?;
+                }
+                std::fs::rename(upload, &path).map_err(blob_error)?;
+                *stored = Some(path);
+            }
+            refs.insert(meta.digest.as_str(), count + 1)
+                .map_err(blob_error)?;
+            objects
+                .insert((bucket, meta.key.as_str()), encode_meta(meta)?.as_slice())
+                .map_err(blob_error)?;
+            usage.insert(bucket, used + meta.size).map_err(blob_error)?;
+
+            match previous {
+                Some(previous) => release(&mut refs, &previous.digest)?,
+                None => None,
+            }
+        };
+        txn.commit().map_err(blob_error)?;
+        Ok(released)
+    }
+
+    fn head_blocking(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<BlobMeta>, HostFunctionError> {
+        let txn = self.inner.db.begin_read().map_err(blob_error)?;
+        let objects = txn.open_table(OBJECTS).map_err(blob_error)?;
+        objects
+            .get((bucket, key))
+            .map_err(blob_error)?
+            .map(|v| decode_meta(v.value()))
+            .transpose()
+    }
+
+    fn delete_blocking(&self, bucket: &str, key: &str) -> Result<bool, HostFunctionError> {
+        let _guard = self
+            .inner
+            .write_lock
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        let txn = self.inner.db.begin_write().map_err(blob_error)?;
+        let released = {
+            let mut objects = txn.open_table(OBJECTS).map_err(blob_error)?;
+            let mut refs = txn.open_table(REFS).map_err(blob_error)?;
+            let mut usage = txn.open_table(USAGE).map_err(blob_error)?;
+
+            let Some(meta) = objects
+                .remove((bucket, key))
+                .map_err(blob_error)?
+                .map(|v| decode_meta(v.value()))
+                .transpose()?
+            else {
+                return Ok(false);
+            };
+
+            let used = usage
+                .get(bucket)
+                .map_err(blob_error)?
+                .map_or(0, |v| v.value());
+            let used = used.saturating_sub(meta.size);
+            if used == 0 && bucket_is_empty(&objects, bucket)? {
+                usage.remove(bucket).map_err(blob_error)?;
+            } else {
+                usage.insert(bucket, used).map_err(blob_error)?;
+            }
+            release(&mut refs, &meta.digest)?
+        };
+        txn.commit().map_err(blob_error)?;
+
+        if let Some(digest) = released {
+            let _ = std::fs::remove_file(self.object_path(&digest));
+        }
+        debug!(bucket, key, "Deleted blob");
+        Ok(true)
+    }
+
+    fn list_blocking(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<BlobMeta>, HostFunctionError> {
+        let txn = self.inner.db.begin_read().map_err(blob_error)?;
+        let objects = txn.open_table(OBJECTS).map_err(blob_error)?;
+        let mut result = Vec::new();
+        for entry in objects.range((bucket, prefix)..).map_err(blob_error)? {
+            let (k, v) = entry.map_err(blob_error)?;
+            let (b, key) = k.value();
+            if b != bucket || !key.starts_with(prefix) || result.len() >= limit {
+                break;
+            }
+            result.push(decode_meta(v.value())?);
+        }
+        Ok(result)
+    }
+
+    fn buckets_blocking(&self) -> Result<Vec<BucketUsage>, HostFunctionError> {
+        let txn = self.inner.db.begin_read().map_err(blob_error)?;
+        let objects = txn.open_table(OBJECTS).map_err(blob_error)?;
+        let usage = txn.open_table(USAGE).map_err(blob_error)?;
+
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for entry in objects.iter().map_err(blob_error)? {
+            let (k, _) = entry.map_err(blob_error)?;
+            *counts.entry(k.value().0.to_string()).or_default() += 1;
+        }
+
+        let mut result = Vec::new();
+        for entry in usage.iter().map_err(blob_error)? {
+            let (bucket, bytes) = entry.map_err(blob_error)?;
+            let bucket = bucket.value().to_string();
+            result.push(BucketUsage {
+                objects: counts.get(&bucket).copied().unwrap_or(0),
+                bucket,
+                bytes: bytes.value(),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Path of the object file for a digest.
+    fn object_path(&self, digest: &str) -> PathBuf {
+        let (dir, file) = digest.split_at(2.min(digest.len()));
+        self.inner.root.join("objects").join(dir).join(file)
+    }
+}
+
+impl std::fmt::Debug for BlobStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobStore")
+            .field("root", &self.inner.root)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An upload in progress.
+///
+/// Content is hashed while it is written to a scratch file, which is
+/// removed if the upload is dropped before being committed.
+pub struct BlobWriter {
+    file: Option<tokio::fs::File>,
+    path: PathBuf,
+    hasher: Sha256,
+    size: u64,
+    max_size: u64,
+}
+
+impl BlobWriter {
+    /// Append a chunk of content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object would exceed the maximum object size
+    /// or the scratch file cannot be written.
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<(), HostFunctionError> {
+        let size = self.size + chunk.len() as u64;
+        if size > self.max_size {
+            return Err(HostFunctionError::InvalidArgument {
+                reason: format!("blob exceeds {} bytes", self.max_size),
+            });
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Err(HostFunctionError::Blob("upload is closed".to_string()));
+        };
+        file.write_all(chunk).await.map_err(blob_error)?;
+        self.hasher.update(chunk);
+        self.size = size;
+        Ok(())
+    }
+
+    /// Number of bytes written so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Drop for BlobWriter {
+    fn drop(&mut self) {
+        // After a commit the scratch file has been moved or is a duplicate
+        // of an existing object; either way it is no longer needed.
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A download in progress.
+pub struct BlobReader {
+    file: tokio::fs::File,
+}
+
+impl BlobReader {
+    /// Read up to `max` bytes; an empty result means the end was reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the object file cannot be read.
+    pub async fn read(&mut self, max: usize) -> Result<Vec<u8>, HostFunctionError> {
+        let mut buf = vec![0; max.min(MAX_BLOB_READ_BYTES)];
+        let n = self.file.read(&mut buf).await.map_err(blob_error)?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+}
+
+/// Per-request blob storage state: open uploads and downloads.
+///
+/// Created on first use and kept in the [`WorkerContext`] extensions;
+/// streams left open when the request ends are discarded.
+#[derive(Default)]
+pub struct BlobContext {
+    uploads: HashMap<i32, PendingUpload>,
+    downloads: HashMap<i32, BlobReader>,
+    next_handle: i32,
+}
+
+/// An upload and where it will be stored.
+struct PendingUpload {
+    bucket: String,
+    key: String,
+    content_type: Option<String>,
+    writer: BlobWriter,
+}
+
+impl BlobContext {
+    fn open_streams(&self) -> usize {
+        self.uploads.len() + self.downloads.len()
+    }
+
+    fn next_handle(&mut self) -> i32 {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        handle
+    }
+}
+
+impl std::fmt::Debug for BlobContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlobContext")
+            .field("uploads", &self.uploads.len())
+            .field("downloads", &self.downloads.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host implementation for the blob interface.
+///
+/// Opening a stream, `head`, `delete` and `list` check the caller's
+/// buckets and quota, and count towards
+/// [`ExecutionMetrics::blob_operations`]; reading and writing chunks of an
+/// open stream does not.
+///
+/// [`ExecutionMetrics::blob_operations`]: edge_runtime_core::ExecutionMetrics::blob_operations
+pub struct BlobHost;
+
+impl BlobHost {
+    /// Start uploading an object, returning its stream handle.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// upload cannot be started.
+    pub async fn put_start(
+        ctx: &mut WorkerContext,
+        bucket: &str,
+        key: &str,
+        content_type: Option<String>,
+    ) -> Result<i32, HostFunctionError> {
+        let (store, _) = Self::begin(ctx, "put", bucket, key)?;
+        Self::check_open_streams(ctx)?;
+        let writer = store.writer().await?;
+
+        let blobs = Self::context(ctx);
+        let handle = blobs.next_handle();
+        blobs.uploads.insert(
+            handle,
+            PendingUpload {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                content_type,
+                writer,
+            },
+        );
+        Ok(handle)
+    }
+
+    /// Append a chunk to an upload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown, the object would exceed
+    /// the maximum size, or the chunk cannot be written.
+    pub async fn put_write(
+        ctx: &mut WorkerContext,
+        handle: i32,
+        chunk: &[u8],
+    ) -> Result<(), HostFunctionError> {
+        let mut upload = Self::take_upload(ctx, handle)?;
+        let result = upload.writer.write(chunk).await;
+        if result.is_ok() {
+            Self::context(ctx).uploads.insert(handle, upload);
+        }
+        result
+    }
+
+    /// Finish an upload and store the object.
+    ///
+    /// The handle is closed whether or not the object was stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown, the bucket quota would be
+    /// exceeded, or the object cannot be stored.
+    pub async fn put_finish(
+        ctx: &mut WorkerContext,
+        handle: i32,
+    ) -> Result<BlobMeta, HostFunctionError> {
+        let upload = Self::take_upload(ctx, handle)?;
+        let (store, quota) = {
+            let Some(store) = ctx.extensions().get::<BlobStore>().cloned() else {
+                return Err(not_configured());
+            };
+            let quota = ctx
+                .extensions()
+                .get::<Permissions>()
+                .map_or(0, |p| p.max_blob_bucket_bytes);
+            (store, quota)
+        };
+        store
+            .commit(
+                upload.writer,
+                &upload.bucket,
+                &upload.key,
+                upload.content_type,
+                quota,
+            )
+            .await
+    }
+
+    /// Open an object for reading.
+    ///
+    /// Returns the stream handle and the object's metadata, or `None` if
+    /// the object does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// object cannot be opened.
+    pub async fn get_open(
+        ctx: &mut WorkerContext,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<(i32, BlobMeta)>, HostFunctionError> {
+        let (store, _) = Self::begin(ctx, "get", bucket, key)?;
+        Self::check_open_streams(ctx)?;
+        let Some((meta, reader)) = store.reader(bucket, key).await? else {
+            return Ok(None);
+        };
+
+        let blobs = Self::context(ctx);
+        let handle = blobs.next_handle();
+        blobs.downloads.insert(handle, reader);
+        Ok(Some((handle, meta)))
+    }
+
+    /// Read up to `max` bytes from a download.
+    ///
+    /// Returns an empty chunk at the end of the object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown or the object cannot be
+    /// read.
+    pub async fn read(
+        ctx: &mut WorkerContext,
+        handle: i32,
+        max: usize,
+    ) -> Result<Vec<u8>, HostFunctionError> {
+        let Some(mut reader) = Self::context(ctx).downloads.remove(&handle) else {
+            return Err(unknown_handle(handle));
+        };
+        let result = reader.read(max).await;
+        Self::context(ctx).downloads.insert(handle, reader);
+        result
+    }
+
+    /// Close an upload or download.
+    ///
+    /// Closing an unfinished upload discards it. Returns `false` if the
+    /// handle is unknown.
+    pub fn close(ctx: &mut WorkerContext, handle: i32) -> bool {
+        let blobs = Self::context(ctx);
+        blobs.uploads.remove(&handle).is_some() || blobs.downloads.remove(&handle).is_some()
+    }
+
+    /// Get the metadata of an object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// index cannot be read.
+    pub async fn head(
+        ctx: &mut WorkerContext,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<BlobMeta>, HostFunctionError> {
+        let (store, _) = Self::begin(ctx, "head", bucket, key)?;
+        store.head(bucket, key).await
+    }
+
+    /// Delete an object, returning `true` if it existed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// index cannot be updated.
+    pub async fn delete(
+        ctx: &mut WorkerContext,
+        bucket: &str,
+        key: &str,
+    ) -> Result<bool, HostFunctionError> {
+        let (store, _) = Self::begin(ctx, "delete", bucket, key)?;
+        store.delete(bucket, key).await
+    }
+
+    /// List objects whose keys start with `prefix`.
+    ///
+    /// At most [`MAX_BLOB_LIST_OBJECTS`] objects are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// index cannot be read.
+    pub async fn list(
+        ctx: &mut WorkerContext,
+        bucket: &str,
+        prefix: &str,
+    ) -> Result<Vec<BlobMeta>, HostFunctionError> {
+        let (store, _) = Self::begin(ctx, "list", bucket, prefix)?;
+        store.list(bucket, prefix, MAX_BLOB_LIST_OBJECTS).await
+    }
+
+    /// Numeric error code returned to core modules.
+    ///
+    /// `-1` is reserved for "not found" and invalid guest memory.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    /// Check access and limits, and count the operation.
+    ///
+    /// Returns the store and the caller's bucket quota.
+    fn begin(
+        ctx: &mut WorkerContext,
+        operation: &str,
+        bucket: &str,
+        key: &str,
+    ) -> Result<(BlobStore, u64), HostFunctionError> {
+        let Some(permissions) = ctx.extensions().get::<Permissions>() else {
+            return Err(denied(bucket));
+        };
+        if !permissions.is_blob_bucket_allowed(bucket) {
+            warn!(request_id = %ctx.request_id, bucket, operation, "Blob access denied");
+            return Err(denied(bucket));
+        }
+        let max_operations = permissions.max_blob_operations;
+        let quota = permissions.max_blob_bucket_bytes;
+
+        let Some(store) = ctx.extensions().get::<BlobStore>().cloned() else {
+            return Err(not_configured());
+        };
+
+        if ctx.metrics.blob_operations >= max_operations {
+            warn!(
+                request_id = %ctx.request_id,
+                max = max_operations,
+                "Blob operation limit exceeded"
+            );
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "blob".to_string(),
+            });
+        }
+        ctx.metrics.blob_operations += 1;
+
+        validate_name(bucket, key, operation == "list")?;
+        debug!(request_id = %ctx.request_id, bucket, key, operation, "Blob operation");
+
+        Ok((store, quota))
+    }
+
+    /// Check that another stream may be opened.
+    fn check_open_streams(ctx: &mut WorkerContext) -> Result<(), HostFunctionError> {
+        if Self::context(ctx).open_streams() >= MAX_OPEN_BLOB_STREAMS {
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "blob streams".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Remove an upload from the request state.
+    fn take_upload(
+        ctx: &mut WorkerContext,
+        handle: i32,
+    ) -> Result<PendingUpload, HostFunctionError> {
+        Self::context(ctx)
+            .uploads
+            .remove(&handle)
+            .ok_or_else(|| unknown_handle(handle))
+    }
+
+    /// Get the request's blob state, creating it on first use.
+    fn context(ctx: &mut WorkerContext) -> &mut BlobContext {
+        if ctx.extensions().get::<BlobContext>().is_none() {
+            ctx.extensions_mut().insert(BlobContext::default());
+        }
+        ctx.extensions_mut()
+            .get_mut::<BlobContext>()
+            .expect("blob context was just inserted")
+    }
+}
+
+/// Decrement the reference count of a digest.
+///
+/// Returns the digest if its object is no longer referenced.
+fn release(
+    refs: &mut redb::Table<'_, &'static str, u64>,
+    digest: &str,
+) -> Result<Option<String>, HostFunctionError> {
+    let count = refs
+        .get(digest)
+        .map_err(blob_error)?
+        .map_or(0, |v| v.value());
+    if count <= 1 {
+        refs.remove(digest).map_err(blob_error)?;
+        Ok(Some(digest.to_string()))
+    } else {
+        refs.insert(digest, count - 1).map_err(blob_error)?;
+        Ok(None)
+    }
+}
+
+/// Check whether a bucket has no objects left.
+fn bucket_is_empty(
+    objects: &redb::Table<'_, (&'static str, &'static str), &'static [u8]>,
+    bucket: &str,
+) -> Result<bool, HostFunctionError> {
+    let mut range = objects.range((bucket, "")..).map_err(blob_error)?;
+    match range.next() {
+        Some(entry) => Ok(entry.map_err(blob_error)?.0.value().0 != bucket),
+        None => Ok(true),
+    }
+}
+
+/// Validate a bucket name and key (or a `list` prefix, which may be empty).
+fn validate_name(bucket: &str, key: &str, allow_empty: bool) -> Result<(), HostFunctionError> {
+    let reason = if bucket.is_empty() || bucket.len() > MAX_BLOB_BUCKET_BYTES {
+        format!("blob bucket must be 1 to {MAX_BLOB_BUCKET_BYTES} bytes")
+    } else if key.is_empty() && !allow_empty {
+        "blob key must not be empty".to_string()
+    } else if key.len() > MAX_BLOB_KEY_BYTES {
+        format!("blob key exceeds {MAX_BLOB_KEY_BYTES} bytes")
+    } else if key.contains('\n') {
+        "blob key must not contain newlines".to_string()
+    } else {
+        return Ok(());
+    };
+
+    Err(HostFunctionError::InvalidArgument { reason })
+}
+
+fn encode_meta(meta: &BlobMeta) -> Result<Vec<u8>, HostFunctionError> {
+    serde_json::to_vec(meta).map_err(blob_error)
+}
+
+fn decode_meta(bytes: &[u8]) -> Result<BlobMeta, HostFunctionError> {
+    serde_json::from_slice(bytes).map_err(blob_error)
+}
+
+/// Hex-encode a digest.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+/// Current time in milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Error for executions without access to a bucket.
+fn denied(bucket: &str) -> HostFunctionError {
+    HostFunctionError::PermissionDenied {
+        resource: format!("blob bucket '{bucket}'"),
+    }
+}
+
+fn not_configured() -> HostFunctionError {
+    HostFunctionError::Blob("blob storage is not configured".to_string())
+}
+
+fn unknown_handle(handle: i32) -> HostFunctionError {
+    HostFunctionError::InvalidArgument {
+        reason: format!("unknown blob stream handle {handle}"),
+    }
+}
+
+/// Wrap a storage failure.
+fn blob_error(error: impl std::fmt::Display) -> HostFunctionError {
+    HostFunctionError::Blob(error.to_string())
+}
+
+/// Wrap a failure while opening the index.
+fn open_error(error: impl std::fmt::Display) -> RuntimeError {
+    RuntimeError::invalid_config(format!("Failed to open blob index: {error}"))
+}
+
+/// Run an index operation on the blocking thread pool.
+async fn blocking<T, F>(f: F) -> Result<T, HostFunctionError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, HostFunctionError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(blob_error)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(dir: &tempfile::TempDir) -> BlobStore {
+        BlobStore::open(&BlobConfig {
+            directory: Some(dir.path().to_string_lossy().into_owned()),
+            max_object_bytes: 1024,
+            ..BlobConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn ctx(store: &BlobStore, quota: u64) -> WorkerContext {
+        let mut ctx = WorkerContext::new("test".to_string());
+        ctx.extensions_mut().insert(
+            Permissions::builder()
+                .allow_blob_buckets(["files"], 100, quota)
+                .build(),
+        );
+        ctx.extensions_mut().insert(store.clone());
+        ctx
+    }
+
+    async fn put(
+        ctx: &mut WorkerContext,
+        key: &str,
+        chunks: &[&[u8]],
+    ) -> Result<BlobMeta, HostFunctionError> {
+        let handle = BlobHost::put_start(ctx, "files", key, Some("text/plain".into())).await?;
+        for chunk in chunks {
+            BlobHost::put_write(ctx, handle, chunk).await?;
+        }
+        BlobHost::put_finish(ctx, handle).await
+    }
+
+    #[tokio::test]
+    async fn test_streaming_put_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(&dir);
+        let mut ctx = ctx(&store, 1024);
+
+        let meta = put(&mut ctx, "a.txt", &[b"hello ", b"world"])
+            .await
+            .unwrap();
+        assert_eq!(meta.size, 11);
+        assert_eq!(meta.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(
+            meta.digest,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert!(
+            dir.path()
+                .join("objects/b9")
+                .join(&meta.digest[2..])
+                .exists()
+        );
+
+        let (handle, head) = BlobHost::get_open(&mut ctx, "files", "a.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(head, meta);
+        let mut data = Vec::new();
+        loop {
+            let chunk = BlobHost::read(&mut ctx, handle, 4).await.unwrap();
+            if chunk.is_empty() {
+                break;
+            }
+            data.extend(chunk);
+        }
+        assert_eq!(data, b"hello world");
+        assert!(BlobHost::close(&mut ctx, handle));
+
+        assert!(
+            BlobHost::get_open(&mut ctx, "files", "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_content_is_deduplicated() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(&dir);
+        let mut ctx = ctx(&store, 1024);
+
+        let a = put(&mut ctx, "a", &[b"same"]).await.unwrap();
+        let b = put(&mut ctx, "b", &[b"same"]).await.unwrap();
+        assert_eq!(a.digest, b.digest);
+        let path = store.object_path(&a.digest);
+
+        assert!(BlobHost::delete(&mut ctx, "files", "a").await.unwrap());
+        assert!(path.exists());
+        assert!(BlobHost::delete(&mut ctx, "files", "b").await.unwrap());
+        assert!(!path.exists());
+        assert!(!BlobHost::delete(&mut ctx, "files", "b").await.unwrap());
+        assert!(store.buckets().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(&dir);
+        let mut ctx = ctx(&store, 1024);
+
+        put(&mut ctx, "img/1", &[b"one"]).await.unwrap();
+        put(&mut ctx, "img/2", &[b"two"]).await.unwrap();
+        put(&mut ctx, "doc/1", &[b"three"]).await.unwrap();
+        put(&mut ctx, "img/2", &[b"2"]).await.unwrap();
+
+        let keys: Vec<String> = BlobHost::list(&mut ctx, "files", "img/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.key)
+            .collect();
+        assert_eq!(keys, ["img/1", "img/2"]);
+
+        let buckets = store.buckets().await.unwrap();
+        assert_eq!(
+            buckets,
+            vec![BucketUsage {
+                bucket: "files".into(),
+                objects: 3,
+                bytes: 9,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limits_and_permissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(&dir);
+        let mut ctx = ctx(&store, 10);
+
+        let err = BlobHost::head(&mut ctx, "other", "a").await.unwrap_err();
+        assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+
+        put(&mut ctx, "a", &[b"12345678"]).await.unwrap();
+        let err = put(&mut ctx, "b", &[b"123"]).await.unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+        // Replacing an object only counts the difference.
+        put(&mut ctx, "a", &[b"1234567890"]).await.unwrap();
+
+        let handle = BlobHost::put_start(&mut ctx, "files", "big", None)
+            .await
+            .unwrap();
+        let err = BlobHost::put_write(&mut ctx, handle, &[0; 2048])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::InvalidArgument { .. }));
+        assert!(!BlobHost::close(&mut ctx, handle));
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("tmp")).unwrap().count(),
+            0
+        );
+    }
+}
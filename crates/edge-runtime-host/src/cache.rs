@@ -0,0 +1,498 @@
+//! Cache API host function implementation.
+//!
+//! This module provides the host-side implementation of the cache
+//! interface, which lets guest code store computed responses and upstream
+//! fetches and serve them on later requests.
+//!
+//! Responses are:
+//!
+//! - **Keyed** by request URL and the request headers named in the
+//!   response's `Vary` header
+//! - **Shared** across all requests of the same module, and isolated
+//!   between modules
+//! - **Bounded** by a per-module size budget, evicting the least recently
+//!   used entries first
+//! - **Expired** according to the response's `Cache-Control` `s-maxage`
+//!   or `max-age` directive; responses without either are not stored
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use edge_runtime_core::store::WorkerContext;
+use tracing::debug;
+
+use crate::exchange::{GuestRequest, GuestResponse};
+
+/// Shared response cache, partitioned by module.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    modules: Arc<Mutex<HashMap<String, ModuleCache>>>,
+    max_bytes_per_module: usize,
+}
+
+/// Usage of a module's cache partition.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    /// Number of cached responses.
+    pub entries: usize,
+    /// Total size of cached responses in bytes.
+    pub bytes: usize,
+}
+
+impl ResponseCache {
+    /// Create a cache with the given per-module size budget.
+    pub fn new(max_bytes_per_module: usize) -> Self {
+        Self {
+            modules: Arc::new(Mutex::new(HashMap::new())),
+            max_bytes_per_module,
+        }
+    }
+
+    /// Look up a fresh response for `request`.
+    ///
+    /// Only `GET` and `HEAD` requests can match. The returned response has
+    /// an `age` header with the seconds since it was stored.
+    pub fn get(&self, module_id: &str, request: &GuestRequest) -> Option<GuestResponse> {
+        if !is_cacheable_method(&request.method) {
+            return None;
+        }
+        let mut modules = self.modules.lock().ok()?;
+        modules.get_mut(module_id)?.get(request)
+    }
+
+    /// Store `response` for `request`.
+    ///
+    /// Returns `false` if the response is not cacheable: the request is not
+    /// a `GET`, `Cache-Control` does not allow shared caching, `Vary` is
+    /// `*`, or the response is larger than the module's budget.
+    pub fn put(&self, module_id: &str, request: &GuestRequest, response: GuestResponse) -> bool {
+        if request.method != "GET" {
+            return false;
+        }
+        let Some(ttl) = cache_ttl(&response) else {
+            return false;
+        };
+        let Some(vary) = vary_names(&response) else {
+            return false;
+        };
+
+        let entry = CacheEntry::new(&request.uri, response, ttl);
+        if entry.size > self.max_bytes_per_module {
+            return false;
+        }
+
+        let Ok(mut modules) = self.modules.lock() else {
+            return false;
+        };
+        modules.entry(module_id.to_string()).or_default().insert(
+            request,
+            vary,
+            entry,
+            self.max_bytes_per_module,
+        );
+        true
+    }
+
+    /// Remove all cached variants of `url`.
+    ///
+    /// Returns `true` if anything was removed.
+    pub fn delete(&self, module_id: &str, url: &str) -> bool {
+        let Ok(mut modules) = self.modules.lock() else {
+            return false;
+        };
+        modules
+            .get_mut(module_id)
+            .is_some_and(|cache| cache.remove_url(url))
+    }
+
+    /// Drop a module's whole cache partition.
+    pub fn clear(&self, module_id: &str) {
+        if let Ok(mut modules) = self.modules.lock() {
+            modules.remove(module_id);
+        }
+    }
+
+    /// Get the usage of a module's cache partition.
+    pub fn usage(&self, module_id: &str) -> CacheUsage {
+        self.modules
+            .lock()
+            .ok()
+            .and_then(|modules| {
+                modules.get(module_id).map(|cache| CacheUsage {
+                    entries: cache.entries.len(),
+                    bytes: cache.bytes,
+                })
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A cached response.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    url: String,
+    response: GuestResponse,
+    stored_at: Instant,
+    expires_at: Instant,
+    size: usize,
+    /// Position in the LRU order.
+    last_used: u64,
+}
+
+impl CacheEntry {
+    fn new(url: &str, response: GuestResponse, ttl: Duration) -> Self {
+        let size = url.len()
+            + response.body.len()
+            + response
+                .headers
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>();
+        let now = Instant::now();
+
+        Self {
+            url: url.to_string(),
+            response,
+            stored_at: now,
+            expires_at: now + ttl,
+            size,
+            last_used: 0,
+        }
+    }
+}
+
+/// Header names a URL varies on, and how many variants are stored.
+#[derive(Debug)]
+struct Variants {
+    vary: Vec<String>,
+    count: usize,
+}
+
+/// One module's cache partition.
+#[derive(Debug, Default)]
+struct ModuleCache {
+    /// Entries by variant key (URL plus varying header values).
+    entries: HashMap<String, CacheEntry>,
+    /// Variant keys by last use, least recent first.
+    lru: BTreeMap<u64, String>,
+    /// Variants stored per URL.
+    urls: HashMap<String, Variants>,
+    /// Monotonic use counter.
+    tick: u64,
+    /// Total size of all entries.
+    bytes: usize,
+}
+
+impl ModuleCache {
+    fn get(&mut self, request: &GuestRequest) -> Option<GuestResponse> {
+        let variants = self.urls.get(&request.uri)?;
+        let key = variant_key(&request.uri, &variants.vary, request);
+
+        let expired = Instant::now() >= self.entries.get(&key)?.expires_at;
+        if expired {
+            self.remove(&key);
+            return None;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        let entry = self.entries.get_mut(&key)?;
+        self.lru.remove(&entry.last_used);
+        self.lru.insert(tick, key);
+        entry.last_used = tick;
+
+        let mut response = entry.response.clone();
+        response.set_header("age", &entry.stored_at.elapsed().as_secs().to_string());
+        Some(response)
+    }
+
+    fn insert(
+        &mut self,
+        request: &GuestRequest,
+        vary: Vec<String>,
+        mut entry: CacheEntry,
+        max_bytes: usize,
+    ) {
+        // A changed Vary invalidates variants stored under the old one.
+        if self
+            .urls
+            .get(&request.uri)
+            .is_some_and(|variants| variants.vary != vary)
+        {
+            self.remove_url(&request.uri);
+        }
+        let key = variant_key(&request.uri, &vary, request);
+        self.remove(&key);
+
+        while self.bytes + entry.size > max_bytes {
+            let Some(oldest) = self.lru.values().next().cloned() else {
+                break;
+            };
+            debug!(key = %oldest, "Evicting cached response");
+            self.remove(&oldest);
+        }
+
+        self.tick += 1;
+        entry.last_used = self.tick;
+        self.bytes += entry.size;
+        self.lru.insert(self.tick, key.clone());
+        self.urls
+            .entry(request.uri.clone())
+            .or_insert(Variants { vary, count: 0 })
+            .count += 1;
+        self.entries.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        let Some(entry) = self.entries.remove(key) else {
+            return false;
+        };
+        self.lru.remove(&entry.last_used);
+        self.bytes -= entry.size;
+
+        if let Some(variants) = self.urls.get_mut(&entry.url) {
+            variants.count -= 1;
+            if variants.count == 0 {
+                self.urls.remove(&entry.url);
+            }
+        }
+        true
+    }
+
+    fn remove_url(&mut self, url: &str) -> bool {
+        let keys: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.url == url)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut removed = false;
+        for key in &keys {
+            removed |= self.remove(key);
+        }
+        removed
+    }
+}
+
+/// Returns `true` if responses to `method` can be served from the cache.
+fn is_cacheable_method(method: &str) -> bool {
+    method == "GET" || method == "HEAD"
+}
+
+/// Build the key of a cache variant from the URL and varying headers.
+fn variant_key(url: &str, vary: &[String], request: &GuestRequest) -> String {
+    let mut key = format!("{url}\n");
+    for name in vary {
+        key.push_str(name);
+        key.push('=');
+        key.push_str(request.header(name).unwrap_or_default());
+        key.push('\n');
+    }
+    key
+}
+
+/// Lowercased header names listed in `Vary`, or `None` for `Vary: *`.
+fn vary_names(response: &GuestResponse) -> Option<Vec<String>> {
+    let mut names: Vec<String> = response
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+        .flat_map(|(_, value)| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if names.iter().any(|name| name == "*") {
+        return None;
+    }
+    names.sort();
+    names.dedup();
+    Some(names)
+}
+
+/// Freshness lifetime from `Cache-Control`, if the response may be stored
+/// in a shared cache.
+///
+/// `s-maxage` takes precedence over `max-age`. `no-store`, `no-cache` and
+/// `private` prevent caching, as does a lifetime of zero.
+pub fn cache_ttl(response: &GuestResponse) -> Option<Duration> {
+    let mut max_age = None;
+    let mut s_maxage = None;
+
+    let directives = response
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("cache-control"))
+        .flat_map(|(_, value)| value.split(','));
+
+    for directive in directives {
+        let directive = directive.trim().to_ascii_lowercase();
+        match directive.split_once('=') {
+            Some(("max-age", secs)) => max_age = secs.trim_matches('"').parse::<u64>().ok(),
+            Some(("s-maxage", secs)) => s_maxage = secs.trim_matches('"').parse::<u64>().ok(),
+            None if matches!(directive.as_str(), "no-store" | "no-cache" | "private") => {
+                return None;
+            }
+            _ => {}
+        }
+    }
+
+    s_maxage
+        .or(max_age)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// Host implementation for the cache interface.
+///
+/// Operations use the cache partition of the executing module and count
+/// towards [`ExecutionMetrics::cache_hits`] and
+/// [`ExecutionMetrics::cache_misses`].
+///
+/// [`ExecutionMetrics::cache_hits`]: edge_runtime_core::ExecutionMetrics::cache_hits
+/// [`ExecutionMetrics::cache_misses`]: edge_runtime_core::ExecutionMetrics::cache_misses
+pub struct CacheHost;
+
+impl CacheHost {
+    /// Look up a cached response for `request`.
+    pub fn lookup(ctx: &mut WorkerContext, request: &GuestRequest) -> Option<GuestResponse> {
+        let response = Self::partition(ctx).and_then(|(cache, module)| cache.get(&module, request));
+
+        if response.is_some() {
+            ctx.metrics.cache_hits += 1;
+        } else {
+            ctx.metrics.cache_misses += 1;
+        }
+        debug!(
+            request_id = %ctx.request_id,
+            url = %request.uri,
+            hit = response.is_some(),
+            "Cache lookup"
+        );
+
+        response
+    }
+
+    /// Store `response` for `request`, returning `true` if it was cached.
+    pub fn store(ctx: &WorkerContext, request: &GuestRequest, response: GuestResponse) -> bool {
+        Self::partition(ctx).is_some_and(|(cache, module)| cache.put(&module, request, response))
+    }
+
+    /// Remove all cached variants of `url`.
+    pub fn delete(ctx: &WorkerContext, url: &str) -> bool {
+        Self::partition(ctx).is_some_and(|(cache, module)| cache.delete(&module, url))
+    }
+
+    /// The attached cache and the executing module's ID.
+    fn partition(ctx: &WorkerContext) -> Option<(ResponseCache, String)> {
+        let cache = ctx.extensions().get::<ResponseCache>()?.clone();
+        Some((cache, ctx.module_id.clone()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(cache_control: &str, body: &str) -> GuestResponse {
+        GuestResponse {
+            status: 200,
+            headers: vec![("cache-control".into(), cache_control.into())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn request(uri: &str) -> GuestRequest {
+        GuestRequest::new("GET", uri)
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let ttl = |cc| cache_ttl(&response(cc, ""));
+
+        assert_eq!(ttl("max-age=60"), Some(Duration::from_secs(60)));
+        assert_eq!(
+            ttl("max-age=60, s-maxage=10"),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(ttl("public, max-age=0"), None);
+        assert_eq!(ttl("private, max-age=60"), None);
+        assert_eq!(ttl("no-store"), None);
+        assert_eq!(ttl(""), None);
+    }
+
+    #[test]
+    fn test_put_and_match() {
+        let cache = ResponseCache::new(1024);
+        assert!(cache.put("app", &request("/a"), response("max-age=60", "hello")));
+
+        let hit = cache.get("app", &request("/a")).unwrap();
+        assert_eq!(hit.body, b"hello");
+        assert_eq!(hit.header("age"), Some("0"));
+
+        assert!(cache.get("app", &request("/b")).is_none());
+        assert!(cache.get("other", &request("/a")).is_none());
+        assert!(cache.get("app", &GuestRequest::new("POST", "/a")).is_none());
+
+        assert!(cache.delete("app", "/a"));
+        assert!(cache.get("app", &request("/a")).is_none());
+    }
+
+    #[test]
+    fn test_uncacheable_not_stored() {
+        let cache = ResponseCache::new(1024);
+
+        assert!(!cache.put("app", &request("/a"), response("no-store", "x")));
+        assert!(!cache.put(
+            "app",
+            &GuestRequest::new("POST", "/a"),
+            response("max-age=60", "x")
+        ));
+
+        let mut vary_all = response("max-age=60", "x");
+        vary_all.set_header("vary", "*");
+        assert!(!cache.put("app", &request("/a"), vary_all));
+        assert_eq!(cache.usage("app"), CacheUsage::default());
+    }
+
+    #[test]
+    fn test_vary() {
+        let cache = ResponseCache::new(1024);
+        let mut en = request("/a");
+        en.set_header("Accept-Language", "en");
+        let mut de = request("/a");
+        de.set_header("Accept-Language", "de");
+
+        let mut english = response("max-age=60", "hello");
+        english.set_header("Vary", "Accept-Language");
+        assert!(cache.put("app", &en, english));
+
+        assert_eq!(cache.get("app", &en).unwrap().body, b"hello");
+        assert!(cache.get("app", &de).is_none());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        // Each entry is 55 bytes, so three fit
+        let cache = ResponseCache::new(200);
+        let body = "x".repeat(30);
+
+        for uri in ["/1", "/2", "/3"] {
+            assert!(cache.put("app", &request(uri), response("max-age=60", &body)));
+        }
+        // Touch /1 so that /2 is the least recently used
+        assert!(cache.get("app", &request("/1")).is_some());
+        assert!(cache.put("app", &request("/4"), response("max-age=60", &body)));
+
+        assert!(cache.get("app", &request("/1")).is_some());
+        assert!(cache.get("app", &request("/2")).is_none());
+        assert!(cache.get("app", &request("/4")).is_some());
+        assert_eq!(cache.usage("app").entries, 3);
+
+        let too_large = "x".repeat(300);
+        assert!(!cache.put("app", &request("/big"), response("max-age=60", &too_large)));
+    }
+}
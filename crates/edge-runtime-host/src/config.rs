@@ -0,0 +1,460 @@
+//! Configuration and secrets host function implementation.
+//!
+//! This module provides the host-side implementation of the config
+//! interface, which lets guest code read settings at request time instead
+//! of compiling them into the module.
+//!
+//! - **Values** come from the module's `config` table in the configuration
+//!   file and can be replaced through the Admin API
+//! - **Secrets** come from an encrypted secrets file that can be reloaded
+//!   while the server runs; their values are never logged or returned by
+//!   the Admin API
+//!
+//! Both are looked up on every call, so updates apply to the next request
+//! without reloading the module.
+//!
+//! # Secrets File
+//!
+//! The plaintext is a TOML document with a table of secrets per module ID:
+//!
+//! ```toml
+//! [api]
+//! DATABASE_PASSWORD = "hunter2"
+//! ```
+//!
+//! It is stored encrypted with AES-256-GCM under a 32-byte [`SecretsKey`].
+//! Use [`encrypt_secrets`] to produce the file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use edge_runtime_common::HostFunctionError;
+use edge_runtime_core::store::WorkerContext;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use tracing::debug;
+
+/// Leading bytes of an encrypted secrets file, also authenticated with it.
+const SECRETS_MAGIC: &[u8; 8] = b"EDGESEC1";
+
+/// Length of a [`SecretsKey`] in bytes.
+pub const SECRETS_KEY_LEN: usize = 32;
+
+/// Per-module configuration values and secrets.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime. Clones
+/// share the same data, so updates are seen by all executions.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigStore {
+    values: Arc<RwLock<HashMap<String, BTreeMap<String, String>>>>,
+    secrets: Arc<RwLock<HashMap<String, BTreeMap<String, Secret>>>>,
+    source: Option<Arc<SecretsSource>>,
+}
+
+impl ConfigStore {
+    /// Create a store without values or secrets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store whose secrets are loaded from an encrypted file.
+    ///
+    /// The file is read again by [`reload_secrets`](Self::reload_secrets).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or decrypted.
+    pub fn with_secrets_file(
+        path: impl Into<PathBuf>,
+        key: SecretsKey,
+    ) -> Result<Self, HostFunctionError> {
+        let store = Self {
+            source: Some(Arc::new(SecretsSource {
+                path: path.into(),
+                key,
+            })),
+            ..Self::default()
+        };
+        store.reload_secrets()?;
+        Ok(store)
+    }
+
+    /// Check whether secrets are loaded from a file.
+    pub fn has_secrets_file(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Read and decrypt the secrets file again, replacing all secrets.
+    ///
+    /// Returns the number of secrets loaded. The current secrets are kept
+    /// if the file cannot be loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no secrets file is configured, or it cannot be
+    /// read or decrypted.
+    pub fn reload_secrets(&self) -> Result<usize, HostFunctionError> {
+        let source = self
+            .source
+            .as_ref()
+            .ok_or_else(|| config_error("no secrets file configured"))?;
+        let data = std::fs::read(&source.path).map_err(|e| {
+            config_error(format!(
+                "failed to read secrets file '{}': {e}",
+                source.path.display()
+            ))
+        })?;
+        let modules = decrypt_secrets(&source.key, &data)?;
+
+        let count = modules.values().map(BTreeMap::len).sum();
+        let secrets = modules
+            .into_iter()
+            .map(|(module, secrets)| {
+                let secrets = secrets.into_iter().map(|(k, v)| (k, Secret(v))).collect();
+                (module, secrets)
+            })
+            .collect();
+        *self.secrets.write().map_err(|_| poisoned())? = secrets;
+
+        debug!(path = %source.path.display(), secrets = count, "Secrets loaded");
+        Ok(count)
+    }
+
+    /// Get a configuration value of a module.
+    pub fn get(&self, module_id: &str, key: &str) -> Option<String> {
+        let values = self.values.read().ok()?;
+        values.get(module_id)?.get(key).cloned()
+    }
+
+    /// Get a secret of a module.
+    pub fn get_secret(&self, module_id: &str, key: &str) -> Option<String> {
+        let secrets = self.secrets.read().ok()?;
+        secrets.get(module_id)?.get(key).map(|s| s.0.clone())
+    }
+
+    /// Get all configuration values of a module.
+    pub fn values(&self, module_id: &str) -> BTreeMap<String, String> {
+        self.values
+            .read()
+            .ok()
+            .and_then(|values| values.get(module_id).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Replace all configuration values of a module.
+    pub fn set_values(&self, module_id: &str, values: BTreeMap<String, String>) {
+        if let Ok(mut all) = self.values.write() {
+            if values.is_empty() {
+                all.remove(module_id);
+            } else {
+                all.insert(module_id.to_string(), values);
+            }
+        }
+    }
+
+    /// Get the names of a module's secrets, without their values.
+    pub fn secret_keys(&self, module_id: &str) -> Vec<String> {
+        self.secrets
+            .read()
+            .ok()
+            .and_then(|secrets| secrets.get(module_id).map(|s| s.keys().cloned().collect()))
+            .unwrap_or_default()
+    }
+}
+
+/// Where secrets are loaded from.
+#[derive(Debug)]
+struct SecretsSource {
+    path: PathBuf,
+    key: SecretsKey,
+}
+
+/// A secret value that is never printed.
+#[derive(Clone)]
+struct Secret(String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+/// AES-256-GCM key of the secrets file.
+#[derive(Clone)]
+pub struct SecretsKey([u8; SECRETS_KEY_LEN]);
+
+impl SecretsKey {
+    /// Parse a key from 64 hex digits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `hex` is not exactly 32 hex-encoded bytes.
+    pub fn from_hex(hex: &str) -> Result<Self, HostFunctionError> {
+        let hex = hex.trim();
+        let invalid = || {
+            config_error(format!(
+                "secrets key must be {} hex digits",
+                SECRETS_KEY_LEN * 2
+            ))
+        };
+        if hex.len() != SECRETS_KEY_LEN * 2 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut key = [0; SECRETS_KEY_LEN];
+        for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(key))
+    }
+
+    /// Generate a random key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the system random number generator fails.
+    pub fn generate() -> Result<Self, HostFunctionError> {
+        let mut key = [0; SECRETS_KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| config_error("failed to generate secrets key"))?;
+        Ok(Self(key))
+    }
+
+    /// Encode the key as hex digits.
+    pub fn to_hex(&self) -> String {
+        use fmt::Write;
+
+        self.0.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+    }
+
+    fn aead_key(&self) -> Result<LessSafeKey, HostFunctionError> {
+        UnboundKey::new(&AES_256_GCM, &self.0)
+            .map(LessSafeKey::new)
+            .map_err(|_| config_error("invalid secrets key"))
+    }
+}
+
+impl fmt::Debug for SecretsKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretsKey(<redacted>)")
+    }
+}
+
+/// Encrypt a plaintext secrets document into the secrets file format.
+///
+/// # Errors
+///
+/// Returns an error if `plaintext` is not a valid secrets document or
+/// encryption fails.
+pub fn encrypt_secrets(key: &SecretsKey, plaintext: &str) -> Result<Vec<u8>, HostFunctionError> {
+    parse_secrets(plaintext)?;
+
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| config_error("failed to generate nonce"))?;
+
+    let mut sealed = plaintext.as_bytes().to_vec();
+    key.aead_key()?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(SECRETS_MAGIC),
+            &mut sealed,
+        )
+        .map_err(|_| config_error("failed to encrypt secrets"))?;
+
+    let mut data = Vec::with_capacity(SECRETS_MAGIC.len() + NONCE_LEN + sealed.len());
+    data.extend_from_slice(SECRETS_MAGIC);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+/// Decrypt a secrets file into secrets by module ID.
+///
+/// # Errors
+///
+/// Returns an error if the data is not a secrets file, was encrypted with
+/// a different key, or was modified.
+pub fn decrypt_secrets(
+    key: &SecretsKey,
+    data: &[u8],
+) -> Result<HashMap<String, BTreeMap<String, String>>, HostFunctionError> {
+    let Some(rest) = data.strip_prefix(SECRETS_MAGIC) else {
+        return Err(config_error("not an encrypted secrets file"));
+    };
+    if rest.len() < NONCE_LEN {
+        return Err(config_error("truncated secrets file"));
+    }
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| config_error("truncated secrets file"))?;
+    let mut sealed = sealed.to_vec();
+    let plaintext = key
+        .aead_key()?
+        .open_in_place(nonce, Aad::from(SECRETS_MAGIC), &mut sealed)
+        .map_err(|_| config_error("failed to decrypt secrets file (wrong key?)"))?;
+    let plaintext = std::str::from_utf8(plaintext)
+        .map_err(|_| config_error("secrets file is not valid UTF-8"))?;
+
+    parse_secrets(plaintext)
+}
+
+fn parse_secrets(
+    plaintext: &str,
+) -> Result<HashMap<String, BTreeMap<String, String>>, HostFunctionError> {
+    // Parse errors quote the offending line, which may hold a secret.
+    toml::from_str(plaintext)
+        .map_err(|_| config_error("secrets must be a TOML table of string values per module ID"))
+}
+
+/// Configuration host functions.
+pub struct ConfigHost;
+
+impl ConfigHost {
+    /// Get a configuration value of the executing module.
+    pub fn get(ctx: &WorkerContext, key: &str) -> Option<String> {
+        let value = Self::partition(ctx).and_then(|(store, module)| store.get(&module, key));
+        debug!(
+            request_id = %ctx.request_id,
+            key = key,
+            found = value.is_some(),
+            "Config get"
+        );
+        value
+    }
+
+    /// Get a secret of the executing module.
+    ///
+    /// Only the key is logged, never the value.
+    pub fn get_secret(ctx: &WorkerContext, key: &str) -> Option<String> {
+        let value = Self::partition(ctx).and_then(|(store, module)| store.get_secret(&module, key));
+        debug!(
+            request_id = %ctx.request_id,
+            key = key,
+            found = value.is_some(),
+            "Secret get"
+        );
+        value
+    }
+
+    /// The attached store and the executing module's ID.
+    fn partition(ctx: &WorkerContext) -> Option<(ConfigStore, String)> {
+        let store = ctx.extensions().get::<ConfigStore>()?.clone();
+        Some((store, ctx.module_id.clone()?))
+    }
+}
+
+fn config_error(message: impl Into<String>) -> HostFunctionError {
+    HostFunctionError::Config(message.into())
+}
+
+fn poisoned() -> HostFunctionError {
+    config_error("secrets lock poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &str = r#"
+        [api]
+        DATABASE_PASSWORD = "hunter2"
+        TOKEN = "abc"
+
+        [worker]
+        TOKEN = "xyz"
+    "#;
+
+    #[test]
+    fn test_secrets_key_hex() {
+        let key = SecretsKey::generate().unwrap();
+        let parsed = SecretsKey::from_hex(&key.to_hex()).unwrap();
+        assert_eq!(parsed.0, key.0);
+
+        assert!(SecretsKey::from_hex("abcd").is_err());
+        assert!(SecretsKey::from_hex(&"zz".repeat(SECRETS_KEY_LEN)).is_err());
+        assert_eq!(format!("{key:?}"), "SecretsKey(<redacted>)");
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_secrets() {
+        let key = SecretsKey::generate().unwrap();
+        let data = encrypt_secrets(&key, PLAINTEXT).unwrap();
+        assert!(data.starts_with(SECRETS_MAGIC));
+        assert!(!String::from_utf8_lossy(&data).contains("hunter2"));
+
+        let secrets = decrypt_secrets(&key, &data).unwrap();
+        assert_eq!(secrets["api"]["DATABASE_PASSWORD"], "hunter2");
+        assert_eq!(secrets["worker"]["TOKEN"], "xyz");
+
+        let other = SecretsKey::generate().unwrap();
+        assert!(decrypt_secrets(&other, &data).is_err());
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt_secrets(&key, &tampered).is_err());
+        assert!(decrypt_secrets(&key, b"plain").is_err());
+    }
+
+    #[test]
+    fn test_invalid_secrets_document() {
+        let key = SecretsKey::generate().unwrap();
+        let err = encrypt_secrets(&key, "[api]\nTOKEN = 5\n").unwrap_err();
+        assert!(matches!(err, HostFunctionError::Config(_)));
+    }
+
+    #[test]
+    fn test_store_values() {
+        let store = ConfigStore::new();
+        store.set_values(
+            "api",
+            BTreeMap::from([("region".to_string(), "eu".to_string())]),
+        );
+
+        assert_eq!(store.get("api", "region").as_deref(), Some("eu"));
+        assert!(store.get("api", "missing").is_none());
+        assert!(store.get("other", "region").is_none());
+
+        store.set_values("api", BTreeMap::new());
+        assert!(store.values("api").is_empty());
+    }
+
+    #[test]
+    fn test_store_secrets_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.enc");
+        let key = SecretsKey::generate().unwrap();
+        std::fs::write(&path, encrypt_secrets(&key, PLAINTEXT).unwrap()).unwrap();
+
+        let store = ConfigStore::with_secrets_file(&path, key.clone()).unwrap();
+        assert_eq!(
+            store.get_secret("api", "DATABASE_PASSWORD").as_deref(),
+            Some("hunter2")
+        );
+        assert!(store.get_secret("worker", "DATABASE_PASSWORD").is_none());
+        assert_eq!(store.secret_keys("api"), ["DATABASE_PASSWORD", "TOKEN"]);
+        assert!(!format!("{store:?}").contains("hunter2"));
+
+        std::fs::write(
+            &path,
+            encrypt_secrets(&key, "[api]\nTOKEN = \"rotated\"\n").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(store.reload_secrets().unwrap(), 1);
+        assert_eq!(store.get_secret("api", "TOKEN").as_deref(), Some("rotated"));
+        assert!(store.get_secret("api", "DATABASE_PASSWORD").is_none());
+
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(store.reload_secrets().is_err());
+        assert_eq!(store.get_secret("api", "TOKEN").as_deref(), Some("rotated"));
+    }
+}
@@ -0,0 +1,447 @@
+//! Request/response exchange between the runtime and guest code.
+//!
+//! This module provides the host-side state that lets guest code read the
+//! request that triggered its execution and build the response returned to
+//! the caller. The runtime attaches an [`Exchange`] to each execution's
+//! [`WorkerContext`] extensions before calling the entry point.
+//!
+//! When modules are chained into a pipeline, each stage may also modify the
+//! request passed to the next stage, edit the response produced by an
+//! earlier stage, or end the pipeline early by sending its response.
+
+use edge_runtime_core::store::WorkerContext;
+use tracing::warn;
+
+/// Maximum size of a response body written by guest code, in bytes.
+pub const MAX_RESPONSE_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Request passed to guest code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuestRequest {
+    /// HTTP method (GET, POST, etc.)
+    pub method: String,
+    /// Request URI.
+    pub uri: String,
+    /// Request headers.
+    pub headers: Vec<(String, String)>,
+    /// Request body.
+    pub body: Vec<u8>,
+}
+
+impl GuestRequest {
+    /// Create a new request without headers or body.
+    pub fn new(method: &str, uri: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Get a header value by name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Set a header, replacing any existing values (case-insensitive).
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        replace_header(&mut self.headers, name, value);
+    }
+}
+
+/// Response produced by guest code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// Response headers.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+impl GuestResponse {
+    /// Get a header value by name (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Set a header, replacing any existing values (case-insensitive).
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        replace_header(&mut self.headers, name, value);
+    }
+}
+
+impl Default for GuestResponse {
+    fn default() -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+/// Per-execution request/response state.
+#[derive(Debug, Clone, Default)]
+pub struct Exchange {
+    /// The incoming request.
+    request: GuestRequest,
+    /// The response being built by guest code.
+    response: GuestResponse,
+    /// Whether a response exists (written by the guest or passed in).
+    written: bool,
+    /// Whether the guest sent its response, ending a pipeline early.
+    sent: bool,
+}
+
+impl Exchange {
+    /// Create an exchange for the given request.
+    pub fn new(request: GuestRequest) -> Self {
+        Self {
+            request,
+            response: GuestResponse::default(),
+            written: false,
+            sent: false,
+        }
+    }
+
+    /// Create an exchange that already carries a response.
+    ///
+    /// Used by pipelines to hand the response of an earlier stage to the
+    /// next one. The response is returned unchanged unless the guest
+    /// modifies it.
+    pub fn with_response(request: GuestRequest, response: GuestResponse) -> Self {
+        Self {
+            request,
+            response,
+            written: true,
+            sent: false,
+        }
+    }
+
+    /// Get the incoming request.
+    pub fn request(&self) -> &GuestRequest {
+        &self.request
+    }
+
+    /// Consume the exchange and return the (possibly modified) request.
+    pub fn into_request(self) -> GuestRequest {
+        self.request
+    }
+
+    /// Returns `true` if the guest sent its response.
+    pub fn is_sent(&self) -> bool {
+        self.sent
+    }
+
+    /// Get the response built so far.
+    pub fn response(&self) -> &GuestResponse {
+        &self.response
+    }
+
+    /// Take the response if guest code produced one.
+    ///
+    /// Returns `None` if the guest never set a status, header or body and
+    /// no response was passed in.
+    pub fn take_response(&mut self) -> Option<GuestResponse> {
+        if !self.written {
+            return None;
+        }
+        self.written = false;
+        Some(std::mem::take(&mut self.response))
+    }
+}
+
+/// Host implementation for the request/response exchange.
+pub struct ExchangeHost;
+
+impl ExchangeHost {
+    /// Get the incoming request for the current execution.
+    pub fn request(ctx: &WorkerContext) -> Option<&GuestRequest> {
+        ctx.extensions().get::<Exchange>().map(Exchange::request)
+    }
+
+    /// Get the response built so far, if one exists.
+    pub fn response(ctx: &WorkerContext) -> Option<&GuestResponse> {
+        ctx.extensions()
+            .get::<Exchange>()
+            .filter(|exchange| exchange.written)
+            .map(Exchange::response)
+    }
+
+    /// Replace the whole response, e.g. with one served from a cache.
+    pub fn set_response(ctx: &mut WorkerContext, response: GuestResponse) -> bool {
+        Self::with_exchange(ctx, |exchange| {
+            exchange.response = response;
+            exchange.written = true;
+        })
+    }
+
+    /// Replace the request URI passed on to the next pipeline stage.
+    pub fn set_request_uri(ctx: &mut WorkerContext, uri: &str) -> bool {
+        Self::with_exchange(ctx, |exchange| exchange.request.uri = uri.to_string())
+    }
+
+    /// Set a request header passed on to the next pipeline stage.
+    pub fn set_request_header(ctx: &mut WorkerContext, name: &str, value: &str) -> bool {
+        if name.is_empty() {
+            return false;
+        }
+        Self::with_exchange(ctx, |exchange| exchange.request.set_header(name, value))
+    }
+
+    /// Replace the request body passed on to the next pipeline stage.
+    pub fn set_request_body(ctx: &mut WorkerContext, body: Vec<u8>) -> bool {
+        Self::with_exchange(ctx, |exchange| exchange.request.body = body)
+    }
+
+    /// Set the response status code.
+    ///
+    /// Returns `false` if the status is outside `100..=999` or no exchange
+    /// is attached to the execution.
+    pub fn set_status(ctx: &mut WorkerContext, status: u16) -> bool {
+        if !(100..=999).contains(&status) {
+            warn!(request_id = %ctx.request_id, status, "Rejected invalid response status");
+            return false;
+        }
+
+        let Some(exchange) = ctx.extensions_mut().get_mut::<Exchange>() else {
+            return false;
+        };
+        exchange.response.status = status;
+        exchange.written = true;
+        true
+    }
+
+    /// Set a response header, replacing any existing values.
+    ///
+    /// Returns `false` if the header name is empty or no exchange is
+    /// attached to the execution.
+    pub fn set_header(ctx: &mut WorkerContext, name: &str, value: &str) -> bool {
+        if name.is_empty() {
+            return false;
+        }
+
+        Self::with_exchange(ctx, |exchange| {
+            exchange.response.set_header(name, value);
+            exchange.written = true;
+        })
+    }
+
+    /// Append bytes to the response body.
+    ///
+    /// Returns `false` if the body would exceed [`MAX_RESPONSE_BODY_BYTES`]
+    /// or no exchange is attached to the execution.
+    pub fn write_body(ctx: &mut WorkerContext, bytes: &[u8]) -> bool {
+        let request_id = ctx.request_id.clone();
+        let Some(exchange) = ctx.extensions_mut().get_mut::<Exchange>() else {
+            return false;
+        };
+
+        if exchange.response.body.len() + bytes.len() > MAX_RESPONSE_BODY_BYTES {
+            warn!(
+                request_id = %request_id,
+                limit = MAX_RESPONSE_BODY_BYTES,
+                "Response body size limit exceeded"
+            );
+            return false;
+        }
+
+        exchange.response.body.extend_from_slice(bytes);
+        exchange.written = true;
+        true
+    }
+
+    /// Replace the response body.
+    ///
+    /// Returns `false` if the body exceeds [`MAX_RESPONSE_BODY_BYTES`] or
+    /// no exchange is attached to the execution.
+    pub fn set_body(ctx: &mut WorkerContext, body: Vec<u8>) -> bool {
+        if body.len() > MAX_RESPONSE_BODY_BYTES {
+            warn!(
+                request_id = %ctx.request_id,
+                limit = MAX_RESPONSE_BODY_BYTES,
+                "Response body size limit exceeded"
+            );
+            return false;
+        }
+
+        Self::with_exchange(ctx, |exchange| {
+            exchange.response.body = body;
+            exchange.written = true;
+        })
+    }
+
+    /// Send the response, ending a pipeline after the current stage.
+    pub fn send(ctx: &mut WorkerContext) -> bool {
+        Self::with_exchange(ctx, |exchange| {
+            exchange.written = true;
+            exchange.sent = true;
+        })
+    }
+
+    /// Apply `f` to the attached exchange, returning `false` if none exists.
+    fn with_exchange(ctx: &mut WorkerContext, f: impl FnOnce(&mut Exchange)) -> bool {
+        match ctx.extensions_mut().get_mut::<Exchange>() {
+            Some(exchange) => {
+                f(exchange);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Parse a block of `name: value` header lines separated by `\n`.
+///
+/// Blank lines and lines without a colon are ignored; names and values are
+/// trimmed.
+pub fn parse_header_block(block: &str) -> Vec<(String, String)> {
+    block
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// Replace all values of a header with a single value (case-insensitive).
+fn replace_header(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    headers.push((name.to_string(), value.to_string()));
+}
+
+/// Find a header value by name (case-insensitive).
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context_with_exchange() -> WorkerContext {
+        let mut ctx = WorkerContext::new("test".into());
+        let mut request = GuestRequest::new("POST", "/orders");
+        request
+            .headers
+            .push(("Content-Type".into(), "application/json".into()));
+        ctx.extensions_mut().insert(Exchange::new(request));
+        ctx
+    }
+
+    #[test]
+    fn test_request_header_case_insensitive() {
+        let ctx = context_with_exchange();
+        let request = ExchangeHost::request(&ctx).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert!(request.header("x-missing").is_none());
+    }
+
+    #[test]
+    fn test_response_untouched() {
+        let mut ctx = context_with_exchange();
+        let exchange = ctx.extensions_mut().get_mut::<Exchange>().unwrap();
+
+        assert!(exchange.take_response().is_none());
+    }
+
+    #[test]
+    fn test_build_response() {
+        let mut ctx = context_with_exchange();
+
+        assert!(ExchangeHost::set_status(&mut ctx, 201));
+        assert!(ExchangeHost::set_header(&mut ctx, "X-Id", "42"));
+        assert!(ExchangeHost::write_body(&mut ctx, b"cre"));
+        assert!(ExchangeHost::write_body(&mut ctx, b"ated"));
+
+        let response = ctx
+            .extensions_mut()
+            .get_mut::<Exchange>()
+            .unwrap()
+            .take_response()
+            .unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.header("x-id"), Some("42"));
+        assert_eq!(response.body, b"created");
+    }
+
+    #[test]
+    fn test_set_header_replaces() {
+        let mut ctx = context_with_exchange();
+
+        assert!(ExchangeHost::set_header(&mut ctx, "X-Id", "1"));
+        assert!(ExchangeHost::set_header(&mut ctx, "x-id", "2"));
+
+        let response = ExchangeHost::response(&ctx).unwrap();
+        assert_eq!(response.headers.len(), 1);
+        assert_eq!(response.header("X-Id"), Some("2"));
+    }
+
+    #[test]
+    fn test_modify_request_and_send() {
+        let mut ctx = context_with_exchange();
+
+        assert!(ExchangeHost::set_request_uri(&mut ctx, "/v2/orders"));
+        assert!(ExchangeHost::set_request_header(
+            &mut ctx,
+            "content-type",
+            "text/plain"
+        ));
+        assert!(ExchangeHost::set_request_body(&mut ctx, b"hi".to_vec()));
+        assert!(ExchangeHost::send(&mut ctx));
+
+        let exchange = ctx.extensions_mut().remove::<Exchange>().unwrap();
+        assert!(exchange.is_sent());
+
+        let request = exchange.into_request();
+        assert_eq!(request.uri, "/v2/orders");
+        assert_eq!(request.header("Content-Type"), Some("text/plain"));
+        assert_eq!(request.body, b"hi");
+    }
+
+    #[test]
+    fn test_with_response_passes_through() {
+        let response = GuestResponse {
+            status: 404,
+            ..GuestResponse::default()
+        };
+        let mut exchange = Exchange::with_response(GuestRequest::default(), response.clone());
+
+        assert_eq!(exchange.take_response(), Some(response));
+    }
+
+    #[test]
+    fn test_invalid_status() {
+        let mut ctx = context_with_exchange();
+        assert!(!ExchangeHost::set_status(&mut ctx, 42));
+    }
+
+    #[test]
+    fn test_parse_header_block() {
+        let headers = parse_header_block("Accept: text/plain\n\nX-Id:42\nbogus\n");
+        assert_eq!(
+            headers,
+            vec![
+                ("Accept".to_string(), "text/plain".to_string()),
+                ("X-Id".to_string(), "42".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_without_exchange() {
+        let mut ctx = WorkerContext::new("test".into());
+
+        assert!(ExchangeHost::request(&ctx).is_none());
+        assert!(!ExchangeHost::set_status(&mut ctx, 200));
+        assert!(!ExchangeHost::write_body(&mut ctx, b"x"));
+    }
+}
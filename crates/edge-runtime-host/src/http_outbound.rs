@@ -3,6 +3,9 @@
 //! This module provides the host-side implementation of the HTTP outbound
 //! interface, allowing guest components to make HTTP requests to external
 //! services with security controls.
+//!
+//! Requests carry the trace context of the calling span in `traceparent`
+//! and `tracestate` headers, unless the guest set its own `traceparent`.
 
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
@@ -11,6 +14,7 @@ use reqwest::Client;
 use tracing::{debug, info, warn};
 
 use crate::Permissions;
+use crate::otel::{self, TRACEPARENT, TRACESTATE};
 use edge_runtime_common::{HostFunctionError, RuntimeError};
 
 /// HTTP outbound host implementation.
@@ -204,6 +208,9 @@ impl HttpOutboundHost {
         for (key, value) in &request.headers {
             req_builder = req_builder.header(key, value);
         }
+        for (key, value) in trace_headers(&request.headers) {
+            req_builder = req_builder.header(key, value);
+        }
 
         // Add body
         if let Some(body) = request.body {
@@ -303,6 +310,25 @@ impl From<HttpError> for RuntimeError {
     }
 }
 
+/// Trace context headers for an outbound request, empty if the guest set
+/// its own `traceparent` or no trace is active.
+fn trace_headers(headers: &[(String, String)]) -> Vec<(&'static str, String)> {
+    if headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(TRACEPARENT))
+    {
+        return Vec::new();
+    }
+    let Some(context) = otel::current_trace_context() else {
+        return Vec::new();
+    };
+    let mut headers = vec![(TRACEPARENT, context.traceparent())];
+    if let Some(state) = context.tracestate {
+        headers.push((TRACESTATE, state));
+    }
+    headers
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,6 +378,35 @@ mod tests {
         assert!(matches!(result, Err(HttpError::RateLimited)));
     }
 
+    #[test]
+    fn test_trace_headers() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        assert!(trace_headers(&[]).is_empty());
+
+        let subscriber = tracing_subscriber::registry().with(crate::OtelLayer::new());
+        tracing::subscriber::with_default(subscriber, || {
+            let remote = crate::TraceContext::parse(
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                Some("vendor=abc"),
+            )
+            .unwrap();
+            let span = tracing::info_span!("handle_function");
+            otel::set_parent(&span, &remote);
+            let _entered = span.enter();
+
+            let headers = trace_headers(&[("Accept".to_string(), "*/*".to_string())]);
+            assert_eq!(headers.len(), 2);
+            let sent = crate::TraceContext::parse(&headers[0].1, Some(&headers[1].1)).unwrap();
+            assert_eq!(sent.trace_id, remote.trace_id);
+            assert_ne!(sent.span_id, remote.span_id);
+            assert_eq!(headers[1], (TRACESTATE, "vendor=abc".to_string()));
+
+            let own = [("TraceParent".to_string(), remote.traceparent())];
+            assert!(trace_headers(&own).is_empty());
+        });
+    }
+
     #[tokio::test]
     async fn test_permission_denied() {
         let perms = Permissions::builder()
@@ -0,0 +1,962 @@
+//! Key-value store host function implementation.
+//!
+//! This module provides the host-side implementation of the KV interface,
+//! which gives guest code a small persistent key-value store.
+//!
+//! Storage is pluggable through the [`KvBackend`] trait:
+//!
+//! - [`MemoryKvBackend`]: in-process store, lost on restart
+//! - [`FileKvBackend`]: embedded database file (redb)
+//!
+//! Access is controlled per module through [`Permissions`]: a module can
+//! only see keys in its `kv_namespace`, and each execution is limited in
+//! the number of operations, the size of each value it writes and the
+//! total bytes it writes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use edge_runtime_common::{HostFunctionError, KvBackendKind, KvConfig, RuntimeError};
+use edge_runtime_core::store::WorkerContext;
+use redb::{Database, ReadableTable, TableDefinition};
+use tracing::{debug, warn};
+
+use crate::Permissions;
+
+/// Maximum size of a key in bytes.
+pub const MAX_KV_KEY_BYTES: usize = 512;
+
+/// Maximum number of keys returned by a single `list` call.
+pub const MAX_KV_LIST_KEYS: usize = 1000;
+
+/// Storage backend for the KV interface.
+///
+/// Keys are scoped by namespace; backends must never return keys from
+/// another namespace. Expired entries must behave as if they were absent.
+#[async_trait]
+pub trait KvBackend: Send + Sync {
+    /// Get the value stored under `key`.
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, HostFunctionError>;
+
+    /// Store `value` under `key`, optionally expiring after `ttl`.
+    async fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<(), HostFunctionError>;
+
+    /// Delete `key`, returning `true` if it existed.
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool, HostFunctionError>;
+
+    /// List up to `limit` keys starting with `prefix`, in lexicographic order.
+    async fn list(
+        &self,
+        namespace: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, HostFunctionError>;
+
+    /// Atomically replace the value of `key` if it currently equals
+    /// `expected` (`None` meaning absent).
+    ///
+    /// Returns `true` if the value was replaced.
+    async fn compare_and_swap(
+        &self,
+        namespace: &str,
+        key: &str,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool, HostFunctionError>;
+}
+
+/// Shared handle to the configured KV backend.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime.
+#[derive(Clone)]
+pub struct KvStore {
+    backend: Arc<dyn KvBackend>,
+}
+
+impl KvStore {
+    /// Create a store using the given backend.
+    pub fn new(backend: Arc<dyn KvBackend>) -> Self {
+        Self { backend }
+    }
+
+    /// Create an in-memory store.
+    pub fn memory() -> Self {
+        Self::new(Arc::new(MemoryKvBackend::new()))
+    }
+
+    /// Open the store described by `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `file` backend has no path or the database
+    /// cannot be opened.
+    pub fn open(config: &KvConfig) -> Result<Self, RuntimeError> {
+        match config.backend {
+            KvBackendKind::Memory => Ok(Self::memory()),
+            KvBackendKind::File => {
+                let path = config.path.as_deref().ok_or_else(|| {
+                    RuntimeError::invalid_config("KV file backend requires a path")
+                })?;
+                Ok(Self::new(Arc::new(FileKvBackend::open(path)?)))
+            }
+        }
+    }
+
+    /// Get the backend.
+    pub fn backend(&self) -> &dyn KvBackend {
+        self.backend.as_ref()
+    }
+}
+
+impl std::fmt::Debug for KvStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KvStore").finish_non_exhaustive()
+    }
+}
+
+/// Host implementation for the KV interface.
+///
+/// Each operation checks the caller's namespace and quotas before reaching
+/// the backend, and counts towards [`ExecutionMetrics::kv_operations`].
+/// Written values count towards [`ExecutionMetrics::kv_bytes_written`].
+///
+/// [`ExecutionMetrics::kv_operations`]: edge_runtime_core::ExecutionMetrics::kv_operations
+/// [`ExecutionMetrics::kv_bytes_written`]: edge_runtime_core::ExecutionMetrics::kv_bytes_written
+pub struct KvHost;
+
+impl KvHost {
+    /// Get the value stored under `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// backend fails.
+    pub async fn get(
+        ctx: &mut WorkerContext,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, HostFunctionError> {
+        let (store, namespace) = Self::begin(ctx, "get", key, 0)?;
+        store.backend.get(&namespace, key).await
+    }
+
+    /// Store `value` under `key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// backend fails.
+    pub async fn set(
+        ctx: &mut WorkerContext,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<(), HostFunctionError> {
+        let (store, namespace) = Self::begin(ctx, "set", key, value.len())?;
+        store.backend.set(&namespace, key, value, ttl).await
+    }
+
+    /// Delete `key`, returning `true` if it existed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// backend fails.
+    pub async fn delete(ctx: &mut WorkerContext, key: &str) -> Result<bool, HostFunctionError> {
+        let (store, namespace) = Self::begin(ctx, "delete", key, 0)?;
+        store.backend.delete(&namespace, key).await
+    }
+
+    /// List keys starting with `prefix`.
+    ///
+    /// At most [`MAX_KV_LIST_KEYS`] keys are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// backend fails.
+    pub async fn list(
+        ctx: &mut WorkerContext,
+        prefix: &str,
+    ) -> Result<Vec<String>, HostFunctionError> {
+        let (store, namespace) = Self::begin(ctx, "list", prefix, 0)?;
+        store
+            .backend
+            .list(&namespace, prefix, MAX_KV_LIST_KEYS)
+            .await
+    }
+
+    /// Atomically replace the value of `key` if it equals `expected`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// backend fails.
+    pub async fn compare_and_swap(
+        ctx: &mut WorkerContext,
+        key: &str,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool, HostFunctionError> {
+        let (store, namespace) = Self::begin(ctx, "compare-and-swap", key, value.len())?;
+        store
+            .backend
+            .compare_and_swap(&namespace, key, expected, value, ttl)
+            .await
+    }
+
+    /// Numeric error code returned to core modules.
+    ///
+    /// `-1` is reserved for "not found" and invalid guest memory.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    /// Check access and quotas, and count the operation.
+    ///
+    /// Returns the store and the caller's namespace.
+    fn begin(
+        ctx: &mut WorkerContext,
+        operation: &str,
+        key: &str,
+        value_len: usize,
+    ) -> Result<(KvStore, String), HostFunctionError> {
+        let Some(permissions) = ctx.extensions().get::<Permissions>() else {
+            return Err(denied());
+        };
+        let Some(namespace) = permissions.kv_namespace() else {
+            warn!(request_id = %ctx.request_id, operation, "KV access denied");
+            return Err(denied());
+        };
+        let namespace = namespace.to_string();
+        let max_operations = permissions.max_kv_operations;
+        let max_value_bytes = permissions.max_kv_value_bytes;
+        let max_bytes_written = permissions.max_kv_bytes_written;
+
+        let Some(store) = ctx.extensions().get::<KvStore>().cloned() else {
+            return Err(HostFunctionError::KvStore(
+                "KV store is not configured".to_string(),
+            ));
+        };
+
+        if ctx.metrics.kv_operations >= max_operations {
+            warn!(
+                request_id = %ctx.request_id,
+                max = max_operations,
+                "KV operation limit exceeded"
+            );
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "kv".to_string(),
+            });
+        }
+        ctx.metrics.kv_operations += 1;
+
+        validate_key(key, operation == "list")?;
+        if value_len > max_value_bytes {
+            return Err(HostFunctionError::InvalidArgument {
+                reason: format!("KV value exceeds {max_value_bytes} bytes"),
+            });
+        }
+        let bytes_written = ctx.metrics.kv_bytes_written + value_len as u64;
+        if bytes_written > max_bytes_written {
+            warn!(
+                request_id = %ctx.request_id,
+                max = max_bytes_written,
+                "KV write quota exceeded"
+            );
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "kv write".to_string(),
+            });
+        }
+        ctx.metrics.kv_bytes_written = bytes_written;
+
+        debug!(
+            request_id = %ctx.request_id,
+            namespace = %namespace,
+            operation,
+            key,
+            "KV operation"
+        );
+
+        Ok((store, namespace))
+    }
+}
+
+/// Error for executions without KV access.
+fn denied() -> HostFunctionError {
+    HostFunctionError::PermissionDenied {
+        resource: "kv".to_string(),
+    }
+}
+
+/// Validate a key (or a `list` prefix, which may be empty).
+fn validate_key(key: &str, allow_empty: bool) -> Result<(), HostFunctionError> {
+    let reason = if key.is_empty() && !allow_empty {
+        "KV key must not be empty".to_string()
+    } else if key.len() > MAX_KV_KEY_BYTES {
+        format!("KV key exceeds {MAX_KV_KEY_BYTES} bytes")
+    } else if key.contains('\n') {
+        "KV key must not contain newlines".to_string()
+    } else {
+        return Ok(());
+    };
+
+    Err(HostFunctionError::InvalidArgument { reason })
+}
+
+/// Current time in milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Expiry timestamp for a TTL, in milliseconds since the Unix epoch.
+fn expires_at(ttl: Option<Duration>) -> Option<u64> {
+    ttl.map(|ttl| now_ms().saturating_add(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX)))
+}
+
+/// Wrap a backend failure.
+fn backend_error(error: impl std::fmt::Display) -> HostFunctionError {
+    HostFunctionError::KvStore(error.to_string())
+}
+
+/// A stored value with its optional expiry.
+#[derive(Debug, Clone)]
+struct Entry {
+    value: Vec<u8>,
+    expires_at: Option<u64>,
+}
+
+impl Entry {
+    fn is_live(&self, now: u64) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+/// Number of writes to a backend between sweeps for expired entries.
+const PURGE_INTERVAL: u64 = 1024;
+
+/// In-memory KV backend.
+///
+/// Data is lost when the process exits. Expired entries are removed when
+/// they are accessed and swept from all namespaces every
+/// [`PURGE_INTERVAL`] writes.
+#[derive(Debug, Default)]
+pub struct MemoryKvBackend {
+    namespaces: Mutex<HashMap<String, BTreeMap<String, Entry>>>,
+    writes: AtomicU64,
+}
+
+impl MemoryKvBackend {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `f` to a namespace if it exists, without creating it.
+    fn read_namespace<T>(
+        &self,
+        namespace: &str,
+        f: impl FnOnce(&mut BTreeMap<String, Entry>) -> T,
+    ) -> Result<Option<T>, HostFunctionError> {
+        let mut namespaces = self.namespaces.lock().map_err(backend_error)?;
+        Ok(namespaces.get_mut(namespace).map(f))
+    }
+
+    /// Apply `f` to a namespace, creating it if needed.
+    fn write_namespace<T>(
+        &self,
+        namespace: &str,
+        f: impl FnOnce(&mut BTreeMap<String, Entry>) -> T,
+    ) -> Result<T, HostFunctionError> {
+        let mut namespaces = self.namespaces.lock().map_err(backend_error)?;
+        if (self.writes.fetch_add(1, Ordering::Relaxed) + 1) % PURGE_INTERVAL == 0 {
+            Self::purge_expired(&mut namespaces);
+        }
+        let entries = namespaces.entry(namespace.to_string()).or_default();
+        Ok(f(entries))
+    }
+
+    /// Remove expired entries and namespaces left empty.
+    fn purge_expired(namespaces: &mut HashMap<String, BTreeMap<String, Entry>>) {
+        let now = now_ms();
+        namespaces.retain(|_, entries| {
+            entries.retain(|_, entry| entry.is_live(now));
+            !entries.is_empty()
+        });
+    }
+
+    /// Get the live value of `key`, removing it if it expired.
+    fn live_value<'a>(entries: &'a mut BTreeMap<String, Entry>, key: &str) -> Option<&'a [u8]> {
+        if entries.get(key).is_some_and(|e| !e.is_live(now_ms())) {
+            entries.remove(key);
+        }
+        entries.get(key).map(|e| e.value.as_slice())
+    }
+}
+
+#[async_trait]
+impl KvBackend for MemoryKvBackend {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, HostFunctionError> {
+        self.read_namespace(namespace, |entries| {
+            Self::live_value(entries, key).map(<[u8]>::to_vec)
+        })
+        .map(Option::flatten)
+    }
+
+    async fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<(), HostFunctionError> {
+        self.write_namespace(namespace, |entries| {
+            entries.insert(
+                key.to_string(),
+                Entry {
+                    value,
+                    expires_at: expires_at(ttl),
+                },
+            );
+        })
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool, HostFunctionError> {
+        self.read_namespace(namespace, |entries| {
+            entries
+                .remove(key)
+                .is_some_and(|entry| entry.is_live(now_ms()))
+        })
+        .map(|deleted| deleted.unwrap_or(false))
+    }
+
+    async fn list(
+        &self,
+        namespace: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, HostFunctionError> {
+        let now = now_ms();
+        self.read_namespace(namespace, |entries| {
+            entries
+                .range(prefix.to_string()..)
+                .take_while(|(key, _)| key.starts_with(prefix))
+                .filter(|(_, entry)| entry.is_live(now))
+                .map(|(key, _)| key.clone())
+                .take(limit)
+                .collect()
+        })
+        .map(Option::unwrap_or_default)
+    }
+
+    async fn compare_and_swap(
+        &self,
+        namespace: &str,
+        key: &str,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool, HostFunctionError> {
+        self.write_namespace(namespace, |entries| {
+            if Self::live_value(entries, key) != expected.as_deref() {
+                return false;
+            }
+            entries.insert(
+                key.to_string(),
+                Entry {
+                    value,
+                    expires_at: expires_at(ttl),
+                },
+            );
+            true
+        })
+    }
+}
+
+/// Table holding all namespaces, keyed by `(namespace, key)`.
+///
+/// Values are an 8-byte big-endian expiry timestamp (`0` for none)
+/// followed by the value bytes.
+const KV_TABLE: TableDefinition<(&str, &str), &[u8]> = TableDefinition::new("kv");
+
+/// File-backed KV backend using an embedded database.
+///
+/// Every write is committed durably before the call returns. Database
+/// access runs on the blocking thread pool. Expired entries are skipped
+/// when read and deleted by a sweep in the write transaction of every
+/// [`PURGE_INTERVAL`]th write.
+pub struct FileKvBackend {
+    db: Arc<Database>,
+    writes: AtomicU64,
+}
+
+impl FileKvBackend {
+    /// Open or create the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or initialized.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RuntimeError> {
+        let path = path.as_ref();
+        let map_err = |e: redb::Error| {
+            RuntimeError::invalid_config(format!(
+                "Failed to open KV database '{}': {e}",
+                path.display()
+            ))
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let db = Database::create(path).map_err(|e| map_err(e.into()))?;
+
+        // Create the table up front so read transactions can always open it.
+        let txn = db.begin_write().map_err(|e| map_err(e.into()))?;
+        txn.open_table(KV_TABLE).map_err(|e| map_err(e.into()))?;
+        txn.commit().map_err(|e| map_err(e.into()))?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            writes: AtomicU64::new(0),
+        })
+    }
+
+    /// Count a write, returning `true` if it should sweep expired entries.
+    fn sweep_due(&self) -> bool {
+        (self.writes.fetch_add(1, Ordering::Relaxed) + 1) % PURGE_INTERVAL == 0
+    }
+
+    /// Delete expired entries of all namespaces inside an open write
+    /// transaction.
+    fn purge_expired(txn: &redb::WriteTransaction) -> Result<(), HostFunctionError> {
+        let now = now_ms();
+        let mut table = txn.open_table(KV_TABLE).map_err(backend_error)?;
+        table
+            .retain(|_, value| Self::decode(value, now).is_some())
+            .map_err(backend_error)
+    }
+
+    /// Run a database operation on the blocking thread pool.
+    async fn blocking<T, F>(&self, f: F) -> Result<T, HostFunctionError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T, HostFunctionError> + Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .map_err(backend_error)?
+    }
+
+    fn encode(value: &[u8], expires_at: Option<u64>) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + value.len());
+        bytes.extend_from_slice(&expires_at.unwrap_or(0).to_be_bytes());
+        bytes.extend_from_slice(value);
+        bytes
+    }
+
+    /// Decode a stored value, returning `None` if it expired.
+    fn decode(bytes: &[u8], now: u64) -> Option<&[u8]> {
+        let (expiry, value) = bytes.split_at_checked(8)?;
+        let expires_at = u64::from_be_bytes(expiry.try_into().ok()?);
+        (expires_at == 0 || expires_at > now).then_some(value)
+    }
+
+    /// Write a value inside an open write transaction.
+    fn insert(
+        txn: &redb::WriteTransaction,
+        namespace: &str,
+        key: &str,
+        value: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<(), HostFunctionError> {
+        let mut table = txn.open_table(KV_TABLE).map_err(backend_error)?;
+        table
+            .insert(
+                (namespace, key),
+                Self::encode(value, expires_at(ttl)).as_slice(),
+            )
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for FileKvBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileKvBackend").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl KvBackend for FileKvBackend {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, HostFunctionError> {
+        let (namespace, key) = (namespace.to_string(), key.to_string());
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(backend_error)?;
+            let table = txn.open_table(KV_TABLE).map_err(backend_error)?;
+            let value = table
+                .get((namespace.as_str(), key.as_str()))
+                .map_err(backend_error)?;
+            Ok(value.and_then(|v| Self::decode(v.value(), now_ms()).map(<[u8]>::to_vec)))
+        })
+        .await
+    }
+
+    async fn set(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<(), HostFunctionError> {
+        let (namespace, key) = (namespace.to_string(), key.to_string());
+        let sweep = self.sweep_due();
+        self.blocking(move |db| {
+            let txn = db.begin_write().map_err(backend_error)?;
+            if sweep {
+                Self::purge_expired(&txn)?;
+            }
+            Self::insert(&txn, &namespace, &key, &value, ttl)?;
+            txn.commit().map_err(backend_error)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<bool, HostFunctionError> {
+        let (namespace, key) = (namespace.to_string(), key.to_string());
+        self.blocking(move |db| {
+            let txn = db.begin_write().map_err(backend_error)?;
+            let existed = {
+                let mut table = txn.open_table(KV_TABLE).map_err(backend_error)?;
+                let removed = table
+                    .remove((namespace.as_str(), key.as_str()))
+                    .map_err(backend_error)?;
+                removed.is_some_and(|v| Self::decode(v.value(), now_ms()).is_some())
+            };
+            txn.commit().map_err(backend_error)?;
+            Ok(existed)
+        })
+        .await
+    }
+
+    async fn list(
+        &self,
+        namespace: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<String>, HostFunctionError> {
+        let (namespace, prefix) = (namespace.to_string(), prefix.to_string());
+        self.blocking(move |db| {
+            let txn = db.begin_read().map_err(backend_error)?;
+            let table = txn.open_table(KV_TABLE).map_err(backend_error)?;
+            let now = now_ms();
+
+            let mut keys = Vec::new();
+            for item in table
+                .range((namespace.as_str(), prefix.as_str())..)
+                .map_err(backend_error)?
+            {
+                let (key, value) = item.map_err(backend_error)?;
+                let (ns, key) = key.value();
+                if ns != namespace || !key.starts_with(prefix.as_str()) {
+                    break;
+                }
+                if Self::decode(value.value(), now).is_some() {
+                    keys.push(key.to_string());
+                    if keys.len() >= limit {
+                        break;
+                    }
+                }
+            }
+            Ok(keys)
+        })
+        .await
+    }
+
+    async fn compare_and_swap(
+        &self,
+        namespace: &str,
+        key: &str,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+        ttl: Option<Duration>,
+    ) -> Result<bool, HostFunctionError> {
+        let (namespace, key) = (namespace.to_string(), key.to_string());
+        let sweep = self.sweep_due();
+        self.blocking(move |db| {
+            // Write transactions are serialized, so the check and the
+            // update are atomic.
+            let txn = db.begin_write().map_err(backend_error)?;
+            if sweep {
+                Self::purge_expired(&txn)?;
+            }
+            let current = {
+                let table = txn.open_table(KV_TABLE).map_err(backend_error)?;
+                let current = table
+                    .get((namespace.as_str(), key.as_str()))
+                    .map_err(backend_error)?;
+                current.and_then(|v| Self::decode(v.value(), now_ms()).map(<[u8]>::to_vec))
+            };
+
+            if current != expected {
+                if sweep {
+                    txn.commit().map_err(backend_error)?;
+                } else {
+                    txn.abort().map_err(backend_error)?;
+                }
+                return Ok(false);
+            }
+
+            Self::insert(&txn, &namespace, &key, &value, ttl)?;
+            txn.commit().map_err(backend_error)?;
+            Ok(true)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::{EngineConfig, ExecutionConfig};
+    use edge_runtime_core::WasmEngine;
+    use edge_runtime_core::store::create_store;
+
+    async fn exercise(backend: &dyn KvBackend) {
+        backend
+            .set("a", "user:1", b"alice".to_vec(), None)
+            .await
+            .unwrap();
+        backend
+            .set("a", "user:2", b"bob".to_vec(), None)
+            .await
+            .unwrap();
+        backend
+            .set("a", "order:1", b"x".to_vec(), None)
+            .await
+            .unwrap();
+        backend
+            .set("b", "user:3", b"carol".to_vec(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(backend.get("a", "user:1").await.unwrap().unwrap(), b"alice");
+        assert!(backend.get("b", "user:1").await.unwrap().is_none());
+        assert_eq!(
+            backend.list("a", "user:", 10).await.unwrap(),
+            vec!["user:1", "user:2"]
+        );
+        assert_eq!(backend.list("a", "", 2).await.unwrap().len(), 2);
+
+        assert!(backend.delete("a", "user:2").await.unwrap());
+        assert!(!backend.delete("a", "user:2").await.unwrap());
+
+        // Compare-and-swap on an absent key, then on a stale value
+        assert!(
+            backend
+                .compare_and_swap("a", "counter", None, b"1".to_vec(), None)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !backend
+                .compare_and_swap("a", "counter", None, b"2".to_vec(), None)
+                .await
+                .unwrap()
+        );
+        assert!(
+            backend
+                .compare_and_swap("a", "counter", Some(b"1".to_vec()), b"2".to_vec(), None)
+                .await
+                .unwrap()
+        );
+        assert_eq!(backend.get("a", "counter").await.unwrap().unwrap(), b"2");
+
+        // Expired entries behave as absent
+        backend
+            .set("a", "temp", b"t".to_vec(), Some(Duration::ZERO))
+            .await
+            .unwrap();
+        assert!(backend.get("a", "temp").await.unwrap().is_none());
+        assert!(
+            !backend
+                .list("a", "te", 10)
+                .await
+                .unwrap()
+                .contains(&"temp".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend() {
+        exercise(&MemoryKvBackend::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_reads_do_not_create_namespaces() {
+        let backend = MemoryKvBackend::new();
+        assert!(backend.get("missing", "key").await.unwrap().is_none());
+        assert!(backend.list("missing", "", 10).await.unwrap().is_empty());
+        assert!(!backend.delete("missing", "key").await.unwrap());
+
+        assert!(backend.namespaces.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_purges_expired() {
+        let backend = MemoryKvBackend::new();
+        let ttl = Some(Duration::from_millis(1));
+        backend.set("temp", "a", b"1".to_vec(), ttl).await.unwrap();
+        backend.set("other", "b", b"2".to_vec(), ttl).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        for _ in 2..PURGE_INTERVAL {
+            backend.set("live", "c", b"3".to_vec(), None).await.unwrap();
+        }
+
+        let namespaces = backend.namespaces.lock().unwrap();
+        assert_eq!(namespaces.keys().collect::<Vec<_>>(), vec!["live"]);
+    }
+
+    #[tokio::test]
+    async fn test_file_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kv.redb");
+
+        let backend = FileKvBackend::open(&path).unwrap();
+        exercise(&backend).await;
+        drop(backend);
+
+        // Data survives reopening
+        let backend = FileKvBackend::open(&path).unwrap();
+        assert_eq!(backend.get("a", "user:1").await.unwrap().unwrap(), b"alice");
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_purges_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = FileKvBackend::open(dir.path().join("kv.redb")).unwrap();
+        let ttl = Some(Duration::from_millis(1));
+        backend.set("temp", "a", b"1".to_vec(), ttl).await.unwrap();
+        backend.set("other", "b", b"2".to_vec(), ttl).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        for _ in 2..PURGE_INTERVAL {
+            backend.set("live", "c", b"3".to_vec(), None).await.unwrap();
+        }
+
+        let txn = backend.db.begin_read().unwrap();
+        let table = txn.open_table(KV_TABLE).unwrap();
+        let keys: Vec<_> = table
+            .iter()
+            .unwrap()
+            .map(|item| {
+                let (key, _) = item.unwrap();
+                let (namespace, key) = key.value();
+                (namespace.to_string(), key.to_string())
+            })
+            .collect();
+        assert_eq!(keys, vec![("live".to_string(), "c".to_string())]);
+    }
+
+    fn context(permissions: Permissions) -> wasmtime::Store<WorkerContext> {
+        let engine = WasmEngine::new(&EngineConfig {
+            pooling_allocator: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut store = create_store(&engine, &ExecutionConfig::default(), "test".into()).unwrap();
+        let ext = store.data_mut().extensions_mut();
+        ext.insert(permissions);
+        ext.insert(KvStore::memory());
+        store
+    }
+
+    #[tokio::test]
+    async fn test_host_quotas() {
+        let perms = Permissions::builder()
+            .kv_namespace("app", 2, 4, 1024)
+            .build();
+        let mut store = context(perms);
+        let ctx = store.data_mut();
+
+        KvHost::set(ctx, "k", b"1234".to_vec(), None).await.unwrap();
+
+        let too_large = KvHost::set(ctx, "k", b"12345".to_vec(), None).await;
+        assert!(matches!(
+            too_large,
+            Err(HostFunctionError::InvalidArgument { .. })
+        ));
+
+        let limited = KvHost::get(ctx, "k").await;
+        assert!(matches!(
+            limited,
+            Err(HostFunctionError::RateLimitExceeded { .. })
+        ));
+        assert_eq!(ctx.metrics.kv_operations, 2);
+    }
+
+    #[tokio::test]
+    async fn test_host_write_quota() {
+        let perms = Permissions::builder().kv_namespace("app", 10, 4, 6).build();
+        let mut store = context(perms);
+        let ctx = store.data_mut();
+
+        KvHost::set(ctx, "a", b"1234".to_vec(), None).await.unwrap();
+        KvHost::compare_and_swap(ctx, "b", None, b"12".to_vec(), None)
+            .await
+            .unwrap();
+
+        let over_quota = KvHost::set(ctx, "c", b"1".to_vec(), None).await;
+        assert!(matches!(
+            over_quota,
+            Err(HostFunctionError::RateLimitExceeded { .. })
+        ));
+        assert!(KvHost::get(ctx, "c").await.unwrap().is_none());
+        assert_eq!(ctx.metrics.kv_bytes_written, 6);
+    }
+
+    #[tokio::test]
+    async fn test_host_requires_namespace() {
+        let mut store = context(Permissions::builder().enable_logging().build());
+
+        let result = KvHost::get(store.data_mut(), "k").await;
+
+        let error = result.unwrap_err();
+        assert!(matches!(error, HostFunctionError::PermissionDenied { .. }));
+        assert_eq!(KvHost::error_code(&error), -2);
+    }
+
+    #[tokio::test]
+    async fn test_host_rejects_invalid_keys() {
+        let perms = Permissions::builder()
+            .kv_namespace("app", 10, 10, 1024)
+            .build();
+        let mut store = context(perms);
+        let ctx = store.data_mut();
+
+        assert!(KvHost::get(ctx, "").await.is_err());
+        assert!(KvHost::get(ctx, "a\nb").await.is_err());
+        assert!(KvHost::list(ctx, "").await.is_ok());
+    }
+}
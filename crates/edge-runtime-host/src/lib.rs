@@ -6,9 +6,24 @@
 //!
 //! # Interfaces
 //!
-//! - [`logging`]: Structured logging from guest code
+//! - [`logging`]: Structured logging from guest code, with per-module limits and sampling
+//! - [`blob`]: Content-addressed object storage with streaming access
+//! - [`cache`]: Response cache shared across requests of a module
+//! - [`config`]: Per-module configuration values and encrypted secrets
+//! - [`crypto`]: Hashing, HMAC, signatures and encryption with host-held keys
+//! - [`exchange`]: Request/response exchange with guest code
 //! - [`http_outbound`]: Outbound HTTP requests with security controls
+//! - [`kv`]: Key-value store with pluggable backends
+//! - [`lifecycle`]: Deferred work after the response (`wait_until`)
+//! - [`metrics`]: Custom counters, gauges and histograms aggregated per module
+//! - [`otel`]: OTLP export of the runtime's spans and W3C trace context propagation
 //! - [`permissions`]: Capability-based security configuration
+//! - [`service`]: Module-to-module invocation (service bindings)
+//! - [`sockets`]: Outbound TCP and TLS connections with address allowlists
+//! - [`spans`]: Guest tracing spans nested in the request's trace
+//! - [`sql`]: Embedded `SQLite` database per namespace
+//! - [`timer`]: Sleeping without consuming fuel
+//! - [`websocket`]: Outbound WebSocket connections to allowed hosts
 //! - [`linker`]: Host function registration for Wasmtime linkers
 //!
 //! # Security Model
@@ -31,14 +46,44 @@
 //! let runner = create_instance_runner(engine)?;
 //! ```
 
+pub mod blob;
+pub mod cache;
+pub mod config;
+pub mod crypto;
+pub mod exchange;
 pub mod http_outbound;
+pub mod kv;
+pub mod lifecycle;
 pub mod linker;
 pub mod logging;
+pub mod metrics;
+pub mod otel;
 pub mod permissions;
+pub mod service;
+pub mod sockets;
+pub mod spans;
+pub mod sql;
+pub mod timer;
+pub mod websocket;
 
+pub use blob::{BlobHost, BlobMeta, BlobStore, BucketUsage};
+pub use cache::{CacheHost, ResponseCache};
+pub use config::{ConfigHost, ConfigStore, SecretsKey};
+pub use crypto::{CryptoHost, DigestAlgorithm, Keyring};
+pub use exchange::{Exchange, ExchangeHost, GuestRequest, GuestResponse};
 pub use http_outbound::HttpOutboundHost;
-pub use logging::LoggingHost;
+pub use kv::{FileKvBackend, KvBackend, KvHost, KvStore, MemoryKvBackend};
+pub use lifecycle::LifecycleHost;
+pub use logging::{LogPolicy, LoggingHost};
+pub use metrics::{GuestMetrics, MetricKind, MetricsHost};
+pub use otel::{OtelLayer, TraceContext};
 pub use permissions::Permissions;
+pub use service::{ServiceContext, ServiceHost, ServiceInvoker};
+pub use sockets::{SocketConnector, SocketsHost};
+pub use spans::{SpanParent, SpansHost};
+pub use sql::{SqlHost, SqlRows, SqlStore, SqlValue};
+pub use timer::TimerHost;
+pub use websocket::{WebSocketHost, WebSocketMessage};
 
 use std::sync::Arc;
 
@@ -61,5 +106,6 @@ use wasmtime::Engine;
 pub fn create_instance_runner(engine: Arc<Engine>) -> Result<InstanceRunner, RuntimeError> {
     let mut runner = InstanceRunner::new(engine);
     linker::register_all(runner.linker_mut())?;
+    linker::register_all_component(runner.component_linker_mut())?;
     Ok(runner)
 }
@@ -0,0 +1,79 @@
+//! Request lifecycle host function implementation.
+//!
+//! This module provides the host-side implementation of the lifecycle
+//! interface, which lets guest code register work to run after the
+//! response has been sent (`wait_until`).
+
+use edge_runtime_core::store::{MAX_DEFERRED_CALLS, WorkerContext};
+use tracing::{debug, warn};
+
+/// Host implementation for the lifecycle interface.
+///
+/// Deferred calls are recorded in the [`WorkerContext`] and executed by the
+/// runtime once the response has been flushed, using a separate fuel and
+/// time budget.
+pub struct LifecycleHost;
+
+impl LifecycleHost {
+    /// Register an exported function to call after the response is sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The worker context of the current request
+    /// * `export` - Name of the guest export to call (takes no arguments)
+    ///
+    /// # Returns
+    ///
+    /// `true` if the call was registered, `false` if the export name is
+    /// empty or the per-request limit has been reached.
+    pub fn wait_until(ctx: &mut WorkerContext, export: &str) -> bool {
+        if export.is_empty() {
+            warn!(request_id = %ctx.request_id, "Rejected wait_until with empty export name");
+            return false;
+        }
+
+        if !ctx.defer(export.to_string()) {
+            warn!(
+                request_id = %ctx.request_id,
+                export = %export,
+                limit = MAX_DEFERRED_CALLS,
+                "Deferred call limit reached"
+            );
+            return false;
+        }
+
+        debug!(request_id = %ctx.request_id, export = %export, "Registered deferred call");
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_until_registers() {
+        let mut ctx = WorkerContext::new("test".into());
+
+        assert!(LifecycleHost::wait_until(&mut ctx, "flush"));
+        assert_eq!(ctx.take_deferred(), vec!["flush".to_string()]);
+    }
+
+    #[test]
+    fn test_wait_until_rejects_empty() {
+        let mut ctx = WorkerContext::new("test".into());
+
+        assert!(!LifecycleHost::wait_until(&mut ctx, ""));
+        assert!(!ctx.has_deferred());
+    }
+
+    #[test]
+    fn test_wait_until_limit() {
+        let mut ctx = WorkerContext::new("test".into());
+
+        for _ in 0..MAX_DEFERRED_CALLS {
+            assert!(LifecycleHost::wait_until(&mut ctx, "flush"));
+        }
+        assert!(!LifecycleHost::wait_until(&mut ctx, "flush"));
+    }
+}
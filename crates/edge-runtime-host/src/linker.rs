@@ -3,17 +3,48 @@
 //! This module provides functions to register host functions on Wasmtime linkers,
 //! enabling WebAssembly modules to call into the host runtime.
 
-use edge_runtime_common::RuntimeError;
-use edge_runtime_core::store::WorkerContext;
+use std::time::Duration;
+
+use edge_runtime_common::{HostFunctionError, RuntimeError};
+use edge_runtime_core::store::{LogLevel, WorkerContext};
 use tracing::warn;
-use wasmtime::{Caller, Linker};
+use wasmtime::component::{ComponentType, Lift, Linker as ComponentLinker, Lower};
+use wasmtime::{AsContextMut, Caller, Linker, StoreContextMut, Trap};
 
+use crate::blob::{BlobHost, BlobMeta};
+use crate::cache::CacheHost;
+use crate::config::ConfigHost;
+use crate::crypto::{CryptoHost, DigestAlgorithm};
+use crate::exchange::{ExchangeHost, GuestRequest, GuestResponse, parse_header_block};
+use crate::kv::KvHost;
+use crate::lifecycle::LifecycleHost;
 use crate::logging::{LoggingHost, level_from_i32};
+use crate::metrics::{MetricKind, MetricsHost};
+use crate::service::{ServiceContext, ServiceError, ServiceHost};
+use crate::sockets::SocketsHost;
+use crate::spans::SpansHost;
+use crate::sql::{SqlHost, SqlRows, SqlValue};
+use crate::timer::TimerHost;
+use crate::websocket::{WebSocketHost, WebSocketMessage};
 
 /// Register all standard host functions on a core module linker.
 ///
 /// This registers the following host functions:
-/// - `env::log` - Logging function for guest code
+/// - `env::log` / `env::log_with_fields` - Logging functions for guest code
+/// - `env::wait_until` - Deferred work after the response
+/// - `env::sleep` - Wait without consuming fuel
+/// - `env::request_*` / `env::response_*` - Request/response exchange
+/// - `env::service_*` - Module-to-module service calls
+/// - `env::kv_*` - Key-value store
+/// - `env::cache_*` - Response cache
+/// - `env::sql_*` - Embedded SQL database
+/// - `env::blob_*` - Blob storage
+/// - `env::config_*` - Module configuration and secrets
+/// - `env::crypto_*` - Hashing, signatures and encryption
+/// - `env::socket_*` - Outbound TCP connections
+/// - `env::ws_*` - Outbound WebSocket connections
+/// - `env::metric_*` - Custom metrics
+/// - `env::span_*` - Guest tracing spans
 ///
 /// # Arguments
 ///
@@ -24,13 +55,70 @@ use crate::logging::{LoggingHost, level_from_i32};
 /// Returns an error if function registration fails.
 pub fn register_all(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
     register_logging(linker)?;
+    register_lifecycle(linker)?;
+    register_timer(linker)?;
+    register_exchange(linker)?;
+    register_service(linker)?;
+    register_kv(linker)?;
+    register_cache(linker)?;
+    register_sql(linker)?;
+    register_blob(linker)?;
+    register_config(linker)?;
+    register_crypto(linker)?;
+    register_sockets(linker)?;
+    register_websocket(linker)?;
+    register_metrics(linker)?;
+    register_spans(linker)?;
+    Ok(())
+}
+
+/// Register all standard host interfaces on a component linker.
+///
+/// This registers the following interfaces:
+/// - `edge:runtime/logging` - Logging for guest code
+/// - `edge:runtime/lifecycle` - Deferred work after the response
+/// - `edge:runtime/timer` - Wait without consuming fuel
+/// - `edge:runtime/service` - Module-to-module service calls
+/// - `edge:runtime/kv` - Key-value store
+/// - `edge:runtime/cache` - Response cache
+/// - `edge:runtime/sql` - Embedded SQL database
+/// - `edge:runtime/blob` - Blob storage
+/// - `edge:runtime/config` - Module configuration and secrets
+/// - `edge:runtime/crypto` - Hashing, signatures and encryption
+/// - `edge:runtime/sockets` - Outbound TCP connections
+/// - `edge:runtime/websocket` - Outbound WebSocket connections
+/// - `edge:runtime/metrics` - Custom metrics
+/// - `edge:runtime/spans` - Guest tracing spans
+///
+/// # Errors
+///
+/// Returns an error if function registration fails.
+pub fn register_all_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    register_logging_component(linker)?;
+    register_lifecycle_component(linker)?;
+    register_timer_component(linker)?;
+    register_service_component(linker)?;
+    register_kv_component(linker)?;
+    register_cache_component(linker)?;
+    register_sql_component(linker)?;
+    register_blob_component(linker)?;
+    register_config_component(linker)?;
+    register_crypto_component(linker)?;
+    register_sockets_component(linker)?;
+    register_websocket_component(linker)?;
+    register_metrics_component(linker)?;
+    register_spans_component(linker)?;
     Ok(())
 }
 
-/// Register the logging host function.
+/// Register the logging host functions.
 ///
 /// Registers `env::log(level: i32, ptr: i32, len: i32)` which allows guest
-/// code to emit logs at various levels (debug, info, warn, error).
+/// code to emit logs at various levels (debug, info, warn, error), and
+/// `env::log_with_fields(level: i32, ptr: i32, len: i32, fields_ptr: i32, fields_len: i32)`
+/// which attaches structured key-value fields to the entry.
 ///
 /// # Memory Protocol
 ///
@@ -38,90 +126,3115 @@ pub fn register_all(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeErr
 /// - `level`: Log level (0=debug, 1=info, 2=warn, 3=error)
 /// - `ptr`: Pointer to the message string in guest memory
 /// - `len`: Length of the message in bytes (UTF-8)
+/// - `fields_ptr`/`fields_len`: Fields as a header block of `name: value`
+///   lines, possibly empty
 pub fn register_logging(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register log function: {e}"))
+    };
+
     linker
         .func_wrap(
             "env",
             "log",
             |mut caller: Caller<'_, WorkerContext>, level: i32, ptr: i32, len: i32| {
-                // Validate pointer and length are non-negative
-                if ptr < 0 || len < 0 {
-                    warn!(
-                        ptr = ptr,
-                        len = len,
-                        "Invalid pointer or length (negative value)"
-                    );
+                let Some(message) = read_guest_string(&mut caller, ptr, len) else {
                     return;
-                }
+                };
 
-                let Some(memory) = caller
-                    .get_export("memory")
-                    .and_then(wasmtime::Extern::into_memory)
-                else {
-                    warn!("Memory export not found in guest module");
+                LoggingHost::log(caller.data_mut(), level_from_i32(level), &message);
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "log_with_fields",
+            |mut caller: Caller<'_, WorkerContext>,
+             level: i32,
+             ptr: i32,
+             len: i32,
+             fields_ptr: i32,
+             fields_len: i32| {
+                let Some(message) = read_guest_string(&mut caller, ptr, len) else {
+                    return;
+                };
+                let Some(fields) = read_guest_pairs(&mut caller, fields_ptr, fields_len) else {
                     return;
                 };
 
-                // Read message from guest memory and convert to owned String
-                // to avoid borrow checker issues with caller.data_mut()
-                #[allow(clippy::cast_sign_loss)]
-                let message = {
-                    let data = memory.data(&caller);
-                    let start = ptr as usize;
-                    let Some(end) = start.checked_add(len as usize) else {
-                        warn!(ptr = ptr, len = len, "Pointer + length overflow");
-                        return;
-                    };
+                LoggingHost::log_with_fields(
+                    caller.data_mut(),
+                    level_from_i32(level),
+                    &message,
+                    fields,
+                );
+            },
+        )
+        .map_err(map_err)?;
 
-                    // Bounds check
-                    if end > data.len() {
-                        warn!(
-                            start = start,
-                            end = end,
-                            memory_size = data.len(),
-                            "Memory access out of bounds"
-                        );
-                        return;
-                    }
+    Ok(())
+}
+
+/// Register the logging interface on a component linker.
+///
+/// Registers `edge:runtime/logging@0.1.0` with `log`, `log-with-fields`
+/// and the `debug`, `info`, `warn` and `error` shorthands.
+pub fn register_logging_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register logging interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/logging@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "log",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (level, message): (LogLevelRecord, String)| {
+                LoggingHost::log(store.data_mut(), level.into(), &message);
+                Ok(())
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "log-with-fields",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (level, message, fields): (LogLevelRecord, String, Vec<(String, String)>)| {
+                LoggingHost::log_with_fields(store.data_mut(), level.into(), &message, fields);
+                Ok(())
+            },
+        )
+        .map_err(map_err)?;
+
+    let shorthands = [
+        ("debug", LogLevel::Debug),
+        ("info", LogLevel::Info),
+        ("warn", LogLevel::Warn),
+        ("error", LogLevel::Error),
+    ];
+    for (function, level) in shorthands {
+        instance
+            .func_wrap(
+                function,
+                move |mut store: StoreContextMut<'_, WorkerContext>, (message,): (String,)| {
+                    LoggingHost::log(store.data_mut(), level, &message);
+                    Ok(())
+                },
+            )
+            .map_err(map_err)?;
+    }
+
+    Ok(())
+}
+
+/// Log level of the logging interface.
+///
+/// Variants are only constructed by lifting guest values.
+#[allow(dead_code)]
+#[derive(ComponentType, Lift, Clone, Copy)]
+#[component(enum)]
+#[repr(u8)]
+enum LogLevelRecord {
+    #[component(name = "debug")]
+    Debug,
+    #[component(name = "info")]
+    Info,
+    #[component(name = "warn")]
+    Warn,
+    #[component(name = "error")]
+    Error,
+}
 
-                    std::str::from_utf8(&data[start..end])
-                        .unwrap_or("<invalid utf8>")
-                        .to_string()
+impl From<LogLevelRecord> for LogLevel {
+    fn from(level: LogLevelRecord) -> Self {
+        match level {
+            LogLevelRecord::Debug => Self::Debug,
+            LogLevelRecord::Info => Self::Info,
+            LogLevelRecord::Warn => Self::Warn,
+            LogLevelRecord::Error => Self::Error,
+        }
+    }
+}
+
+/// Register the lifecycle host function.
+///
+/// Registers `env::wait_until(ptr: i32, len: i32) -> i32` which allows guest
+/// code to schedule an exported function to run after the response has been
+/// sent.
+///
+/// # Memory Protocol
+///
+/// The guest passes:
+/// - `ptr`: Pointer to the export name in guest memory
+/// - `len`: Length of the export name in bytes (UTF-8)
+///
+/// Returns `0` if the call was registered and `-1` otherwise.
+pub fn register_lifecycle(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    linker
+        .func_wrap(
+            "env",
+            "wait_until",
+            |mut caller: Caller<'_, WorkerContext>, ptr: i32, len: i32| -> i32 {
+                let Some(export) = read_guest_string(&mut caller, ptr, len) else {
+                    return -1;
                 };
 
-                LoggingHost::log(caller.data_mut(), level_from_i32(level), &message);
+                if LifecycleHost::wait_until(caller.data_mut(), &export) {
+                    0
+                } else {
+                    -1
+                }
             },
         )
         .map_err(|e| {
-            RuntimeError::invalid_config(format!("Failed to register log function: {e}"))
+            RuntimeError::invalid_config(format!("Failed to register wait_until function: {e}"))
         })?;
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use edge_runtime_common::EngineConfig;
-    use edge_runtime_core::WasmEngine;
+/// Register the `edge:runtime/lifecycle` interface on a component linker.
+pub fn register_lifecycle_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    linker
+        .instance("edge:runtime/lifecycle@0.1.0")
+        .and_then(|mut instance| {
+            instance.func_wrap(
+                "wait-until",
+                |mut store: StoreContextMut<'_, WorkerContext>, (callback,): (String,)| {
+                    Ok((LifecycleHost::wait_until(store.data_mut(), &callback),))
+                },
+            )
+        })
+        .map_err(|e| {
+            RuntimeError::invalid_config(format!("Failed to register lifecycle interface: {e}"))
+        })?;
 
-    #[test]
-    fn test_register_logging() {
-        let config = EngineConfig::default();
-        let engine = WasmEngine::new(&config).unwrap();
-        let mut linker = Linker::new(engine.inner());
+    Ok(())
+}
 
-        let result = register_logging(&mut linker);
-        assert!(result.is_ok());
-    }
+/// Register the timer host function.
+///
+/// Registers `env::sleep(ms: i32) -> i32` which suspends the guest for up
+/// to `ms` milliseconds without consuming fuel. The sleep ends early at the
+/// execution deadline.
+///
+/// Returns the number of milliseconds slept, or `-1` if `ms` is negative.
+pub fn register_timer(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    linker
+        .func_wrap_async(
+            "env",
+            "sleep",
+            |mut caller: Caller<'_, WorkerContext>, (ms,): (i32,)| {
+                Box::new(async move {
+                    let Ok(ms) = u64::try_from(ms) else {
+                        return Ok(-1);
+                    };
+                    let slept =
+                        TimerHost::sleep(caller.data_mut(), Duration::from_millis(ms)).await;
+                    Ok(i32::try_from(slept.as_millis()).unwrap_or(i32::MAX))
+                })
+            },
+        )
+        .map_err(|e| {
+            RuntimeError::invalid_config(format!("Failed to register sleep function: {e}"))
+        })?;
 
-    #[test]
-    fn test_register_all() {
-        let config = EngineConfig::default();
-        let engine = WasmEngine::new(&config).unwrap();
-        let mut linker = Linker::new(engine.inner());
+    Ok(())
+}
 
-        let result = register_all(&mut linker);
+/// Register the `edge:runtime/timer` interface on a component linker.
+pub fn register_timer_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    linker
+        .instance("edge:runtime/timer@0.1.0")
+        .and_then(|mut instance| {
+            instance.func_wrap_async(
+                "sleep",
+                |mut store: StoreContextMut<'_, WorkerContext>, (ms,): (u32,)| {
+                    Box::new(async move {
+                        let requested = Duration::from_millis(u64::from(ms));
+                        let slept = TimerHost::sleep(store.data_mut(), requested).await;
+                        Ok((u32::try_from(slept.as_millis()).unwrap_or(u32::MAX),))
+                    })
+                },
+            )
+        })
+        .map_err(|e| {
+            RuntimeError::invalid_config(format!("Failed to register timer interface: {e}"))
+        })?;
+
+    Ok(())
+}
+
+/// Register the request/response exchange host functions.
+///
+/// Registers the following functions, which let guest code read the request
+/// that triggered the execution and build its response:
+///
+/// - `env::request_method(buf: i32, cap: i32) -> i32`
+/// - `env::request_uri(buf: i32, cap: i32) -> i32`
+/// - `env::request_header(name_ptr: i32, name_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::request_body(buf: i32, cap: i32) -> i32`
+/// - `env::request_set_uri(ptr: i32, len: i32) -> i32`
+/// - `env::request_set_header(name_ptr: i32, name_len: i32, value_ptr: i32, value_len: i32) -> i32`
+/// - `env::request_set_body(ptr: i32, len: i32) -> i32`
+/// - `env::response_status() -> i32`
+/// - `env::response_header(name_ptr: i32, name_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::response_body(buf: i32, cap: i32) -> i32`
+/// - `env::response_set_status(status: i32) -> i32`
+/// - `env::response_set_header(name_ptr: i32, name_len: i32, value_ptr: i32, value_len: i32) -> i32`
+/// - `env::response_write(ptr: i32, len: i32) -> i32`
+/// - `env::response_set_body(ptr: i32, len: i32) -> i32`
+/// - `env::response_send() -> i32`
+///
+/// The `request_set_*` functions modify the request passed to the next
+/// stage of a pipeline. The `response_*` getters read the response built so
+/// far, including one produced by an earlier stage, and return `-1` if there
+/// is none. `response_send` ends a pipeline after the current stage.
+///
+/// # Memory Protocol
+///
+/// Functions that return data copy at most `cap` bytes into the guest buffer
+/// at `buf` and return the full length of the value, so the guest can call
+/// again with a larger buffer if needed. They return `-1` if the value is
+/// not available.
+///
+/// Functions that modify the response return `0` on success and `-1` on
+/// failure.
+pub fn register_exchange(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    register_exchange_request(linker)?;
+    register_exchange_response(linker)
+}
+
+/// Register the `request_*` functions of the exchange interface.
+fn register_exchange_request(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register exchange function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "request_method",
+            |mut caller: Caller<'_, WorkerContext>, buf: i32, cap: i32| -> i32 {
+                let value = ExchangeHost::request(caller.data()).map(|r| r.method.clone());
+                write_guest_value(&mut caller, buf, cap, value.map(String::into_bytes))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "request_uri",
+            |mut caller: Caller<'_, WorkerContext>, buf: i32, cap: i32| -> i32 {
+                let value = ExchangeHost::request(caller.data()).map(|r| r.uri.clone());
+                write_guest_value(&mut caller, buf, cap, value.map(String::into_bytes))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "request_header",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             buf: i32,
+             cap: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let value = ExchangeHost::request(caller.data())
+                    .and_then(|r| r.header(&name))
+                    .map(|v| v.as_bytes().to_vec());
+                write_guest_value(&mut caller, buf, cap, value)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "request_body",
+            |mut caller: Caller<'_, WorkerContext>, buf: i32, cap: i32| -> i32 {
+                let value = ExchangeHost::request(caller.data()).map(|r| r.body.clone());
+                write_guest_value(&mut caller, buf, cap, value)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "request_set_uri",
+            |mut caller: Caller<'_, WorkerContext>, ptr: i32, len: i32| -> i32 {
+                let Some(uri) = read_guest_string(&mut caller, ptr, len) else {
+                    return -1;
+                };
+                status_code(ExchangeHost::set_request_uri(caller.data_mut(), &uri))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "request_set_header",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             value_ptr: i32,
+             value_len: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let Some(value) = read_guest_string(&mut caller, value_ptr, value_len) else {
+                    return -1;
+                };
+                status_code(ExchangeHost::set_request_header(
+                    caller.data_mut(),
+                    &name,
+                    &value,
+                ))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "request_set_body",
+            |mut caller: Caller<'_, WorkerContext>, ptr: i32, len: i32| -> i32 {
+                let Some(body) = read_guest_bytes(&mut caller, ptr, len) else {
+                    return -1;
+                };
+                status_code(ExchangeHost::set_request_body(caller.data_mut(), body))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the `response_*` functions of the exchange interface.
+fn register_exchange_response(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register exchange function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "response_status",
+            |caller: Caller<'_, WorkerContext>| -> i32 {
+                ExchangeHost::response(caller.data()).map_or(-1, |r| i32::from(r.status))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_header",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             buf: i32,
+             cap: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let value = ExchangeHost::response(caller.data())
+                    .and_then(|r| r.header(&name))
+                    .map(|v| v.as_bytes().to_vec());
+                write_guest_value(&mut caller, buf, cap, value)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_body",
+            |mut caller: Caller<'_, WorkerContext>, buf: i32, cap: i32| -> i32 {
+                let value = ExchangeHost::response(caller.data()).map(|r| r.body.clone());
+                write_guest_value(&mut caller, buf, cap, value)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_set_status",
+            |mut caller: Caller<'_, WorkerContext>, status: i32| -> i32 {
+                let set = u16::try_from(status)
+                    .is_ok_and(|status| ExchangeHost::set_status(caller.data_mut(), status));
+                status_code(set)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_set_header",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             value_ptr: i32,
+             value_len: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let Some(value) = read_guest_string(&mut caller, value_ptr, value_len) else {
+                    return -1;
+                };
+                status_code(ExchangeHost::set_header(caller.data_mut(), &name, &value))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_write",
+            |mut caller: Caller<'_, WorkerContext>, ptr: i32, len: i32| -> i32 {
+                let Some(bytes) = read_guest_bytes(&mut caller, ptr, len) else {
+                    return -1;
+                };
+                status_code(ExchangeHost::write_body(caller.data_mut(), &bytes))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_set_body",
+            |mut caller: Caller<'_, WorkerContext>, ptr: i32, len: i32| -> i32 {
+                let Some(body) = read_guest_bytes(&mut caller, ptr, len) else {
+                    return -1;
+                };
+                status_code(ExchangeHost::set_body(caller.data_mut(), body))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "response_send",
+            |mut caller: Caller<'_, WorkerContext>| -> i32 {
+                status_code(ExchangeHost::send(caller.data_mut()))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the service binding host functions.
+///
+/// Registers the following functions, which let guest code invoke other
+/// loaded modules in-process:
+///
+/// - `env::service_invoke(target_ptr, target_len, method_ptr, method_len,
+///   uri_ptr, uri_len, headers_ptr, headers_len, body_ptr, body_len) -> i32`
+/// - `env::service_response_status(handle: i32) -> i32`
+/// - `env::service_response_header(handle: i32, name_ptr: i32, name_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::service_response_body(handle: i32, buf: i32, cap: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// `service_invoke` takes the target module ID, method, URI, headers and
+/// body as pointer/length pairs. Headers are encoded as `name: value` lines
+/// separated by `\n`. An empty method defaults to `GET`.
+///
+/// It returns a non-negative response handle on success, `-1` for invalid
+/// arguments, or the negative [`ServiceError::code`] of the failure. The
+/// handle is passed to the `service_response_*` functions, which follow the
+/// same buffer convention as [`register_exchange`].
+pub fn register_service(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register service function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "service_invoke",
+            |mut caller: Caller<'_, WorkerContext>,
+             (
+                target_ptr,
+                target_len,
+                method_ptr,
+                method_len,
+                uri_ptr,
+                uri_len,
+                headers_ptr,
+                headers_len,
+                body_ptr,
+                body_len,
+            ): (i32, i32, i32, i32, i32, i32, i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let target = read_guest_string(&mut caller, target_ptr, target_len);
+                    let method = read_guest_string(&mut caller, method_ptr, method_len);
+                    let uri = read_guest_string(&mut caller, uri_ptr, uri_len);
+                    let headers = read_guest_string(&mut caller, headers_ptr, headers_len);
+                    let body = read_guest_bytes(&mut caller, body_ptr, body_len);
+
+                    let (Some(target), Some(method), Some(uri), Some(headers), Some(body)) =
+                        (target, method, uri, headers, body)
+                    else {
+                        return Ok(-1);
+                    };
+
+                    let request = GuestRequest {
+                        method: if method.is_empty() {
+                            "GET".into()
+                        } else {
+                            method
+                        },
+                        uri,
+                        headers: parse_header_block(&headers),
+                        body,
+                    };
+
+                    let handle = match ServiceHost::invoke(&mut caller, &target, request).await {
+                        Ok(response) => caller
+                            .data_mut()
+                            .extensions_mut()
+                            .get_mut::<ServiceContext>()
+                            .map_or(ServiceError::Unavailable.code(), |service| {
+                                service.push_response(response)
+                            }),
+                        Err(e) => {
+                            warn!(
+                                request_id = %caller.data().request_id,
+                                target = %target,
+                                error = %e,
+                                "Service call failed"
+                            );
+                            e.code()
+                        }
+                    };
+
+                    Ok(handle)
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "service_response_status",
+            |caller: Caller<'_, WorkerContext>, handle: i32| -> i32 {
+                service_response(caller.data(), handle).map_or(-1, |r| i32::from(r.status))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "service_response_header",
+            |mut caller: Caller<'_, WorkerContext>,
+             handle: i32,
+             name_ptr: i32,
+             name_len: i32,
+             buf: i32,
+             cap: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let value = service_response(caller.data(), handle)
+                    .and_then(|r| r.header(&name))
+                    .map(|v| v.as_bytes().to_vec());
+                write_guest_value(&mut caller, buf, cap, value)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "service_response_body",
+            |mut caller: Caller<'_, WorkerContext>, handle: i32, buf: i32, cap: i32| -> i32 {
+                let value = service_response(caller.data(), handle).map(|r| r.body.clone());
+                write_guest_value(&mut caller, buf, cap, value)
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// HTTP request record of the `edge:runtime/service` and
+/// `edge:runtime/cache` interfaces.
+#[derive(ComponentType, Lift, Lower)]
+#[component(record)]
+struct HttpRequestRecord {
+    method: String,
+    uri: String,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+}
+
+/// HTTP response record of the `edge:runtime/service` and
+/// `edge:runtime/cache` interfaces.
+#[derive(ComponentType, Lift, Lower)]
+#[component(record)]
+struct HttpResponseRecord {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl From<HttpRequestRecord> for GuestRequest {
+    fn from(request: HttpRequestRecord) -> Self {
+        Self {
+            method: request.method,
+            uri: request.uri,
+            headers: request.headers,
+            body: request.body.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<HttpResponseRecord> for GuestResponse {
+    fn from(response: HttpResponseRecord) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        }
+    }
+}
+
+impl From<GuestResponse> for HttpResponseRecord {
+    fn from(response: GuestResponse) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        }
+    }
+}
+
+/// Register the `edge:runtime/service` interface on a component linker.
+pub fn register_service_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    linker
+        .instance("edge:runtime/service@0.1.0")
+        .and_then(|mut instance| {
+            instance.func_wrap_async(
+                "invoke",
+                |mut store: StoreContextMut<'_, WorkerContext>,
+                 (module_id, request): (String, HttpRequestRecord)| {
+                    Box::new(async move {
+                        let result = ServiceHost::invoke(&mut store, &module_id, request.into())
+                            .await
+                            .map(HttpResponseRecord::from)
+                            .map_err(|e| e.to_string());
+
+                        Ok((result,))
+                    })
+                },
+            )
+        })
+        .map_err(|e| {
+            RuntimeError::invalid_config(format!("Failed to register service interface: {e}"))
+        })?;
+
+    Ok(())
+}
+
+/// Register the key-value store host functions.
+///
+/// Registers:
+/// - `env::kv_get(key_ptr: i32, key_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::kv_set(key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32, ttl_ms: i64) -> i32`
+/// - `env::kv_delete(key_ptr: i32, key_len: i32) -> i32`
+/// - `env::kv_list(prefix_ptr: i32, prefix_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::kv_cas(key_ptr: i32, key_len: i32, expected_ptr: i32, expected_len: i32,
+///   value_ptr: i32, value_len: i32, ttl_ms: i64) -> i32`
+///
+/// # Memory Protocol
+///
+/// `kv_get` and `kv_list` follow the same buffer convention as
+/// [`register_exchange`], returning `-1` if the key does not exist.
+/// `kv_list` writes matching keys separated by `\n`.
+///
+/// A `ttl_ms` of `0` or less stores the value without expiry. `kv_cas`
+/// takes an `expected_ptr` of `-1` to mean "key must be absent".
+///
+/// `kv_set` returns `0` on success, `kv_delete` and `kv_cas` return `1` if
+/// the key was deleted or swapped and `0` otherwise. All functions return
+/// `-1` for invalid guest memory, or the negative [`KvHost::error_code`] of
+/// a failed operation.
+pub fn register_kv(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register kv function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "kv_get",
+            |mut caller: Caller<'_, WorkerContext>,
+             (key_ptr, key_len, buf, cap): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(key) = read_guest_string(&mut caller, key_ptr, key_len) else {
+                        return Ok(-1);
+                    };
+                    match KvHost::get(caller.data_mut(), &key).await {
+                        Ok(value) => Ok(write_guest_value(&mut caller, buf, cap, value)),
+                        Err(e) => Ok(kv_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "kv_set",
+            |mut caller: Caller<'_, WorkerContext>,
+             (key_ptr, key_len, value_ptr, value_len, ttl_ms): (i32, i32, i32, i32, i64)| {
+                Box::new(async move {
+                    let key = read_guest_string(&mut caller, key_ptr, key_len);
+                    let value = read_guest_bytes(&mut caller, value_ptr, value_len);
+                    let (Some(key), Some(value)) = (key, value) else {
+                        return Ok(-1);
+                    };
+                    match KvHost::set(caller.data_mut(), &key, value, ttl(ttl_ms)).await {
+                        Ok(()) => Ok(0),
+                        Err(e) => Ok(kv_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "kv_delete",
+            |mut caller: Caller<'_, WorkerContext>, (key_ptr, key_len): (i32, i32)| {
+                Box::new(async move {
+                    let Some(key) = read_guest_string(&mut caller, key_ptr, key_len) else {
+                        return Ok(-1);
+                    };
+                    match KvHost::delete(caller.data_mut(), &key).await {
+                        Ok(deleted) => Ok(i32::from(deleted)),
+                        Err(e) => Ok(kv_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "kv_list",
+            |mut caller: Caller<'_, WorkerContext>,
+             (prefix_ptr, prefix_len, buf, cap): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(prefix) = read_guest_string(&mut caller, prefix_ptr, prefix_len)
+                    else {
+                        return Ok(-1);
+                    };
+                    match KvHost::list(caller.data_mut(), &prefix).await {
+                        Ok(keys) => {
+                            let value = keys.join("\n").into_bytes();
+                            Ok(write_guest_value(&mut caller, buf, cap, Some(value)))
+                        }
+                        Err(e) => Ok(kv_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "kv_cas",
+            |mut caller: Caller<'_, WorkerContext>,
+             (key_ptr, key_len, expected_ptr, expected_len, value_ptr, value_len, ttl_ms): (
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+                i64,
+            )| {
+                Box::new(async move {
+                    let key = read_guest_string(&mut caller, key_ptr, key_len);
+                    let expected = if expected_ptr == -1 {
+                        Some(None)
+                    } else {
+                        read_guest_bytes(&mut caller, expected_ptr, expected_len).map(Some)
+                    };
+                    let value = read_guest_bytes(&mut caller, value_ptr, value_len);
+                    let (Some(key), Some(expected), Some(value)) = (key, expected, value) else {
+                        return Ok(-1);
+                    };
+                    match KvHost::compare_and_swap(
+                        caller.data_mut(),
+                        &key,
+                        expected,
+                        value,
+                        ttl(ttl_ms),
+                    )
+                    .await
+                    {
+                        Ok(swapped) => Ok(i32::from(swapped)),
+                        Err(e) => Ok(kv_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the key-value store interface on a component linker.
+///
+/// Registers `edge:runtime/kv@0.1.0` with `get`, `set`, `delete`, `list`
+/// and `compare-and-swap`. Errors are returned to the guest as strings.
+pub fn register_kv_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register kv interface: {e}"))
+    };
+
+    let mut instance = linker.instance("edge:runtime/kv@0.1.0").map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "get",
+            |mut store: StoreContextMut<'_, WorkerContext>, (key,): (String,)| {
+                Box::new(async move {
+                    let result = KvHost::get(store.data_mut(), &key).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "set",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (key, value, ttl_ms): (String, Vec<u8>, Option<u64>)| {
+                Box::new(async move {
+                    let ttl = ttl_ms.map(Duration::from_millis);
+                    let result = KvHost::set(store.data_mut(), &key, value, ttl).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "delete",
+            |mut store: StoreContextMut<'_, WorkerContext>, (key,): (String,)| {
+                Box::new(async move {
+                    let result = KvHost::delete(store.data_mut(), &key).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "list",
+            |mut store: StoreContextMut<'_, WorkerContext>, (prefix,): (String,)| {
+                Box::new(async move {
+                    let result = KvHost::list(store.data_mut(), &prefix).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "compare-and-swap",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (key, expected, value, ttl_ms): (String, Option<Vec<u8>>, Vec<u8>, Option<u64>)| {
+                Box::new(async move {
+                    let ttl = ttl_ms.map(Duration::from_millis);
+                    let result =
+                        KvHost::compare_and_swap(store.data_mut(), &key, expected, value, ttl)
+                            .await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the response cache host functions.
+///
+/// Registers:
+/// - `env::cache_match(url_ptr: i32, url_len: i32, headers_ptr: i32, headers_len: i32) -> i32`
+/// - `env::cache_put(url_ptr: i32, url_len: i32, headers_ptr: i32, headers_len: i32) -> i32`
+/// - `env::cache_delete(url_ptr: i32, url_len: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// The URL and request headers identify a `GET` request; headers are
+/// encoded as `name: value` lines separated by `\n` and only matter for
+/// responses with a `Vary` header.
+///
+/// `cache_match` replaces the current response with the cached one on a
+/// hit and returns `1`, or returns `0` on a miss. `cache_put` stores the
+/// current response and returns `1`, or `0` if it is not cacheable.
+/// `cache_delete` returns `1` if anything was removed. All functions
+/// return `-1` for invalid guest memory; `cache_put` also returns `-1` if
+/// there is no response yet.
+pub fn register_cache(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register cache function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "cache_match",
+            |mut caller: Caller<'_, WorkerContext>,
+             url_ptr: i32,
+             url_len: i32,
+             headers_ptr: i32,
+             headers_len: i32|
+             -> i32 {
+                let Some(request) =
+                    read_cache_request(&mut caller, url_ptr, url_len, headers_ptr, headers_len)
+                else {
+                    return -1;
+                };
+                match CacheHost::lookup(caller.data_mut(), &request) {
+                    Some(response) => {
+                        i32::from(ExchangeHost::set_response(caller.data_mut(), response))
+                    }
+                    None => 0,
+                }
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "cache_put",
+            |mut caller: Caller<'_, WorkerContext>,
+             url_ptr: i32,
+             url_len: i32,
+             headers_ptr: i32,
+             headers_len: i32|
+             -> i32 {
+                let Some(request) =
+                    read_cache_request(&mut caller, url_ptr, url_len, headers_ptr, headers_len)
+                else {
+                    return -1;
+                };
+                let Some(response) = ExchangeHost::response(caller.data()).cloned() else {
+                    return -1;
+                };
+                i32::from(CacheHost::store(caller.data(), &request, response))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "cache_delete",
+            |mut caller: Caller<'_, WorkerContext>, url_ptr: i32, url_len: i32| -> i32 {
+                let Some(url) = read_guest_string(&mut caller, url_ptr, url_len) else {
+                    return -1;
+                };
+                i32::from(CacheHost::delete(caller.data(), &url))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the response cache interface on a component linker.
+///
+/// Registers `edge:runtime/cache@0.1.0` with `match`, `put` and `delete`.
+pub fn register_cache_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register cache interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/cache@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "match",
+            |mut store: StoreContextMut<'_, WorkerContext>, (request,): (HttpRequestRecord,)| {
+                let response = CacheHost::lookup(store.data_mut(), &request.into());
+                Ok((response.map(HttpResponseRecord::from),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "put",
+            |store: StoreContextMut<'_, WorkerContext>,
+             (request, response): (HttpRequestRecord, HttpResponseRecord)| {
+                let stored = CacheHost::store(store.data(), &request.into(), response.into());
+                Ok((stored,))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "delete",
+            |store: StoreContextMut<'_, WorkerContext>, (request,): (HttpRequestRecord,)| {
+                Ok((CacheHost::delete(store.data(), &request.uri),))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the SQL database host functions.
+///
+/// Registers:
+/// - `env::sql_execute(sql_ptr: i32, sql_len: i32, params_ptr: i32, params_len: i32) -> i64`
+/// - `env::sql_query(sql_ptr: i32, sql_len: i32, params_ptr: i32, params_len: i32, buf: i32, cap: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// Parameters are a JSON array of values: `null`, numbers, strings, or
+/// byte arrays for blobs. A zero `params_len` binds no parameters.
+///
+/// `sql_execute` returns the number of rows changed. `sql_query` writes
+/// the result as JSON `{"columns": [...], "rows": [[...], ...]}` to the
+/// buffer and returns its full length; if the buffer is too small, nothing
+/// is written and the query must be run again with a larger buffer.
+///
+/// Both functions return `-1` for invalid guest memory, or the negative
+/// [`SqlHost::error_code`] of a failed statement.
+pub fn register_sql(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register sql function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "sql_execute",
+            |mut caller: Caller<'_, WorkerContext>,
+             (sql_ptr, sql_len, params_ptr, params_len): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let Some((sql, params)) =
+                        read_sql_statement(&mut caller, sql_ptr, sql_len, params_ptr, params_len)
+                    else {
+                        return Ok(-1);
+                    };
+                    let result = match params {
+                        Ok(params) => SqlHost::execute(caller.data_mut(), sql, params).await,
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(changed) => Ok(i64::try_from(changed).unwrap_or(i64::MAX)),
+                        Err(e) => Ok(i64::from(sql_error(&caller, &e))),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "sql_query",
+            |mut caller: Caller<'_, WorkerContext>,
+             (sql_ptr, sql_len, params_ptr, params_len, buf, cap): (
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+            )| {
+                Box::new(async move {
+                    let Some((sql, params)) =
+                        read_sql_statement(&mut caller, sql_ptr, sql_len, params_ptr, params_len)
+                    else {
+                        return Ok(-1);
+                    };
+                    let result = match params {
+                        Ok(params) => SqlHost::query(caller.data_mut(), sql, params).await,
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(rows) => {
+                            let value = serde_json::to_vec(&rows).ok();
+                            Ok(write_guest_value(&mut caller, buf, cap, value))
+                        }
+                        Err(e) => Ok(sql_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// SQL value of the `edge:runtime/sql` interface.
+#[derive(ComponentType, Lift, Lower)]
+#[component(variant)]
+enum SqlValueRecord {
+    #[component(name = "null")]
+    Null,
+    #[component(name = "integer")]
+    Integer(i64),
+    #[component(name = "real")]
+    Real(f64),
+    #[component(name = "text")]
+    Text(String),
+    #[component(name = "blob")]
+    Blob(Vec<u8>),
+}
+
+/// Query result record of the `edge:runtime/sql` interface.
+#[derive(ComponentType, Lift, Lower)]
+#[component(record)]
+struct SqlRowsRecord {
+    columns: Vec<String>,
+    rows: Vec<Vec<SqlValueRecord>>,
+}
+
+impl From<SqlValueRecord> for SqlValue {
+    fn from(value: SqlValueRecord) -> Self {
+        match value {
+            SqlValueRecord::Null => Self::Null,
+            SqlValueRecord::Integer(value) => Self::Integer(value),
+            SqlValueRecord::Real(value) => Self::Real(value),
+            SqlValueRecord::Text(value) => Self::Text(value),
+            SqlValueRecord::Blob(value) => Self::Blob(value),
+        }
+    }
+}
+
+impl From<SqlValue> for SqlValueRecord {
+    fn from(value: SqlValue) -> Self {
+        match value {
+            SqlValue::Null => Self::Null,
+            SqlValue::Integer(value) => Self::Integer(value),
+            SqlValue::Real(value) => Self::Real(value),
+            SqlValue::Text(value) => Self::Text(value),
+            SqlValue::Blob(value) => Self::Blob(value),
+        }
+    }
+}
+
+impl From<SqlRows> for SqlRowsRecord {
+    fn from(rows: SqlRows) -> Self {
+        Self {
+            columns: rows.columns,
+            rows: rows
+                .rows
+                .into_iter()
+                .map(|row| row.into_iter().map(Into::into).collect())
+                .collect(),
+        }
+    }
+}
+
+/// Register the SQL database interface on a component linker.
+///
+/// Registers `edge:runtime/sql@0.1.0` with `execute` and `query`.
+pub fn register_sql_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register sql interface: {e}"))
+    };
+
+    let mut instance = linker.instance("edge:runtime/sql@0.1.0").map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "execute",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (statement, params): (String, Vec<SqlValueRecord>)| {
+                Box::new(async move {
+                    let params = params.into_iter().map(Into::into).collect();
+                    let result = SqlHost::execute(store.data_mut(), statement, params).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "query",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (statement, params): (String, Vec<SqlValueRecord>)| {
+                Box::new(async move {
+                    let params = params.into_iter().map(Into::into).collect();
+                    let result = SqlHost::query(store.data_mut(), statement, params).await;
+                    Ok((result.map(SqlRowsRecord::from).map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the blob storage host functions.
+///
+/// Registers:
+/// - `env::blob_put_start(bucket_ptr: i32, bucket_len: i32, key_ptr: i32, key_len: i32, type_ptr: i32, type_len: i32) -> i32`
+/// - `env::blob_write(handle: i32, ptr: i32, len: i32) -> i32`
+/// - `env::blob_put_finish(handle: i32, buf: i32, cap: i32) -> i32`
+/// - `env::blob_get_open(bucket_ptr: i32, bucket_len: i32, key_ptr: i32, key_len: i32) -> i32`
+/// - `env::blob_read(handle: i32, buf: i32, cap: i32) -> i32`
+/// - `env::blob_close(handle: i32) -> i32`
+/// - `env::blob_head(bucket_ptr: i32, bucket_len: i32, key_ptr: i32, key_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::blob_delete(bucket_ptr: i32, bucket_len: i32, key_ptr: i32, key_len: i32) -> i32`
+/// - `env::blob_list(bucket_ptr: i32, bucket_len: i32, prefix_ptr: i32, prefix_len: i32, buf: i32, cap: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// Objects are streamed through handles. `blob_put_start` and
+/// `blob_get_open` return a handle (`blob_get_open` returns `-1` if the
+/// object does not exist); a zero `type_len` stores no content type.
+/// `blob_write` appends a chunk to an upload and `blob_read` fills the
+/// buffer with the next chunk of a download, returning the number of bytes
+/// read (`0` at the end). `blob_put_finish` stores the object and closes
+/// the handle; `blob_close` discards an unfinished upload or ends a
+/// download.
+///
+/// Metadata is written as JSON `{"key", "size", "digest", "content_type",
+/// "created_ms"}` by `blob_put_finish` and `blob_head`, and as a JSON array
+/// of such objects by `blob_list`; these return the full length of the
+/// JSON like other buffer-filling functions. `blob_delete` returns `1` if
+/// the object existed.
+///
+/// All functions return `-1` for invalid guest memory, or the negative
+/// [`BlobHost::error_code`] of a failed operation.
+pub fn register_blob(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    register_blob_streams(linker)?;
+    register_blob_objects(linker)
+}
+
+fn register_blob_streams(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register blob function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_put_start",
+            |mut caller: Caller<'_, WorkerContext>,
+             (bucket_ptr, bucket_len, key_ptr, key_len, type_ptr, type_len): (
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+            )| {
+                Box::new(async move {
+                    let bucket = read_guest_string(&mut caller, bucket_ptr, bucket_len);
+                    let key = read_guest_string(&mut caller, key_ptr, key_len);
+                    let content_type = if type_len == 0 {
+                        Some(None)
+                    } else {
+                        read_guest_string(&mut caller, type_ptr, type_len).map(Some)
+                    };
+                    let (Some(bucket), Some(key), Some(content_type)) = (bucket, key, content_type)
+                    else {
+                        return Ok(-1);
+                    };
+                    match BlobHost::put_start(caller.data_mut(), &bucket, &key, content_type).await
+                    {
+                        Ok(handle) => Ok(handle),
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_write",
+            |mut caller: Caller<'_, WorkerContext>, (handle, ptr, len): (i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(chunk) = read_guest_bytes(&mut caller, ptr, len) else {
+                        return Ok(-1);
+                    };
+                    match BlobHost::put_write(caller.data_mut(), handle, &chunk).await {
+                        Ok(()) => Ok(0),
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_put_finish",
+            |mut caller: Caller<'_, WorkerContext>, (handle, buf, cap): (i32, i32, i32)| {
+                Box::new(async move {
+                    match BlobHost::put_finish(caller.data_mut(), handle).await {
+                        Ok(meta) => {
+                            let value = serde_json::to_vec(&meta).ok();
+                            Ok(write_guest_value(&mut caller, buf, cap, value))
+                        }
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_get_open",
+            |mut caller: Caller<'_, WorkerContext>,
+             (bucket_ptr, bucket_len, key_ptr, key_len): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let bucket = read_guest_string(&mut caller, bucket_ptr, bucket_len);
+                    let key = read_guest_string(&mut caller, key_ptr, key_len);
+                    let (Some(bucket), Some(key)) = (bucket, key) else {
+                        return Ok(-1);
+                    };
+                    match BlobHost::get_open(caller.data_mut(), &bucket, &key).await {
+                        Ok(Some((handle, _))) => Ok(handle),
+                        Ok(None) => Ok(-1),
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_read",
+            |mut caller: Caller<'_, WorkerContext>, (handle, buf, cap): (i32, i32, i32)| {
+                Box::new(async move {
+                    let max = usize::try_from(cap).unwrap_or(0);
+                    match BlobHost::read(caller.data_mut(), handle, max).await {
+                        Ok(chunk) => Ok(write_guest_value(&mut caller, buf, cap, Some(chunk))),
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "blob_close",
+            |mut caller: Caller<'_, WorkerContext>, handle: i32| -> i32 {
+                status_code(BlobHost::close(caller.data_mut(), handle))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+fn register_blob_objects(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register blob function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_head",
+            |mut caller: Caller<'_, WorkerContext>,
+             (bucket_ptr, bucket_len, key_ptr, key_len, buf, cap): (
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+            )| {
+                Box::new(async move {
+                    let bucket = read_guest_string(&mut caller, bucket_ptr, bucket_len);
+                    let key = read_guest_string(&mut caller, key_ptr, key_len);
+                    let (Some(bucket), Some(key)) = (bucket, key) else {
+                        return Ok(-1);
+                    };
+                    match BlobHost::head(caller.data_mut(), &bucket, &key).await {
+                        Ok(meta) => {
+                            let value = meta.and_then(|m| serde_json::to_vec(&m).ok());
+                            Ok(write_guest_value(&mut caller, buf, cap, value))
+                        }
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_delete",
+            |mut caller: Caller<'_, WorkerContext>,
+             (bucket_ptr, bucket_len, key_ptr, key_len): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let bucket = read_guest_string(&mut caller, bucket_ptr, bucket_len);
+                    let key = read_guest_string(&mut caller, key_ptr, key_len);
+                    let (Some(bucket), Some(key)) = (bucket, key) else {
+                        return Ok(-1);
+                    };
+                    match BlobHost::delete(caller.data_mut(), &bucket, &key).await {
+                        Ok(deleted) => Ok(i32::from(deleted)),
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "blob_list",
+            |mut caller: Caller<'_, WorkerContext>,
+             (bucket_ptr, bucket_len, prefix_ptr, prefix_len, buf, cap): (
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+                i32,
+            )| {
+                Box::new(async move {
+                    let bucket = read_guest_string(&mut caller, bucket_ptr, bucket_len);
+                    let prefix = read_guest_string(&mut caller, prefix_ptr, prefix_len);
+                    let (Some(bucket), Some(prefix)) = (bucket, prefix) else {
+                        return Ok(-1);
+                    };
+                    match BlobHost::list(caller.data_mut(), &bucket, &prefix).await {
+                        Ok(objects) => {
+                            let value = serde_json::to_vec(&objects).ok();
+                            Ok(write_guest_value(&mut caller, buf, cap, value))
+                        }
+                        Err(e) => Ok(blob_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Object metadata record of the `edge:runtime/blob` interface.
+#[derive(ComponentType, Lift, Lower)]
+#[component(record)]
+struct BlobMetaRecord {
+    key: String,
+    size: u64,
+    digest: String,
+    #[component(name = "content-type")]
+    content_type: Option<String>,
+    #[component(name = "created-ms")]
+    created_ms: u64,
+}
+
+impl From<BlobMeta> for BlobMetaRecord {
+    fn from(meta: BlobMeta) -> Self {
+        Self {
+            key: meta.key,
+            size: meta.size,
+            digest: meta.digest,
+            content_type: meta.content_type,
+            created_ms: meta.created_ms,
+        }
+    }
+}
+
+/// Register the blob storage interface on a component linker.
+///
+/// Registers `edge:runtime/blob@0.1.0` with `put-start`, `write`,
+/// `put-finish`, `get-open`, `read`, `close`, `head`, `delete` and `list`.
+pub fn register_blob_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register blob interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/blob@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "put-start",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (bucket, key, content_type): (String, String, Option<String>)| {
+                Box::new(async move {
+                    let result =
+                        BlobHost::put_start(store.data_mut(), &bucket, &key, content_type).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "write",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle, chunk): (i32, Vec<u8>)| {
+                Box::new(async move {
+                    let result = BlobHost::put_write(store.data_mut(), handle, &chunk).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "put-finish",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle,): (i32,)| {
+                Box::new(async move {
+                    let result = BlobHost::put_finish(store.data_mut(), handle).await;
+                    Ok((result.map(BlobMetaRecord::from).map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "get-open",
+            |mut store: StoreContextMut<'_, WorkerContext>, (bucket, key): (String, String)| {
+                Box::new(async move {
+                    let result = BlobHost::get_open(store.data_mut(), &bucket, &key).await;
+                    let result = result.map(|opened| {
+                        opened.map(|(handle, meta)| (handle, BlobMetaRecord::from(meta)))
+                    });
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "read",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle, max): (i32, u32)| {
+                Box::new(async move {
+                    let max = usize::try_from(max).unwrap_or(usize::MAX);
+                    let result = BlobHost::read(store.data_mut(), handle, max).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "close",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle,): (i32,)| {
+                Ok((BlobHost::close(store.data_mut(), handle),))
+            },
+        )
+        .map_err(map_err)?;
+
+    register_blob_component_objects(&mut instance)
+}
+
+fn register_blob_component_objects(
+    instance: &mut wasmtime::component::LinkerInstance<'_, WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register blob interface: {e}"))
+    };
+
+    instance
+        .func_wrap_async(
+            "head",
+            |mut store: StoreContextMut<'_, WorkerContext>, (bucket, key): (String, String)| {
+                Box::new(async move {
+                    let result = BlobHost::head(store.data_mut(), &bucket, &key).await;
+                    let result = result.map(|meta| meta.map(BlobMetaRecord::from));
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "delete",
+            |mut store: StoreContextMut<'_, WorkerContext>, (bucket, key): (String, String)| {
+                Box::new(async move {
+                    let result = BlobHost::delete(store.data_mut(), &bucket, &key).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "list",
+            |mut store: StoreContextMut<'_, WorkerContext>, (bucket, prefix): (String, String)| {
+                Box::new(async move {
+                    let result = BlobHost::list(store.data_mut(), &bucket, &prefix).await;
+                    let result = result.map(|objects| {
+                        objects
+                            .into_iter()
+                            .map(BlobMetaRecord::from)
+                            .collect::<Vec<_>>()
+                    });
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the configuration host functions.
+///
+/// Registers:
+/// - `env::config_get(key_ptr: i32, key_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::config_get_secret(key_ptr: i32, key_len: i32, buf: i32, cap: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// Both functions write the value to the buffer and return its full
+/// length; if the buffer is too small, nothing is written and the call
+/// must be repeated with a larger buffer. They return `-1` if the key is
+/// not set or for invalid guest memory.
+pub fn register_config(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register config function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "config_get",
+            |mut caller: Caller<'_, WorkerContext>,
+             key_ptr: i32,
+             key_len: i32,
+             buf: i32,
+             cap: i32|
+             -> i32 {
+                let Some(key) = read_guest_string(&mut caller, key_ptr, key_len) else {
+                    return -1;
+                };
+                let value = ConfigHost::get(caller.data(), &key);
+                write_guest_value(&mut caller, buf, cap, value.map(String::into_bytes))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "config_get_secret",
+            |mut caller: Caller<'_, WorkerContext>,
+             key_ptr: i32,
+             key_len: i32,
+             buf: i32,
+             cap: i32|
+             -> i32 {
+                let Some(key) = read_guest_string(&mut caller, key_ptr, key_len) else {
+                    return -1;
+                };
+                let value = ConfigHost::get_secret(caller.data(), &key);
+                write_guest_value(&mut caller, buf, cap, value.map(String::into_bytes))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the configuration interface on a component linker.
+///
+/// Registers `edge:runtime/config@0.1.0` with `get` and `get-secret`.
+pub fn register_config_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register config interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/config@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "get",
+            |store: StoreContextMut<'_, WorkerContext>, (key,): (String,)| {
+                Ok((ConfigHost::get(store.data(), &key),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "get-secret",
+            |store: StoreContextMut<'_, WorkerContext>, (key,): (String,)| {
+                Ok((ConfigHost::get_secret(store.data(), &key),))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the crypto host functions.
+///
+/// Registers:
+/// - `env::crypto_digest(alg: i32, data_ptr: i32, data_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::crypto_hmac(alg: i32, key_ptr: i32, key_len: i32, data_ptr: i32, data_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::crypto_sign(name_ptr: i32, name_len: i32, data_ptr: i32, data_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::crypto_verify(name_ptr: i32, name_len: i32, data_ptr: i32, data_len: i32, sig_ptr: i32, sig_len: i32) -> i32`
+/// - `env::crypto_encrypt(name_ptr: i32, name_len: i32, data_ptr: i32, data_len: i32, aad_ptr: i32, aad_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::crypto_decrypt(name_ptr: i32, name_len: i32, data_ptr: i32, data_len: i32, aad_ptr: i32, aad_len: i32, buf: i32, cap: i32) -> i32`
+/// - `env::crypto_random(buf: i32, len: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// `alg` is `0` for SHA-1, `1` for SHA-256 and `2` for SHA-512. Keys for
+/// signing and encryption are host-held keys referenced by name.
+///
+/// Functions producing bytes write them to the buffer and return their
+/// full length; if the buffer is too small, nothing is written and the
+/// call must be repeated with a larger buffer. `crypto_verify` returns `1`
+/// for a valid signature and `0` otherwise; `crypto_random` fills `len`
+/// bytes and returns `len`.
+///
+/// All functions return `-1` for invalid guest memory, or the negative
+/// [`CryptoHost::error_code`] of a failed operation. The work is charged
+/// to the store's fuel; running out of fuel traps.
+pub fn register_crypto(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    register_crypto_hashing(linker)?;
+    register_crypto_keys(linker)
+}
+
+fn register_crypto_hashing(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register crypto function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "crypto_digest",
+            |mut caller: Caller<'_, WorkerContext>,
+             alg: i32,
+             data_ptr: i32,
+             data_len: i32,
+             buf: i32,
+             cap: i32|
+             -> wasmtime::Result<i32> {
+                let Some(data) = read_guest_bytes(&mut caller, data_ptr, data_len) else {
+                    return Ok(-1);
+                };
+                let Some(algorithm) = DigestAlgorithm::from_i32(alg) else {
+                    return Ok(crypto_error(&caller, &invalid_digest(alg)));
+                };
+                charge_crypto_fuel(&mut caller, data.len(), false)?;
+                let digest = CryptoHost::digest(algorithm, &data);
+                Ok(write_guest_value(&mut caller, buf, cap, Some(digest)))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "crypto_hmac",
+            |mut caller: Caller<'_, WorkerContext>,
+             alg: i32,
+             key_ptr: i32,
+             key_len: i32,
+             data_ptr: i32,
+             data_len: i32,
+             buf: i32,
+             cap: i32|
+             -> wasmtime::Result<i32> {
+                let key = read_guest_bytes(&mut caller, key_ptr, key_len);
+                let data = read_guest_bytes(&mut caller, data_ptr, data_len);
+                let (Some(key), Some(data)) = (key, data) else {
+                    return Ok(-1);
+                };
+                let Some(algorithm) = DigestAlgorithm::from_i32(alg) else {
+                    return Ok(crypto_error(&caller, &invalid_digest(alg)));
+                };
+                charge_crypto_fuel(&mut caller, key.len() + data.len(), false)?;
+                let mac = CryptoHost::hmac(algorithm, &key, &data);
+                Ok(write_guest_value(&mut caller, buf, cap, Some(mac)))
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "crypto_random",
+            |mut caller: Caller<'_, WorkerContext>, buf: i32, len: i32| -> wasmtime::Result<i32> {
+                let Ok(len) = usize::try_from(len) else {
+                    return Ok(-1);
+                };
+                charge_crypto_fuel(&mut caller, len, false)?;
+                match CryptoHost::random_bytes(len) {
+                    Ok(bytes) => Ok(write_guest_value(
+                        &mut caller,
+                        buf,
+                        i32::try_from(len)?,
+                        Some(bytes),
+                    )),
+                    Err(e) => Ok(crypto_error(&caller, &e)),
+                }
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+fn register_crypto_keys(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register crypto function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "crypto_sign",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             data_ptr: i32,
+             data_len: i32,
+             buf: i32,
+             cap: i32|
+             -> wasmtime::Result<i32> {
+                let name = read_guest_string(&mut caller, name_ptr, name_len);
+                let data = read_guest_bytes(&mut caller, data_ptr, data_len);
+                let (Some(name), Some(data)) = (name, data) else {
+                    return Ok(-1);
+                };
+                charge_crypto_fuel(&mut caller, data.len(), true)?;
+                match CryptoHost::sign(caller.data(), &name, &data) {
+                    Ok(signature) => Ok(write_guest_value(&mut caller, buf, cap, Some(signature))),
+                    Err(e) => Ok(crypto_error(&caller, &e)),
+                }
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "crypto_verify",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             data_ptr: i32,
+             data_len: i32,
+             sig_ptr: i32,
+             sig_len: i32|
+             -> wasmtime::Result<i32> {
+                let name = read_guest_string(&mut caller, name_ptr, name_len);
+                let data = read_guest_bytes(&mut caller, data_ptr, data_len);
+                let signature = read_guest_bytes(&mut caller, sig_ptr, sig_len);
+                let (Some(name), Some(data), Some(signature)) = (name, data, signature) else {
+                    return Ok(-1);
+                };
+                charge_crypto_fuel(&mut caller, data.len(), true)?;
+                match CryptoHost::verify(caller.data(), &name, &data, &signature) {
+                    Ok(valid) => Ok(i32::from(valid)),
+                    Err(e) => Ok(crypto_error(&caller, &e)),
+                }
+            },
+        )
+        .map_err(map_err)?;
+
+    for (name, encrypt) in [("crypto_encrypt", true), ("crypto_decrypt", false)] {
+        linker
+            .func_wrap(
+                "env",
+                name,
+                move |mut caller: Caller<'_, WorkerContext>,
+                      name_ptr: i32,
+                      name_len: i32,
+                      data_ptr: i32,
+                      data_len: i32,
+                      aad_ptr: i32,
+                      aad_len: i32,
+                      buf: i32,
+                      cap: i32|
+                      -> wasmtime::Result<i32> {
+                    let name = read_guest_string(&mut caller, name_ptr, name_len);
+                    let data = read_guest_bytes(&mut caller, data_ptr, data_len);
+                    let aad = read_guest_bytes(&mut caller, aad_ptr, aad_len);
+                    let (Some(name), Some(data), Some(aad)) = (name, data, aad) else {
+                        return Ok(-1);
+                    };
+                    charge_crypto_fuel(&mut caller, data.len() + aad.len(), false)?;
+                    let result = if encrypt {
+                        CryptoHost::encrypt(caller.data(), &name, &data, &aad)
+                    } else {
+                        CryptoHost::decrypt(caller.data(), &name, &data, &aad)
+                    };
+                    match result {
+                        Ok(output) => Ok(write_guest_value(&mut caller, buf, cap, Some(output))),
+                        Err(e) => Ok(crypto_error(&caller, &e)),
+                    }
+                },
+            )
+            .map_err(map_err)?;
+    }
+
+    Ok(())
+}
+
+/// Hash algorithm of the crypto interface.
+///
+/// Variants are only constructed by lifting guest values.
+#[allow(dead_code)]
+#[derive(ComponentType, Lift, Clone, Copy)]
+#[component(enum)]
+#[repr(u8)]
+enum DigestAlgorithmRecord {
+    #[component(name = "sha1")]
+    Sha1,
+    #[component(name = "sha256")]
+    Sha256,
+    #[component(name = "sha512")]
+    Sha512,
+}
+
+impl From<DigestAlgorithmRecord> for DigestAlgorithm {
+    fn from(algorithm: DigestAlgorithmRecord) -> Self {
+        match algorithm {
+            DigestAlgorithmRecord::Sha1 => Self::Sha1,
+            DigestAlgorithmRecord::Sha256 => Self::Sha256,
+            DigestAlgorithmRecord::Sha512 => Self::Sha512,
+        }
+    }
+}
+
+/// Register the crypto interface on a component linker.
+///
+/// Registers `edge:runtime/crypto@0.1.0` with `digest`, `hmac`, `sign`,
+/// `verify`, `encrypt`, `decrypt` and `get-random-bytes`.
+pub fn register_crypto_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register crypto interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/crypto@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "digest",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (algorithm, data): (DigestAlgorithmRecord, Vec<u8>)| {
+                charge_crypto_fuel(&mut store, data.len(), false)?;
+                Ok((CryptoHost::digest(algorithm.into(), &data),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "hmac",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (algorithm, key, data): (DigestAlgorithmRecord, Vec<u8>, Vec<u8>)| {
+                charge_crypto_fuel(&mut store, key.len() + data.len(), false)?;
+                Ok((CryptoHost::hmac(algorithm.into(), &key, &data),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "sign",
+            |mut store: StoreContextMut<'_, WorkerContext>, (key, data): (String, Vec<u8>)| {
+                charge_crypto_fuel(&mut store, data.len(), true)?;
+                let result = CryptoHost::sign(store.data(), &key, &data);
+                Ok((result.map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "verify",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (key, data, signature): (String, Vec<u8>, Vec<u8>)| {
+                charge_crypto_fuel(&mut store, data.len(), true)?;
+                let result = CryptoHost::verify(store.data(), &key, &data, &signature);
+                Ok((result.map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "encrypt",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (key, plaintext, aad): (String, Vec<u8>, Vec<u8>)| {
+                charge_crypto_fuel(&mut store, plaintext.len() + aad.len(), false)?;
+                let result = CryptoHost::encrypt(store.data(), &key, &plaintext, &aad);
+                Ok((result.map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "decrypt",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (key, ciphertext, aad): (String, Vec<u8>, Vec<u8>)| {
+                charge_crypto_fuel(&mut store, ciphertext.len() + aad.len(), false)?;
+                let result = CryptoHost::decrypt(store.data(), &key, &ciphertext, &aad);
+                Ok((result.map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "get-random-bytes",
+            |mut store: StoreContextMut<'_, WorkerContext>, (len,): (u32,)| {
+                let len = usize::try_from(len)?;
+                charge_crypto_fuel(&mut store, len, false)?;
+                Ok((CryptoHost::random_bytes(len).map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the outbound TCP socket host functions.
+///
+/// Registers:
+/// - `env::socket_connect(host_ptr: i32, host_len: i32, port: i32, flags: i32) -> i32`
+/// - `env::socket_send(handle: i32, ptr: i32, len: i32) -> i32`
+/// - `env::socket_recv(handle: i32, buf: i32, cap: i32) -> i32`
+/// - `env::socket_close(handle: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// `socket_connect` returns a connection handle; bit 0 of `flags` wraps the
+/// connection in TLS. `socket_send` sends the whole buffer and returns `0`.
+/// `socket_recv` waits for data and fills the buffer, returning the number
+/// of bytes read (`0` once the peer closed the connection).
+///
+/// All functions return `-1` for invalid guest memory or an unknown handle
+/// in `socket_close`, or the negative [`SocketsHost::error_code`] of a
+/// failed operation.
+pub fn register_sockets(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register socket function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "socket_connect",
+            |mut caller: Caller<'_, WorkerContext>,
+             (host_ptr, host_len, port, flags): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(host) = read_guest_string(&mut caller, host_ptr, host_len) else {
+                        return Ok(-1);
+                    };
+                    let Ok(port) = u16::try_from(port) else {
+                        let error = HostFunctionError::InvalidArgument {
+                            reason: format!("invalid port {port}"),
+                        };
+                        return Ok(socket_error(&caller, &error));
+                    };
+                    let tls = flags & 1 != 0;
+                    match SocketsHost::connect(caller.data_mut(), &host, port, tls).await {
+                        Ok(handle) => Ok(handle),
+                        Err(e) => Ok(socket_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "socket_send",
+            |mut caller: Caller<'_, WorkerContext>, (handle, ptr, len): (i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(data) = read_guest_bytes(&mut caller, ptr, len) else {
+                        return Ok(-1);
+                    };
+                    match SocketsHost::send(caller.data_mut(), handle, &data).await {
+                        Ok(()) => Ok(0),
+                        Err(e) => Ok(socket_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "socket_recv",
+            |mut caller: Caller<'_, WorkerContext>, (handle, buf, cap): (i32, i32, i32)| {
+                Box::new(async move {
+                    let max = usize::try_from(cap).unwrap_or(0);
+                    match SocketsHost::recv(caller.data_mut(), handle, max).await {
+                        Ok(chunk) => Ok(write_guest_value(&mut caller, buf, cap, Some(chunk))),
+                        Err(e) => Ok(socket_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "socket_close",
+            |mut caller: Caller<'_, WorkerContext>, handle: i32| -> i32 {
+                status_code(SocketsHost::close(caller.data_mut(), handle))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the `edge:runtime/sockets` interface on a component linker.
+///
+/// Registers `edge:runtime/sockets@0.1.0` with `connect`, `send`, `recv`
+/// and `close`.
+pub fn register_sockets_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register sockets interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/sockets@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "connect",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (host, port, tls): (String, u16, bool)| {
+                Box::new(async move {
+                    let result = SocketsHost::connect(store.data_mut(), &host, port, tls).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "send",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle, data): (i32, Vec<u8>)| {
+                Box::new(async move {
+                    let result = SocketsHost::send(store.data_mut(), handle, &data).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "recv",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle, max): (i32, u32)| {
+                Box::new(async move {
+                    let max = usize::try_from(max).unwrap_or(usize::MAX);
+                    let result = SocketsHost::recv(store.data_mut(), handle, max).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "close",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle,): (i32,)| {
+                Ok((SocketsHost::close(store.data_mut(), handle),))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the outbound WebSocket host functions.
+///
+/// Registers:
+/// - `env::ws_connect(url_ptr: i32, url_len: i32, headers_ptr: i32, headers_len: i32) -> i32`
+/// - `env::ws_send(handle: i32, kind: i32, ptr: i32, len: i32) -> i32`
+/// - `env::ws_recv(handle: i32, buf: i32, cap: i32, timeout_ms: i32) -> i32`
+/// - `env::ws_close(handle: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// `ws_connect` returns a connection handle; the optional headers are
+/// `name: value` lines separated by `\n`. Message kinds are `0` for text
+/// and `1` for binary; `ws_send` returns `0`.
+///
+/// `ws_recv` waits up to `timeout_ms` (the request timeout if not
+/// positive) and writes the message kind as one byte followed by the
+/// payload, returning the total length, or `0` once the connection is
+/// closed. If the message does not fit in `cap` bytes, nothing is written
+/// and the message is kept for the next call; the return value is the
+/// length needed.
+///
+/// All functions return `-1` for invalid guest memory or an unknown handle
+/// in `ws_close`, or the negative [`WebSocketHost::error_code`] of a failed
+/// operation.
+pub fn register_websocket(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register websocket function: {e}"))
+    };
+
+    linker
+        .func_wrap_async(
+            "env",
+            "ws_connect",
+            |mut caller: Caller<'_, WorkerContext>,
+             (url_ptr, url_len, headers_ptr, headers_len): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(url) = read_guest_string(&mut caller, url_ptr, url_len) else {
+                        return Ok(-1);
+                    };
+                    let Some(headers) = read_guest_pairs(&mut caller, headers_ptr, headers_len)
+                    else {
+                        return Ok(-1);
+                    };
+                    match WebSocketHost::connect(caller.data_mut(), &url, &headers).await {
+                        Ok(handle) => Ok(handle),
+                        Err(e) => Ok(websocket_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "ws_send",
+            |mut caller: Caller<'_, WorkerContext>,
+             (handle, kind, ptr, len): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let Some(data) = read_guest_bytes(&mut caller, ptr, len) else {
+                        return Ok(-1);
+                    };
+                    let message = match kind {
+                        0 => {
+                            let Ok(text) = String::from_utf8(data) else {
+                                let error = HostFunctionError::InvalidArgument {
+                                    reason: "text message is not valid UTF-8".to_string(),
+                                };
+                                return Ok(websocket_error(&caller, &error));
+                            };
+                            WebSocketMessage::Text(text)
+                        }
+                        1 => WebSocketMessage::Binary(data),
+                        _ => {
+                            let error = HostFunctionError::InvalidArgument {
+                                reason: format!("unknown message kind {kind}"),
+                            };
+                            return Ok(websocket_error(&caller, &error));
+                        }
+                    };
+                    match WebSocketHost::send(caller.data_mut(), handle, &message).await {
+                        Ok(()) => Ok(0),
+                        Err(e) => Ok(websocket_error(&caller, &e)),
+                    }
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap_async(
+            "env",
+            "ws_recv",
+            |mut caller: Caller<'_, WorkerContext>,
+             (handle, buf, cap, timeout_ms): (i32, i32, i32, i32)| {
+                Box::new(async move {
+                    let timeout = u64::try_from(timeout_ms)
+                        .ok()
+                        .filter(|ms| *ms > 0)
+                        .map(Duration::from_millis);
+                    let message =
+                        match WebSocketHost::recv(caller.data_mut(), handle, timeout).await {
+                            Ok(Some(message)) => message,
+                            Ok(None) => return Ok(0),
+                            Err(e) => return Ok(websocket_error(&caller, &e)),
+                        };
+
+                    let needed = i32::try_from(message.len() + 1).unwrap_or(i32::MAX);
+                    if needed > cap {
+                        WebSocketHost::unread(caller.data_mut(), handle, message);
+                        return Ok(needed);
+                    }
+                    let value = match message {
+                        WebSocketMessage::Text(text) => [&[0][..], text.as_bytes()].concat(),
+                        WebSocketMessage::Binary(data) => [&[1][..], &data].concat(),
+                    };
+                    Ok(write_guest_value(&mut caller, buf, cap, Some(value)))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "ws_close",
+            |mut caller: Caller<'_, WorkerContext>, handle: i32| -> i32 {
+                status_code(WebSocketHost::close(caller.data_mut(), handle))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// WebSocket message of the `edge:runtime/websocket` interface.
+#[derive(ComponentType, Lift, Lower)]
+#[component(variant)]
+enum WebSocketMessageRecord {
+    #[component(name = "text")]
+    Text(String),
+    #[component(name = "binary")]
+    Binary(Vec<u8>),
+}
+
+impl From<WebSocketMessageRecord> for WebSocketMessage {
+    fn from(message: WebSocketMessageRecord) -> Self {
+        match message {
+            WebSocketMessageRecord::Text(text) => Self::Text(text),
+            WebSocketMessageRecord::Binary(data) => Self::Binary(data),
+        }
+    }
+}
+
+impl From<WebSocketMessage> for WebSocketMessageRecord {
+    fn from(message: WebSocketMessage) -> Self {
+        match message {
+            WebSocketMessage::Text(text) => Self::Text(text),
+            WebSocketMessage::Binary(data) => Self::Binary(data),
+        }
+    }
+}
+
+/// Register the `edge:runtime/websocket` interface on a component linker.
+///
+/// Registers `edge:runtime/websocket@0.1.0` with `connect`, `send`, `recv`
+/// and `close`.
+pub fn register_websocket_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register websocket interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/websocket@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "connect",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (url, headers): (String, Vec<(String, String)>)| {
+                Box::new(async move {
+                    let result = WebSocketHost::connect(store.data_mut(), &url, &headers).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "send",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (handle, message): (i32, WebSocketMessageRecord)| {
+                Box::new(async move {
+                    let message = WebSocketMessage::from(message);
+                    let result = WebSocketHost::send(store.data_mut(), handle, &message).await;
+                    Ok((result.map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap_async(
+            "recv",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (handle, timeout_ms): (i32, Option<u32>)| {
+                Box::new(async move {
+                    let timeout = timeout_ms.map(|ms| Duration::from_millis(u64::from(ms)));
+                    let result = WebSocketHost::recv(store.data_mut(), handle, timeout).await;
+                    Ok((result
+                        .map(|message| message.map(WebSocketMessageRecord::from))
+                        .map_err(|e| e.to_string()),))
+                })
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "close",
+            |mut store: StoreContextMut<'_, WorkerContext>, (handle,): (i32,)| {
+                Ok((WebSocketHost::close(store.data_mut(), handle),))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the custom metrics host functions.
+///
+/// Registers:
+/// - `env::metric_counter_add(name_ptr: i32, name_len: i32, labels_ptr: i32, labels_len: i32, value: f64) -> i32`
+/// - `env::metric_gauge_set(name_ptr: i32, name_len: i32, labels_ptr: i32, labels_len: i32, value: f64) -> i32`
+/// - `env::metric_histogram_record(name_ptr: i32, name_len: i32, labels_ptr: i32, labels_len: i32, value: f64) -> i32`
+///
+/// # Memory Protocol
+///
+/// Labels are passed as a header block of `name: value` lines and may be
+/// empty.
+///
+/// All functions return `0` on success, `-1` for invalid guest memory, or
+/// the negative [`MetricsHost::error_code`] of a failed operation.
+pub fn register_metrics(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let functions = [
+        ("metric_counter_add", MetricKind::Counter),
+        ("metric_gauge_set", MetricKind::Gauge),
+        ("metric_histogram_record", MetricKind::Histogram),
+    ];
+
+    for (function, kind) in functions {
+        linker
+            .func_wrap(
+                "env",
+                function,
+                move |mut caller: Caller<'_, WorkerContext>,
+                      name_ptr: i32,
+                      name_len: i32,
+                      labels_ptr: i32,
+                      labels_len: i32,
+                      value: f64|
+                      -> i32 {
+                    let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                        return -1;
+                    };
+                    let Some(labels) = read_guest_pairs(&mut caller, labels_ptr, labels_len) else {
+                        return -1;
+                    };
+                    match record_metric(caller.data(), kind, &name, &labels, value) {
+                        Ok(()) => 0,
+                        Err(e) => metrics_error(&caller, &e),
+                    }
+                },
+            )
+            .map_err(|e| {
+                RuntimeError::invalid_config(format!("Failed to register metrics function: {e}"))
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Register the custom metrics interface on a component linker.
+///
+/// Registers `edge:runtime/metrics@0.1.0` with `counter-add`, `gauge-set`
+/// and `histogram-record`.
+pub fn register_metrics_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register metrics interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/metrics@0.1.0")
+        .map_err(map_err)?;
+
+    let functions = [
+        ("counter-add", MetricKind::Counter),
+        ("gauge-set", MetricKind::Gauge),
+        ("histogram-record", MetricKind::Histogram),
+    ];
+
+    for (function, kind) in functions {
+        instance
+            .func_wrap(
+                function,
+                move |store: StoreContextMut<'_, WorkerContext>,
+                      (name, labels, value): (String, Vec<(String, String)>, f64)| {
+                    let result = record_metric(store.data(), kind, &name, &labels, value);
+                    Ok((result.map_err(|e| e.to_string()),))
+                },
+            )
+            .map_err(map_err)?;
+    }
+
+    Ok(())
+}
+
+/// Register the guest tracing span host functions.
+///
+/// Registers:
+/// - `env::span_enter(name_ptr: i32, name_len: i32, attrs_ptr: i32, attrs_len: i32) -> i32`
+/// - `env::span_exit(id: i32) -> i32`
+/// - `env::span_event(id: i32, name_ptr: i32, name_len: i32, attrs_ptr: i32, attrs_len: i32) -> i32`
+///
+/// # Memory Protocol
+///
+/// Attributes are passed as a header block of `name: value` lines and may
+/// be empty. `span_enter` returns the ID of the new span; `span_event`
+/// records the event in the span with that ID, or in the parent of all
+/// guest spans for ID `0`.
+///
+/// `span_exit` and `span_event` return `0` on success. All functions
+/// return `-1` for invalid guest memory or an unknown ID in `span_exit`,
+/// or the negative [`SpansHost::error_code`] of a failed operation.
+pub fn register_spans(linker: &mut Linker<WorkerContext>) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register span function: {e}"))
+    };
+
+    linker
+        .func_wrap(
+            "env",
+            "span_enter",
+            |mut caller: Caller<'_, WorkerContext>,
+             name_ptr: i32,
+             name_len: i32,
+             attrs_ptr: i32,
+             attrs_len: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let Some(attributes) = read_guest_pairs(&mut caller, attrs_ptr, attrs_len) else {
+                    return -1;
+                };
+                match SpansHost::enter(caller.data_mut(), &name, &attributes) {
+                    Ok(id) => i32::try_from(id).unwrap_or(i32::MAX),
+                    Err(e) => span_error(&caller, &e),
+                }
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "span_exit",
+            |mut caller: Caller<'_, WorkerContext>, id: i32| -> i32 {
+                let closed =
+                    u32::try_from(id).is_ok_and(|id| SpansHost::exit(caller.data_mut(), id));
+                status_code(closed)
+            },
+        )
+        .map_err(map_err)?;
+
+    linker
+        .func_wrap(
+            "env",
+            "span_event",
+            |mut caller: Caller<'_, WorkerContext>,
+             id: i32,
+             name_ptr: i32,
+             name_len: i32,
+             attrs_ptr: i32,
+             attrs_len: i32|
+             -> i32 {
+                let Some(name) = read_guest_string(&mut caller, name_ptr, name_len) else {
+                    return -1;
+                };
+                let Some(attributes) = read_guest_pairs(&mut caller, attrs_ptr, attrs_len) else {
+                    return -1;
+                };
+                let result = u32::try_from(id)
+                    .map_err(|_| HostFunctionError::InvalidArgument {
+                        reason: format!("unknown span {id}"),
+                    })
+                    .and_then(|id| SpansHost::event(caller.data_mut(), id, &name, &attributes));
+                match result {
+                    Ok(()) => 0,
+                    Err(e) => span_error(&caller, &e),
+                }
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Register the guest tracing span interface on a component linker.
+///
+/// Registers `edge:runtime/spans@0.1.0` with `span-enter`, `span-exit`
+/// and `span-event`.
+pub fn register_spans_component(
+    linker: &mut ComponentLinker<WorkerContext>,
+) -> Result<(), RuntimeError> {
+    let map_err = |e: wasmtime::Error| {
+        RuntimeError::invalid_config(format!("Failed to register spans interface: {e}"))
+    };
+
+    let mut instance = linker
+        .instance("edge:runtime/spans@0.1.0")
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "span-enter",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (name, attributes): (String, Vec<(String, String)>)| {
+                let result = SpansHost::enter(store.data_mut(), &name, &attributes);
+                Ok((result.map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "span-exit",
+            |mut store: StoreContextMut<'_, WorkerContext>, (id,): (u32,)| {
+                Ok((SpansHost::exit(store.data_mut(), id),))
+            },
+        )
+        .map_err(map_err)?;
+
+    instance
+        .func_wrap(
+            "span-event",
+            |mut store: StoreContextMut<'_, WorkerContext>,
+             (id, name, attributes): (u32, String, Vec<(String, String)>)| {
+                let result = SpansHost::event(store.data_mut(), id, &name, &attributes);
+                Ok((result.map_err(|e| e.to_string()),))
+            },
+        )
+        .map_err(map_err)?;
+
+    Ok(())
+}
+
+/// Read the URL and header block of a cache request from guest memory.
+fn read_cache_request(
+    caller: &mut Caller<'_, WorkerContext>,
+    url_ptr: i32,
+    url_len: i32,
+    headers_ptr: i32,
+    headers_len: i32,
+) -> Option<GuestRequest> {
+    let url = read_guest_string(caller, url_ptr, url_len)?;
+    let headers = read_guest_string(caller, headers_ptr, headers_len)?;
+
+    let mut request = GuestRequest::new("GET", &url);
+    request.headers = parse_header_block(&headers);
+    Some(request)
+}
+
+/// Convert a guest TTL in milliseconds; `0` or less means no expiry.
+fn ttl(ttl_ms: i64) -> Option<Duration> {
+    u64::try_from(ttl_ms)
+        .ok()
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis)
+}
+
+/// Log a failed KV operation and return its error code.
+fn kv_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "KV operation failed"
+    );
+    KvHost::error_code(error)
+}
+
+/// Read a SQL statement and its JSON-encoded parameters from the guest.
+///
+/// Returns `None` for invalid guest memory, and an error for parameters
+/// that are not a JSON array of values.
+#[allow(clippy::type_complexity)]
+fn read_sql_statement(
+    caller: &mut Caller<'_, WorkerContext>,
+    sql_ptr: i32,
+    sql_len: i32,
+    params_ptr: i32,
+    params_len: i32,
+) -> Option<(String, Result<Vec<SqlValue>, HostFunctionError>)> {
+    let sql = read_guest_string(caller, sql_ptr, sql_len)?;
+    if params_len == 0 {
+        return Some((sql, Ok(Vec::new())));
+    }
+    let params = read_guest_bytes(caller, params_ptr, params_len)?;
+    let params = serde_json::from_slice(&params).map_err(|e| HostFunctionError::InvalidArgument {
+        reason: format!("invalid SQL parameters: {e}"),
+    });
+    Some((sql, params))
+}
+
+/// Log a failed SQL statement and return its error code.
+fn sql_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "SQL statement failed"
+    );
+    SqlHost::error_code(error)
+}
+
+/// Log a failed blob operation and return its error code.
+fn blob_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "Blob operation failed"
+    );
+    BlobHost::error_code(error)
+}
+
+/// Log a failed crypto operation and return its error code.
+fn crypto_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "Crypto operation failed"
+    );
+    CryptoHost::error_code(error)
+}
+
+/// Log a failed socket operation and return its error code.
+fn socket_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "Socket operation failed"
+    );
+    SocketsHost::error_code(error)
+}
+
+/// Log a failed WebSocket operation and return its error code.
+fn websocket_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "WebSocket operation failed"
+    );
+    WebSocketHost::error_code(error)
+}
+
+/// Log a failed metric operation and return its error code.
+fn metrics_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "Metric operation failed"
+    );
+    MetricsHost::error_code(error)
+}
+
+/// Log a failed span operation and return its error code.
+fn span_error(caller: &Caller<'_, WorkerContext>, error: &HostFunctionError) -> i32 {
+    warn!(
+        request_id = %caller.data().request_id,
+        error = %error,
+        "Span operation failed"
+    );
+    SpansHost::error_code(error)
+}
+
+/// Record a metric of the given kind for the executing module.
+fn record_metric(
+    ctx: &WorkerContext,
+    kind: MetricKind,
+    name: &str,
+    labels: &[(String, String)],
+    value: f64,
+) -> Result<(), HostFunctionError> {
+    match kind {
+        MetricKind::Counter => MetricsHost::counter_add(ctx, name, labels, value),
+        MetricKind::Gauge => MetricsHost::gauge_set(ctx, name, labels, value),
+        MetricKind::Histogram => MetricsHost::histogram_record(ctx, name, labels, value),
+    }
+}
+
+fn invalid_digest(alg: i32) -> HostFunctionError {
+    HostFunctionError::InvalidArgument {
+        reason: format!("unknown digest algorithm {alg}"),
+    }
+}
+
+/// Charge host-side crypto work to the store's fuel.
+///
+/// Traps with [`Trap::OutOfFuel`] if the remaining fuel does not cover the
+/// cost. Stores without fuel metering are not charged.
+fn charge_crypto_fuel(
+    mut store: impl AsContextMut<Data = WorkerContext>,
+    bytes: usize,
+    signature: bool,
+) -> wasmtime::Result<()> {
+    let mut store = store.as_context_mut();
+    let Ok(remaining) = store.get_fuel() else {
+        return Ok(());
+    };
+    let cost = CryptoHost::fuel_cost(store.data(), bytes, signature);
+    if remaining < cost {
+        store.set_fuel(0)?;
+        return Err(Trap::OutOfFuel.into());
+    }
+    store.set_fuel(remaining - cost)
+}
+
+/// Look up a service response by handle.
+fn service_response(ctx: &WorkerContext, handle: i32) -> Option<&GuestResponse> {
+    ctx.extensions()
+        .get::<ServiceContext>()
+        .and_then(|service| service.response(handle))
+}
+
+/// Convert a success flag to the `0` / `-1` return convention.
+fn status_code(ok: bool) -> i32 {
+    if ok { 0 } else { -1 }
+}
+
+/// Read raw bytes from the guest's exported memory.
+///
+/// Returns `None` (after logging a warning) if the pointer or length is
+/// invalid, the guest exports no memory, or the range is out of bounds.
+pub(crate) fn read_guest_bytes(
+    caller: &mut Caller<'_, WorkerContext>,
+    ptr: i32,
+    len: i32,
+) -> Option<Vec<u8>> {
+    // Validate pointer and length are non-negative
+    if ptr < 0 || len < 0 {
+        warn!(
+            ptr = ptr,
+            len = len,
+            "Invalid pointer or length (negative value)"
+        );
+        return None;
+    }
+
+    let Some(memory) = caller
+        .get_export("memory")
+        .and_then(wasmtime::Extern::into_memory)
+    else {
+        warn!("Memory export not found in guest module");
+        return None;
+    };
+
+    #[allow(clippy::cast_sign_loss)]
+    let (start, len) = (ptr as usize, len as usize);
+    let data = memory.data(&caller);
+    let Some(end) = start.checked_add(len) else {
+        warn!(ptr = ptr, len = len, "Pointer + length overflow");
+        return None;
+    };
+
+    // Bounds check
+    if end > data.len() {
+        warn!(
+            start = start,
+            end = end,
+            memory_size = data.len(),
+            "Memory access out of bounds"
+        );
+        return None;
+    }
+
+    Some(data[start..end].to_vec())
+}
+
+/// Copy a host value into a guest buffer.
+///
+/// Writes at most `cap` bytes of `value` to `buf` and returns the full
+/// length of the value, or `-1` if the value is missing or the buffer is
+/// invalid.
+pub(crate) fn write_guest_value(
+    caller: &mut Caller<'_, WorkerContext>,
+    buf: i32,
+    cap: i32,
+    value: Option<Vec<u8>>,
+) -> i32 {
+    let Some(value) = value else {
+        return -1;
+    };
+
+    let (Ok(start), Ok(cap)) = (usize::try_from(buf), usize::try_from(cap)) else {
+        warn!(buf = buf, cap = cap, "Invalid buffer (negative value)");
+        return -1;
+    };
+
+    let Some(memory) = caller
+        .get_export("memory")
+        .and_then(wasmtime::Extern::into_memory)
+    else {
+        warn!("Memory export not found in guest module");
+        return -1;
+    };
+
+    let n = value.len().min(cap);
+    if let Err(e) = memory.write(&mut *caller, start, &value[..n]) {
+        warn!(buf = buf, len = n, error = %e, "Memory write out of bounds");
+        return -1;
+    }
+
+    i32::try_from(value.len()).unwrap_or(i32::MAX)
+}
+
+/// Read a UTF-8 string from the guest's exported memory.
+///
+/// Returns `None` (after logging a warning) if the pointer or length is
+/// invalid, the guest exports no memory, or the range is out of bounds.
+/// Invalid UTF-8 is replaced with `<invalid utf8>`.
+pub(crate) fn read_guest_string(
+    caller: &mut Caller<'_, WorkerContext>,
+    ptr: i32,
+    len: i32,
+) -> Option<String> {
+    read_guest_bytes(caller, ptr, len)
+        .map(|bytes| String::from_utf8(bytes).unwrap_or_else(|_| "<invalid utf8>".to_string()))
+}
+
+/// Read name/value pairs passed as a header block of `name: value` lines.
+///
+/// A zero length yields no pairs without touching guest memory.
+fn read_guest_pairs(
+    caller: &mut Caller<'_, WorkerContext>,
+    ptr: i32,
+    len: i32,
+) -> Option<Vec<(String, String)>> {
+    if len == 0 {
+        return Some(Vec::new());
+    }
+    read_guest_string(caller, ptr, len).map(|block| parse_header_block(&block))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::EngineConfig;
+    use edge_runtime_core::WasmEngine;
+
+    #[test]
+    fn test_register_logging() {
+        let config = EngineConfig::default();
+        let engine = WasmEngine::new(&config).unwrap();
+        let mut linker = Linker::new(engine.inner());
+
+        let result = register_logging(&mut linker);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_register_all() {
+        let config = EngineConfig::default();
+        let engine = WasmEngine::new(&config).unwrap();
+        let mut linker = Linker::new(engine.inner());
+
+        let result = register_all(&mut linker);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_register_all_component() {
+        let config = EngineConfig::default();
+        let engine = WasmEngine::new(&config).unwrap();
+        let mut linker = ComponentLinker::new(engine.inner());
+
+        let result = register_all_component(&mut linker);
         assert!(result.is_ok());
     }
 }
@@ -3,10 +3,72 @@
 //! This module provides the host-side implementation of the logging interface,
 //! allowing guest components to emit structured logs that are captured by
 //! the runtime.
+//!
+//! Entries are filtered by the module's [`LogPolicy`]: entries below the
+//! minimum level are discarded, debug and info entries are kept for a
+//! sampled fraction of requests, and once a request reaches its entry or
+//! byte limit further entries are dropped and counted in a single marker
+//! entry.
 
+use edge_runtime_common::LoggingConfig;
 use edge_runtime_core::store::{LogEntry, LogLevel, WorkerContext};
+use ring::rand::{SecureRandom, SystemRandom};
 use tracing::{debug, error, info, warn};
 
+/// Maximum number of fields on a log entry; further fields are dropped.
+pub const MAX_LOG_FIELDS: usize = 32;
+
+/// Log filtering and limits of a module.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime. Without
+/// it, [`LogPolicy::default`] applies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogPolicy {
+    /// Entries below this level are discarded.
+    pub min_level: LogLevel,
+    /// Fraction of requests, from 0 to 1, whose debug and info entries are
+    /// kept.
+    pub sample_rate: f64,
+    /// Maximum number of entries kept per request.
+    pub max_entries: usize,
+    /// Maximum total size of messages and fields kept per request.
+    pub max_bytes: usize,
+}
+
+impl LogPolicy {
+    /// Create a policy from the server-wide logging configuration.
+    pub fn new(config: &LoggingConfig) -> Self {
+        Self {
+            min_level: config.min_level.into(),
+            sample_rate: config.sample_rate,
+            max_entries: config.max_entries_per_request,
+            max_bytes: config.max_bytes_per_request,
+        }
+    }
+}
+
+impl Default for LogPolicy {
+    fn default() -> Self {
+        Self::new(&LoggingConfig::default())
+    }
+}
+
+/// Per-request log state, stored in the [`WorkerContext`] extensions.
+#[derive(Debug, Default)]
+struct LogState {
+    /// Whether debug and info entries of this request are kept; decided
+    /// at the first such entry.
+    sampled: Option<bool>,
+    /// Number of entries kept.
+    entries: usize,
+    /// Total size of the entries kept.
+    bytes: usize,
+    /// Number of entries dropped for exceeding the limits.
+    dropped: usize,
+    /// Index of the marker entry in `ctx.logs`.
+    marker: Option<usize>,
+}
+
 /// Host implementation for the logging interface.
 ///
 /// This struct provides the logging capabilities to guest components.
@@ -26,21 +88,154 @@ impl LoggingHost {
     /// * `level` - The log level
     /// * `message` - The log message
     pub fn log(ctx: &mut WorkerContext, level: LogLevel, message: &str) {
-        // Store in context for later retrieval
-        ctx.logs.push(LogEntry {
-            level,
-            message: message.to_string(),
-            timestamp: std::time::Instant::now(),
-        });
+        Self::log_with_fields(ctx, level, message, Vec::new());
+    }
+
+    /// Log a message with structured key-value fields.
+    ///
+    /// Fields are stored with the entry and emitted as a JSON object in the
+    /// `fields` field of the tracing event. Only the first
+    /// [`MAX_LOG_FIELDS`] fields are kept.
+    ///
+    /// Entries filtered out by the module's [`LogPolicy`] are neither
+    /// stored nor emitted.
+    pub fn log_with_fields(
+        ctx: &mut WorkerContext,
+        level: LogLevel,
+        message: &str,
+        mut fields: Vec<(String, String)>,
+    ) {
+        fields.truncate(MAX_LOG_FIELDS);
+        if !Self::admit(ctx, level, message, &fields) {
+            return;
+        }
 
         // Also emit via tracing for observability
         let request_id = &ctx.request_id;
+        let json = (!fields.is_empty()).then(|| fields_json(&fields));
+        let fields_json = json.as_deref();
         match level {
-            LogLevel::Debug => debug!(request_id, guest_log = true, "{}", message),
-            LogLevel::Info => info!(request_id, guest_log = true, "{}", message),
-            LogLevel::Warn => warn!(request_id, guest_log = true, "{}", message),
-            LogLevel::Error => error!(request_id, guest_log = true, "{}", message),
+            LogLevel::Debug => {
+                debug!(
+                    request_id,
+                    guest_log = true,
+                    fields = fields_json,
+                    "{}",
+                    message
+                );
+            }
+            LogLevel::Info => {
+                info!(
+                    request_id,
+                    guest_log = true,
+                    fields = fields_json,
+                    "{}",
+                    message
+                );
+            }
+            LogLevel::Warn => {
+                warn!(
+                    request_id,
+                    guest_log = true,
+                    fields = fields_json,
+                    "{}",
+                    message
+                );
+            }
+            LogLevel::Error => {
+                error!(
+                    request_id,
+                    guest_log = true,
+                    fields = fields_json,
+                    "{}",
+                    message
+                );
+            }
+        }
+
+        // Store in context for later retrieval
+        ctx.logs
+            .push(LogEntry::new(level, message.to_string(), fields));
+    }
+
+    /// Apply the module's [`LogPolicy`] to an entry.
+    ///
+    /// Returns `false` if the entry must be discarded. Entries dropped for
+    /// exceeding the limits are counted in the marker entry.
+    fn admit(
+        ctx: &mut WorkerContext,
+        level: LogLevel,
+        message: &str,
+        fields: &[(String, String)],
+    ) -> bool {
+        let policy = ctx
+            .extensions()
+            .get::<LogPolicy>()
+            .copied()
+            .unwrap_or_default();
+        if level < policy.min_level {
+            return false;
+        }
+
+        let state = Self::context(ctx);
+        if level < LogLevel::Warn
+            && !*state
+                .sampled
+                .get_or_insert_with(|| sample(policy.sample_rate))
+        {
+            return false;
+        }
+
+        let size = message.len()
+            + fields
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>();
+        if state.dropped == 0
+            && state.entries < policy.max_entries
+            && state.bytes + size <= policy.max_bytes
+        {
+            state.entries += 1;
+            state.bytes += size;
+            return true;
+        }
+
+        state.dropped += 1;
+        let dropped = state.dropped;
+        let marker = state.marker;
+        if dropped == 1 {
+            warn!(
+                request_id = %ctx.request_id,
+                max_entries = policy.max_entries,
+                max_bytes = policy.max_bytes,
+                "Guest log limit reached, dropping further entries"
+            );
+        }
+
+        let message = format!("{dropped} log entries dropped (per-request limit reached)");
+        // The marker is always the last entry, unless the logs were taken
+        // since it was pushed.
+        match marker {
+            Some(index) if index + 1 == ctx.logs.len() => {
+                ctx.logs[index].message = message;
+            }
+            _ => {
+                ctx.logs
+                    .push(LogEntry::new(LogLevel::Warn, message, Vec::new()));
+                let index = ctx.logs.len() - 1;
+                Self::context(ctx).marker = Some(index);
+            }
         }
+        false
+    }
+
+    fn context(ctx: &mut WorkerContext) -> &mut LogState {
+        if ctx.extensions().get::<LogState>().is_none() {
+            ctx.extensions_mut().insert(LogState::default());
+        }
+        ctx.extensions_mut()
+            .get_mut::<LogState>()
+            .expect("log state was just inserted")
     }
 
     /// Convenience function for debug-level logging.
@@ -64,6 +259,32 @@ impl LoggingHost {
     }
 }
 
+/// Decide whether a request is sampled at the given rate.
+fn sample(rate: f64) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let mut bytes = [0u8; 4];
+    if SystemRandom::new().fill(&mut bytes).is_err() {
+        return true;
+    }
+    f64::from(u32::from_le_bytes(bytes)) < rate * f64::from(u32::MAX)
+}
+
+/// Format name/value pairs as a JSON object.
+///
+/// Later pairs overwrite earlier ones with the same name.
+pub(crate) fn fields_json(fields: &[(String, String)]) -> String {
+    let map: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .map(|(name, value)| (name.clone(), serde_json::Value::String(value.clone())))
+        .collect();
+    serde_json::Value::Object(map).to_string()
+}
+
 /// Convert a numeric log level to [`LogLevel`].
 ///
 /// This is used when receiving log levels from Wasm as integers.
@@ -128,6 +349,110 @@ mod tests {
         assert_eq!(ctx.logs[3].level, LogLevel::Error);
     }
 
+    #[test]
+    fn test_log_with_fields() {
+        let mut ctx = WorkerContext::new("test".into());
+        let fields = (0..40)
+            .map(|i| (format!("key{i}"), i.to_string()))
+            .collect();
+
+        LoggingHost::log_with_fields(&mut ctx, LogLevel::Warn, "slow query", fields);
+
+        assert_eq!(ctx.logs[0].message, "slow query");
+        assert_eq!(ctx.logs[0].fields.len(), MAX_LOG_FIELDS);
+        assert_eq!(ctx.logs[0].fields[1], ("key1".to_string(), "1".to_string()));
+    }
+
+    fn with_policy(policy: LogPolicy) -> WorkerContext {
+        let mut ctx = WorkerContext::new("test".into());
+        ctx.extensions_mut().insert(policy);
+        ctx
+    }
+
+    #[test]
+    fn test_min_level() {
+        let mut ctx = with_policy(LogPolicy {
+            min_level: LogLevel::Warn,
+            ..LogPolicy::default()
+        });
+
+        LoggingHost::log_info(&mut ctx, "info");
+        LoggingHost::log_warn(&mut ctx, "warn");
+        LoggingHost::log_error(&mut ctx, "error");
+
+        let messages: Vec<_> = ctx.logs.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, ["warn", "error"]);
+    }
+
+    #[test]
+    fn test_sampling_keeps_warnings() {
+        let mut ctx = with_policy(LogPolicy {
+            sample_rate: 0.0,
+            ..LogPolicy::default()
+        });
+
+        LoggingHost::log_debug(&mut ctx, "debug");
+        LoggingHost::log_info(&mut ctx, "info");
+        LoggingHost::log_warn(&mut ctx, "warn");
+
+        assert_eq!(ctx.logs.len(), 1);
+        assert_eq!(ctx.logs[0].level, LogLevel::Warn);
+    }
+
+    #[test]
+    fn test_entry_limit_marker() {
+        let mut ctx = with_policy(LogPolicy {
+            max_entries: 3,
+            ..LogPolicy::default()
+        });
+
+        for i in 0..10 {
+            LoggingHost::log_info(&mut ctx, &format!("line {i}"));
+        }
+
+        assert_eq!(ctx.logs.len(), 4);
+        assert_eq!(ctx.logs[2].message, "line 2");
+        assert_eq!(ctx.logs[3].level, LogLevel::Warn);
+        assert_eq!(
+            ctx.logs[3].message,
+            "7 log entries dropped (per-request limit reached)"
+        );
+
+        // Entries logged after the logs were taken get a new marker.
+        ctx.logs.clear();
+        LoggingHost::log_error(&mut ctx, "late");
+        assert_eq!(ctx.logs.len(), 1);
+        assert!(ctx.logs[0].message.starts_with("8 log entries dropped"));
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let mut ctx = with_policy(LogPolicy {
+            max_bytes: 10,
+            ..LogPolicy::default()
+        });
+
+        LoggingHost::log_info(&mut ctx, "12345");
+        LoggingHost::log_with_fields(
+            &mut ctx,
+            LogLevel::Info,
+            "12",
+            vec![("k".to_string(), "vv".to_string())],
+        );
+        LoggingHost::log_info(&mut ctx, "1");
+        // Once entries are dropped, smaller entries are dropped as well.
+        LoggingHost::log_info(&mut ctx, "");
+
+        assert_eq!(ctx.logs.len(), 3);
+        assert!(ctx.logs[2].message.starts_with("2 log entries dropped"));
+    }
+
+    #[test]
+    fn test_fields_json() {
+        let fields = [("user".to_string(), "a\"b".to_string())];
+        assert_eq!(fields_json(&fields), r#"{"user":"a\"b"}"#);
+    }
+
     #[test]
     fn test_level_from_i32() {
         assert_eq!(level_from_i32(0), LogLevel::Debug);
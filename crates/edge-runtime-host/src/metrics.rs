@@ -0,0 +1,613 @@
+//! Custom metrics host function implementation.
+//!
+//! This module provides the host-side implementation of the metrics
+//! interface, which lets guest code record business metrics such as orders
+//! processed or cache hit ratios.
+//!
+//! Metrics are:
+//!
+//! - **Typed** as counters, gauges or histograms; a name keeps the type it
+//!   was first recorded with
+//! - **Labelled** with name/value pairs, each distinct label set forming a
+//!   separate series
+//! - **Aggregated** on the host across all requests of a module, and
+//!   isolated between modules
+//! - **Bounded** by a per-module limit on the number of series, so a guest
+//!   using unbounded label values cannot exhaust host memory
+//!
+//! [`GuestMetrics::render`] exports all series in the Prometheus text
+//! format, with metric names prefixed by the module ID.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use edge_runtime_common::{HostFunctionError, MetricsConfig};
+use edge_runtime_core::store::WorkerContext;
+use tracing::debug;
+
+/// Upper bounds of the histogram buckets, in the unit of the recorded values.
+pub const HISTOGRAM_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Maximum number of labels on a series.
+pub const MAX_METRIC_LABELS: usize = 8;
+
+/// Maximum length of a metric or label name in bytes.
+const MAX_NAME_BYTES: usize = 128;
+
+/// Maximum length of a label value in bytes.
+const MAX_LABEL_VALUE_BYTES: usize = 256;
+
+/// Type of a guest metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// Monotonically increasing total.
+    Counter,
+    /// Value that can go up and down.
+    Gauge,
+    /// Distribution of observed values.
+    Histogram,
+}
+
+impl MetricKind {
+    /// Name of the type in the Prometheus text format.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Histogram => "histogram",
+        }
+    }
+}
+
+/// Guest metrics shared across requests, partitioned by module.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime.
+#[derive(Debug, Clone)]
+pub struct GuestMetrics {
+    modules: Arc<Mutex<HashMap<String, ModuleMetrics>>>,
+    max_series_per_module: usize,
+}
+
+impl GuestMetrics {
+    /// Create a registry with the given per-module series limit.
+    pub fn new(max_series_per_module: usize) -> Self {
+        Self {
+            modules: Arc::new(Mutex::new(HashMap::new())),
+            max_series_per_module,
+        }
+    }
+
+    /// Record `value` for the series of `name` with `labels`.
+    ///
+    /// Counters add `value`, gauges are set to it and histograms observe
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostFunctionError::InvalidArgument`] for invalid names,
+    /// labels or values, [`HostFunctionError::Metrics`] if `name` was
+    /// recorded with a different type, and
+    /// [`HostFunctionError::RateLimitExceeded`] if the series would exceed
+    /// the module's series limit.
+    pub fn record(
+        &self,
+        module_id: &str,
+        kind: MetricKind,
+        name: &str,
+        labels: &[(String, String)],
+        value: f64,
+    ) -> Result<(), HostFunctionError> {
+        validate_name(name, "metric")?;
+        let labels = validate_labels(labels)?;
+        if !value.is_finite() || (kind == MetricKind::Counter && value < 0.0) {
+            return Err(invalid(format!("invalid value {value} for {name}")));
+        }
+
+        let mut modules = self
+            .modules
+            .lock()
+            .map_err(|_| HostFunctionError::Metrics("registry is poisoned".to_string()))?;
+        let module = modules.entry(module_id.to_string()).or_default();
+
+        if let Some(family) = module.families.get(name) {
+            if family.kind != kind {
+                return Err(HostFunctionError::Metrics(format!(
+                    "{name} is a {}, not a {}",
+                    family.kind.as_str(),
+                    kind.as_str()
+                )));
+            }
+        }
+
+        let exists = module
+            .families
+            .get(name)
+            .is_some_and(|family| family.series.contains_key(&labels));
+        if !exists {
+            if module.series >= self.max_series_per_module {
+                return Err(HostFunctionError::RateLimitExceeded {
+                    operation: format!(
+                        "metric series (limit {} per module)",
+                        self.max_series_per_module
+                    ),
+                });
+            }
+            module.series += 1;
+        }
+
+        module
+            .families
+            .entry(name.to_string())
+            .or_insert_with(|| Family {
+                kind,
+                series: BTreeMap::new(),
+            })
+            .series
+            .entry(labels)
+            .or_insert_with(|| Series::new(kind))
+            .record(value);
+        Ok(())
+    }
+
+    /// Drop all metrics of a module.
+    pub fn clear(&self, module_id: &str) {
+        if let Ok(mut modules) = self.modules.lock() {
+            modules.remove(module_id);
+        }
+    }
+
+    /// Get the number of series recorded by a module.
+    pub fn series_count(&self, module_id: &str) -> usize {
+        self.modules
+            .lock()
+            .ok()
+            .and_then(|modules| modules.get(module_id).map(|module| module.series))
+            .unwrap_or_default()
+    }
+
+    /// Render all metrics in the Prometheus text format.
+    ///
+    /// Metric names are prefixed with the module ID, with characters not
+    /// allowed in metric names replaced by `_`: metric `orders_total` of
+    /// module `shop-api` is exported as `shop_api_orders_total`.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let Ok(modules) = self.modules.lock() else {
+            return out;
+        };
+
+        let mut module_ids: Vec<_> = modules.keys().collect();
+        module_ids.sort();
+        for module_id in module_ids {
+            let prefix = sanitize_prefix(module_id);
+            for (name, family) in &modules[module_id].families {
+                let name = format!("{prefix}_{name}");
+                family.render(&name, &mut out);
+            }
+        }
+        out
+    }
+}
+
+impl Default for GuestMetrics {
+    fn default() -> Self {
+        Self::new(MetricsConfig::default().max_series_per_module)
+    }
+}
+
+/// Metrics of one module.
+#[derive(Debug, Default)]
+struct ModuleMetrics {
+    families: BTreeMap<String, Family>,
+    /// Number of series across all families.
+    series: usize,
+}
+
+/// All series of one metric name.
+#[derive(Debug)]
+struct Family {
+    kind: MetricKind,
+    /// Series by label set, sorted by label name.
+    series: BTreeMap<Vec<(String, String)>, Series>,
+}
+
+impl Family {
+    fn render(&self, name: &str, out: &mut String) {
+        let _ = writeln!(out, "# TYPE {name} {}", self.kind.as_str());
+        for (labels, series) in &self.series {
+            match series {
+                Series::Counter(value) | Series::Gauge(value) => {
+                    let _ = writeln!(out, "{name}{} {value}", format_labels(labels, None));
+                }
+                Series::Histogram {
+                    buckets,
+                    sum,
+                    count,
+                } => {
+                    let mut cumulative = 0;
+                    for (bound, bucket) in HISTOGRAM_BUCKETS.iter().zip(buckets) {
+                        cumulative += bucket;
+                        let labels = format_labels(labels, Some(&bound.to_string()));
+                        let _ = writeln!(out, "{name}_bucket{labels} {cumulative}");
+                    }
+                    let inf = format_labels(labels, Some("+Inf"));
+                    let labels = format_labels(labels, None);
+                    let _ = writeln!(out, "{name}_bucket{inf} {count}");
+                    let _ = writeln!(out, "{name}_sum{labels} {sum}");
+                    let _ = writeln!(out, "{name}_count{labels} {count}");
+                }
+            }
+        }
+    }
+}
+
+/// Aggregated value of one series.
+#[derive(Debug)]
+enum Series {
+    Counter(f64),
+    Gauge(f64),
+    Histogram {
+        /// Non-cumulative count per bucket of [`HISTOGRAM_BUCKETS`].
+        buckets: [u64; HISTOGRAM_BUCKETS.len()],
+        sum: f64,
+        count: u64,
+    },
+}
+
+impl Series {
+    fn new(kind: MetricKind) -> Self {
+        match kind {
+            MetricKind::Counter => Self::Counter(0.0),
+            MetricKind::Gauge => Self::Gauge(0.0),
+            MetricKind::Histogram => Self::Histogram {
+                buckets: [0; HISTOGRAM_BUCKETS.len()],
+                sum: 0.0,
+                count: 0,
+            },
+        }
+    }
+
+    fn record(&mut self, value: f64) {
+        match self {
+            Self::Counter(total) => *total += value,
+            Self::Gauge(current) => *current = value,
+            Self::Histogram {
+                buckets,
+                sum,
+                count,
+            } => {
+                if let Some(index) = HISTOGRAM_BUCKETS.iter().position(|bound| value <= *bound) {
+                    buckets[index] += 1;
+                }
+                *sum += value;
+                *count += 1;
+            }
+        }
+    }
+}
+
+/// Check a metric or label name against the Prometheus naming rules.
+fn validate_name(name: &str, what: &str) -> Result<(), HostFunctionError> {
+    let valid_first = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_BYTES
+        && name.starts_with(valid_first)
+        && name.chars().all(|c| valid_first(c) || c.is_ascii_digit());
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid(format!("invalid {what} name {name:?}")))
+    }
+}
+
+/// Validate labels and sort them by name.
+fn validate_labels(
+    labels: &[(String, String)],
+) -> Result<Vec<(String, String)>, HostFunctionError> {
+    if labels.len() > MAX_METRIC_LABELS {
+        return Err(invalid(format!(
+            "too many labels ({}, limit {MAX_METRIC_LABELS})",
+            labels.len()
+        )));
+    }
+
+    let mut sorted = labels.to_vec();
+    sorted.sort();
+    for (index, (name, value)) in sorted.iter().enumerate() {
+        validate_name(name, "label")?;
+        if name.starts_with("__") || name == "le" {
+            return Err(invalid(format!("reserved label name {name:?}")));
+        }
+        if index > 0 && sorted[index - 1].0 == *name {
+            return Err(invalid(format!("duplicate label {name:?}")));
+        }
+        if value.len() > MAX_LABEL_VALUE_BYTES {
+            return Err(invalid(format!("value of label {name:?} is too long")));
+        }
+    }
+    Ok(sorted)
+}
+
+/// Turn a module ID into a metric name prefix.
+fn sanitize_prefix(module_id: &str) -> String {
+    let mut prefix: String = module_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !prefix.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        prefix.insert(0, '_');
+    }
+    prefix
+}
+
+/// Format a label set, with an optional histogram bucket bound.
+fn format_labels(labels: &[(String, String)], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{}\"", escape_label_value(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{le}\""));
+    }
+
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn invalid(reason: String) -> HostFunctionError {
+    HostFunctionError::InvalidArgument { reason }
+}
+
+/// Metrics host functions.
+///
+/// Metrics are recorded in the partition of the executing module.
+pub struct MetricsHost;
+
+impl MetricsHost {
+    /// Add `value` to a counter.
+    ///
+    /// # Errors
+    ///
+    /// See [`GuestMetrics::record`]; `value` must not be negative.
+    pub fn counter_add(
+        ctx: &WorkerContext,
+        name: &str,
+        labels: &[(String, String)],
+        value: f64,
+    ) -> Result<(), HostFunctionError> {
+        Self::record(ctx, MetricKind::Counter, name, labels, value)
+    }
+
+    /// Set a gauge to `value`.
+    ///
+    /// # Errors
+    ///
+    /// See [`GuestMetrics::record`].
+    pub fn gauge_set(
+        ctx: &WorkerContext,
+        name: &str,
+        labels: &[(String, String)],
+        value: f64,
+    ) -> Result<(), HostFunctionError> {
+        Self::record(ctx, MetricKind::Gauge, name, labels, value)
+    }
+
+    /// Record an observation in a histogram.
+    ///
+    /// # Errors
+    ///
+    /// See [`GuestMetrics::record`].
+    pub fn histogram_record(
+        ctx: &WorkerContext,
+        name: &str,
+        labels: &[(String, String)],
+        value: f64,
+    ) -> Result<(), HostFunctionError> {
+        Self::record(ctx, MetricKind::Histogram, name, labels, value)
+    }
+
+    /// Map an error to the negative status code returned to core modules.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    fn record(
+        ctx: &WorkerContext,
+        kind: MetricKind,
+        name: &str,
+        labels: &[(String, String)],
+        value: f64,
+    ) -> Result<(), HostFunctionError> {
+        let (Some(metrics), Some(module_id)) =
+            (ctx.extensions().get::<GuestMetrics>(), &ctx.module_id)
+        else {
+            return Err(HostFunctionError::Metrics(
+                "metrics are not available".to_string(),
+            ));
+        };
+
+        debug!(
+            request_id = %ctx.request_id,
+            metric = %name,
+            kind = kind.as_str(),
+            value,
+            "Record metric"
+        );
+        metrics.record(module_id, kind, name, labels, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_counter_and_gauge() {
+        let metrics = GuestMetrics::new(10);
+        let paid = labels(&[("status", "paid")]);
+        metrics
+            .record("shop", MetricKind::Counter, "orders_total", &paid, 2.0)
+            .unwrap();
+        metrics
+            .record("shop", MetricKind::Counter, "orders_total", &paid, 1.0)
+            .unwrap();
+        metrics
+            .record("shop", MetricKind::Gauge, "queue_depth", &[], 7.0)
+            .unwrap();
+        metrics
+            .record("shop", MetricKind::Gauge, "queue_depth", &[], 3.0)
+            .unwrap();
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE shop_orders_total counter\n"));
+        assert!(text.contains("shop_orders_total{status=\"paid\"} 3\n"));
+        assert!(text.contains("shop_queue_depth 3\n"));
+        assert_eq!(metrics.series_count("shop"), 2);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let metrics = GuestMetrics::new(10);
+        for value in [0.003, 0.2, 20.0] {
+            metrics
+                .record(
+                    "api-v2",
+                    MetricKind::Histogram,
+                    "latency_seconds",
+                    &[],
+                    value,
+                )
+                .unwrap();
+        }
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE api_v2_latency_seconds histogram\n"));
+        assert!(text.contains("api_v2_latency_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(text.contains("api_v2_latency_seconds_bucket{le=\"0.25\"} 2\n"));
+        assert!(text.contains("api_v2_latency_seconds_bucket{le=\"10\"} 2\n"));
+        assert!(text.contains("api_v2_latency_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("api_v2_latency_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn test_cardinality_limit() {
+        let metrics = GuestMetrics::new(2);
+        for user in ["a", "b"] {
+            metrics
+                .record(
+                    "m",
+                    MetricKind::Counter,
+                    "hits",
+                    &labels(&[("user", user)]),
+                    1.0,
+                )
+                .unwrap();
+        }
+
+        let err = metrics
+            .record(
+                "m",
+                MetricKind::Counter,
+                "hits",
+                &labels(&[("user", "c")]),
+                1.0,
+            )
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+
+        // Existing series can still be updated, and other modules are not
+        // affected.
+        metrics
+            .record(
+                "m",
+                MetricKind::Counter,
+                "hits",
+                &labels(&[("user", "a")]),
+                1.0,
+            )
+            .unwrap();
+        metrics
+            .record("other", MetricKind::Counter, "hits", &[], 1.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let metrics = GuestMetrics::new(10);
+        let record = |kind, name, labels: &[(String, String)], value| {
+            metrics.record("m", kind, name, labels, value)
+        };
+
+        assert!(record(MetricKind::Counter, "1st", &[], 1.0).is_err());
+        assert!(record(MetricKind::Counter, "a-b", &[], 1.0).is_err());
+        assert!(record(MetricKind::Counter, "hits", &[], -1.0).is_err());
+        assert!(record(MetricKind::Gauge, "temp", &[], f64::NAN).is_err());
+        assert!(record(MetricKind::Counter, "hits", &labels(&[("le", "1")]), 1.0).is_err());
+        assert!(
+            record(
+                MetricKind::Counter,
+                "hits",
+                &labels(&[("a", "1"), ("a", "2")]),
+                1.0
+            )
+            .is_err()
+        );
+
+        record(MetricKind::Counter, "hits", &[], 1.0).unwrap();
+        let err = record(MetricKind::Gauge, "hits", &[], 1.0).unwrap_err();
+        assert!(matches!(err, HostFunctionError::Metrics(_)));
+    }
+
+    #[test]
+    fn test_label_escaping_and_order() {
+        let metrics = GuestMetrics::new(10);
+        metrics
+            .record(
+                "m",
+                MetricKind::Counter,
+                "events",
+                &labels(&[("path", "/a\"b"), ("method", "GET")]),
+                1.0,
+            )
+            .unwrap();
+
+        let text = metrics.render();
+        assert!(text.contains("m_events{method=\"GET\",path=\"/a\\\"b\"} 1\n"));
+    }
+
+    #[test]
+    fn test_clear() {
+        let metrics = GuestMetrics::new(10);
+        metrics
+            .record("m", MetricKind::Counter, "hits", &[], 1.0)
+            .unwrap();
+        metrics.clear("m");
+        assert_eq!(metrics.series_count("m"), 0);
+        assert!(metrics.render().is_empty());
+    }
+}
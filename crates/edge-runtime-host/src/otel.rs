@@ -0,0 +1,786 @@
+//! OpenTelemetry export of the runtime's `tracing` spans.
+//!
+//! This module provides [`OtelLayer`], a `tracing-subscriber` layer that
+//! gives every span a W3C trace ID and span ID, and the trace context
+//! propagation built on them:
+//!
+//! - [`TraceContext::parse`] reads the `traceparent` and `tracestate`
+//!   headers of an inbound request, and [`set_parent`] continues the
+//!   caller's trace in the request's span.
+//! - [`current_trace_context`] returns the context to send with outbound
+//!   requests, as done by [`HttpOutboundHost::fetch`](crate::HttpOutboundHost::fetch).
+//!
+//! Once [`OtelLayer::start_export`] is called with an OTLP endpoint, closed
+//! spans of sampled traces are posted in batches to the collector as
+//! OTLP/HTTP JSON. A span is named after its `otel.name` field if present,
+//! and its kind is read from `otel.kind` (`server`, `client`, `producer`,
+//! `consumer`, otherwise `internal`). Other fields are exported as
+//! attributes and events as span events; an `error` event or an
+//! `otel.status_code = "error"` field marks the span as failed.
+//!
+//! The layer never blocks the spans' threads: finished spans are dropped
+//! when the export queue is full.
+
+use std::fmt::{self, Write as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Span, Subscriber, warn};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use edge_runtime_common::{RuntimeError, TracingConfig};
+
+/// Header carrying the trace ID, parent span ID and flags.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Header carrying vendor-specific trace state.
+pub const TRACESTATE: &str = "tracestate";
+
+/// `sampled` trace flag.
+const SAMPLED: u8 = 0x01;
+
+/// Maximum length of a propagated `tracestate` header in bytes.
+const MAX_TRACESTATE_BYTES: usize = 512;
+
+/// Maximum number of events exported per span.
+const MAX_EVENTS_PER_SPAN: usize = 128;
+
+/// W3C trace context of a span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// Trace ID.
+    pub trace_id: [u8; 16],
+    /// ID of the span, the parent of spans continuing the trace.
+    pub span_id: [u8; 8],
+    /// Trace flags.
+    pub flags: u8,
+    /// Vendor-specific trace state, propagated unchanged.
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Parse `traceparent` and `tracestate` header values.
+    ///
+    /// Returns `None` for an invalid `traceparent`. An empty or oversized
+    /// `tracestate` is ignored.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let [version] = decode_hex::<1>(parts.next()?)?;
+        let trace_id = decode_hex::<16>(parts.next()?)?;
+        let span_id = decode_hex::<8>(parts.next()?)?;
+        let [flags] = decode_hex::<1>(parts.next()?)?;
+        // Later versions may append fields; version 00 has exactly four.
+        if version == 0xff || (version == 0 && parts.next().is_some()) {
+            return None;
+        }
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+
+        let tracestate = tracestate
+            .map(str::trim)
+            .filter(|state| !state.is_empty() && state.len() <= MAX_TRACESTATE_BYTES)
+            .map(str::to_string);
+        Some(Self {
+            trace_id,
+            span_id,
+            flags,
+            tracestate,
+        })
+    }
+
+    /// The `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            hex(&self.trace_id),
+            hex(&self.span_id),
+            self.flags
+        )
+    }
+
+    /// The trace ID as lowercase hex.
+    pub fn trace_id_hex(&self) -> String {
+        hex(&self.trace_id)
+    }
+
+    /// Whether the caller records this trace.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED != 0
+    }
+}
+
+/// The trace context of the current span.
+pub fn current_trace_context() -> Option<TraceContext> {
+    trace_context(&Span::current())
+}
+
+/// The trace context of a span.
+///
+/// Returns `None` if the span is disabled or the subscriber has no
+/// [`OtelLayer`].
+pub fn trace_context(span: &Span) -> Option<TraceContext> {
+    span.with_subscriber(|(id, dispatch)| {
+        let registry = dispatch.downcast_ref::<Registry>()?;
+        let span = registry.span(id)?;
+        let extensions = span.extensions();
+        let data = extensions.get::<SpanData>()?;
+        Some(TraceContext {
+            trace_id: data.trace_id,
+            span_id: data.span_id,
+            flags: data.flags,
+            tracestate: data.tracestate.clone(),
+        })
+    })
+    .flatten()
+}
+
+/// Continue a remote trace in a span.
+///
+/// The span becomes a child of the remote span, and takes its trace ID,
+/// flags and trace state. Spans opened inside it afterwards belong to the
+/// same trace. Returns `false` if the span is disabled or the subscriber
+/// has no [`OtelLayer`].
+pub fn set_parent(span: &Span, context: &TraceContext) -> bool {
+    span.with_subscriber(|(id, dispatch)| {
+        let Some(registry) = dispatch.downcast_ref::<Registry>() else {
+            return false;
+        };
+        let Some(span) = registry.span(id) else {
+            return false;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<SpanData>() else {
+            return false;
+        };
+        data.trace_id = context.trace_id;
+        data.parent_span_id = Some(context.span_id);
+        data.flags = context.flags;
+        data.tracestate.clone_from(&context.tracestate);
+        if !context.is_sampled() {
+            data.record = None;
+        }
+        true
+    })
+    .unwrap_or(false)
+}
+
+/// Trace identity of a span, stored in its extensions.
+#[derive(Debug)]
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    flags: u8,
+    tracestate: Option<String>,
+    /// Present only while exporting spans of a sampled trace.
+    record: Option<SpanRecord>,
+}
+
+/// Exported contents of a span.
+#[derive(Debug)]
+struct SpanRecord {
+    name: String,
+    kind: u8,
+    start_nanos: u64,
+    attributes: Vec<(String, String)>,
+    events: Vec<Value>,
+    error: bool,
+}
+
+impl SpanRecord {
+    fn new(metadata: &Metadata<'_>) -> Self {
+        Self {
+            name: metadata.name().to_string(),
+            kind: 1,
+            start_nanos: now_nanos(),
+            attributes: Vec::new(),
+            events: Vec::new(),
+            error: false,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: String) {
+        match key {
+            "otel.name" => self.name = value,
+            "otel.kind" => self.kind = span_kind(&value),
+            "otel.status_code" => self.error = value.eq_ignore_ascii_case("error"),
+            _ => match self.attributes.iter_mut().find(|(name, _)| name == key) {
+                Some((_, existing)) => *existing = value,
+                None => self.attributes.push((key.to_string(), value)),
+            },
+        }
+    }
+
+    /// The OTLP JSON encoding of the span.
+    fn to_json(&self, data: &SpanData, end_nanos: u64) -> Value {
+        let mut span = json!({
+            "traceId": hex(&data.trace_id),
+            "spanId": hex(&data.span_id),
+            "name": self.name,
+            "kind": self.kind,
+            "startTimeUnixNano": self.start_nanos.to_string(),
+            "endTimeUnixNano": end_nanos.to_string(),
+            "attributes": attributes_json(&self.attributes),
+            "events": self.events,
+        });
+        if let Some(parent) = &data.parent_span_id {
+            span["parentSpanId"] = json!(hex(parent));
+        }
+        if let Some(state) = &data.tracestate {
+            span["traceState"] = json!(state);
+        }
+        if self.error {
+            span["status"] = json!({ "code": 2 });
+        }
+        span
+    }
+}
+
+/// OTLP span kind of an `otel.kind` value.
+fn span_kind(kind: &str) -> u8 {
+    match kind.to_ascii_lowercase().as_str() {
+        "server" => 2,
+        "client" => 3,
+        "producer" => 4,
+        "consumer" => 5,
+        _ => 1,
+    }
+}
+
+fn attributes_json(attributes: &[(String, String)]) -> Vec<Value> {
+    attributes
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// Records span fields into a [`SpanRecord`].
+struct SpanVisitor<'a>(&'a mut SpanRecord);
+
+impl Visit for SpanVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.set(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.set(field.name(), format!("{value:?}"));
+    }
+}
+
+/// Collects the message and fields of an event.
+#[derive(Default)]
+struct EventVisitor {
+    message: Option<String>,
+    attributes: Vec<(String, String)>,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{value:?}"));
+    }
+}
+
+impl EventVisitor {
+    fn record(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.attributes.push((field.name().to_string(), value));
+        }
+    }
+}
+
+/// Span exporter shared by the layer and its clones.
+struct Exporter {
+    sender: mpsc::Sender<Value>,
+    dropped: AtomicU64,
+}
+
+/// `tracing-subscriber` layer assigning trace context to spans and
+/// exporting them over OTLP.
+///
+/// Cloning is cheap; all clones share the same exporter.
+#[derive(Clone, Default)]
+pub struct OtelLayer {
+    exporter: Arc<OnceLock<Exporter>>,
+}
+
+impl OtelLayer {
+    /// Create a layer that propagates trace context without exporting.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start exporting spans to the configured OTLP endpoint.
+    ///
+    /// Does nothing without an endpoint or if export already started.
+    /// Must be called inside a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be built.
+    pub fn start_export(&self, config: &TracingConfig) -> Result<(), RuntimeError> {
+        let Some(endpoint) = &config.otlp_endpoint else {
+            return Ok(());
+        };
+        if self.is_exporting() {
+            return Ok(());
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| {
+                RuntimeError::invalid_config(format!("Failed to create span exporter: {e}"))
+            })?;
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let exporter = Exporter {
+            sender,
+            dropped: AtomicU64::new(0),
+        };
+        if self.exporter.set(exporter).is_err() {
+            return Ok(());
+        }
+
+        let batch = OtlpBatch {
+            client,
+            url: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+            resource: json!({
+                "attributes": attributes_json(&[(
+                    "service.name".to_string(),
+                    config.service_name.clone(),
+                )]),
+            }),
+            batch_size: config.batch_size.max(1),
+            pending: Vec::new(),
+        };
+        tokio::spawn(run_exporter(
+            batch,
+            receiver,
+            Duration::from_millis(config.export_interval_ms.max(1)),
+        ));
+        Ok(())
+    }
+
+    /// Whether spans are being exported.
+    pub fn is_exporting(&self) -> bool {
+        self.exporter.get().is_some()
+    }
+
+    /// Number of finished spans dropped because the export queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.exporter
+            .get()
+            .map_or(0, |exporter| exporter.dropped.load(Ordering::Relaxed))
+    }
+}
+
+impl fmt::Debug for OtelLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtelLayer")
+            .field("exporting", &self.is_exporting())
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for OtelLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut parent = None;
+        if let Some(parent_span) = span.parent() {
+            if let Some(data) = parent_span.extensions().get::<SpanData>() {
+                parent = Some((
+                    data.trace_id,
+                    data.span_id,
+                    data.flags,
+                    data.tracestate.clone(),
+                ));
+            }
+        }
+        let (trace_id, parent_span_id, flags, tracestate) = match parent {
+            Some((trace_id, span_id, flags, tracestate)) => {
+                (trace_id, Some(span_id), flags, tracestate)
+            }
+            None => (new_trace_id(), None, SAMPLED, None),
+        };
+
+        let record = (self.is_exporting() && flags & SAMPLED != 0).then(|| {
+            let mut record = SpanRecord::new(attrs.metadata());
+            attrs.record(&mut SpanVisitor(&mut record));
+            record
+        });
+        span.extensions_mut().insert(SpanData {
+            trace_id,
+            span_id: new_span_id(),
+            parent_span_id,
+            flags,
+            tracestate,
+            record,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(record) = extensions
+            .get_mut::<SpanData>()
+            .and_then(|data| data.record.as_mut())
+        {
+            values.record(&mut SpanVisitor(record));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(record) = extensions
+            .get_mut::<SpanData>()
+            .and_then(|data| data.record.as_mut())
+        else {
+            return;
+        };
+
+        if *event.metadata().level() == Level::ERROR {
+            record.error = true;
+        }
+        if record.events.len() >= MAX_EVENTS_PER_SPAN {
+            return;
+        }
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        visitor
+            .attributes
+            .push(("level".to_string(), event.metadata().level().to_string()));
+        record.events.push(json!({
+            "timeUnixNano": now_nanos().to_string(),
+            "name": visitor.message.unwrap_or_else(|| event.metadata().name().to_string()),
+            "attributes": attributes_json(&visitor.attributes),
+        }));
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(exporter) = self.exporter.get() else {
+            return;
+        };
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(mut data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        let Some(record) = data.record.take() else {
+            return;
+        };
+
+        let json = record.to_json(&data, now_nanos());
+        if exporter.sender.try_send(json).is_err() {
+            exporter.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Pending spans and the collector they are posted to.
+struct OtlpBatch {
+    client: reqwest::Client,
+    url: String,
+    resource: Value,
+    batch_size: usize,
+    pending: Vec<Value>,
+}
+
+impl OtlpBatch {
+    async fn export(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let spans = std::mem::take(&mut self.pending);
+        let count = spans.len();
+        let body = json!({
+            "resourceSpans": [{
+                "resource": self.resource,
+                "scopeSpans": [{
+                    "scope": { "name": "edge-runtime" },
+                    "spans": spans,
+                }],
+            }],
+        });
+
+        let result = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => {
+                warn!(status = %response.status(), spans = count, "OTLP collector rejected spans");
+            }
+            Err(e) => warn!(error = %e, spans = count, "Failed to export spans"),
+        }
+    }
+}
+
+/// Batch finished spans and post them until the layer is dropped.
+async fn run_exporter(
+    mut batch: OtlpBatch,
+    mut receiver: mpsc::Receiver<Value>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            span = receiver.recv() => {
+                let Some(span) = span else { break };
+                batch.pending.push(span);
+                if batch.pending.len() >= batch.batch_size {
+                    batch.export().await;
+                }
+            }
+            _ = ticker.tick() => batch.export().await,
+        }
+    }
+
+    batch.export().await;
+}
+
+/// A random non-zero 64-bit value.
+///
+/// IDs only need to be unique, not unpredictable: a randomly seeded
+/// counter mixed by `SplitMix64` avoids a system call per span.
+fn random_u64() -> u64 {
+    static SEED: OnceLock<u64> = OnceLock::new();
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let seed = *SEED.get_or_init(|| {
+        let mut bytes = [0; 8];
+        if SystemRandom::new().fill(&mut bytes).is_err() {
+            return now_nanos();
+        }
+        u64::from_le_bytes(bytes)
+    });
+    loop {
+        let step = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut z = seed.wrapping_add(step.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        if z != 0 {
+            return z;
+        }
+    }
+}
+
+fn new_span_id() -> [u8; 8] {
+    random_u64().to_be_bytes()
+}
+
+fn new_trace_id() -> [u8; 16] {
+    let mut id = [0; 16];
+    id[..8].copy_from_slice(&random_u64().to_be_bytes());
+    id[8..].copy_from_slice(&random_u64().to_be_bytes());
+    id
+}
+
+/// Current time in nanoseconds since the Unix epoch.
+fn now_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+/// Decode `N` bytes from exactly `2 * N` lowercase hex digits.
+fn decode_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != 2 * N
+        || !s
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    const TRACEPARENT_VALUE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_traceparent() {
+        let context = TraceContext::parse(TRACEPARENT_VALUE, Some("vendor=abc")).unwrap();
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(hex(&context.span_id), "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(context.tracestate.as_deref(), Some("vendor=abc"));
+        assert_eq!(context.traceparent(), TRACEPARENT_VALUE);
+
+        let unsampled = TraceContext::parse(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00",
+            None,
+        )
+        .unwrap();
+        assert!(!unsampled.is_sampled());
+        assert!(
+            TraceContext::parse(
+                "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-ext",
+                None
+            )
+            .is_some()
+        );
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-ext",
+        ] {
+            assert!(TraceContext::parse(invalid, None).is_none(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_context_propagation() {
+        let subscriber = tracing_subscriber::registry().with(OtelLayer::new());
+        tracing::subscriber::with_default(subscriber, || {
+            let remote = TraceContext::parse(TRACEPARENT_VALUE, Some("vendor=abc")).unwrap();
+            let request = info_span!("handle_function");
+            assert!(set_parent(&request, &remote));
+
+            let _entered = request.enter();
+            let child = info_span!("execute_core");
+            let _child_entered = child.enter();
+            let context = current_trace_context().unwrap();
+            assert_eq!(context.trace_id, remote.trace_id);
+            assert_ne!(context.span_id, remote.span_id);
+            assert_ne!(context.span_id, trace_context(&request).unwrap().span_id);
+            assert_eq!(context.tracestate.as_deref(), Some("vendor=abc"));
+
+            let other = info_span!(parent: None, "other");
+            assert_ne!(trace_context(&other).unwrap().trace_id, remote.trace_id);
+        });
+
+        assert!(current_trace_context().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_export_to_collector() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let collector = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                assert!(n > 0);
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length: usize = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    if body.len() >= length {
+                        break;
+                    }
+                }
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let layer = OtelLayer::new();
+        layer
+            .start_export(&TracingConfig {
+                otlp_endpoint: Some(endpoint),
+                service_name: "edge-test".to_string(),
+                batch_size: 2,
+                ..TracingConfig::default()
+            })
+            .unwrap();
+        assert!(layer.is_exporting());
+
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let remote = TraceContext::parse(TRACEPARENT_VALUE, None).unwrap();
+            let request = info_span!("handle_function", otel.kind = "server", function_id = "api");
+            set_parent(&request, &remote);
+            let _entered = request.enter();
+            let guest = info_span!("guest_span", otel.name = "render");
+            guest.in_scope(|| tracing::error!(attempt = 2, "template missing"));
+        });
+
+        let request = collector.await.unwrap();
+        assert!(request.starts_with("POST /v1/traces "));
+        let body: Value = serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "edge-test"
+        );
+        let spans = resource["scopeSpans"][0]["spans"].as_array().unwrap();
+        assert_eq!(spans.len(), 2);
+
+        let (guest, request) = (&spans[0], &spans[1]);
+        assert_eq!(request["name"], "handle_function");
+        assert_eq!(request["kind"], 2);
+        assert_eq!(request["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(request["parentSpanId"], "00f067aa0ba902b7");
+        assert_eq!(request["attributes"][0]["key"], "function_id");
+
+        assert_eq!(guest["name"], "render");
+        assert_eq!(guest["traceId"], request["traceId"]);
+        assert_eq!(guest["parentSpanId"], request["spanId"]);
+        assert_eq!(guest["status"]["code"], 2);
+        assert_eq!(guest["events"][0]["name"], "template missing");
+    }
+}
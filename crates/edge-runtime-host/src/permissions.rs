@@ -4,6 +4,7 @@
 //! operations a guest component is allowed to perform.
 
 use std::collections::HashSet;
+use std::net::IpAddr;
 
 /// Permission configuration for a function execution.
 ///
@@ -35,6 +36,73 @@ pub struct Permissions {
 
     /// Enable logging.
     pub logging_enabled: bool,
+
+    /// Modules this function may invoke via service bindings.
+    ///
+    /// Entries are module IDs; `*` allows calling any loaded module.
+    pub allowed_services: HashSet<String>,
+
+    /// Key-value store namespace this function reads and writes.
+    ///
+    /// `None` disables KV access.
+    pub kv_namespace: Option<String>,
+
+    /// Maximum KV operations per execution.
+    pub max_kv_operations: u32,
+
+    /// Maximum size of a single KV value in bytes.
+    pub max_kv_value_bytes: usize,
+
+    /// Maximum bytes of KV values written per execution.
+    pub max_kv_bytes_written: u64,
+
+    /// SQL database namespace this function uses.
+    ///
+    /// `None` disables SQL access.
+    pub sql_namespace: Option<String>,
+
+    /// Maximum SQL statements per execution.
+    pub max_sql_statements: u32,
+
+    /// Only allow statements that do not modify the database.
+    pub sql_read_only: bool,
+
+    /// Blob storage buckets this function reads and writes.
+    ///
+    /// Entries are bucket names; `*` allows any bucket.
+    pub blob_buckets: HashSet<String>,
+
+    /// Maximum blob operations per execution.
+    pub max_blob_operations: u32,
+
+    /// Size limit of each bucket this function writes to, in bytes.
+    pub max_blob_bucket_bytes: u64,
+
+    /// Host-held crypto keys this function may use.
+    ///
+    /// Entries are key names; `*` allows any key.
+    pub crypto_keys: HashSet<String>,
+
+    /// Addresses this function may open TCP connections to.
+    ///
+    /// Patterns are `host:port`, where the host is matched like
+    /// `allowed_http_hosts` and the port may be `*`. A lone `*` allows any
+    /// address.
+    pub allowed_socket_addresses: HashSet<String>,
+
+    /// Maximum TCP connections opened per execution.
+    pub max_socket_connections: u32,
+
+    /// Maximum bytes sent and received over TCP connections per execution.
+    pub max_socket_bytes: u64,
+
+    /// Maximum WebSocket connections opened per execution.
+    ///
+    /// WebSocket URLs are checked against `allowed_http_hosts`.
+    pub max_websocket_connections: u32,
+
+    /// Maximum WebSocket messages sent and received per execution.
+    pub max_websocket_messages: u32,
 }
 
 impl Permissions {
@@ -54,10 +122,27 @@ impl Permissions {
         allowed_hosts.insert("*".to_string());
 
         Self {
-            allowed_http_hosts: allowed_hosts,
+            allowed_http_hosts: allowed_hosts.clone(),
             http_enabled: true,
             max_http_requests: 100,
             logging_enabled: true,
+            allowed_services: allowed_hosts,
+            kv_namespace: None,
+            max_kv_operations: 0,
+            max_kv_value_bytes: 0,
+            max_kv_bytes_written: 0,
+            sql_namespace: None,
+            max_sql_statements: 0,
+            sql_read_only: false,
+            blob_buckets: HashSet::new(),
+            max_blob_operations: 0,
+            max_blob_bucket_bytes: 0,
+            crypto_keys: HashSet::new(),
+            allowed_socket_addresses: HashSet::new(),
+            max_socket_connections: 0,
+            max_socket_bytes: 0,
+            max_websocket_connections: 0,
+            max_websocket_messages: 0,
         }
     }
 
@@ -98,6 +183,53 @@ impl Permissions {
             .any(|pattern| Self::matches_pattern(pattern, &host))
     }
 
+    /// Check if invoking the given module via service bindings is allowed.
+    pub fn is_service_allowed(&self, module_id: &str) -> bool {
+        self.allowed_services.contains("*") || self.allowed_services.contains(module_id)
+    }
+
+    /// Get the KV namespace, if KV access is granted.
+    pub fn kv_namespace(&self) -> Option<&str> {
+        self.kv_namespace.as_deref()
+    }
+
+    /// Get the SQL namespace, if SQL access is granted.
+    pub fn sql_namespace(&self) -> Option<&str> {
+        self.sql_namespace.as_deref()
+    }
+
+    /// Check if access to the given blob storage bucket is allowed.
+    pub fn is_blob_bucket_allowed(&self, bucket: &str) -> bool {
+        self.blob_buckets.contains("*") || self.blob_buckets.contains(bucket)
+    }
+
+    /// Check if use of the given host-held crypto key is allowed.
+    pub fn is_crypto_key_allowed(&self, name: &str) -> bool {
+        self.crypto_keys.contains("*") || self.crypto_keys.contains(name)
+    }
+
+    /// Check if opening a TCP connection to `host:port` is allowed.
+    ///
+    /// This only matches the address against the allowed patterns; callers
+    /// must still block private addresses with [`Self::is_private_host`] and
+    /// [`Self::is_private_ip`].
+    pub fn is_socket_allowed(&self, host: &str, port: u16) -> bool {
+        let host = host.to_lowercase();
+
+        self.allowed_socket_addresses.iter().any(|pattern| {
+            if pattern == "*" {
+                return true;
+            }
+            let Some((host_pattern, port_pattern)) = pattern.rsplit_once(':') else {
+                return false;
+            };
+            let host_pattern = host_pattern.trim_start_matches('[').trim_end_matches(']');
+
+            (port_pattern == "*" || port_pattern.parse() == Ok(port))
+                && (host_pattern == "*" || Self::matches_pattern(host_pattern, &host))
+        })
+    }
+
     /// Check if a host matches a permission pattern.
     fn matches_pattern(pattern: &str, host: &str) -> bool {
         let pattern = pattern.to_lowercase();
@@ -124,38 +256,56 @@ impl Permissions {
             return false;
         };
 
-        let Some(host_str) = parsed.host_str() else {
-            return false;
-        };
-        let host = host_str.to_lowercase();
+        parsed
+            .host()
+            .is_some_and(|host| Self::is_private_url_host(&host))
+    }
 
-        // Block localhost
-        if host == "localhost" || host == "127.0.0.1" {
-            return true;
+    /// Check if a bare host name or IP address is private/internal.
+    ///
+    /// Applies the same rules as [`Self::is_private_address`]. Names are
+    /// not resolved; check the resolved addresses with
+    /// [`Self::is_private_ip`] before connecting.
+    pub fn is_private_host(host: &str) -> bool {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Self::is_private_ip(ip);
         }
 
-        // Block cloud metadata endpoints
-        if host == "169.254.169.254" || host == "metadata.google.internal" {
-            return true;
+        url::Host::parse(host).is_ok_and(|host| Self::is_private_url_host(&host))
+    }
+
+    /// Check if an IP address is private/internal.
+    pub fn is_private_ip(ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(v4) => {
+                v4.is_private()
+                    || v4.is_loopback()
+                    || v4.is_link_local()
+                    || v4.is_broadcast()
+                    || v4.is_documentation()
+                    || v4.is_unspecified()
+            }
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => Self::is_private_ip(IpAddr::V4(v4)),
+                None => v6.is_loopback() || v6.is_unspecified(),
+            },
         }
+    }
 
-        // Use the url crate's host parsing for proper IPv6 handling
-        if let Some(url_host) = parsed.host() {
-            return match url_host {
-                url::Host::Ipv4(v4) => {
-                    v4.is_private()
-                        || v4.is_loopback()
-                        || v4.is_link_local()
-                        || v4.is_broadcast()
-                        || v4.is_documentation()
-                        || v4.is_unspecified()
+    /// Check if a parsed host is private/internal.
+    fn is_private_url_host<S: AsRef<str>>(host: &url::Host<S>) -> bool {
+        match host {
+            // Hosts of non-special URL schemes are not parsed as addresses.
+            url::Host::Domain(domain) => {
+                if let Ok(ip) = domain.as_ref().parse() {
+                    return Self::is_private_ip(ip);
                 }
-                url::Host::Ipv6(v6) => v6.is_loopback() || v6.is_unspecified(),
-                url::Host::Domain(_) => false,
-            };
+                let domain = domain.as_ref().to_lowercase();
+                domain == "localhost" || domain == "metadata.google.internal"
+            }
+            url::Host::Ipv4(v4) => Self::is_private_ip(IpAddr::V4(*v4)),
+            url::Host::Ipv6(v6) => Self::is_private_ip(IpAddr::V6(*v6)),
         }
-
-        false
     }
 }
 
@@ -189,6 +339,133 @@ impl PermissionsBuilder {
         self
     }
 
+    /// Allow invoking specific modules via service bindings.
+    ///
+    /// # Arguments
+    ///
+    /// * `module_ids` - Module IDs to allow, or `*` for any module
+    #[must_use]
+    pub fn allow_services<I, S>(mut self, module_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner.allowed_services = module_ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Grant KV access to a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Namespace the function reads and writes
+    /// * `max_operations` - Maximum KV operations per execution
+    /// * `max_value_bytes` - Maximum size of a single value
+    /// * `max_bytes_written` - Maximum bytes of values written per execution
+    #[must_use]
+    pub fn kv_namespace(
+        mut self,
+        namespace: impl Into<String>,
+        max_operations: u32,
+        max_value_bytes: usize,
+        max_bytes_written: u64,
+    ) -> Self {
+        self.inner.kv_namespace = Some(namespace.into());
+        self.inner.max_kv_operations = max_operations;
+        self.inner.max_kv_value_bytes = max_value_bytes;
+        self.inner.max_kv_bytes_written = max_bytes_written;
+        self
+    }
+
+    /// Grant SQL access to a namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - Namespace of the database the function uses
+    /// * `max_statements` - Maximum SQL statements per execution
+    #[must_use]
+    pub fn sql_namespace(mut self, namespace: impl Into<String>, max_statements: u32) -> Self {
+        self.inner.sql_namespace = Some(namespace.into());
+        self.inner.max_sql_statements = max_statements;
+        self
+    }
+
+    /// Only allow SQL statements that do not modify the database.
+    #[must_use]
+    pub fn sql_read_only(mut self) -> Self {
+        self.inner.sql_read_only = true;
+        self
+    }
+
+    /// Grant access to blob storage buckets.
+    ///
+    /// # Arguments
+    ///
+    /// * `buckets` - Bucket names; `*` allows any bucket
+    /// * `max_operations` - Maximum blob operations per execution
+    /// * `max_bucket_bytes` - Size limit of each bucket written to
+    #[must_use]
+    pub fn allow_blob_buckets<I, S>(
+        mut self,
+        buckets: I,
+        max_operations: u32,
+        max_bucket_bytes: u64,
+    ) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner.blob_buckets = buckets.into_iter().map(Into::into).collect();
+        self.inner.max_blob_operations = max_operations;
+        self.inner.max_blob_bucket_bytes = max_bucket_bytes;
+        self
+    }
+
+    /// Grant use of host-held crypto keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - Key names; `*` allows any key
+    #[must_use]
+    pub fn allow_crypto_keys<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner.crypto_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Allow opening TCP connections to specific addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `addresses` - `host:port` patterns (e.g., `db.example.com:5432`, `*.example.com:*`)
+    /// * `max_connections` - Maximum connections opened per execution
+    /// * `max_bytes` - Maximum bytes sent and received per execution
+    #[must_use]
+    pub fn allow_sockets<I, S>(mut self, addresses: I, max_connections: u32, max_bytes: u64) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.inner.allowed_socket_addresses = addresses.into_iter().map(Into::into).collect();
+        self.inner.max_socket_connections = max_connections;
+        self.inner.max_socket_bytes = max_bytes;
+        self
+    }
+
+    /// Set the WebSocket limits per execution.
+    ///
+    /// WebSocket connections are only allowed to hosts granted with
+    /// [`Self::allow_http_hosts`].
+    #[must_use]
+    pub fn websocket_limits(mut self, max_connections: u32, max_messages: u32) -> Self {
+        self.inner.max_websocket_connections = max_connections;
+        self.inner.max_websocket_messages = max_messages;
+        self
+    }
+
     /// Enable logging.
     #[must_use]
     pub fn enable_logging(mut self) -> Self {
@@ -213,6 +490,10 @@ mod tests {
         assert!(!perms.http_enabled);
         assert!(!perms.logging_enabled);
         assert!(perms.allowed_http_hosts.is_empty());
+        assert!(perms.kv_namespace().is_none());
+        assert!(perms.sql_namespace().is_none());
+        assert!(!perms.is_blob_bucket_allowed("uploads"));
+        assert!(!perms.is_crypto_key_allowed("signing"));
     }
 
     #[test]
@@ -287,6 +568,42 @@ mod tests {
         assert!(!Permissions::is_private_address("https://8.8.8.8/"));
     }
 
+    #[test]
+    fn test_private_host() {
+        assert!(Permissions::is_private_host("localhost"));
+        assert!(Permissions::is_private_host("10.1.2.3"));
+        assert!(Permissions::is_private_host("::1"));
+        assert!(Permissions::is_private_host("::ffff:192.168.0.1"));
+        assert!(Permissions::is_private_host("metadata.google.internal"));
+        assert!(!Permissions::is_private_host("db.example.com"));
+        assert!(!Permissions::is_private_host("8.8.8.8"));
+    }
+
+    #[test]
+    fn test_socket_allowed() {
+        let perms = Permissions::builder()
+            .allow_sockets(["smtp.example.com:465", "*.redis.example.com:*"], 4, 1024)
+            .build();
+
+        assert!(perms.is_socket_allowed("smtp.example.com", 465));
+        assert!(perms.is_socket_allowed("SMTP.example.com", 465));
+        assert!(!perms.is_socket_allowed("smtp.example.com", 25));
+        assert!(perms.is_socket_allowed("cache.redis.example.com", 6379));
+        assert!(!perms.is_socket_allowed("redis.evil.com", 6379));
+
+        assert!(!Permissions::none().is_socket_allowed("smtp.example.com", 465));
+    }
+
+    #[test]
+    fn test_service_allowed() {
+        let perms = Permissions::builder().allow_services(["auth"]).build();
+        assert!(perms.is_service_allowed("auth"));
+        assert!(!perms.is_service_allowed("pricing"));
+
+        assert!(!Permissions::none().is_service_allowed("auth"));
+        assert!(Permissions::all().is_service_allowed("pricing"));
+    }
+
     #[test]
     fn test_builder() {
         let perms = Permissions::builder()
@@ -0,0 +1,354 @@
+//! Service bindings: module-to-module invocation.
+//!
+//! This module provides the host-side implementation of the service
+//! interface, which lets a guest call another loaded module in-process
+//! instead of going over public HTTP.
+//!
+//! The actual execution of the target module is delegated to a
+//! [`ServiceInvoker`] supplied by the embedding runtime, since the module
+//! registry lives above this crate. Calls are subject to:
+//!
+//! - **Permissions**: the caller must be allowed to call the target
+//!   (see [`Permissions::is_service_allowed`])
+//! - **Depth limits**: nested calls may not exceed the configured depth
+//! - **Shared fuel**: the callee runs on the caller's remaining fuel, and
+//!   whatever it consumes is deducted from the caller
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use edge_runtime_core::store::WorkerContext;
+use thiserror::Error;
+use tracing::{debug, warn};
+use wasmtime::AsContextMut;
+
+use crate::Permissions;
+use crate::exchange::{GuestRequest, GuestResponse};
+
+/// Maximum number of service calls a single execution may make.
+pub const MAX_SERVICE_CALLS: usize = 64;
+
+/// Errors returned by service calls.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ServiceError {
+    /// Service bindings are not configured for this execution.
+    #[error("Service bindings are not available")]
+    Unavailable,
+
+    /// The caller is not allowed to invoke the target module.
+    #[error("Permission denied: cannot invoke '{target}'")]
+    PermissionDenied {
+        /// The target module ID.
+        target: String,
+    },
+
+    /// The target module is not loaded.
+    #[error("Module not found: {target}")]
+    NotFound {
+        /// The target module ID.
+        target: String,
+    },
+
+    /// The call would exceed the maximum nesting depth.
+    #[error("Service call depth limit of {max_depth} exceeded")]
+    DepthExceeded {
+        /// The configured maximum depth.
+        max_depth: u32,
+    },
+
+    /// The per-execution call limit was reached.
+    #[error("Service call limit of {MAX_SERVICE_CALLS} exceeded")]
+    TooManyCalls,
+
+    /// The callee ran out of the shared fuel budget.
+    #[error("Fuel exhausted during service call")]
+    FuelExhausted,
+
+    /// The callee trapped or failed to execute.
+    #[error("Service call failed: {message}")]
+    Failed {
+        /// Description of the failure.
+        message: String,
+    },
+}
+
+impl ServiceError {
+    /// Numeric error code returned to core modules.
+    pub fn code(&self) -> i32 {
+        match self {
+            ServiceError::PermissionDenied { .. } => -2,
+            ServiceError::NotFound { .. } => -3,
+            ServiceError::DepthExceeded { .. } => -4,
+            ServiceError::FuelExhausted => -5,
+            ServiceError::Failed { .. } => -6,
+            ServiceError::TooManyCalls => -7,
+            ServiceError::Unavailable => -8,
+        }
+    }
+}
+
+/// A call from one module to another.
+#[derive(Debug, Clone)]
+pub struct ServiceCall {
+    /// Request ID of the originating request, shared by the callee.
+    pub request_id: String,
+    /// Calling module ID, if known.
+    pub caller: Option<String>,
+    /// Target module ID.
+    pub target: String,
+    /// Request passed to the target.
+    pub request: GuestRequest,
+    /// Nesting depth the target will run at.
+    pub depth: u32,
+    /// Fuel available to the target, or `None` if fuel is not metered.
+    pub fuel: Option<u64>,
+    /// Deadline of the caller, which the target must finish by, or `None`
+    /// if the caller has no timeout.
+    pub deadline: Option<Instant>,
+}
+
+/// Outcome of a service call.
+#[derive(Debug, Clone)]
+pub struct ServiceOutcome {
+    /// The target's response or the reason it failed.
+    pub result: Result<GuestResponse, ServiceError>,
+    /// Fuel consumed by the target, to be charged to the caller.
+    pub fuel_consumed: u64,
+}
+
+/// Executes service calls on behalf of guest code.
+///
+/// Implemented by the embedding runtime, which owns the module registry.
+#[async_trait]
+pub trait ServiceInvoker: Send + Sync {
+    /// Run the target module with the given request.
+    async fn invoke(&self, call: ServiceCall) -> ServiceOutcome;
+}
+
+/// Per-execution service binding state.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime.
+pub struct ServiceContext {
+    /// Invoker used to run target modules.
+    invoker: Arc<dyn ServiceInvoker>,
+    /// Nesting depth of the current execution.
+    depth: u32,
+    /// Maximum nesting depth.
+    max_depth: u32,
+    /// Responses received so far, indexed by handle.
+    responses: Vec<GuestResponse>,
+}
+
+impl ServiceContext {
+    /// Create service state for an execution at the given depth.
+    pub fn new(invoker: Arc<dyn ServiceInvoker>, depth: u32, max_depth: u32) -> Self {
+        Self {
+            invoker,
+            depth,
+            max_depth,
+            responses: Vec::new(),
+        }
+    }
+
+    /// Nesting depth of the current execution.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Get a response received by an earlier call.
+    pub fn response(&self, handle: i32) -> Option<&GuestResponse> {
+        usize::try_from(handle)
+            .ok()
+            .and_then(|index| self.responses.get(index))
+    }
+
+    /// Store a response and return its handle.
+    pub fn push_response(&mut self, response: GuestResponse) -> i32 {
+        self.responses.push(response);
+        i32::try_from(self.responses.len() - 1).unwrap_or(i32::MAX)
+    }
+}
+
+impl std::fmt::Debug for ServiceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServiceContext")
+            .field("depth", &self.depth)
+            .field("max_depth", &self.max_depth)
+            .field("responses", &self.responses.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host implementation for the service interface.
+pub struct ServiceHost;
+
+impl ServiceHost {
+    /// Invoke another module with the given request.
+    ///
+    /// Checks permissions and limits, runs the target with the caller's
+    /// remaining fuel and time, and deducts the fuel it consumed from the caller.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ServiceError`] if the call is not allowed or the target
+    /// fails.
+    pub async fn invoke(
+        mut store: impl AsContextMut<Data = WorkerContext>,
+        target: &str,
+        request: GuestRequest,
+    ) -> Result<GuestResponse, ServiceError> {
+        let mut cx = store.as_context_mut();
+        let fuel = cx.get_fuel().ok();
+        let ctx = cx.data_mut();
+
+        let allowed = ctx
+            .extensions()
+            .get::<Permissions>()
+            .is_some_and(|perms| perms.is_service_allowed(target));
+        if !allowed {
+            warn!(request_id = %ctx.request_id, target = %target, "Service call denied");
+            return Err(ServiceError::PermissionDenied {
+                target: target.to_string(),
+            });
+        }
+
+        let calls = ctx.metrics.service_calls as usize;
+        let Some(service) = ctx.extensions().get::<ServiceContext>() else {
+            return Err(ServiceError::Unavailable);
+        };
+        if service.depth >= service.max_depth {
+            return Err(ServiceError::DepthExceeded {
+                max_depth: service.max_depth,
+            });
+        }
+        if calls >= MAX_SERVICE_CALLS {
+            return Err(ServiceError::TooManyCalls);
+        }
+
+        let invoker = service.invoker.clone();
+        let call = ServiceCall {
+            request_id: ctx.request_id.clone(),
+            caller: ctx.module_id.clone(),
+            target: target.to_string(),
+            request,
+            depth: service.depth + 1,
+            fuel,
+            deadline: ctx.deadline(),
+        };
+        ctx.metrics.service_calls += 1;
+
+        debug!(
+            request_id = %call.request_id,
+            target = %target,
+            depth = call.depth,
+            "Invoking service"
+        );
+
+        let outcome = invoker.invoke(call).await;
+
+        let mut cx = store.as_context_mut();
+        if let Some(fuel) = fuel {
+            // Charge the callee's consumption to the caller. If the callee
+            // exhausted the budget, the caller traps on its next instruction.
+            let _ = cx.set_fuel(fuel.saturating_sub(outcome.fuel_consumed));
+        }
+
+        outcome.result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::{EngineConfig, ExecutionConfig};
+    use edge_runtime_core::WasmEngine;
+    use edge_runtime_core::store::create_store;
+
+    /// Invoker that echoes the request URI and consumes a fixed amount of fuel.
+    struct EchoInvoker;
+
+    #[async_trait]
+    impl ServiceInvoker for EchoInvoker {
+        async fn invoke(&self, call: ServiceCall) -> ServiceOutcome {
+            ServiceOutcome {
+                result: Ok(GuestResponse {
+                    body: call.request.uri.into_bytes(),
+                    ..GuestResponse::default()
+                }),
+                fuel_consumed: 100,
+            }
+        }
+    }
+
+    fn store(permissions: Permissions, depth: u32) -> wasmtime::Store<WorkerContext> {
+        let engine = WasmEngine::new(&EngineConfig {
+            pooling_allocator: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let exec_config = ExecutionConfig {
+            max_fuel: 1000,
+            ..Default::default()
+        };
+        let mut store = create_store(&engine, &exec_config, "test".into()).unwrap();
+        let ext = store.data_mut().extensions_mut();
+        ext.insert(permissions);
+        ext.insert(ServiceContext::new(Arc::new(EchoInvoker), depth, 2));
+        store
+    }
+
+    #[tokio::test]
+    async fn test_invoke_charges_fuel() {
+        let perms = Permissions::builder().allow_services(["auth"]).build();
+        let mut store = store(perms, 0);
+
+        let response = ServiceHost::invoke(&mut store, "auth", GuestRequest::new("GET", "/check"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, b"/check");
+        assert_eq!(store.get_fuel().unwrap(), 900);
+        assert_eq!(store.data().metrics.service_calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_permission_denied() {
+        let perms = Permissions::builder().allow_services(["auth"]).build();
+        let mut store = store(perms, 0);
+
+        let result = ServiceHost::invoke(&mut store, "billing", GuestRequest::default()).await;
+
+        assert!(matches!(result, Err(ServiceError::PermissionDenied { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_invoke_depth_exceeded() {
+        let perms = Permissions::builder().allow_services(["*"]).build();
+        let mut store = store(perms, 2);
+
+        let result = ServiceHost::invoke(&mut store, "auth", GuestRequest::default()).await;
+
+        assert_eq!(result, Err(ServiceError::DepthExceeded { max_depth: 2 }));
+    }
+
+    #[test]
+    fn test_error_codes_distinct() {
+        let errors = [
+            ServiceError::Unavailable,
+            ServiceError::PermissionDenied { target: "a".into() },
+            ServiceError::NotFound { target: "a".into() },
+            ServiceError::DepthExceeded { max_depth: 1 },
+            ServiceError::TooManyCalls,
+            ServiceError::FuelExhausted,
+            ServiceError::Failed {
+                message: "boom".into(),
+            },
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(ServiceError::code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert!(codes.iter().all(|c| *c < -1));
+    }
+}
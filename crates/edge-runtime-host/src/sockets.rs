@@ -0,0 +1,502 @@
+//! Outbound TCP socket host function implementation.
+//!
+//! This module provides the host-side implementation of the sockets
+//! interface, which lets guest code talk to upstreams that do not speak
+//! HTTP, such as Redis, Postgres or SMTP servers.
+//!
+//! Connections are opened by host name and port, optionally wrapped in TLS
+//! verified against the bundled web PKI roots. Access is controlled per
+//! module through [`Permissions::allowed_socket_addresses`]. Private
+//! addresses are blocked both by name and after resolution, so a public
+//! name resolving to an internal address is refused as well.
+//!
+//! Each request may open at most
+//! [`Permissions::max_socket_connections`] connections and transfer at most
+//! [`Permissions::max_socket_bytes`] bytes in both directions. Connections
+//! are per-request handles and are closed when the request ends.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use edge_runtime_common::{HostFunctionError, SocketsConfig};
+use edge_runtime_core::store::WorkerContext;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tracing::{debug, warn};
+
+use crate::Permissions;
+
+/// Maximum number of connections a request may have open.
+pub const MAX_OPEN_SOCKETS: usize = 8;
+
+/// Maximum number of bytes returned by a single receive.
+pub const MAX_SOCKET_READ_BYTES: usize = 64 * 1024;
+
+/// Timeout of sends and receives for executions without a deadline.
+const DEFAULT_IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shared settings for outbound TCP connections, used by the sockets and
+/// WebSocket interfaces.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime.
+#[derive(Clone)]
+pub struct SocketConnector {
+    tls: Arc<ClientConfig>,
+    connect_timeout: Duration,
+}
+
+impl SocketConnector {
+    /// Create a connector with the settings in `config`.
+    pub fn new(config: &SocketsConfig) -> Self {
+        let roots: RootCertStore = webpki_roots::TLS_SERVER_ROOTS.iter().cloned().collect();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let tls = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .expect("ring provider supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        Self {
+            tls: Arc::new(tls),
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+        }
+    }
+
+    /// Get the timeout for establishing a connection.
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Resolve `host`, refuse private addresses, and connect.
+    ///
+    /// Callers bound the time taken by [`Self::connect_timeout`].
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        tls: bool,
+    ) -> Result<SocketStream, HostFunctionError> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(socket_error)?
+            .collect();
+        if addrs.is_empty() {
+            return Err(socket_error(format!("no addresses found for '{host}'")));
+        }
+        if addrs
+            .iter()
+            .any(|addr| Permissions::is_private_ip(addr.ip()))
+        {
+            warn!(host, port, "Socket blocked: resolves to a private address");
+            return Err(denied(host, port));
+        }
+
+        let stream = TcpStream::connect(addrs.as_slice())
+            .await
+            .map_err(socket_error)?;
+        stream.set_nodelay(true).map_err(socket_error)?;
+        if !tls {
+            return Ok(SocketStream::Plain(stream));
+        }
+
+        let name = ServerName::try_from(host.to_string()).map_err(|e| {
+            HostFunctionError::InvalidArgument {
+                reason: format!("invalid TLS server name '{host}': {e}"),
+            }
+        })?;
+        let stream = TlsConnector::from(Arc::clone(&self.tls))
+            .connect(name, stream)
+            .await
+            .map_err(socket_error)?;
+        Ok(SocketStream::Tls(Box::new(stream)))
+    }
+}
+
+impl Default for SocketConnector {
+    fn default() -> Self {
+        Self::new(&SocketsConfig::default())
+    }
+}
+
+impl std::fmt::Debug for SocketConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SocketConnector")
+            .field("connect_timeout", &self.connect_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An open connection, with or without TLS.
+pub(crate) enum SocketStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl SocketStream {
+    pub(crate) async fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.write_all(data).await,
+            Self::Tls(stream) => {
+                stream.write_all(data).await?;
+                stream.flush().await
+            }
+        }
+    }
+
+    pub(crate) async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf).await,
+            Self::Tls(stream) => stream.read(buf).await,
+        }
+    }
+}
+
+/// Connections opened by a request.
+///
+/// Stored in the [`WorkerContext`] extensions on first use; dropping it
+/// closes the connections.
+#[derive(Default)]
+struct SocketContext {
+    streams: HashMap<i32, SocketStream>,
+    next_handle: i32,
+}
+
+impl std::fmt::Debug for SocketContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SocketContext")
+            .field("streams", &self.streams.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host implementation for the sockets interface.
+///
+/// Opening a connection checks the caller's allowed addresses and counts
+/// towards [`ExecutionMetrics::socket_connections`]; sent and received
+/// bytes count towards [`ExecutionMetrics::socket_bytes`].
+///
+/// [`ExecutionMetrics::socket_connections`]: edge_runtime_core::ExecutionMetrics::socket_connections
+/// [`ExecutionMetrics::socket_bytes`]: edge_runtime_core::ExecutionMetrics::socket_bytes
+pub struct SocketsHost;
+
+impl SocketsHost {
+    /// Open a connection to `host:port`, returning its handle.
+    ///
+    /// With `tls`, the connection is wrapped in TLS and the server
+    /// certificate is verified for `host`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// connection cannot be established in time.
+    pub async fn connect(
+        ctx: &mut WorkerContext,
+        host: &str,
+        port: u16,
+        tls: bool,
+    ) -> Result<i32, HostFunctionError> {
+        let connector = Self::begin(ctx, host, port)?;
+        let timeout = Self::remaining(ctx, connector.connect_timeout);
+
+        let stream = tokio::time::timeout(timeout, connector.connect(host, port, tls))
+            .await
+            .map_err(|_| socket_error("connection timed out"))??;
+
+        debug!(request_id = %ctx.request_id, host, port, tls, "Socket connected");
+        Ok(Self::insert(ctx, stream))
+    }
+
+    /// Send all of `data` on a connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown, the byte budget would be
+    /// exceeded, or the data cannot be sent in time.
+    pub async fn send(
+        ctx: &mut WorkerContext,
+        handle: i32,
+        data: &[u8],
+    ) -> Result<(), HostFunctionError> {
+        let max = Self::check_bytes(ctx)?;
+        if ctx.metrics.socket_bytes + data.len() as u64 > max {
+            return Err(budget_exceeded());
+        }
+
+        let timeout = Self::remaining(ctx, DEFAULT_IO_TIMEOUT);
+        let mut stream = Self::take(ctx, handle)?;
+        let result = tokio::time::timeout(timeout, stream.write_all(data)).await;
+        Self::context(ctx).streams.insert(handle, stream);
+
+        result
+            .map_err(|_| socket_error("send timed out"))?
+            .map_err(socket_error)?;
+        ctx.metrics.socket_bytes += data.len() as u64;
+        Ok(())
+    }
+
+    /// Receive up to `max` bytes from a connection.
+    ///
+    /// Waits until data arrives. Returns an empty chunk once the peer has
+    /// closed the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown, the byte budget is used
+    /// up, or nothing arrives in time.
+    pub async fn recv(
+        ctx: &mut WorkerContext,
+        handle: i32,
+        max: usize,
+    ) -> Result<Vec<u8>, HostFunctionError> {
+        let budget = Self::check_bytes(ctx)?;
+        let left = budget.saturating_sub(ctx.metrics.socket_bytes);
+        if left == 0 {
+            return Err(budget_exceeded());
+        }
+        let len = max
+            .min(MAX_SOCKET_READ_BYTES)
+            .min(usize::try_from(left).unwrap_or(usize::MAX));
+
+        let timeout = Self::remaining(ctx, DEFAULT_IO_TIMEOUT);
+        let mut stream = Self::take(ctx, handle)?;
+        let mut buf = vec![0; len];
+        let result = tokio::time::timeout(timeout, stream.read(&mut buf)).await;
+        Self::context(ctx).streams.insert(handle, stream);
+
+        let n = result
+            .map_err(|_| socket_error("receive timed out"))?
+            .map_err(socket_error)?;
+        buf.truncate(n);
+        ctx.metrics.socket_bytes += n as u64;
+        Ok(buf)
+    }
+
+    /// Close a connection.
+    ///
+    /// Returns `false` if the handle is unknown.
+    pub fn close(ctx: &mut WorkerContext, handle: i32) -> bool {
+        Self::context(ctx).streams.remove(&handle).is_some()
+    }
+
+    /// Numeric error code returned to core modules.
+    ///
+    /// `-1` is reserved for invalid guest memory.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    /// Check access and limits, and count the connection.
+    fn begin(
+        ctx: &mut WorkerContext,
+        host: &str,
+        port: u16,
+    ) -> Result<SocketConnector, HostFunctionError> {
+        let Some(permissions) = ctx.extensions().get::<Permissions>() else {
+            return Err(denied(host, port));
+        };
+        if !permissions.is_socket_allowed(host, port) {
+            warn!(request_id = %ctx.request_id, host, port, "Socket blocked: not in allowed addresses");
+            return Err(denied(host, port));
+        }
+        if Permissions::is_private_host(host) {
+            warn!(request_id = %ctx.request_id, host, port, "Socket blocked: private address");
+            return Err(denied(host, port));
+        }
+        let max_connections = permissions.max_socket_connections;
+
+        if ctx.metrics.socket_connections >= max_connections {
+            warn!(
+                request_id = %ctx.request_id,
+                max = max_connections,
+                "Socket connection limit exceeded"
+            );
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "socket connections".to_string(),
+            });
+        }
+        if Self::context(ctx).streams.len() >= MAX_OPEN_SOCKETS {
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "open sockets".to_string(),
+            });
+        }
+        ctx.metrics.socket_connections += 1;
+
+        Ok(ctx
+            .extensions()
+            .get::<SocketConnector>()
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Get the request's byte budget, failing if it is used up.
+    fn check_bytes(ctx: &WorkerContext) -> Result<u64, HostFunctionError> {
+        let max = ctx
+            .extensions()
+            .get::<Permissions>()
+            .map_or(0, |p| p.max_socket_bytes);
+        if ctx.metrics.socket_bytes >= max {
+            warn!(request_id = %ctx.request_id, max, "Socket byte budget exceeded");
+            return Err(budget_exceeded());
+        }
+        Ok(max)
+    }
+
+    /// Bound `timeout` by the remaining execution time.
+    fn remaining(ctx: &WorkerContext, timeout: Duration) -> Duration {
+        ctx.deadline().map_or(timeout, |deadline| {
+            timeout.min(deadline.saturating_duration_since(Instant::now()))
+        })
+    }
+
+    /// Add a connection to the request state, returning its handle.
+    fn insert(ctx: &mut WorkerContext, stream: SocketStream) -> i32 {
+        let sockets = Self::context(ctx);
+        let handle = sockets.next_handle;
+        sockets.next_handle += 1;
+        sockets.streams.insert(handle, stream);
+        handle
+    }
+
+    /// Remove a connection from the request state while it is in use.
+    fn take(ctx: &mut WorkerContext, handle: i32) -> Result<SocketStream, HostFunctionError> {
+        Self::context(ctx).streams.remove(&handle).ok_or_else(|| {
+            HostFunctionError::InvalidArgument {
+                reason: format!("unknown socket handle {handle}"),
+            }
+        })
+    }
+
+    /// Get the request's socket state, creating it on first use.
+    fn context(ctx: &mut WorkerContext) -> &mut SocketContext {
+        if ctx.extensions().get::<SocketContext>().is_none() {
+            ctx.extensions_mut().insert(SocketContext::default());
+        }
+        ctx.extensions_mut()
+            .get_mut::<SocketContext>()
+            .expect("socket context was just inserted")
+    }
+}
+
+/// Error for addresses the execution may not connect to.
+fn denied(host: &str, port: u16) -> HostFunctionError {
+    HostFunctionError::PermissionDenied {
+        resource: format!("socket '{host}:{port}'"),
+    }
+}
+
+fn budget_exceeded() -> HostFunctionError {
+    HostFunctionError::RateLimitExceeded {
+        operation: "socket bytes".to_string(),
+    }
+}
+
+/// Wrap a connection failure.
+fn socket_error(error: impl std::fmt::Display) -> HostFunctionError {
+    HostFunctionError::Socket(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn ctx(permissions: Permissions) -> WorkerContext {
+        let mut ctx = WorkerContext::new("test".into());
+        ctx.extensions_mut().insert(permissions);
+        ctx
+    }
+
+    /// Connect to a local echo server, bypassing the private address check.
+    async fn echo(ctx: &mut WorkerContext) -> i32 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let (mut reader, mut writer) = socket.split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        SocketsHost::insert(ctx, SocketStream::Plain(stream))
+    }
+
+    #[tokio::test]
+    async fn test_connect_denied() {
+        let mut ctx = ctx(Permissions::builder()
+            .allow_sockets(["db.example.com:5432"], 4, 1024)
+            .build());
+
+        let err = SocketsHost::connect(&mut ctx, "db.example.com", 5433, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+        assert_eq!(ctx.metrics.socket_connections, 0);
+    }
+
+    #[tokio::test]
+    async fn test_connect_private_address_blocked() {
+        let mut ctx = ctx(Permissions::builder().allow_sockets(["*"], 4, 1024).build());
+
+        for host in ["127.0.0.1", "localhost", "10.0.0.1", "169.254.169.254"] {
+            let err = SocketsHost::connect(&mut ctx, host, 6379, false)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit() {
+        let mut ctx = ctx(Permissions::builder().allow_sockets(["*"], 0, 1024).build());
+
+        let err = SocketsHost::connect(&mut ctx, "db.example.com", 5432, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_send_recv_within_budget() {
+        let mut ctx = ctx(Permissions::builder().allow_sockets(["*"], 4, 10).build());
+        let handle = echo(&mut ctx).await;
+
+        SocketsHost::send(&mut ctx, handle, b"PING").await.unwrap();
+        let reply = SocketsHost::recv(&mut ctx, handle, 64).await.unwrap();
+        assert_eq!(reply, b"PING");
+        assert_eq!(ctx.metrics.socket_bytes, 8);
+
+        // Only two bytes of the budget are left.
+        let err = SocketsHost::send(&mut ctx, handle, b"PING")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+
+        assert!(SocketsHost::close(&mut ctx, handle));
+        assert!(!SocketsHost::close(&mut ctx, handle));
+        let err = SocketsHost::recv(&mut ctx, handle, 64).await.unwrap_err();
+        assert!(matches!(err, HostFunctionError::InvalidArgument { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_recv_bounded_by_deadline() {
+        let mut ctx = ctx(Permissions::builder().allow_sockets(["*"], 4, 1024).build());
+        ctx.set_deadline(Duration::from_millis(50));
+        let handle = echo(&mut ctx).await;
+
+        let err = SocketsHost::recv(&mut ctx, handle, 64).await.unwrap_err();
+        assert!(matches!(err, HostFunctionError::Socket(_)));
+        // The connection stays open after a timeout.
+        assert!(SocketsHost::close(&mut ctx, handle));
+    }
+}
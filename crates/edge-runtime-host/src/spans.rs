@@ -0,0 +1,290 @@
+//! Guest tracing span host function implementation.
+//!
+//! This module provides the host-side implementation of the spans
+//! interface, which lets guest code mark internal phases of a request (for
+//! example "parse", "authorize", "render") as `tracing` spans.
+//!
+//! Guest spans are children of the innermost guest span still open, or of
+//! the [`SpanParent`] attached by the runtime: the span of the invocation
+//! inside the `handle_function` span. A request's trace thereby shows the
+//! guest's phases alongside instantiation and outbound fetches.
+//!
+//! Spans are recorded as `guest_span` with the guest-provided name in the
+//! `otel.name` field and the attributes as a JSON object in the
+//! `attributes` field. Spans still open at the end of the execution are
+//! closed when the store is dropped.
+
+use edge_runtime_common::HostFunctionError;
+use edge_runtime_core::store::WorkerContext;
+use tracing::{Span, info, info_span};
+
+use crate::logging::fields_json;
+
+/// Maximum number of spans open at the same time.
+pub const MAX_OPEN_SPANS: usize = 64;
+
+/// Maximum number of spans opened per request.
+pub const MAX_SPANS_PER_REQUEST: u32 = 1024;
+
+/// Maximum number of attributes on a span or event.
+const MAX_ATTRIBUTES: usize = 32;
+
+/// Maximum length of a span or event name in bytes.
+const MAX_NAME_BYTES: usize = 128;
+
+/// Parent span of the spans opened by guest code.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime. Without
+/// it, guest spans are children of the span current at the host call.
+#[derive(Debug, Clone)]
+pub struct SpanParent(pub Span);
+
+/// Per-request span state, stored in the [`WorkerContext`] extensions.
+#[derive(Debug, Default)]
+struct SpansContext {
+    /// Open spans by ID, in the order they were opened.
+    open: Vec<(u32, Span)>,
+    /// Number of spans opened so far; also the last ID handed out.
+    opened: u32,
+}
+
+/// Spans host functions.
+pub struct SpansHost;
+
+impl SpansHost {
+    /// Open a span and return its ID.
+    ///
+    /// IDs start at `1`; `0` refers to the parent of all guest spans in
+    /// [`event`](Self::event).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostFunctionError::InvalidArgument`] for an invalid name
+    /// or too many attributes, and [`HostFunctionError::RateLimitExceeded`]
+    /// if too many spans are open or were opened by this request.
+    pub fn enter(
+        ctx: &mut WorkerContext,
+        name: &str,
+        attributes: &[(String, String)],
+    ) -> Result<u32, HostFunctionError> {
+        validate(name, attributes)?;
+
+        let parent = Self::parent(ctx);
+        let request_id = ctx.request_id.clone();
+        let spans = Self::context(ctx);
+        if spans.open.len() >= MAX_OPEN_SPANS {
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: format!("open spans (limit {MAX_OPEN_SPANS})"),
+            });
+        }
+        if spans.opened >= MAX_SPANS_PER_REQUEST {
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: format!("spans (limit {MAX_SPANS_PER_REQUEST} per request)"),
+            });
+        }
+
+        let parent = spans.open.last().map_or(parent, |(_, span)| span.clone());
+        let span = info_span!(
+            parent: &parent,
+            "guest_span",
+            otel.name = %name,
+            request_id = %request_id,
+            attributes = %fields_json(attributes),
+        );
+
+        spans.opened += 1;
+        spans.open.push((spans.opened, span));
+        Ok(spans.opened)
+    }
+
+    /// Close a span.
+    ///
+    /// Returns `false` if no span with this ID is open. Spans opened inside
+    /// it stay open.
+    pub fn exit(ctx: &mut WorkerContext, id: u32) -> bool {
+        let spans = Self::context(ctx);
+        match spans.open.iter().position(|(open, _)| *open == id) {
+            Some(index) => {
+                spans.open.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Record an event in a span.
+    ///
+    /// `id` `0` records the event in the parent of all guest spans.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HostFunctionError::InvalidArgument`] for an unknown span
+    /// ID, an invalid name or too many attributes.
+    pub fn event(
+        ctx: &mut WorkerContext,
+        id: u32,
+        name: &str,
+        attributes: &[(String, String)],
+    ) -> Result<(), HostFunctionError> {
+        validate(name, attributes)?;
+
+        let span = if id == 0 {
+            Self::parent(ctx)
+        } else {
+            Self::context(ctx)
+                .open
+                .iter()
+                .find(|(open, _)| *open == id)
+                .map(|(_, span)| span.clone())
+                .ok_or_else(|| invalid(format!("unknown span {id}")))?
+        };
+
+        info!(
+            parent: &span,
+            request_id = %ctx.request_id,
+            guest_event = true,
+            attributes = %fields_json(attributes),
+            "{name}"
+        );
+        Ok(())
+    }
+
+    /// Map an error to the negative status code returned to core modules.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    /// The parent of top-level guest spans.
+    fn parent(ctx: &WorkerContext) -> Span {
+        ctx.extensions()
+            .get::<SpanParent>()
+            .map_or_else(Span::current, |parent| parent.0.clone())
+    }
+
+    fn context(ctx: &mut WorkerContext) -> &mut SpansContext {
+        if ctx.extensions().get::<SpansContext>().is_none() {
+            ctx.extensions_mut().insert(SpansContext::default());
+        }
+        ctx.extensions_mut()
+            .get_mut::<SpansContext>()
+            .expect("spans context was just inserted")
+    }
+}
+
+fn validate(name: &str, attributes: &[(String, String)]) -> Result<(), HostFunctionError> {
+    if name.is_empty() || name.len() > MAX_NAME_BYTES {
+        return Err(invalid(format!("name must be 1 to {MAX_NAME_BYTES} bytes")));
+    }
+    if attributes.len() > MAX_ATTRIBUTES {
+        return Err(invalid(format!(
+            "too many attributes ({}, limit {MAX_ATTRIBUTES})",
+            attributes.len()
+        )));
+    }
+    Ok(())
+}
+
+fn invalid(reason: String) -> HostFunctionError {
+    HostFunctionError::InvalidArgument { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::Subscriber;
+    use tracing::span::{Attributes, Id};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+
+    use super::*;
+
+    /// Records the name of each new span with the name of its parent.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<(String, Option<String>)>>>);
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let span = ctx.span(id).unwrap();
+            let parent = span.parent().map(|parent| parent.name().to_string());
+            self.0
+                .lock()
+                .unwrap()
+                .push((attrs.metadata().name().to_string(), parent));
+        }
+    }
+
+    fn attributes(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_spans_are_nested_under_parent() {
+        let recorder = Recorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let mut ctx = WorkerContext::new("test".into());
+            ctx.extensions_mut()
+                .insert(SpanParent(info_span!("handle_function")));
+
+            let outer = SpansHost::enter(&mut ctx, "parse", &[]).unwrap();
+            let inner =
+                SpansHost::enter(&mut ctx, "validate", &attributes(&[("rows", "3")])).unwrap();
+            assert_eq!((outer, inner), (1, 2));
+
+            assert!(SpansHost::exit(&mut ctx, inner));
+            assert!(SpansHost::exit(&mut ctx, outer));
+            assert!(!SpansHost::exit(&mut ctx, outer));
+            SpansHost::enter(&mut ctx, "render", &[]).unwrap();
+        });
+
+        let spans = recorder.0.lock().unwrap();
+        let parents: Vec<_> = spans.iter().map(|(_, parent)| parent.as_deref()).collect();
+        assert_eq!(
+            parents,
+            [
+                None,
+                Some("handle_function"),
+                Some("guest_span"),
+                Some("handle_function"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event() {
+        let mut ctx = WorkerContext::new("test".into());
+        let id = SpansHost::enter(&mut ctx, "fetch", &[]).unwrap();
+
+        SpansHost::event(&mut ctx, id, "retry", &attributes(&[("attempt", "2")])).unwrap();
+        SpansHost::event(&mut ctx, 0, "checkpoint", &[]).unwrap();
+        let err = SpansHost::event(&mut ctx, 42, "lost", &[]).unwrap_err();
+        assert!(matches!(err, HostFunctionError::InvalidArgument { .. }));
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut ctx = WorkerContext::new("test".into());
+        assert!(SpansHost::enter(&mut ctx, "", &[]).is_err());
+
+        for _ in 0..MAX_OPEN_SPANS {
+            SpansHost::enter(&mut ctx, "span", &[]).unwrap();
+        }
+        let err = SpansHost::enter(&mut ctx, "span", &[]).unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+
+        for id in 1..=u32::try_from(MAX_OPEN_SPANS).unwrap() {
+            assert!(SpansHost::exit(&mut ctx, id));
+        }
+        SpansHost::enter(&mut ctx, "span", &[]).unwrap();
+    }
+}
@@ -0,0 +1,905 @@
+//! SQL database host function implementation.
+//!
+//! This module provides the host-side implementation of the SQL interface,
+//! which gives guest code an embedded `SQLite` database.
+//!
+//! Each SQL namespace is a separate database: a `<namespace>.sqlite` file
+//! in the configured directory, or an in-memory database if no directory
+//! is set. Access is controlled per module through [`Permissions`]: a
+//! module can only use the database of its `sql_namespace`, may be limited
+//! to read-only statements, and each execution is limited in the number of
+//! statements it runs.
+//!
+//! Statements run on a blocking thread and are interrupted once the
+//! execution's deadline passes, so a slow query cannot outlive the request
+//! timeout. Guest statements run under an authorizer that rejects
+//! `ATTACH`, `DETACH` and any `PRAGMA` other than a few read-only schema
+//! queries, so guests cannot lift the size limit or change how the
+//! database file is written.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use edge_runtime_common::{HostFunctionError, RuntimeError, SqlConfig};
+use edge_runtime_core::store::WorkerContext;
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::limits::Limit;
+use rusqlite::types::{ToSqlOutput, ValueRef};
+use rusqlite::{Connection, DatabaseName, ErrorCode, OpenFlags, ToSql};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::Permissions;
+
+/// Maximum length of a namespace, which is also a file name.
+const MAX_NAMESPACE_BYTES: usize = 64;
+
+/// Number of `SQLite` virtual machine instructions between deadline checks.
+const DEADLINE_CHECK_INTERVAL: i32 = 1000;
+
+/// Pragmas guests may run to inspect their schema.
+///
+/// Their argument names a table or index, so they never change settings.
+const READ_ONLY_PRAGMAS: &[&str] = &[
+    "foreign_key_list",
+    "index_info",
+    "index_list",
+    "index_xinfo",
+    "table_info",
+    "table_list",
+    "table_xinfo",
+];
+
+/// A value bound to a statement parameter or read from a result column.
+///
+/// In JSON, values are `null`, numbers, strings, or byte arrays for blobs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SqlValue {
+    /// SQL `NULL`.
+    Null,
+    /// 64-bit signed integer.
+    Integer(i64),
+    /// 64-bit floating point number.
+    Real(f64),
+    /// UTF-8 text.
+    Text(String),
+    /// Binary data.
+    Blob(Vec<u8>),
+}
+
+impl ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            Self::Null => ValueRef::Null,
+            Self::Integer(value) => ValueRef::Integer(*value),
+            Self::Real(value) => ValueRef::Real(*value),
+            Self::Text(value) => ValueRef::Text(value.as_bytes()),
+            Self::Blob(value) => ValueRef::Blob(value),
+        }))
+    }
+}
+
+impl From<ValueRef<'_>> for SqlValue {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => Self::Null,
+            ValueRef::Integer(value) => Self::Integer(value),
+            ValueRef::Real(value) => Self::Real(value),
+            ValueRef::Text(value) => Self::Text(String::from_utf8_lossy(value).into_owned()),
+            ValueRef::Blob(value) => Self::Blob(value.to_vec()),
+        }
+    }
+}
+
+/// Result of a query.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SqlRows {
+    /// Column names, in result order.
+    pub columns: Vec<String>,
+    /// Rows, each with one value per column.
+    pub rows: Vec<Vec<SqlValue>>,
+}
+
+/// Shared handle to the SQL databases.
+///
+/// Attached to the [`WorkerContext`] extensions by the runtime. Databases
+/// are opened on first use and kept open; statements on the same database
+/// run one at a time.
+#[derive(Clone)]
+pub struct SqlStore {
+    inner: Arc<SqlStoreInner>,
+}
+
+struct SqlStoreInner {
+    directory: Option<PathBuf>,
+    max_database_bytes: u64,
+    max_rows: usize,
+    databases: Mutex<HashMap<String, Arc<Mutex<Connection>>>>,
+    temp_files: AtomicU64,
+}
+
+impl SqlStore {
+    /// Open the SQL store described by `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database directory cannot be created.
+    pub fn open(config: &SqlConfig) -> Result<Self, RuntimeError> {
+        let directory = config.directory.as_ref().map(PathBuf::from);
+        if let Some(directory) = &directory {
+            std::fs::create_dir_all(directory).map_err(|e| {
+                RuntimeError::invalid_config(format!(
+                    "Failed to create SQL directory '{}': {e}",
+                    directory.display()
+                ))
+            })?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(SqlStoreInner {
+                directory,
+                max_database_bytes: config.max_database_bytes,
+                max_rows: config.max_rows,
+                databases: Mutex::new(HashMap::new()),
+                temp_files: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// Create a store with in-memory databases and default limits.
+    pub fn memory() -> Self {
+        Self::open(&SqlConfig::default()).expect("in-memory SQL store needs no directory")
+    }
+
+    /// Get the maximum size of each database in bytes.
+    pub fn max_database_bytes(&self) -> u64 {
+        self.inner.max_database_bytes
+    }
+
+    /// Execute a statement that returns no rows.
+    ///
+    /// Returns the number of rows changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statement is invalid, modifies the database
+    /// while `read_only` is set, exceeds a limit, or passes `deadline`.
+    pub async fn execute(
+        &self,
+        namespace: &str,
+        sql: String,
+        params: Vec<SqlValue>,
+        read_only: bool,
+        deadline: Option<Instant>,
+    ) -> Result<u64, HostFunctionError> {
+        self.with_statement(namespace, sql, read_only, deadline, move |stmt| {
+            let changed = stmt.execute(rusqlite::params_from_iter(params.iter()))?;
+            Ok(u64::try_from(changed).unwrap_or(u64::MAX))
+        })
+        .await
+    }
+
+    /// Run a query and return its rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the statement is invalid, modifies the database
+    /// while `read_only` is set, returns more than the configured maximum
+    /// number of rows, or passes `deadline`.
+    pub async fn query(
+        &self,
+        namespace: &str,
+        sql: String,
+        params: Vec<SqlValue>,
+        read_only: bool,
+        deadline: Option<Instant>,
+    ) -> Result<SqlRows, HostFunctionError> {
+        let max_rows = self.inner.max_rows;
+        self.with_statement(namespace, sql, read_only, deadline, move |stmt| {
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let mut result = SqlRows {
+                columns,
+                rows: Vec::new(),
+            };
+            let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+            while let Some(row) = rows.next()? {
+                if result.rows.len() >= max_rows {
+                    return Err(StatementError::TooManyRows(max_rows));
+                }
+                let values = (0..result.columns.len())
+                    .map(|i| row.get_ref(i).map(SqlValue::from))
+                    .collect::<rusqlite::Result<_>>()?;
+                result.rows.push(values);
+            }
+            Ok(result)
+        })
+        .await
+    }
+
+    /// Export a database as a `SQLite` file image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the namespace is invalid or the backup fails.
+    pub async fn export(&self, namespace: &str) -> Result<Vec<u8>, HostFunctionError> {
+        let database = self.database(namespace)?;
+        let path = self.temp_path(namespace);
+        blocking(move || {
+            let conn = database
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let result = conn
+                .backup(DatabaseName::Main, &path, None)
+                .map_err(sql_error)
+                .and_then(|()| std::fs::read(&path).map_err(sql_error));
+            let _ = std::fs::remove_file(&path);
+            result
+        })
+        .await
+    }
+
+    /// Replace a database with a `SQLite` file image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the namespace is invalid, the image exceeds the
+    /// size limit or is not a valid database, or the restore fails.
+    pub async fn import(&self, namespace: &str, image: Vec<u8>) -> Result<(), HostFunctionError> {
+        if image.len() as u64 > self.inner.max_database_bytes {
+            return Err(HostFunctionError::InvalidArgument {
+                reason: format!(
+                    "SQL database exceeds {} bytes",
+                    self.inner.max_database_bytes
+                ),
+            });
+        }
+
+        let database = self.database(namespace)?;
+        let path = self.temp_path(namespace);
+        blocking(move || {
+            let result = std::fs::write(&path, image)
+                .map_err(sql_error)
+                .and_then(|()| check_image(&path))
+                .and_then(|()| {
+                    let mut conn = database
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner);
+                    conn.restore(
+                        DatabaseName::Main,
+                        &path,
+                        None::<fn(rusqlite::backup::Progress)>,
+                    )
+                    .map_err(sql_error)
+                });
+            let _ = std::fs::remove_file(&path);
+            result
+        })
+        .await
+    }
+
+    /// Prepare a single statement and run `f` on it on a blocking thread.
+    async fn with_statement<T, F>(
+        &self,
+        namespace: &str,
+        sql: String,
+        read_only: bool,
+        deadline: Option<Instant>,
+        f: F,
+    ) -> Result<T, HostFunctionError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut rusqlite::Statement<'_>) -> Result<T, StatementError> + Send + 'static,
+    {
+        let database = self.database(namespace)?;
+        blocking(move || {
+            let conn = database
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            if let Some(deadline) = deadline {
+                if Instant::now() >= deadline {
+                    return Err(timed_out());
+                }
+                conn.progress_handler(
+                    DEADLINE_CHECK_INTERVAL,
+                    Some(move || Instant::now() >= deadline),
+                );
+            }
+
+            let result = run_statement(&conn, &sql, read_only, f);
+            conn.progress_handler(0, None::<fn() -> bool>);
+            result
+        })
+        .await
+    }
+
+    /// Get the connection for `namespace`, opening it if needed.
+    fn database(&self, namespace: &str) -> Result<Arc<Mutex<Connection>>, HostFunctionError> {
+        validate_namespace(namespace)?;
+
+        let mut databases = self
+            .inner
+            .databases
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(database) = databases.get(namespace) {
+            return Ok(database.clone());
+        }
+
+        let conn = match &self.inner.directory {
+            Some(directory) => Connection::open(directory.join(format!("{namespace}.sqlite"))),
+            None => Connection::open_in_memory(),
+        }
+        .map_err(sql_error)?;
+        configure(&conn, self.inner.max_database_bytes).map_err(sql_error)?;
+        debug!(namespace, "Opened SQL database");
+
+        let database = Arc::new(Mutex::new(conn));
+        databases.insert(namespace.to_string(), database.clone());
+        Ok(database)
+    }
+
+    /// Path of a scratch file used for imports and exports.
+    fn temp_path(&self, namespace: &str) -> PathBuf {
+        let n = self.inner.temp_files.fetch_add(1, Ordering::Relaxed);
+        let name = format!(".{namespace}.{}.{n}.tmp", std::process::id());
+        match &self.inner.directory {
+            Some(directory) => directory.join(name),
+            None => std::env::temp_dir().join(name),
+        }
+    }
+}
+
+impl Default for SqlStore {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
+
+impl std::fmt::Debug for SqlStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqlStore")
+            .field("directory", &self.inner.directory)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host implementation for the SQL interface.
+///
+/// Each statement checks the caller's namespace and quota before reaching
+/// the database, and counts towards [`ExecutionMetrics::sql_statements`].
+///
+/// [`ExecutionMetrics::sql_statements`]: edge_runtime_core::ExecutionMetrics::sql_statements
+pub struct SqlHost;
+
+impl SqlHost {
+    /// Execute a statement, returning the number of rows changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// statement fails.
+    pub async fn execute(
+        ctx: &mut WorkerContext,
+        sql: String,
+        params: Vec<SqlValue>,
+    ) -> Result<u64, HostFunctionError> {
+        let access = Self::begin(ctx, &sql)?;
+        access
+            .store
+            .execute(
+                &access.namespace,
+                sql,
+                params,
+                access.read_only,
+                ctx.deadline(),
+            )
+            .await
+    }
+
+    /// Run a query and return its rows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a quota is exceeded, or the
+    /// query fails.
+    pub async fn query(
+        ctx: &mut WorkerContext,
+        sql: String,
+        params: Vec<SqlValue>,
+    ) -> Result<SqlRows, HostFunctionError> {
+        let access = Self::begin(ctx, &sql)?;
+        access
+            .store
+            .query(
+                &access.namespace,
+                sql,
+                params,
+                access.read_only,
+                ctx.deadline(),
+            )
+            .await
+    }
+
+    /// Numeric error code returned to core modules.
+    ///
+    /// `-1` is reserved for invalid guest memory.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    /// Check access and quota, and count the statement.
+    fn begin(ctx: &mut WorkerContext, sql: &str) -> Result<SqlAccess, HostFunctionError> {
+        let Some(permissions) = ctx.extensions().get::<Permissions>() else {
+            return Err(denied());
+        };
+        let Some(namespace) = permissions.sql_namespace() else {
+            warn!(request_id = %ctx.request_id, "SQL access denied");
+            return Err(denied());
+        };
+        let namespace = namespace.to_string();
+        let read_only = permissions.sql_read_only;
+        let max_statements = permissions.max_sql_statements;
+
+        let Some(store) = ctx.extensions().get::<SqlStore>().cloned() else {
+            return Err(HostFunctionError::Sql(
+                "SQL store is not configured".to_string(),
+            ));
+        };
+
+        if ctx.metrics.sql_statements >= max_statements {
+            warn!(
+                request_id = %ctx.request_id,
+                max = max_statements,
+                "SQL statement limit exceeded"
+            );
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "sql".to_string(),
+            });
+        }
+        ctx.metrics.sql_statements += 1;
+
+        debug!(
+            request_id = %ctx.request_id,
+            namespace = %namespace,
+            sql,
+            "SQL statement"
+        );
+
+        Ok(SqlAccess {
+            store,
+            namespace,
+            read_only,
+        })
+    }
+}
+
+/// Database access granted to an execution.
+struct SqlAccess {
+    store: SqlStore,
+    namespace: String,
+    read_only: bool,
+}
+
+/// Failure while running a prepared statement.
+enum StatementError {
+    Sqlite(rusqlite::Error),
+    TooManyRows(usize),
+}
+
+impl From<rusqlite::Error> for StatementError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Sqlite(error)
+    }
+}
+
+/// Apply the limits every connection runs with.
+fn configure(conn: &Connection, max_database_bytes: u64) -> rusqlite::Result<()> {
+    // No ATTACH, which also rules out VACUUM INTO: guests may only touch
+    // their own database file.
+    conn.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 0);
+
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let max_pages = (max_database_bytes / page_size.max(1)).max(1);
+    conn.query_row(&format!("PRAGMA max_page_count = {max_pages}"), [], |_| {
+        Ok(())
+    })?;
+
+    // Installed last: the pragmas above are the host's, not the guest's.
+    conn.authorizer(Some(authorize));
+    Ok(())
+}
+
+/// Authorize an action of a guest statement.
+fn authorize(ctx: AuthContext<'_>) -> Authorization {
+    match ctx.action {
+        AuthAction::Pragma { pragma_name, .. }
+            if READ_ONLY_PRAGMAS
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(pragma_name)) =>
+        {
+            Authorization::Allow
+        }
+        AuthAction::Pragma { .. } | AuthAction::Attach { .. } | AuthAction::Detach { .. } => {
+            Authorization::Deny
+        }
+        _ => Authorization::Allow,
+    }
+}
+
+/// Prepare `sql`, enforce read-only mode and run `f`.
+fn run_statement<T>(
+    conn: &Connection,
+    sql: &str,
+    read_only: bool,
+    f: impl FnOnce(&mut rusqlite::Statement<'_>) -> Result<T, StatementError>,
+) -> Result<T, HostFunctionError> {
+    let mut stmt = conn.prepare(sql).map_err(statement_error)?;
+    if read_only && !stmt.readonly() {
+        return Err(HostFunctionError::PermissionDenied {
+            resource: "sql write".to_string(),
+        });
+    }
+
+    f(&mut stmt).map_err(|e| match e {
+        StatementError::Sqlite(e) => statement_error(e),
+        StatementError::TooManyRows(max) => HostFunctionError::InvalidArgument {
+            reason: format!("SQL query returned more than {max} rows"),
+        },
+    })
+}
+
+/// Check that an uploaded file is a readable `SQLite` database.
+fn check_image(path: &std::path::Path) -> Result<(), HostFunctionError> {
+    let conn =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(sql_error)?;
+    let status: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(sql_error)?;
+    if status == "ok" {
+        Ok(())
+    } else {
+        Err(HostFunctionError::InvalidArgument {
+            reason: format!("SQL database failed integrity check: {status}"),
+        })
+    }
+}
+
+/// Validate a namespace, which is used as a file name.
+fn validate_namespace(namespace: &str) -> Result<(), HostFunctionError> {
+    let valid = !namespace.is_empty()
+        && namespace.len() <= MAX_NAMESPACE_BYTES
+        && namespace
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(HostFunctionError::InvalidArgument {
+            reason: format!("invalid SQL namespace '{namespace}'"),
+        })
+    }
+}
+
+/// Map a statement failure, reporting deadline interrupts as timeouts.
+fn statement_error(error: rusqlite::Error) -> HostFunctionError {
+    match error.sqlite_error_code() {
+        Some(ErrorCode::OperationInterrupted) => timed_out(),
+        _ if matches!(error, rusqlite::Error::MultipleStatement) => {
+            HostFunctionError::InvalidArgument {
+                reason: "only one SQL statement may be run at a time".to_string(),
+            }
+        }
+        _ => sql_error(error),
+    }
+}
+
+/// Error for statements interrupted by the execution deadline.
+fn timed_out() -> HostFunctionError {
+    HostFunctionError::Sql("statement exceeded the execution deadline".to_string())
+}
+
+/// Error for executions without SQL access.
+fn denied() -> HostFunctionError {
+    HostFunctionError::PermissionDenied {
+        resource: "sql".to_string(),
+    }
+}
+
+/// Wrap a database failure.
+fn sql_error(error: impl std::fmt::Display) -> HostFunctionError {
+    HostFunctionError::Sql(error.to_string())
+}
+
+/// Run a database operation on the blocking thread pool.
+async fn blocking<T, F>(f: F) -> Result<T, HostFunctionError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, HostFunctionError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(sql_error)?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn ctx(permissions: Permissions, store: &SqlStore) -> WorkerContext {
+        let mut ctx = WorkerContext::new("test".to_string());
+        ctx.extensions_mut().insert(permissions);
+        ctx.extensions_mut().insert(store.clone());
+        ctx
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_query() {
+        let store = SqlStore::memory();
+        let mut ctx = ctx(
+            Permissions::builder().sql_namespace("app", 10).build(),
+            &store,
+        );
+
+        SqlHost::execute(
+            &mut ctx,
+            "CREATE TABLE items (id INTEGER, name TEXT, price REAL, data BLOB)".into(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        let changed = SqlHost::execute(
+            &mut ctx,
+            "INSERT INTO items VALUES (?1, ?2, ?3, ?4), (2, NULL, 0.5, NULL)".into(),
+            vec![
+                SqlValue::Integer(1),
+                SqlValue::Text("apple".into()),
+                SqlValue::Real(1.5),
+                SqlValue::Blob(vec![1, 2]),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(changed, 2);
+
+        let rows = SqlHost::query(
+            &mut ctx,
+            "SELECT id, name, price, data FROM items ORDER BY id".into(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        assert_eq!(rows.columns, ["id", "name", "price", "data"]);
+        assert_eq!(
+            rows.rows,
+            vec![
+                vec![
+                    SqlValue::Integer(1),
+                    SqlValue::Text("apple".into()),
+                    SqlValue::Real(1.5),
+                    SqlValue::Blob(vec![1, 2]),
+                ],
+                vec![
+                    SqlValue::Integer(2),
+                    SqlValue::Null,
+                    SqlValue::Real(0.5),
+                    SqlValue::Null,
+                ],
+            ]
+        );
+        assert_eq!(ctx.metrics.sql_statements, 3);
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_are_isolated() {
+        let store = SqlStore::memory();
+        let mut a = ctx(
+            Permissions::builder().sql_namespace("a", 10).build(),
+            &store,
+        );
+        let mut b = ctx(
+            Permissions::builder().sql_namespace("b", 10).build(),
+            &store,
+        );
+
+        SqlHost::execute(&mut a, "CREATE TABLE t (x)".into(), vec![])
+            .await
+            .unwrap();
+        let err = SqlHost::query(&mut b, "SELECT * FROM t".into(), vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::Sql(_)));
+    }
+
+    #[tokio::test]
+    async fn test_permissions_and_quota() {
+        let store = SqlStore::memory();
+
+        let mut none = ctx(Permissions::none(), &store);
+        let err = SqlHost::query(&mut none, "SELECT 1".into(), vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+
+        let mut read_only = ctx(
+            Permissions::builder()
+                .sql_namespace("app", 2)
+                .sql_read_only()
+                .build(),
+            &store,
+        );
+        let err = SqlHost::execute(&mut read_only, "CREATE TABLE t (x)".into(), vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+        SqlHost::query(&mut read_only, "SELECT 1".into(), vec![])
+            .await
+            .unwrap();
+
+        let err = SqlHost::query(&mut read_only, "SELECT 1".into(), vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let store = SqlStore::open(&SqlConfig {
+            max_rows: 2,
+            max_database_bytes: 64 * 1024,
+            ..SqlConfig::default()
+        })
+        .unwrap();
+        let mut ctx = ctx(
+            Permissions::builder().sql_namespace("app", 10).build(),
+            &store,
+        );
+
+        let err = SqlHost::query(
+            &mut ctx,
+            "SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3".into(),
+            vec![],
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, HostFunctionError::InvalidArgument { .. }));
+
+        let err = SqlHost::execute(&mut ctx, "ATTACH 'other.db' AS other".into(), vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::Sql(_)));
+
+        let err = SqlHost::execute(
+            &mut ctx,
+            "CREATE TABLE big AS WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10000) SELECT randomblob(100) FROM n".into(),
+            vec![],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("full"));
+    }
+
+    #[tokio::test]
+    async fn test_pragmas_are_restricted() {
+        let store = SqlStore::open(&SqlConfig {
+            max_database_bytes: 64 * 1024,
+            ..SqlConfig::default()
+        })
+        .unwrap();
+        let mut ctx = ctx(
+            Permissions::builder().sql_namespace("app", 10).build(),
+            &store,
+        );
+
+        for sql in [
+            "PRAGMA max_page_count = 2147483647",
+            "PRAGMA journal_mode = OFF",
+            "PRAGMA writable_schema = ON",
+            "PRAGMA page_size",
+            "DETACH main",
+        ] {
+            let err = SqlHost::execute(&mut ctx, sql.into(), vec![])
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("not authorized"), "{sql}: {err}");
+        }
+
+        SqlHost::execute(&mut ctx, "CREATE TABLE t (x)".into(), vec![])
+            .await
+            .unwrap();
+        let rows = SqlHost::query(&mut ctx, "PRAGMA table_info(t)".into(), vec![])
+            .await
+            .unwrap();
+        assert_eq!(rows.rows.len(), 1);
+
+        // The size limit still applies.
+        let err = SqlHost::execute(
+            &mut ctx,
+            "INSERT INTO t WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10000) SELECT randomblob(100) FROM n".into(),
+            vec![],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("full"));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_interrupts_statement() {
+        let store = SqlStore::memory();
+        let mut ctx = ctx(
+            Permissions::builder().sql_namespace("app", 10).build(),
+            &store,
+        );
+        ctx.set_deadline(Duration::from_millis(50));
+
+        let started = Instant::now();
+        let err = SqlHost::query(
+            &mut ctx,
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT count(*) FROM n".into(),
+            vec![],
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("deadline"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqlStore::open(&SqlConfig {
+            directory: Some(dir.path().to_string_lossy().into_owned()),
+            ..SqlConfig::default()
+        })
+        .unwrap();
+        let mut ctx = ctx(
+            Permissions::builder().sql_namespace("app", 10).build(),
+            &store,
+        );
+        SqlHost::execute(&mut ctx, "CREATE TABLE t (x)".into(), vec![])
+            .await
+            .unwrap();
+        SqlHost::execute(&mut ctx, "INSERT INTO t VALUES (42)".into(), vec![])
+            .await
+            .unwrap();
+        assert!(dir.path().join("app.sqlite").exists());
+
+        let image = store.export("app").await.unwrap();
+        store.import("copy", image).await.unwrap();
+        let rows = store
+            .query("copy", "SELECT x FROM t".into(), vec![], true, None)
+            .await
+            .unwrap();
+        assert_eq!(rows.rows, vec![vec![SqlValue::Integer(42)]]);
+
+        let err = store
+            .import("copy", b"not a database".to_vec())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::Sql(_)));
+        assert!(store.export("../escape").await.is_err());
+    }
+
+    #[test]
+    fn test_value_json() {
+        let values: Vec<SqlValue> = serde_json::from_str(r#"[null, 1, 1.5, "a", [1, 2]]"#).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                SqlValue::Null,
+                SqlValue::Integer(1),
+                SqlValue::Real(1.5),
+                SqlValue::Text("a".into()),
+                SqlValue::Blob(vec![1, 2]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Timer host function implementation.
+//!
+//! This module provides the host-side implementation of the timer
+//! interface, which lets guest code wait, for example for backoff between
+//! retries.
+//!
+//! Sleeping yields the executing task instead of spinning in WebAssembly,
+//! so it consumes no fuel. The wall-clock time still counts against the
+//! request timeout: a sleep never extends past the execution deadline, and
+//! its duration is reported separately as
+//! [`ExecutionMetrics::sleep_duration`](edge_runtime_core::store::ExecutionMetrics::sleep_duration).
+
+use std::time::{Duration, Instant};
+
+use edge_runtime_core::store::WorkerContext;
+use tracing::debug;
+
+/// Timer host functions.
+pub struct TimerHost;
+
+impl TimerHost {
+    /// Sleep for `requested`, bounded by the remaining execution time.
+    ///
+    /// Returns the time actually slept. If the deadline is reached, the
+    /// guest is interrupted by the runtime as soon as it resumes.
+    pub async fn sleep(ctx: &mut WorkerContext, requested: Duration) -> Duration {
+        let duration = ctx.deadline().map_or(requested, |deadline| {
+            requested.min(deadline.saturating_duration_since(Instant::now()))
+        });
+
+        debug!(
+            request_id = %ctx.request_id,
+            requested_ms = requested.as_millis(),
+            duration_ms = duration.as_millis(),
+            "Sleep"
+        );
+
+        let start = Instant::now();
+        tokio::time::sleep(duration).await;
+        let slept = start.elapsed();
+
+        ctx.metrics.sleep_duration += slept;
+        slept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sleep_records_duration() {
+        let mut ctx = WorkerContext::new("test".into());
+
+        let slept = TimerHost::sleep(&mut ctx, Duration::from_millis(20)).await;
+        assert!(slept >= Duration::from_millis(20));
+        assert_eq!(ctx.metrics.sleep_duration, slept);
+    }
+
+    #[tokio::test]
+    async fn test_sleep_bounded_by_deadline() {
+        let mut ctx = WorkerContext::new("test".into());
+        ctx.set_deadline(Duration::from_millis(30));
+
+        let slept = TimerHost::sleep(&mut ctx, Duration::from_secs(10)).await;
+        assert!(slept < Duration::from_secs(1));
+    }
+}
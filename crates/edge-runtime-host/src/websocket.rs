@@ -0,0 +1,904 @@
+//! WebSocket client host function implementation.
+//!
+//! This module provides the host-side implementation of the WebSocket
+//! interface, which lets guest code consume streaming upstream APIs.
+//!
+//! Connections are opened to `ws://` and `wss://` URLs on the module's
+//! allowed HTTP hosts ([`Permissions::is_http_allowed`]) and are subject to
+//! the same SSRF protection as outbound HTTP; resolved addresses are
+//! checked as well. The client speaks RFC 6455: it answers pings, reassembles
+//! fragmented messages and limits each message to
+//! [`MAX_WEBSOCKET_MESSAGE_BYTES`].
+//!
+//! Each request may open at most
+//! [`Permissions::max_websocket_connections`] connections and exchange at
+//! most [`Permissions::max_websocket_messages`] messages. Connections are
+//! per-request handles; those still open when the request ends are closed
+//! with a close frame in the background.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use edge_runtime_common::HostFunctionError;
+use edge_runtime_core::store::WorkerContext;
+use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use tracing::{debug, warn};
+
+use crate::Permissions;
+use crate::sockets::{SocketConnector, SocketStream};
+
+/// Maximum number of connections a request may have open.
+pub const MAX_OPEN_WEBSOCKETS: usize = 4;
+
+/// Maximum size of a single message in bytes.
+pub const MAX_WEBSOCKET_MESSAGE_BYTES: usize = 1024 * 1024;
+
+/// Maximum size of the handshake response headers.
+const MAX_HANDSHAKE_BYTES: usize = 16 * 1024;
+
+/// Timeout of sends and receives without an explicit or execution deadline.
+const DEFAULT_IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long closing a connection may take once the request has ended.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// GUID appended to the key to compute `Sec-WebSocket-Accept`.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Request headers set by the client itself.
+const RESERVED_HEADERS: [&str; 5] = [
+    "host",
+    "upgrade",
+    "connection",
+    "sec-websocket-key",
+    "sec-websocket-version",
+];
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// A WebSocket data message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebSocketMessage {
+    /// UTF-8 text message.
+    Text(String),
+    /// Binary message.
+    Binary(Vec<u8>),
+}
+
+impl WebSocketMessage {
+    /// Get the size of the payload in bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Binary(data) => data.len(),
+        }
+    }
+
+    /// Check if the payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Where a WebSocket URL points to.
+struct Target {
+    host: String,
+    port: u16,
+    tls: bool,
+    /// Value of the `Host` header.
+    authority: String,
+    /// Path and query of the handshake request.
+    resource: String,
+}
+
+impl Target {
+    fn parse(url: &str) -> Result<Self, HostFunctionError> {
+        let parsed = url::Url::parse(url).map_err(|e| invalid(format!("invalid URL: {e}")))?;
+        let tls = match parsed.scheme() {
+            "ws" => false,
+            "wss" => true,
+            _ => return Err(invalid("URL scheme must be ws or wss")),
+        };
+        let Some(host) = parsed.host_str() else {
+            return Err(invalid("URL has no host"));
+        };
+        let port = parsed
+            .port_or_known_default()
+            .unwrap_or(if tls { 443 } else { 80 });
+
+        Ok(Self {
+            host: host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port,
+            tls,
+            authority: match parsed.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            },
+            resource: parsed[url::Position::BeforePath..url::Position::AfterQuery].to_string(),
+        })
+    }
+}
+
+/// An open WebSocket connection.
+struct WebSocket {
+    stream: SocketStream,
+    /// Received bytes not yet parsed into frames.
+    buf: Vec<u8>,
+    /// Opcode and payload of a fragmented message being received.
+    partial: Option<(u8, Vec<u8>)>,
+    /// Message returned to the connection because it did not fit the
+    /// guest's buffer.
+    pending: Option<WebSocketMessage>,
+    /// Whether a close frame was sent or received.
+    closed: bool,
+}
+
+impl WebSocket {
+    /// Perform the opening handshake on a connected stream.
+    async fn handshake(
+        stream: SocketStream,
+        target: &Target,
+        headers: &[(String, String)],
+    ) -> Result<Self, HostFunctionError> {
+        let mut key = [0; 16];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| ws_error("random number generation failed"))?;
+        let key = BASE64.encode(key);
+
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\
+             User-Agent: edge-runtime/{}\r\n",
+            target.resource,
+            target.authority,
+            env!("CARGO_PKG_VERSION"),
+        );
+        for (name, value) in headers {
+            let _ = write!(request, "{name}: {value}\r\n");
+        }
+        request.push_str("\r\n");
+
+        let mut ws = Self {
+            stream,
+            buf: Vec::new(),
+            partial: None,
+            pending: None,
+            closed: false,
+        };
+        ws.stream
+            .write_all(request.as_bytes())
+            .await
+            .map_err(ws_error)?;
+
+        let end = loop {
+            if let Some(pos) = ws.buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if ws.buf.len() > MAX_HANDSHAKE_BYTES {
+                return Err(ws_error("handshake response too large"));
+            }
+            if !ws.fill().await? {
+                return Err(ws_error("connection closed during handshake"));
+            }
+        };
+
+        {
+            let mut header_buf = [httparse::EMPTY_HEADER; 64];
+            let mut response = httparse::Response::new(&mut header_buf);
+            response.parse(&ws.buf[..end]).map_err(ws_error)?;
+            if response.code != Some(101) {
+                return Err(ws_error(format!(
+                    "handshake failed with status {}",
+                    response.code.unwrap_or_default()
+                )));
+            }
+
+            let expected = accept_key(&key);
+            let accept = response
+                .headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case("sec-websocket-accept"))
+                .map(|h| h.value);
+            if accept != Some(expected.as_bytes()) {
+                return Err(ws_error("invalid Sec-WebSocket-Accept"));
+            }
+        }
+        ws.buf.drain(..end);
+
+        Ok(ws)
+    }
+
+    /// Send a data message.
+    async fn send(&mut self, message: &WebSocketMessage) -> Result<(), HostFunctionError> {
+        if self.closed {
+            return Err(ws_error("connection is closed"));
+        }
+        match message {
+            WebSocketMessage::Text(text) => self.send_frame(OP_TEXT, text.as_bytes()).await,
+            WebSocketMessage::Binary(data) => self.send_frame(OP_BINARY, data).await,
+        }
+    }
+
+    /// Receive the next data message, or `None` once the connection is
+    /// closed.
+    ///
+    /// Pings are answered while waiting. Cancelling the future loses no
+    /// data: bytes stay buffered until a whole frame has arrived.
+    async fn recv(&mut self) -> Result<Option<WebSocketMessage>, HostFunctionError> {
+        if let Some(message) = self.pending.take() {
+            return Ok(Some(message));
+        }
+
+        while !self.closed {
+            let Some((fin, opcode, payload)) = self.parse_frame()? else {
+                if !self.fill().await? {
+                    self.closed = true;
+                    break;
+                }
+                continue;
+            };
+
+            match opcode {
+                OP_PING => self.send_frame(OP_PONG, &payload).await?,
+                OP_PONG => {}
+                OP_CLOSE => {
+                    self.closed = true;
+                    let _ = self
+                        .send_frame(OP_CLOSE, &payload[..payload.len().min(2)])
+                        .await;
+                }
+                OP_TEXT | OP_BINARY if self.partial.is_none() => {
+                    self.partial = Some((opcode, payload));
+                }
+                OP_CONTINUATION if self.partial.is_some() => {
+                    if let Some((_, data)) = &mut self.partial {
+                        if data.len() + payload.len() > MAX_WEBSOCKET_MESSAGE_BYTES {
+                            return Err(ws_error("message too large"));
+                        }
+                        data.extend_from_slice(&payload);
+                    }
+                }
+                _ => return Err(ws_error(format!("unexpected frame opcode {opcode}"))),
+            }
+
+            if fin && opcode <= OP_BINARY {
+                if let Some((opcode, data)) = self.partial.take() {
+                    return message(opcode, data).map(Some);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Send a close frame with status 1000 (normal closure).
+    async fn close(&mut self) {
+        if !self.closed {
+            self.closed = true;
+            let _ = self.send_frame(OP_CLOSE, &1000_u16.to_be_bytes()).await;
+        }
+    }
+
+    /// Send a single masked frame.
+    async fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), HostFunctionError> {
+        let mut mask = [0; 4];
+        SystemRandom::new()
+            .fill(&mut mask)
+            .map_err(|_| ws_error("random number generation failed"))?;
+
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        if let Ok(len @ 0..=125) = u8::try_from(payload.len()) {
+            frame.push(0x80 | len);
+        } else if let Ok(len) = u16::try_from(payload.len()) {
+            // Masked, 16-bit extended length.
+            frame.push(0xFE);
+            frame.extend_from_slice(&len.to_be_bytes());
+        } else {
+            // Masked, 64-bit extended length.
+            frame.push(0xFF);
+            frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+
+        self.stream.write_all(&frame).await.map_err(ws_error)
+    }
+
+    /// Take the next complete frame from the buffer.
+    ///
+    /// Returns `(fin, opcode, payload)`, or `None` if more bytes are needed.
+    fn parse_frame(&mut self) -> Result<Option<(bool, u8, Vec<u8>)>, HostFunctionError> {
+        let buf = &self.buf;
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        if buf[0] & 0x70 != 0 {
+            return Err(ws_error("reserved frame bits set"));
+        }
+        if buf[1] & 0x80 != 0 {
+            return Err(ws_error("server frames must not be masked"));
+        }
+
+        let (len, header) = match buf[1] & 0x7F {
+            126 if buf.len() >= 4 => (u64::from(u16::from_be_bytes([buf[2], buf[3]])), 4),
+            127 if buf.len() >= 10 => {
+                let mut len = [0; 8];
+                len.copy_from_slice(&buf[2..10]);
+                (u64::from_be_bytes(len), 10)
+            }
+            126 | 127 => return Ok(None),
+            len => (u64::from(len), 2),
+        };
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= MAX_WEBSOCKET_MESSAGE_BYTES)
+            .ok_or_else(|| ws_error("message too large"))?;
+        if buf.len() < header + len {
+            return Ok(None);
+        }
+
+        let fin = buf[0] & 0x80 != 0;
+        let opcode = buf[0] & 0x0F;
+        let payload = buf[header..header + len].to_vec();
+        self.buf.drain(..header + len);
+        Ok(Some((fin, opcode, payload)))
+    }
+
+    /// Read more bytes into the buffer, returning `false` at end of stream.
+    async fn fill(&mut self) -> Result<bool, HostFunctionError> {
+        let mut chunk = [0; 8192];
+        let n = self.stream.read(&mut chunk).await.map_err(ws_error)?;
+        self.buf.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+}
+
+/// Connections opened by a request.
+///
+/// Stored in the [`WorkerContext`] extensions on first use. Dropping it
+/// closes the remaining connections in the background.
+#[derive(Default)]
+struct WebSocketContext {
+    connections: HashMap<i32, WebSocket>,
+    next_handle: i32,
+}
+
+impl Drop for WebSocketContext {
+    fn drop(&mut self) {
+        close_in_background(self.connections.drain().map(|(_, ws)| ws).collect());
+    }
+}
+
+impl std::fmt::Debug for WebSocketContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketContext")
+            .field("connections", &self.connections.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Host implementation for the WebSocket interface.
+///
+/// Opening a connection counts towards
+/// [`ExecutionMetrics::websocket_connections`]; messages sent and received
+/// count towards [`ExecutionMetrics::websocket_messages`].
+///
+/// [`ExecutionMetrics::websocket_connections`]: edge_runtime_core::ExecutionMetrics::websocket_connections
+/// [`ExecutionMetrics::websocket_messages`]: edge_runtime_core::ExecutionMetrics::websocket_messages
+pub struct WebSocketHost;
+
+impl WebSocketHost {
+    /// Open a connection to a `ws://` or `wss://` URL, returning its handle.
+    ///
+    /// `headers` are added to the handshake request, for example for
+    /// authentication or `Sec-WebSocket-Protocol`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if access is denied, a limit is exceeded, or the
+    /// handshake does not complete in time.
+    pub async fn connect(
+        ctx: &mut WorkerContext,
+        url: &str,
+        headers: &[(String, String)],
+    ) -> Result<i32, HostFunctionError> {
+        let target = Target::parse(url)?;
+        validate_headers(headers)?;
+        let connector = Self::begin(ctx, url)?;
+        let timeout = remaining(ctx, connector.connect_timeout());
+
+        let ws = tokio::time::timeout(timeout, async {
+            let stream = connector
+                .connect(&target.host, target.port, target.tls)
+                .await?;
+            WebSocket::handshake(stream, &target, headers).await
+        })
+        .await
+        .map_err(|_| ws_error("connection timed out"))??;
+
+        debug!(request_id = %ctx.request_id, url, "WebSocket connected");
+        let sockets = Self::context(ctx);
+        let handle = sockets.next_handle;
+        sockets.next_handle += 1;
+        sockets.connections.insert(handle, ws);
+        Ok(handle)
+    }
+
+    /// Send a message on a connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown, the message limit is
+    /// reached, the message is too large, or it cannot be sent in time.
+    pub async fn send(
+        ctx: &mut WorkerContext,
+        handle: i32,
+        message: &WebSocketMessage,
+    ) -> Result<(), HostFunctionError> {
+        if message.len() > MAX_WEBSOCKET_MESSAGE_BYTES {
+            return Err(invalid(format!(
+                "message exceeds {MAX_WEBSOCKET_MESSAGE_BYTES} bytes"
+            )));
+        }
+        Self::count_message(ctx)?;
+
+        let timeout = remaining(ctx, DEFAULT_IO_TIMEOUT);
+        let mut ws = Self::take(ctx, handle)?;
+        let result = tokio::time::timeout(timeout, ws.send(message)).await;
+        Self::context(ctx).connections.insert(handle, ws);
+
+        result.map_err(|_| ws_error("send timed out"))?
+    }
+
+    /// Receive the next message from a connection.
+    ///
+    /// Waits up to `timeout`, bounded by the remaining execution time.
+    /// Returns `None` once the connection is closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is unknown, the message limit is
+    /// reached, the peer violates the protocol, or nothing arrives in time.
+    pub async fn recv(
+        ctx: &mut WorkerContext,
+        handle: i32,
+        timeout: Option<Duration>,
+    ) -> Result<Option<WebSocketMessage>, HostFunctionError> {
+        let mut ws = Self::take(ctx, handle)?;
+        if ws.pending.is_none() {
+            if let Err(e) = Self::check_messages(ctx) {
+                Self::context(ctx).connections.insert(handle, ws);
+                return Err(e);
+            }
+        }
+        let counted = ws.pending.is_none();
+
+        let timeout = remaining(ctx, timeout.unwrap_or(DEFAULT_IO_TIMEOUT));
+        let result = tokio::time::timeout(timeout, ws.recv()).await;
+        Self::context(ctx).connections.insert(handle, ws);
+
+        let message = result.map_err(|_| ws_error("receive timed out"))??;
+        if counted && message.is_some() {
+            ctx.metrics.websocket_messages += 1;
+        }
+        Ok(message)
+    }
+
+    /// Return a received message to a connection, so the next `recv`
+    /// yields it again without counting it twice.
+    pub(crate) fn unread(ctx: &mut WorkerContext, handle: i32, message: WebSocketMessage) {
+        if let Some(ws) = Self::context(ctx).connections.get_mut(&handle) {
+            ws.pending = Some(message);
+        }
+    }
+
+    /// Close a connection.
+    ///
+    /// The close frame is sent in the background. Returns `false` if the
+    /// handle is unknown.
+    pub fn close(ctx: &mut WorkerContext, handle: i32) -> bool {
+        match Self::context(ctx).connections.remove(&handle) {
+            Some(ws) => {
+                close_in_background(vec![ws]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Numeric error code returned to core modules.
+    ///
+    /// `-1` is reserved for invalid guest memory.
+    pub fn error_code(error: &HostFunctionError) -> i32 {
+        match error {
+            HostFunctionError::PermissionDenied { .. } => -2,
+            HostFunctionError::RateLimitExceeded { .. } => -3,
+            HostFunctionError::InvalidArgument { .. } => -4,
+            _ => -5,
+        }
+    }
+
+    /// Check access and limits, and count the connection.
+    fn begin(ctx: &mut WorkerContext, url: &str) -> Result<SocketConnector, HostFunctionError> {
+        let Some(permissions) = ctx.extensions().get::<Permissions>() else {
+            return Err(denied(url));
+        };
+        if !permissions.is_http_allowed(url) {
+            warn!(request_id = %ctx.request_id, url, "WebSocket blocked: not in allowed hosts");
+            return Err(denied(url));
+        }
+        if Permissions::is_private_address(url) {
+            warn!(request_id = %ctx.request_id, url, "WebSocket blocked: private address");
+            return Err(denied(url));
+        }
+        let max_connections = permissions.max_websocket_connections;
+
+        if ctx.metrics.websocket_connections >= max_connections {
+            warn!(
+                request_id = %ctx.request_id,
+                max = max_connections,
+                "WebSocket connection limit exceeded"
+            );
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "websocket connections".to_string(),
+            });
+        }
+        if Self::context(ctx).connections.len() >= MAX_OPEN_WEBSOCKETS {
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "open websockets".to_string(),
+            });
+        }
+        ctx.metrics.websocket_connections += 1;
+
+        Ok(ctx
+            .extensions()
+            .get::<SocketConnector>()
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Check that another message may be exchanged.
+    fn check_messages(ctx: &WorkerContext) -> Result<(), HostFunctionError> {
+        let max = ctx
+            .extensions()
+            .get::<Permissions>()
+            .map_or(0, |p| p.max_websocket_messages);
+        if ctx.metrics.websocket_messages >= max {
+            warn!(request_id = %ctx.request_id, max, "WebSocket message limit exceeded");
+            return Err(HostFunctionError::RateLimitExceeded {
+                operation: "websocket messages".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the message limit and count a message.
+    fn count_message(ctx: &mut WorkerContext) -> Result<(), HostFunctionError> {
+        Self::check_messages(ctx)?;
+        ctx.metrics.websocket_messages += 1;
+        Ok(())
+    }
+
+    /// Remove a connection from the request state while it is in use.
+    fn take(ctx: &mut WorkerContext, handle: i32) -> Result<WebSocket, HostFunctionError> {
+        Self::context(ctx)
+            .connections
+            .remove(&handle)
+            .ok_or_else(|| invalid(format!("unknown websocket handle {handle}")))
+    }
+
+    /// Get the request's WebSocket state, creating it on first use.
+    fn context(ctx: &mut WorkerContext) -> &mut WebSocketContext {
+        if ctx.extensions().get::<WebSocketContext>().is_none() {
+            ctx.extensions_mut().insert(WebSocketContext::default());
+        }
+        ctx.extensions_mut()
+            .get_mut::<WebSocketContext>()
+            .expect("websocket context was just inserted")
+    }
+}
+
+/// Send close frames on a background task.
+///
+/// Connections are simply dropped outside a Tokio runtime.
+fn close_in_background(connections: Vec<WebSocket>) {
+    if connections.is_empty() {
+        return;
+    }
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        runtime.spawn(async move {
+            for mut ws in connections {
+                let _ = tokio::time::timeout(CLOSE_TIMEOUT, ws.close()).await;
+            }
+        });
+    }
+}
+
+/// Build a data message from a reassembled payload.
+fn message(opcode: u8, data: Vec<u8>) -> Result<WebSocketMessage, HostFunctionError> {
+    if opcode == OP_TEXT {
+        String::from_utf8(data)
+            .map(WebSocketMessage::Text)
+            .map_err(|_| ws_error("text message is not valid UTF-8"))
+    } else {
+        Ok(WebSocketMessage::Binary(data))
+    }
+}
+
+/// Compute the `Sec-WebSocket-Accept` value for a key.
+fn accept_key(key: &str) -> String {
+    let mut context = digest::Context::new(&digest::SHA1_FOR_LEGACY_USE_ONLY);
+    context.update(key.as_bytes());
+    context.update(ACCEPT_GUID.as_bytes());
+    BASE64.encode(context.finish())
+}
+
+/// Reject handshake headers the client sets itself or that could inject
+/// additional lines.
+fn validate_headers(headers: &[(String, String)]) -> Result<(), HostFunctionError> {
+    for (name, value) in headers {
+        if name.is_empty() || name.contains([':', '\r', '\n']) || value.contains(['\r', '\n']) {
+            return Err(invalid(format!("invalid header '{name}'")));
+        }
+        if RESERVED_HEADERS
+            .iter()
+            .any(|reserved| name.eq_ignore_ascii_case(reserved))
+        {
+            return Err(invalid(format!("header '{name}' is set by the runtime")));
+        }
+    }
+    Ok(())
+}
+
+/// Bound `timeout` by the remaining execution time.
+fn remaining(ctx: &WorkerContext, timeout: Duration) -> Duration {
+    ctx.deadline().map_or(timeout, |deadline| {
+        timeout.min(deadline.saturating_duration_since(Instant::now()))
+    })
+}
+
+/// Error for URLs the execution may not connect to.
+fn denied(url: &str) -> HostFunctionError {
+    HostFunctionError::PermissionDenied {
+        resource: format!("websocket '{url}'"),
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> HostFunctionError {
+    HostFunctionError::InvalidArgument {
+        reason: reason.into(),
+    }
+}
+
+/// Wrap a connection or protocol failure.
+fn ws_error(error: impl std::fmt::Display) -> HostFunctionError {
+    HostFunctionError::WebSocket(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn ctx(permissions: Permissions) -> WorkerContext {
+        let mut ctx = WorkerContext::new("test".into());
+        ctx.extensions_mut().insert(permissions);
+        ctx
+    }
+
+    fn permissions(max_messages: u32) -> Permissions {
+        Permissions::builder()
+            .allow_http_hosts(["stream.example.com"])
+            .websocket_limits(1, max_messages)
+            .build()
+    }
+
+    /// Write an unmasked server frame.
+    async fn write_frame(socket: &mut TcpStream, opcode: u8, fin: bool, payload: &[u8]) {
+        let first = if fin { 0x80 | opcode } else { opcode };
+        let len = u8::try_from(payload.len()).unwrap();
+        socket.write_all(&[first, len]).await.unwrap();
+        socket.write_all(payload).await.unwrap();
+    }
+
+    /// Read a masked client frame, returning its opcode and payload.
+    async fn read_frame(socket: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut head = [0; 2];
+        socket.read_exact(&mut head).await.unwrap();
+        assert_ne!(head[1] & 0x80, 0, "client frames must be masked");
+        let len = usize::from(head[1] & 0x7F);
+        let mut mask = [0; 4];
+        socket.read_exact(&mut mask).await.unwrap();
+        let mut payload = vec![0; len];
+        socket.read_exact(&mut payload).await.unwrap();
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+        (head[0] & 0x0F, payload)
+    }
+
+    /// Open a connection to a local server, bypassing the private address
+    /// check, and insert it into the request state.
+    ///
+    /// The server pings, sends a fragmented text message, echoes the next
+    /// message it receives, then closes.
+    async fn connect_local(ctx: &mut WorkerContext) -> i32 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut byte = [0; 1];
+                socket.read_exact(&mut byte).await.unwrap();
+                request.push(byte[0]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("GET /feed?x=1 HTTP/1.1\r\n"));
+            assert!(request.contains("Authorization: Bearer t\r\n"));
+            let key = request
+                .lines()
+                .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+                .unwrap();
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept_key(key)
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+
+            write_frame(&mut socket, OP_PING, true, b"hi").await;
+            write_frame(&mut socket, OP_TEXT, false, b"hel").await;
+            write_frame(&mut socket, OP_CONTINUATION, true, b"lo").await;
+            assert_eq!(read_frame(&mut socket).await, (OP_PONG, b"hi".to_vec()));
+
+            let (opcode, payload) = read_frame(&mut socket).await;
+            write_frame(&mut socket, opcode, true, &payload).await;
+            write_frame(&mut socket, OP_CLOSE, true, &1000_u16.to_be_bytes()).await;
+            assert_eq!(read_frame(&mut socket).await.0, OP_CLOSE);
+        });
+
+        let target = Target::parse("ws://stream.example.com/feed?x=1").unwrap();
+        let stream = SocketStream::Plain(TcpStream::connect(addr).await.unwrap());
+        let headers = [("Authorization".to_string(), "Bearer t".to_string())];
+        let ws = WebSocket::handshake(stream, &target, &headers)
+            .await
+            .unwrap();
+
+        let sockets = WebSocketHost::context(ctx);
+        sockets.connections.insert(0, ws);
+        0
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_target_parse() {
+        let target = Target::parse("wss://stream.example.com:8443/v1?symbols=a,b").unwrap();
+        assert!(target.tls);
+        assert_eq!(target.port, 8443);
+        assert_eq!(target.authority, "stream.example.com:8443");
+        assert_eq!(target.resource, "/v1?symbols=a,b");
+
+        assert_eq!(Target::parse("ws://example.com").unwrap().port, 80);
+        assert!(Target::parse("https://example.com/").is_err());
+    }
+
+    #[test]
+    fn test_validate_headers() {
+        let ok = [("Sec-WebSocket-Protocol".to_string(), "v1".to_string())];
+        assert!(validate_headers(&ok).is_ok());
+
+        let reserved = [("Host".to_string(), "evil.com".to_string())];
+        assert!(validate_headers(&reserved).is_err());
+        let injected = [("X-A".to_string(), "1\r\nHost: evil.com".to_string())];
+        assert!(validate_headers(&injected).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_denied() {
+        let mut ctx = ctx(permissions(10));
+
+        let err = WebSocketHost::connect(&mut ctx, "wss://evil.com/", &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+
+        let mut ctx = self::ctx(
+            Permissions::builder()
+                .allow_http_hosts(["*"])
+                .websocket_limits(1, 10)
+                .build(),
+        );
+        let err = WebSocketHost::connect(&mut ctx, "ws://127.0.0.1:9000/", &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::PermissionDenied { .. }));
+        assert_eq!(ctx.metrics.websocket_connections, 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit() {
+        let mut ctx = ctx(Permissions::builder()
+            .allow_http_hosts(["stream.example.com"])
+            .websocket_limits(0, 10)
+            .build());
+
+        let err = WebSocketHost::connect(&mut ctx, "wss://stream.example.com/", &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_exchange_messages() {
+        let mut ctx = ctx(permissions(10));
+        let handle = connect_local(&mut ctx).await;
+
+        let message = WebSocketHost::recv(&mut ctx, handle, None).await.unwrap();
+        assert_eq!(message, Some(WebSocketMessage::Text("hello".to_string())));
+
+        let echo = WebSocketMessage::Binary(vec![1, 2, 3]);
+        WebSocketHost::send(&mut ctx, handle, &echo).await.unwrap();
+        let reply = WebSocketHost::recv(&mut ctx, handle, None).await.unwrap();
+        assert_eq!(reply, Some(echo.clone()));
+
+        // A returned message is received again without counting twice.
+        WebSocketHost::unread(&mut ctx, handle, echo.clone());
+        let again = WebSocketHost::recv(&mut ctx, handle, None).await.unwrap();
+        assert_eq!(again, Some(echo));
+        assert_eq!(ctx.metrics.websocket_messages, 3);
+
+        // The server closes the connection.
+        let closed = WebSocketHost::recv(&mut ctx, handle, None).await.unwrap();
+        assert_eq!(closed, None);
+        assert!(WebSocketHost::close(&mut ctx, handle));
+    }
+
+    #[tokio::test]
+    async fn test_message_limit() {
+        let mut ctx = ctx(permissions(1));
+        let handle = connect_local(&mut ctx).await;
+
+        WebSocketHost::recv(&mut ctx, handle, None).await.unwrap();
+        let err = WebSocketHost::recv(&mut ctx, handle, Some(Duration::from_millis(10)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::RateLimitExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_recv_timeout() {
+        let mut ctx = ctx(permissions(10));
+        let handle = connect_local(&mut ctx).await;
+        WebSocketHost::recv(&mut ctx, handle, None).await.unwrap();
+
+        // The server waits for a message before sending anything else.
+        let err = WebSocketHost::recv(&mut ctx, handle, Some(Duration::from_millis(50)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HostFunctionError::WebSocket(_)));
+    }
+}
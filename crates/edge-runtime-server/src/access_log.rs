@@ -0,0 +1,622 @@
+//! Access log of function invocations.
+//!
+//! This module provides [`AccessLog`], which writes one line per function
+//! invocation to standard output or a rotating file, and the
+//! [`access_log`] middleware producing the lines. Each line records the
+//! request ID, module ID and content hash, client IP, method, path, status,
+//! bytes in and out, fuel consumed, duration and outcome.
+//!
+//! # Formats
+//!
+//! - `json`: one JSON object per line
+//! - `common`: Common Log Format, followed by the runtime's fields
+//! - `combined`: Combined Log Format, followed by the runtime's fields
+//!
+//! ```text
+//! 203.0.113.7 - - [01/Jan/2025:00:00:00 +0000] "GET /functions/api HTTP/1.1" 200 512 "-" "curl/8.5.0" request_id=6f1c... module=api content_hash=ab12... bytes_in=0 fuel=1200 duration_ms=3.120 outcome=success
+//! ```
+//!
+//! The handler fills in the invocation's details through the
+//! [`AccessRecord`] in the request extensions. For a pipeline, `module_id`
+//! is the pipeline ID and there is no content hash. A request dropped
+//! before its response, on client disconnect or request timeout, is logged
+//! with status 499 and outcome `cancelled`.
+//!
+//! Lines are queued for a writer task; like the log sinks, a full queue
+//! drops lines rather than delaying requests.
+
+use std::fmt::Write as _;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::HttpBody;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, SecondsFormat, Utc};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use edge_runtime_common::{AccessLogConfig, AccessLogFormat, RuntimeError};
+
+use crate::invocation::InvocationOutcome;
+use crate::log_sinks::RotatingFile;
+use crate::state::AppState;
+
+/// Status logged for requests dropped before their response.
+pub const CANCELLED_STATUS: u16 = 499;
+
+/// One access log line.
+#[derive(Debug, Clone)]
+pub struct AccessLogEntry {
+    /// Time the request was received.
+    pub timestamp: DateTime<Utc>,
+    /// Request ID of the invocation.
+    pub request_id: Option<String>,
+    /// Invoked module or pipeline.
+    pub module_id: Option<String>,
+    /// Content hash of the invoked module.
+    pub content_hash: Option<String>,
+    /// Address of the client.
+    pub client_ip: Option<IpAddr>,
+    /// HTTP method.
+    pub method: String,
+    /// Path and query.
+    pub path: String,
+    /// HTTP version, e.g. `HTTP/1.1`.
+    pub protocol: String,
+    /// Response status.
+    pub status: u16,
+    /// Request body size in bytes.
+    pub bytes_in: u64,
+    /// Response body size in bytes.
+    pub bytes_out: u64,
+    /// Fuel consumed by the invocation.
+    pub fuel_consumed: u64,
+    /// Time from receiving the request to producing the response.
+    pub duration: Duration,
+    /// Outcome of the invocation.
+    pub outcome: Option<&'static str>,
+    /// `Referer` header.
+    pub referer: Option<String>,
+    /// `User-Agent` header.
+    pub user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// An entry for a request, before its response.
+    pub fn from_request(request: &Request) -> Self {
+        let headers = request.headers();
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            timestamp: Utc::now(),
+            request_id: None,
+            module_id: None,
+            content_hash: None,
+            client_ip: request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip()),
+            method: request.method().to_string(),
+            path: request
+                .uri()
+                .path_and_query()
+                .map_or_else(|| request.uri().path().to_string(), ToString::to_string),
+            protocol: format!("{:?}", request.version()),
+            status: 0,
+            bytes_in: header(header::CONTENT_LENGTH)
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0),
+            bytes_out: 0,
+            fuel_consumed: 0,
+            duration: Duration::ZERO,
+            outcome: None,
+            referer: header(header::REFERER),
+            user_agent: header(header::USER_AGENT),
+        }
+    }
+
+    /// Format the entry as a line, without the trailing newline.
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Json => self.json(),
+            AccessLogFormat::Common => self.clf(false),
+            AccessLogFormat::Combined => self.clf(true),
+        }
+    }
+
+    fn json(&self) -> String {
+        serde_json::json!({
+            "timestamp": self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "request_id": self.request_id,
+            "module_id": self.module_id,
+            "content_hash": self.content_hash,
+            "client_ip": self.client_ip.map(|ip| ip.to_string()),
+            "method": self.method,
+            "path": self.path,
+            "protocol": self.protocol,
+            "status": self.status,
+            "bytes_in": self.bytes_in,
+            "bytes_out": self.bytes_out,
+            "fuel_consumed": self.fuel_consumed,
+            "duration_ms": self.duration.as_secs_f64() * 1000.0,
+            "outcome": self.outcome,
+            "referer": self.referer,
+            "user_agent": self.user_agent,
+        })
+        .to_string()
+    }
+
+    fn clf(&self, combined: bool) -> String {
+        let mut line = format!(
+            "{} - - [{}] \"{} {} {}\" {} ",
+            self.client_ip
+                .map_or_else(|| "-".to_string(), |ip| ip.to_string()),
+            self.timestamp.format("%d/%b/%Y:%H:%M:%S %z"),
+            escape(&self.method, true),
+            escape(&self.path, true),
+            escape(&self.protocol, true),
+            self.status,
+        );
+        if self.bytes_out == 0 {
+            line.push('-');
+        } else {
+            let _ = write!(line, "{}", self.bytes_out);
+        }
+        if combined {
+            let _ = write!(
+                line,
+                " \"{}\" \"{}\"",
+                escape(self.referer.as_deref().unwrap_or("-"), false),
+                escape(self.user_agent.as_deref().unwrap_or("-"), false),
+            );
+        }
+
+        let field =
+            |value: Option<&str>| value.map_or_else(|| "-".to_string(), |v| escape(v, true));
+        let _ = write!(
+            line,
+            " request_id={} module={} content_hash={} bytes_in={} fuel={} duration_ms={:.3} outcome={}",
+            field(self.request_id.as_deref()),
+            field(self.module_id.as_deref()),
+            field(self.content_hash.as_deref()),
+            self.bytes_in,
+            self.fuel_consumed,
+            self.duration.as_secs_f64() * 1000.0,
+            self.outcome.unwrap_or("-"),
+        );
+        line
+    }
+}
+
+/// Escape a value for a Common Log Format line: backslashes, quotes and
+/// non-printable characters, and spaces if `spaces` is set, as `\xNN`.
+fn escape(value: &str, spaces: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' ' if spaces => escaped.push_str("\\x20"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\x{:02x}", u32::from(c));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Details of an invocation known only to the handler.
+#[derive(Debug, Default)]
+struct InvocationDetails {
+    request_id: Option<String>,
+    module_id: Option<String>,
+    pipeline: bool,
+    bytes_in: Option<u64>,
+    fuel_consumed: u64,
+    outcome: Option<InvocationOutcome>,
+}
+
+/// Invocation details filled in by the handler for the access log.
+///
+/// Inserted in the request extensions by the [`access_log`] middleware.
+/// A record that is not attached to a request is simply discarded.
+#[derive(Debug, Clone, Default)]
+pub struct AccessRecord(Arc<Mutex<InvocationDetails>>);
+
+impl AccessRecord {
+    /// Set the request ID.
+    pub fn set_request_id(&self, request_id: &str) {
+        self.update(|details| details.request_id = Some(request_id.to_string()));
+    }
+
+    /// Set the size of the request body.
+    pub fn set_bytes_in(&self, bytes: usize) {
+        self.update(|details| details.bytes_in = Some(bytes as u64));
+    }
+
+    /// Set the invoked module.
+    pub fn set_module(&self, module_id: &str) {
+        self.update(|details| details.module_id = Some(module_id.to_string()));
+    }
+
+    /// Set the invoked pipeline.
+    pub fn set_pipeline(&self, pipeline_id: &str) {
+        self.update(|details| {
+            details.module_id = Some(pipeline_id.to_string());
+            details.pipeline = true;
+        });
+    }
+
+    /// Set the outcome and fuel consumed.
+    pub fn set_result(&self, outcome: InvocationOutcome, fuel_consumed: u64) {
+        self.update(|details| {
+            details.outcome = Some(outcome);
+            details.fuel_consumed = fuel_consumed;
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut InvocationDetails)) {
+        f(&mut self.0.lock().expect("access record lock poisoned"));
+    }
+}
+
+/// Destination of access log lines.
+enum Output {
+    Stdout(tokio::io::Stdout),
+    File(RotatingFile),
+}
+
+impl Output {
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.write_all(bytes).await,
+            Self::File(file) => file.write(bytes).await,
+        }
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush().await,
+            Self::File(file) => file.flush().await,
+        }
+    }
+}
+
+/// Queue of the running writer.
+struct Writer {
+    format: AccessLogFormat,
+    sender: mpsc::Sender<String>,
+    dropped: AtomicU64,
+}
+
+/// Access log writer.
+///
+/// Cloning is cheap; all clones share the same writer. A disabled log, the
+/// default, ignores entries.
+#[derive(Clone, Default)]
+pub struct AccessLog {
+    writer: Option<Arc<Writer>>,
+}
+
+impl AccessLog {
+    /// Start the access log configured in `config`, or return a disabled
+    /// log if it is not enabled.
+    ///
+    /// Must be called from within a Tokio runtime if enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the log file's directory cannot be created.
+    pub fn open(config: &AccessLogConfig) -> Result<Self, RuntimeError> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let output = match &config.path {
+            Some(path) => {
+                if let Some(directory) = Path::new(path).parent() {
+                    std::fs::create_dir_all(directory).map_err(|e| {
+                        RuntimeError::invalid_config(format!(
+                            "Failed to create access log directory {}: {e}",
+                            directory.display()
+                        ))
+                    })?;
+                }
+                Output::File(RotatingFile::new(
+                    PathBuf::from(path),
+                    config.max_file_bytes,
+                    config.max_files,
+                ))
+            }
+            None => Output::Stdout(tokio::io::stdout()),
+        };
+
+        let (sender, receiver) = mpsc::channel(config.buffer.max(1));
+        tokio::spawn(run_writer(output, receiver));
+        Ok(Self {
+            writer: Some(Arc::new(Writer {
+                format: config.format,
+                sender,
+                dropped: AtomicU64::new(0),
+            })),
+        })
+    }
+
+    /// Returns `true` if entries are written.
+    pub fn is_enabled(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Queue an entry. Never waits; the entry is dropped if the queue is
+    /// full.
+    pub fn log(&self, entry: &AccessLogEntry) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let mut line = entry.format(writer.format);
+        line.push('\n');
+        if writer.sender.try_send(line).is_err() {
+            let dropped = writer.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                warn!(dropped, "Access log is falling behind, dropping lines");
+            }
+        }
+    }
+
+    /// Number of lines dropped so far.
+    pub fn dropped(&self) -> u64 {
+        self.writer
+            .as_ref()
+            .map_or(0, |writer| writer.dropped.load(Ordering::Relaxed))
+    }
+}
+
+impl std::fmt::Debug for AccessLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessLog")
+            .field("format", &self.writer.as_ref().map(|writer| writer.format))
+            .finish()
+    }
+}
+
+/// Write lines until all senders are dropped, flushing whenever the queue
+/// is drained.
+async fn run_writer(mut output: Output, mut receiver: mpsc::Receiver<String>) {
+    while let Some(line) = receiver.recv().await {
+        let mut result = output.write(line.as_bytes()).await;
+        while let Ok(line) = receiver.try_recv() {
+            if result.is_err() {
+                break;
+            }
+            result = output.write(line.as_bytes()).await;
+        }
+        if let Err(e) = result.and(output.flush().await) {
+            warn!(error = %e, "Failed to write access log");
+        }
+    }
+}
+
+/// Entry of a request in flight; logged as cancelled if dropped before
+/// [`finish`](Self::finish).
+struct PendingEntry {
+    state: AppState,
+    record: AccessRecord,
+    entry: Option<AccessLogEntry>,
+    start: Instant,
+}
+
+impl PendingEntry {
+    fn finish(mut self, response: &Response) {
+        let bytes_out = response.body().size_hint().exact().unwrap_or(0);
+        self.write(response.status().as_u16(), bytes_out, None);
+    }
+
+    fn write(&mut self, status: u16, bytes_out: u64, outcome: Option<&'static str>) {
+        let Some(mut entry) = self.entry.take() else {
+            return;
+        };
+        let details =
+            std::mem::take(&mut *self.record.0.lock().expect("access record lock poisoned"));
+
+        entry.status = status;
+        entry.bytes_out = bytes_out;
+        entry.duration = self.start.elapsed();
+        entry.request_id = details.request_id;
+        if !details.pipeline {
+            entry.content_hash = details.module_id.as_deref().and_then(|id| {
+                self.state
+                    .get_module(id)
+                    .map(|module| module.content_hash().to_string())
+            });
+        }
+        entry.module_id = details.module_id;
+        entry.bytes_in = details.bytes_in.unwrap_or(entry.bytes_in);
+        entry.fuel_consumed = details.fuel_consumed;
+        entry.outcome = outcome.or(details.outcome.map(InvocationOutcome::as_str));
+        self.state.access_log().log(&entry);
+    }
+}
+
+impl Drop for PendingEntry {
+    fn drop(&mut self) {
+        self.write(CANCELLED_STATUS, 0, Some("cancelled"));
+    }
+}
+
+/// Middleware logging each request with the details recorded by the
+/// handler in its [`AccessRecord`].
+pub async fn access_log(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !state.access_log().is_enabled() {
+        return next.run(request).await;
+    }
+
+    let record = AccessRecord::default();
+    request.extensions_mut().insert(record.clone());
+    let pending = PendingEntry {
+        entry: Some(AccessLogEntry::from_request(&request)),
+        state,
+        record,
+        start: Instant::now(),
+    };
+
+    let response = next.run(request).await;
+    pending.finish(&response);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use tower::util::ServiceExt;
+
+    use edge_runtime_common::RuntimeConfig;
+
+    use super::*;
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            timestamp: DateTime::from_timestamp(1_735_689_600, 0).unwrap(),
+            request_id: Some("req-1".to_string()),
+            module_id: Some("api".to_string()),
+            content_hash: Some("ab12".to_string()),
+            client_ip: Some("203.0.113.7".parse().unwrap()),
+            method: "GET".to_string(),
+            path: "/functions/api?q=1".to_string(),
+            protocol: "HTTP/1.1".to_string(),
+            status: 200,
+            bytes_in: 0,
+            bytes_out: 512,
+            fuel_consumed: 1200,
+            duration: Duration::from_micros(3120),
+            outcome: Some("success"),
+            referer: None,
+            user_agent: Some("curl/8.5.0 \"test\"".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        let entry = entry();
+        assert_eq!(
+            entry.format(AccessLogFormat::Common),
+            "203.0.113.7 - - [01/Jan/2025:00:00:00 +0000] \"GET /functions/api?q=1 HTTP/1.1\" 200 512 \
+             request_id=req-1 module=api content_hash=ab12 bytes_in=0 fuel=1200 duration_ms=3.120 outcome=success"
+        );
+        assert_eq!(
+            entry.format(AccessLogFormat::Combined),
+            "203.0.113.7 - - [01/Jan/2025:00:00:00 +0000] \"GET /functions/api?q=1 HTTP/1.1\" 200 512 \
+             \"-\" \"curl/8.5.0 \\\"test\\\"\" \
+             request_id=req-1 module=api content_hash=ab12 bytes_in=0 fuel=1200 duration_ms=3.120 outcome=success"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&entry.format(AccessLogFormat::Json)).unwrap();
+        assert_eq!(json["timestamp"], "2025-01-01T00:00:00.000Z");
+        assert_eq!(json["client_ip"], "203.0.113.7");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["bytes_out"], 512);
+        assert_eq!(json["outcome"], "success");
+        assert!(json["referer"].is_null());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a b\"c\\\n", true), "a\\x20b\\\"c\\\\\\x0a");
+        assert_eq!(escape("a b", false), "a b");
+    }
+
+    #[tokio::test]
+    async fn test_logs_invocations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs/access.log");
+        let access_log = AccessLog::open(&AccessLogConfig {
+            enabled: true,
+            path: Some(path.display().to_string()),
+            ..AccessLogConfig::default()
+        })
+        .unwrap();
+
+        let state = AppState::new(&RuntimeConfig::default())
+            .unwrap()
+            .with_access_log(access_log);
+        let module = state
+            .load_module_wat("api", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        let app = crate::router::build_router(state, Duration::from_secs(30));
+
+        for uri in ["/functions/api", "/functions/missing"] {
+            app.clone()
+                .oneshot(
+                    axum::http::Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .header(header::USER_AGENT, "test")
+                        .extension(ConnectInfo(SocketAddr::from(([203, 0, 113, 7], 4000))))
+                        .body(Body::from("hello"))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents = std::fs::read_to_string(&path).unwrap_or_default();
+            if contents.lines().count() >= 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        let ok = &lines[0];
+        assert_eq!(ok["module_id"], "api");
+        assert_eq!(ok["content_hash"], module.content_hash());
+        assert_eq!(ok["client_ip"], "203.0.113.7");
+        assert_eq!(ok["method"], "POST");
+        assert_eq!(ok["status"], 200);
+        assert_eq!(ok["bytes_in"], 5);
+        assert!(ok["bytes_out"].as_u64().unwrap() > 0);
+        assert_eq!(ok["outcome"], "success");
+        assert_eq!(ok["user_agent"], "test");
+        assert!(ok["request_id"].is_string());
+
+        let missing = &lines[1];
+        assert_eq!(missing["status"], 404);
+        assert_eq!(missing["outcome"], "not_found");
+        assert!(missing["content_hash"].is_null());
+    }
+}
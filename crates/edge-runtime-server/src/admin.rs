@@ -13,20 +13,53 @@
 //! - `POST /admin/modules` - Upload a new module
 //! - `GET /admin/modules` - List all modules (detailed)
 //! - `GET /admin/modules/:id` - Get module info
+//! - `GET /admin/modules/:id/stats` - Get a module's invocation statistics
 //! - `DELETE /admin/modules/:id` - Delete a module
+//! - `GET /admin/schedules` - List all module schedules
+//! - `GET /admin/modules/:id/schedule` - Get a module's schedule and last run
+//! - `PUT /admin/modules/:id/schedule` - Set a module's schedule
+//! - `DELETE /admin/modules/:id/schedule` - Remove a module's schedule
+//! - `GET /admin/modules/:id/sql` - Download a module's SQL database
+//! - `PUT /admin/modules/:id/sql` - Replace a module's SQL database
+//! - `GET /admin/modules/:id/config` - Get a module's config values and secret names
+//! - `PUT /admin/modules/:id/config` - Replace a module's config values
+//! - `GET /admin/modules/:id/logging` - Get a module's log level and sample rate
+//! - `PUT /admin/modules/:id/logging` - Change a module's log level or sample rate
+//! - `GET /admin/modules/:id/logs/tail` - Stream a module's logs and request summaries
+//! - `POST /admin/modules/:id/profile` - Profile a module's next invocations
+//! - `GET /admin/modules/:id/profile` - Get the progress of a module's profile
+//! - `GET /admin/modules/:id/profile/download` - Download a module's profile
+//! - `DELETE /admin/modules/:id/profile` - Stop and discard a module's profile
+//! - `POST /admin/secrets/reload` - Reload the encrypted secrets file
+//! - `GET /admin/blobs` - List blob storage buckets
+//! - `GET /admin/blobs/:bucket` - List a bucket's objects
+//! - `GET /admin/blobs/:bucket/*key` - Download an object
+//! - `GET /admin/pipelines` - List all pipelines
+//! - `GET /admin/pipelines/:id` - Get a pipeline
+//! - `PUT /admin/pipelines/:id` - Define or replace a pipeline
+//! - `DELETE /admin/pipelines/:id` - Delete a pipeline
 
 use axum::{
     Extension, Json, Router,
-    extract::Path,
-    http::{HeaderMap, StatusCode},
+    body::{Body, to_bytes},
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode, header},
     response::IntoResponse,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
 };
 use axum_extra::extract::Multipart;
-use serde::Serialize;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
 use tracing::{info, instrument, warn};
 
+use edge_runtime_common::{GuestLogLevel, HostFunctionError, OverlapPolicy};
+use edge_runtime_host::blob::MAX_BLOB_LIST_OBJECTS;
+
+use crate::log_tail::tail_module_logs;
+use crate::module_stats::ModuleStatsSnapshot;
+use crate::profiling::{delete_profile, download_profile, get_profile, start_profile};
 use crate::state::AppState;
 
 /// Admin API state containing app state and auth token.
@@ -47,6 +80,81 @@ pub struct ModuleInfo {
     pub content_hash: String,
     /// Whether this is a Component Model component.
     pub is_component: bool,
+    /// Invocation statistics, in module listings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ModuleStatsSnapshot>,
+}
+
+/// Request body for setting a module schedule.
+#[derive(Debug, Deserialize)]
+pub struct ScheduleRequest {
+    /// Cron expression.
+    pub cron: String,
+    /// Overlap policy (defaults to `skip`).
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+}
+
+/// Query parameters for listing blob objects.
+#[derive(Debug, Deserialize)]
+pub struct BlobListQuery {
+    /// Only list keys starting with this prefix.
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// Module configuration for API responses.
+///
+/// Secrets are listed by name only.
+#[derive(Debug, Serialize)]
+pub struct ModuleConfigInfo {
+    /// Module ID.
+    pub id: String,
+    /// Configuration values.
+    pub values: BTreeMap<String, String>,
+    /// Names of the module's secrets.
+    pub secrets: Vec<String>,
+}
+
+/// Request body for replacing a module's configuration values.
+#[derive(Debug, Deserialize)]
+pub struct ConfigRequest {
+    /// Configuration values; keys not listed are removed.
+    pub values: BTreeMap<String, String>,
+}
+
+/// Module log settings for API responses.
+#[derive(Debug, Serialize)]
+pub struct ModuleLoggingInfo {
+    /// Module ID.
+    pub id: String,
+    /// Minimum level of kept entries.
+    pub min_level: GuestLogLevel,
+    /// Fraction of requests whose debug and info entries are kept.
+    pub sample_rate: f64,
+    /// Maximum number of entries kept per request.
+    pub max_entries_per_request: usize,
+    /// Maximum total size of entries kept per request, in bytes.
+    pub max_bytes_per_request: usize,
+}
+
+/// Request body for changing a module's log settings.
+///
+/// Omitted settings are left unchanged.
+#[derive(Debug, Deserialize)]
+pub struct LoggingRequest {
+    /// Minimum level of kept entries.
+    pub min_level: Option<GuestLogLevel>,
+    /// Fraction of requests, from 0 to 1, whose debug and info entries are
+    /// kept.
+    pub sample_rate: Option<f64>,
+}
+
+/// Request body for defining a pipeline.
+#[derive(Debug, Deserialize)]
+pub struct PipelineRequest {
+    /// Module IDs of the stages, in execution order.
+    pub stages: Vec<String>,
 }
 
 /// Build the Admin API router.
@@ -63,13 +171,40 @@ pub fn build_admin_router(admin_state: AdminState) -> Router<AppState> {
         .route("/modules", get(list_modules_admin))
         .route("/modules/:id", get(get_module_info))
         .route("/modules/:id", delete(delete_module))
+        .route("/modules/:id/stats", get(get_module_stats))
+        .route("/schedules", get(list_schedules))
+        .route("/modules/:id/schedule", get(get_schedule))
+        .route("/modules/:id/schedule", put(set_schedule))
+        .route("/modules/:id/schedule", delete(delete_schedule))
+        .route("/modules/:id/sql", get(download_sql_database))
+        .route("/modules/:id/sql", put(upload_sql_database))
+        .route("/modules/:id/config", get(get_module_config))
+        .route("/modules/:id/config", put(set_module_config))
+        .route("/modules/:id/logging", get(get_module_logging))
+        .route("/modules/:id/logging", put(set_module_logging))
+        .route("/modules/:id/logs/tail", get(tail_module_logs))
+        .route("/modules/:id/profile", post(start_profile))
+        .route("/modules/:id/profile", get(get_profile))
+        .route("/modules/:id/profile", delete(delete_profile))
+        .route("/modules/:id/profile/download", get(download_profile))
+        .route("/secrets/reload", post(reload_secrets))
+        .route("/blobs", get(list_blob_buckets))
+        .route("/blobs/:bucket", get(list_blob_objects))
+        .route("/blobs/:bucket/*key", get(download_blob))
+        .route("/pipelines", get(list_pipelines))
+        .route("/pipelines/:id", get(get_pipeline))
+        .route("/pipelines/:id", put(set_pipeline))
+        .route("/pipelines/:id", delete(delete_pipeline))
         .layer(Extension(admin_state))
 }
 
 /// Verify the admin token from request headers.
 ///
 /// Uses constant-time comparison to prevent timing attacks.
-fn verify_token(headers: &HeaderMap, expected: &str) -> Result<(), (StatusCode, &'static str)> {
+pub(crate) fn verify_token(
+    headers: &HeaderMap,
+    expected: &str,
+) -> Result<(), (StatusCode, &'static str)> {
     match headers.get("X-Admin-Token") {
         Some(token) => {
             let token_bytes = token.to_str().unwrap_or("").as_bytes();
@@ -171,6 +306,7 @@ pub async fn delete_module(
 
     match admin_state.app_state.remove_module(&module_id) {
         Some(_) => {
+            admin_state.app_state.scheduler().unschedule(&module_id);
             info!(id = %module_id, "Module deleted");
             Json(serde_json::json!({
                 "id": module_id,
@@ -216,6 +352,7 @@ pub async fn get_module_info(
             id: module_id,
             content_hash: module.content_hash().to_string(),
             is_component: module.is_component(),
+            stats: None,
         })
         .into_response(),
         None => (
@@ -240,7 +377,8 @@ pub async fn get_module_info(
 ///     {
 ///       "id": "hello",
 ///       "content_hash": "abc123...",
-///       "is_component": false
+///       "is_component": false,
+///       "stats": { "invocations": 42, ... }
 ///     }
 ///   ],
 ///   "count": 1
@@ -260,10 +398,13 @@ pub async fn list_modules_admin(
         .list_modules()
         .into_iter()
         .filter_map(|id| {
-            admin_state.app_state.get_module(&id).map(|m| ModuleInfo {
+            let module = admin_state.app_state.get_module(&id)?;
+            let stats = admin_state.app_state.module_stats(&id);
+            Some(ModuleInfo {
                 id,
-                content_hash: m.content_hash().to_string(),
-                is_component: m.is_component(),
+                content_hash: module.content_hash().to_string(),
+                is_component: module.is_component(),
+                stats: stats.map(|stats| stats.snapshot()),
             })
         })
         .collect();
@@ -277,55 +418,1709 @@ pub async fn list_modules_admin(
     .into_response()
 }
 
-/// Extract module ID and bytes from multipart form data.
-async fn extract_module_from_multipart(
-    mut multipart: Multipart,
-) -> Result<(String, Vec<u8>), &'static str> {
-    let mut module_id: Option<String> = None;
-    let mut wasm_bytes: Option<Vec<u8>> = None;
+/// Get a module's invocation statistics.
+///
+/// Statistics cover the invocations since the module was last loaded.
+/// Percentiles are approximate, within 12.5%.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/stats`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "invocations": 1200,
+///   "errors": { "error": 0, "fuel_exhausted": 2, "not_found": 0, "timeout": 1, "trap": 3 },
+///   "latency_ms": { "p50": 1.2, "p90": 3.1, "p99": 8.4, "max": 21.7 },
+///   "fuel": { "p50": 40960, "p90": 98304, "p99": 131071, "max": 150000 },
+///   "peak_memory_bytes": 1114112,
+///   "last_invocation": "2025-01-01T00:00:00.000Z",
+///   "loaded_at": "2024-12-31T23:00:00.000Z",
+///   "compile_ms": 14.2
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn get_module_stats(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
 
-    while let Ok(Some(field)) = multipart.next_field().await {
-        let name = field.name().unwrap_or("").to_string();
+    match admin_state.app_state.module_stats(&module_id) {
+        Some(stats) => Json(stats.snapshot()).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response(),
+    }
+}
 
-        match name.as_str() {
-            "id" | "module_id" => {
-                module_id = Some(field.text().await.map_err(|_| "Invalid id field")?);
-            }
-            "file" | "wasm" | "module" => {
-                // Extract module ID from filename if not explicitly set
-                if module_id.is_none() {
-                    if let Some(filename) = field.file_name() {
-                        module_id = Some(
-                            std::path::Path::new(filename)
-                                .file_stem()
-                                .and_then(|s| s.to_str())
-                                .unwrap_or("uploaded")
-                                .to_string(),
-                        );
-                    }
-                }
-                wasm_bytes = Some(
-                    field
-                        .bytes()
-                        .await
-                        .map_err(|_| "Failed to read file")?
-                        .to_vec(),
-                );
-            }
-            _ => {}
+/// List all module schedules.
+///
+/// # Request
+///
+/// `GET /admin/schedules`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "schedules": [
+///     {
+///       "module_id": "report",
+///       "cron": "0 * * * *",
+///       "overlap_policy": "skip",
+///       "next_run": "2025-01-01T01:00:00+00:00",
+///       "running": false,
+///       "total_runs": 3,
+///       "skipped_runs": 0,
+///       "last_run": null
+///     }
+///   ],
+///   "count": 1
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn list_schedules(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let schedules = admin_state.app_state.scheduler().list();
+    let count = schedules.len();
+
+    Json(serde_json::json!({
+        "schedules": schedules,
+        "count": count
+    }))
+    .into_response()
+}
+
+/// Get a module's schedule and the status of its last run.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/schedule`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "module_id": "report",
+///   "cron": "0 * * * *",
+///   "overlap_policy": "skip",
+///   "next_run": "2025-01-01T01:00:00+00:00",
+///   "running": false,
+///   "total_runs": 1,
+///   "skipped_runs": 0,
+///   "last_run": {
+///     "request_id": "...",
+///     "started_at": "2025-01-01T00:00:00+00:00",
+///     "duration_ms": 3,
+///     "status": "success",
+///     "error": null,
+///     "fuel_consumed": 1200,
+///     "logs": []
+///   }
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn get_schedule(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    match admin_state.app_state.scheduler().info(&module_id) {
+        Some(info) => Json(info).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("No schedule for module: {module_id}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Set a module's schedule.
+///
+/// # Request
+///
+/// `PUT /admin/modules/:id/schedule`
+///
+/// ```json
+/// {
+///   "cron": "*/5 * * * *",
+///   "overlap_policy": "queue"
+/// }
+/// ```
+///
+/// # Response
+///
+/// The resulting schedule, in the same format as `GET`.
+#[instrument(skip(admin_state, headers, body))]
+pub async fn set_schedule(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+    Json(body): Json<ScheduleRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.get_module(&module_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    }
+
+    let scheduler = admin_state.app_state.scheduler();
+    if let Err(e) = scheduler.schedule(&module_id, &body.cron, body.overlap_policy) {
+        warn!(id = %module_id, error = %e, "Invalid schedule");
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+
+    match scheduler.info(&module_id) {
+        Some(info) => Json(info).into_response(),
+        None => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// Remove a module's schedule.
+///
+/// # Request
+///
+/// `DELETE /admin/modules/:id/schedule`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "report",
+///   "message": "Schedule deleted successfully"
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn delete_schedule(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.scheduler().unschedule(&module_id) {
+        info!(id = %module_id, "Schedule deleted");
+        Json(serde_json::json!({
+            "id": module_id,
+            "message": "Schedule deleted successfully"
+        }))
+        .into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("No schedule for module: {module_id}"),
+        )
+            .into_response()
+    }
+}
+
+/// Download a module's SQL database.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/sql`
+///
+/// # Response
+///
+/// The database as a SQLite file (`application/vnd.sqlite3`).
+#[instrument(skip(admin_state, headers))]
+pub async fn download_sql_database(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let Some(namespace) = sql_namespace(&admin_state, &module_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module has no SQL database: {module_id}"),
+        )
+            .into_response();
+    };
+
+    match admin_state.app_state.sql().export(&namespace).await {
+        Ok(image) => ([(header::CONTENT_TYPE, "application/vnd.sqlite3")], image).into_response(),
+        Err(e) => {
+            warn!(id = %module_id, error = %e, "SQL database export failed");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
         }
     }
+}
 
-    match (module_id, wasm_bytes) {
-        (Some(id), Some(bytes)) => Ok((id, bytes)),
-        (None, Some(_)) => Err("Missing module id"),
-        (_, None) => Err("Missing wasm file"),
+/// Replace a module's SQL database.
+///
+/// # Request
+///
+/// `PUT /admin/modules/:id/sql`
+///
+/// The body is a SQLite database file, at most the configured maximum
+/// database size.
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "report",
+///   "namespace": "analytics",
+///   "bytes": 8192,
+///   "message": "SQL database replaced successfully"
+/// }
+/// ```
+#[instrument(skip(admin_state, headers, body))]
+pub async fn upload_sql_database(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+    body: Body,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let Some(namespace) = sql_namespace(&admin_state, &module_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module has no SQL database: {module_id}"),
+        )
+            .into_response();
+    };
+
+    let sql = admin_state.app_state.sql();
+    let limit = usize::try_from(sql.max_database_bytes()).unwrap_or(usize::MAX);
+    let Ok(image) = to_bytes(body, limit).await else {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("SQL database exceeds {limit} bytes"),
+        )
+            .into_response();
+    };
+
+    let bytes = image.len();
+    match sql.import(&namespace, image.to_vec()).await {
+        Ok(()) => {
+            info!(id = %module_id, namespace = %namespace, bytes, "SQL database replaced");
+            Json(serde_json::json!({
+                "id": module_id,
+                "namespace": namespace,
+                "bytes": bytes,
+                "message": "SQL database replaced successfully"
+            }))
+            .into_response()
+        }
+        Err(e) => {
+            warn!(id = %module_id, error = %e, "SQL database import failed");
+            let status = match e {
+                HostFunctionError::InvalidArgument { .. } | HostFunctionError::Sql(_) => {
+                    StatusCode::BAD_REQUEST
+                }
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string()).into_response()
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Get the SQL namespace a module executes with.
+fn sql_namespace(admin_state: &AdminState, module_id: &str) -> Option<String> {
+    admin_state
+        .app_state
+        .permissions_for(module_id)
+        .sql_namespace()
+        .map(String::from)
+}
+
+/// Get a module's configuration.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/config`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "api",
+///   "values": { "region": "eu-west" },
+///   "secrets": ["DATABASE_PASSWORD"]
+/// }
+/// ```
+///
+/// Secret values are never returned.
+#[instrument(skip(admin_state, headers))]
+pub async fn get_module_config(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.get_module(&module_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    }
+
+    Json(module_config_info(&admin_state, module_id)).into_response()
+}
+
+/// Replace a module's configuration values.
+///
+/// The new values apply to the module's next request; the module is not
+/// reloaded.
+///
+/// # Request
+///
+/// `PUT /admin/modules/:id/config`
+///
+/// ```json
+/// {
+///   "values": { "region": "us-east", "retries": "3" }
+/// }
+/// ```
+///
+/// # Response
+///
+/// The resulting configuration, in the same format as `GET`.
+#[instrument(skip(admin_state, headers, body))]
+pub async fn set_module_config(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+    Json(body): Json<ConfigRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.get_module(&module_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    }
+
+    let keys = body.values.len();
+    admin_state
+        .app_state
+        .config()
+        .set_values(&module_id, body.values);
+    info!(id = %module_id, keys = keys, "Module config updated");
+
+    Json(module_config_info(&admin_state, module_id)).into_response()
+}
+
+/// Get a module's log settings.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/logging`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "api",
+///   "min_level": "info",
+///   "sample_rate": 1.0,
+///   "max_entries_per_request": 1000,
+///   "max_bytes_per_request": 1048576
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn get_module_logging(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.get_module(&module_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    }
+
+    Json(module_logging_info(&admin_state, module_id)).into_response()
+}
+
+/// Change a module's log level or sample rate.
+///
+/// The new settings apply to the module's next request; the module is not
+/// reloaded.
+///
+/// # Request
+///
+/// `PUT /admin/modules/:id/logging`
+///
+/// ```json
+/// {
+///   "min_level": "warn",
+///   "sample_rate": 0.1
+/// }
+/// ```
+///
+/// # Response
+///
+/// The resulting settings, in the same format as `GET`.
+#[instrument(skip(admin_state, headers, body))]
+pub async fn set_module_logging(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+    Json(body): Json<LoggingRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.get_module(&module_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    }
+
+    if body
+        .sample_rate
+        .is_some_and(|rate| !(0.0..=1.0).contains(&rate))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            "sample_rate must be between 0 and 1".to_string(),
+        )
+            .into_response();
+    }
+
+    let mut policy = admin_state.app_state.log_policy_for(&module_id);
+    if let Some(level) = body.min_level {
+        policy.min_level = level.into();
+    }
+    if let Some(rate) = body.sample_rate {
+        policy.sample_rate = rate;
+    }
+    admin_state.app_state.set_log_policy(&module_id, policy);
+    info!(
+        id = %module_id,
+        min_level = ?policy.min_level,
+        sample_rate = policy.sample_rate,
+        "Module log settings updated"
+    );
+
+    Json(module_logging_info(&admin_state, module_id)).into_response()
+}
+
+/// Reload the encrypted secrets file.
+///
+/// Secrets apply to the next request of each module. If the file cannot
+/// be loaded, the current secrets are kept.
+///
+/// # Request
+///
+/// `POST /admin/secrets/reload`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "secrets": 4,
+///   "message": "Secrets reloaded successfully"
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn reload_secrets(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let config = admin_state.app_state.config();
+    if !config.has_secrets_file() {
+        return (StatusCode::NOT_FOUND, "Secrets are not configured").into_response();
+    }
+
+    match config.reload_secrets() {
+        Ok(count) => {
+            info!(secrets = count, "Secrets reloaded");
+            Json(serde_json::json!({
+                "secrets": count,
+                "message": "Secrets reloaded successfully"
+            }))
+            .into_response()
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to reload secrets");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+fn module_config_info(admin_state: &AdminState, module_id: String) -> ModuleConfigInfo {
+    let config = admin_state.app_state.config();
+    ModuleConfigInfo {
+        values: config.values(&module_id),
+        secrets: config.secret_keys(&module_id),
+        id: module_id,
+    }
+}
+
+fn module_logging_info(admin_state: &AdminState, module_id: String) -> ModuleLoggingInfo {
+    let policy = admin_state.app_state.log_policy_for(&module_id);
+    ModuleLoggingInfo {
+        id: module_id,
+        min_level: policy.min_level.into(),
+        sample_rate: policy.sample_rate,
+        max_entries_per_request: policy.max_entries,
+        max_bytes_per_request: policy.max_bytes,
+    }
+}
+
+/// List blob storage buckets.
+///
+/// # Request
+///
+/// `GET /admin/blobs`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "buckets": [
+///     { "bucket": "uploads", "objects": 12, "bytes": 48213 }
+///   ],
+///   "count": 1
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn list_blob_buckets(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let Some(blobs) = admin_state.app_state.blobs() else {
+        return blobs_disabled();
+    };
+    match blobs.buckets().await {
+        Ok(buckets) => {
+            let count = buckets.len();
+            Json(serde_json::json!({
+                "buckets": buckets,
+                "count": count
+            }))
+            .into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// List the objects in a blob storage bucket.
+///
+/// # Request
+///
+/// `GET /admin/blobs/:bucket?prefix=img/`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "bucket": "uploads",
+///   "objects": [
+///     {
+///       "key": "img/logo.png",
+///       "size": 4096,
+///       "digest": "9f86d081...",
+///       "content_type": "image/png",
+///       "created_ms": 1735689600000
+///     }
+///   ],
+///   "count": 1
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn list_blob_objects(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(bucket): Path<String>,
+    Query(query): Query<BlobListQuery>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let Some(blobs) = admin_state.app_state.blobs() else {
+        return blobs_disabled();
+    };
+    match blobs
+        .list(&bucket, &query.prefix, MAX_BLOB_LIST_OBJECTS)
+        .await
+    {
+        Ok(objects) => {
+            let count = objects.len();
+            Json(serde_json::json!({
+                "bucket": bucket,
+                "objects": objects,
+                "count": count
+            }))
+            .into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Download a blob storage object.
+///
+/// # Request
+///
+/// `GET /admin/blobs/:bucket/*key`
+///
+/// # Response
+///
+/// The object content, with its stored content type and an `ETag` of its
+/// digest.
+#[instrument(skip(admin_state, headers))]
+pub async fn download_blob(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path((bucket, key)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let Some(blobs) = admin_state.app_state.blobs() else {
+        return blobs_disabled();
+    };
+    match blobs.read(&bucket, &key).await {
+        Ok(Some((meta, data))) => {
+            let content_type = meta
+                .content_type
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            (
+                [
+                    (header::CONTENT_TYPE, content_type),
+                    (header::ETAG, format!("\"{}\"", meta.digest)),
+                ],
+                data,
+            )
+                .into_response()
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            format!("Object not found: {bucket}/{key}"),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Response for blob endpoints when blob storage is not configured.
+fn blobs_disabled() -> axum::response::Response {
+    (StatusCode::NOT_FOUND, "Blob storage is not configured").into_response()
+}
+
+/// List all pipelines.
+///
+/// # Request
+///
+/// `GET /admin/pipelines`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "pipelines": [
+///     {
+///       "id": "api",
+///       "stages": ["auth", "rate-limit", "app", "compress"]
+///     }
+///   ],
+///   "count": 1
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn list_pipelines(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let pipelines = admin_state.app_state.pipelines().list();
+    let count = pipelines.len();
+
+    Json(serde_json::json!({
+        "pipelines": pipelines,
+        "count": count
+    }))
+    .into_response()
+}
+
+/// Get a pipeline.
+///
+/// # Request
+///
+/// `GET /admin/pipelines/:id`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "api",
+///   "stages": ["auth", "app"]
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn get_pipeline(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(pipeline_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    match admin_state.app_state.pipelines().get(&pipeline_id) {
+        Some(pipeline) => Json(pipeline).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Pipeline not found: {pipeline_id}"),
+        )
+            .into_response(),
+    }
+}
+
+/// Define or replace a pipeline.
+///
+/// Stages do not have to be loaded yet; requests fail with `502` when
+/// they reach a missing stage.
+///
+/// # Request
+///
+/// `PUT /admin/pipelines/:id`
+///
+/// ```json
+/// {
+///   "stages": ["auth", "app"]
+/// }
+/// ```
+///
+/// # Response
+///
+/// The resulting pipeline, in the same format as `GET`.
+#[instrument(skip(admin_state, headers, body))]
+pub async fn set_pipeline(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(pipeline_id): Path<String>,
+    Json(body): Json<PipelineRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    match admin_state
+        .app_state
+        .pipelines()
+        .set(&pipeline_id, body.stages)
+    {
+        Ok(pipeline) => {
+            info!(id = %pipeline_id, stages = ?pipeline.stages, "Pipeline defined");
+            Json(pipeline).into_response()
+        }
+        Err(e) => {
+            warn!(id = %pipeline_id, error = %e, "Invalid pipeline");
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Delete a pipeline.
+///
+/// # Request
+///
+/// `DELETE /admin/pipelines/:id`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "api",
+///   "message": "Pipeline deleted successfully"
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn delete_pipeline(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(pipeline_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state
+        .app_state
+        .pipelines()
+        .remove(&pipeline_id)
+        .is_some()
+    {
+        info!(id = %pipeline_id, "Pipeline deleted");
+        Json(serde_json::json!({
+            "id": pipeline_id,
+            "message": "Pipeline deleted successfully"
+        }))
+        .into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Pipeline not found: {pipeline_id}"),
+        )
+            .into_response()
+    }
+}
+
+/// Extract module ID and bytes from multipart form data.
+async fn extract_module_from_multipart(
+    mut multipart: Multipart,
+) -> Result<(String, Vec<u8>), &'static str> {
+    let mut module_id: Option<String> = None;
+    let mut wasm_bytes: Option<Vec<u8>> = None;
+
+    while let Ok(Some(field)) = multipart.next_field().await {
+        let name = field.name().unwrap_or("").to_string();
+
+        match name.as_str() {
+            "id" | "module_id" => {
+                module_id = Some(field.text().await.map_err(|_| "Invalid id field")?);
+            }
+            "file" | "wasm" | "module" => {
+                // Extract module ID from filename if not explicitly set
+                if module_id.is_none() {
+                    if let Some(filename) = field.file_name() {
+                        module_id = Some(
+                            std::path::Path::new(filename)
+                                .file_stem()
+                                .and_then(|s| s.to_str())
+                                .unwrap_or("uploaded")
+                                .to_string(),
+                        );
+                    }
+                }
+                wasm_bytes = Some(
+                    field
+                        .bytes()
+                        .await
+                        .map_err(|_| "Failed to read file")?
+                        .to_vec(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    match (module_id, wasm_bytes) {
+        (Some(id), Some(bytes)) => Ok((id, bytes)),
+        (None, Some(_)) => Err("Missing module id"),
+        (_, None) => Err("Missing wasm file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use edge_runtime_common::RuntimeConfig;
+    use tower::util::ServiceExt;
+
+    const TOKEN: &str = "secret";
+
+    fn test_state() -> AppState {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat("hello", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        state
+    }
+
+    fn admin_app(state: &AppState) -> axum::Router {
+        build_admin_router(AdminState {
+            app_state: state.clone(),
+            admin_token: TOKEN.to_string(),
+        })
+        .with_state(state.clone())
+    }
+
+    /// Build an Admin API request, authenticated with `token` if given.
+    fn request(method: &str, uri: &str, token: Option<&str>, body: Body) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            builder = builder.header("X-Admin-Token", token);
+        }
+        builder.body(body).unwrap()
+    }
+
+    /// Send an authenticated request with a JSON body.
+    async fn send(
+        app: &axum::Router,
+        method: &str,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> axum::response::Response {
+        let body = if body.is_null() {
+            Body::empty()
+        } else {
+            Body::from(body.to_string())
+        };
+        app.clone()
+            .oneshot(request(method, uri, Some(TOKEN), body))
+            .await
+            .unwrap()
+    }
+
+    async fn body_json(response: axum::response::Response) -> serde_json::Value {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_schedule_endpoints() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        let response = send(
+            &app,
+            "PUT",
+            "/modules/hello/schedule",
+            serde_json::json!({ "cron": "*/5 * * * *", "overlap_policy": "queue" }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let schedule = body_json(response).await;
+        assert_eq!(schedule["module_id"], "hello");
+        assert_eq!(schedule["cron"], "*/5 * * * *");
+        assert_eq!(schedule["overlap_policy"], "queue");
+        assert!(schedule["next_run"].is_string());
+
+        let response = send(&app, "GET", "/schedules", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let list = body_json(response).await;
+        assert_eq!(list["count"], 1);
+        assert_eq!(list["schedules"][0]["module_id"], "hello");
+
+        let response = send(
+            &app,
+            "GET",
+            "/modules/hello/schedule",
+            serde_json::Value::Null,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(
+            &app,
+            "DELETE",
+            "/modules/hello/schedule",
+            serde_json::Value::Null,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for method in ["GET", "DELETE"] {
+            let response = send(
+                &app,
+                method,
+                "/modules/hello/schedule",
+                serde_json::Value::Null,
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{method}");
+        }
+        let list = body_json(send(&app, "GET", "/schedules", serde_json::Value::Null).await).await;
+        assert_eq!(list["count"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_rejects_invalid_requests() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        let response = send(
+            &app,
+            "PUT",
+            "/modules/hello/schedule",
+            serde_json::json!({ "cron": "every five minutes" }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(
+            &app,
+            "PUT",
+            "/modules/missing/schedule",
+            serde_json::json!({ "cron": "*/5 * * * *" }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(state.scheduler().list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_requires_token() {
+        let state = test_state();
+        let app = admin_app(&state);
+        let body = || Body::from(r#"{"cron":"*/5 * * * *"}"#);
+
+        for token in [None, Some("wrong")] {
+            for (method, uri) in [
+                ("GET", "/schedules"),
+                ("GET", "/modules/hello/schedule"),
+                ("PUT", "/modules/hello/schedule"),
+                ("DELETE", "/modules/hello/schedule"),
+            ] {
+                let response = app
+                    .clone()
+                    .oneshot(request(method, uri, token, body()))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    response.status(),
+                    StatusCode::UNAUTHORIZED,
+                    "{method} {uri} {token:?}"
+                );
+            }
+        }
+        assert!(state.scheduler().list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_endpoints() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        let response = send(
+            &app,
+            "PUT",
+            "/pipelines/api",
+            serde_json::json!({ "stages": ["hello", "hello"] }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            body_json(response).await,
+            serde_json::json!({ "id": "api", "stages": ["hello", "hello"] })
+        );
+
+        let list = body_json(send(&app, "GET", "/pipelines", serde_json::Value::Null).await).await;
+        assert_eq!(list["count"], 1);
+        assert_eq!(list["pipelines"][0]["id"], "api");
+
+        let response = send(&app, "DELETE", "/pipelines/api", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.pipelines().get("api").is_none());
+        for method in ["GET", "DELETE"] {
+            let response = send(&app, method, "/pipelines/api", serde_json::Value::Null).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{method}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_with_unknown_module() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        // Stages are resolved per request, so the pipeline is accepted...
+        let response = send(
+            &app,
+            "PUT",
+            "/pipelines/api",
+            serde_json::json!({ "stages": ["hello", "missing"] }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // ...and requests fail when they reach the missing stage.
+        let response = crate::router::build_router(state, std::time::Duration::from_secs(30))
+            .oneshot(request("GET", "/functions/api", None, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        let response = send(
+            &app,
+            "PUT",
+            "/pipelines/api",
+            serde_json::json!({ "stages": [] }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_requires_token() {
+        let state = test_state();
+        state.pipelines().set("api", vec!["hello".into()]).unwrap();
+        let app = admin_app(&state);
+        let body = || Body::from(r#"{"stages":["hello"]}"#);
+
+        for token in [None, Some("wrong")] {
+            for (method, uri) in [
+                ("GET", "/pipelines"),
+                ("GET", "/pipelines/api"),
+                ("PUT", "/pipelines/other"),
+                ("DELETE", "/pipelines/api"),
+            ] {
+                let response = app
+                    .clone()
+                    .oneshot(request(method, uri, token, body()))
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    response.status(),
+                    StatusCode::UNAUTHORIZED,
+                    "{method} {uri} {token:?}"
+                );
+            }
+        }
+        assert!(state.pipelines().get("api").is_some());
+        assert!(state.pipelines().get("other").is_none());
+    }
+
+    /// State with modules `hello` and `copy` using the SQL databases `app`
+    /// and `copy`.
+    fn sql_state(max_database_bytes: u64) -> AppState {
+        let sql = edge_runtime_host::SqlStore::open(&edge_runtime_common::SqlConfig {
+            max_database_bytes,
+            ..Default::default()
+        })
+        .unwrap();
+        let state = test_state().with_sql_store(sql);
+        state
+            .load_module_wat("copy", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        for (module, namespace) in [("hello", "app"), ("copy", "copy")] {
+            state.set_module_permissions(
+                module,
+                edge_runtime_host::Permissions::builder()
+                    .sql_namespace(namespace, 10)
+                    .build(),
+            );
+        }
+        state
+    }
+
+    #[tokio::test]
+    async fn test_sql_database_round_trip() {
+        let state = sql_state(1024 * 1024);
+        let sql = state.sql();
+        sql.execute("app", "CREATE TABLE t (x)".into(), vec![], false, None)
+            .await
+            .unwrap();
+        sql.execute(
+            "app",
+            "INSERT INTO t VALUES ('kept')".into(),
+            vec![],
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        let app = admin_app(&state);
+
+        let response = send(&app, "GET", "/modules/hello/sql", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/vnd.sqlite3"
+        );
+        let image = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/modules/copy/sql",
+                Some(TOKEN),
+                Body::from(image.clone()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_json(response).await;
+        assert_eq!(body["namespace"], "copy");
+        assert_eq!(body["bytes"], image.len());
+
+        let rows = sql
+            .query("copy", "SELECT x FROM t".into(), vec![], true, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            rows.rows,
+            vec![vec![edge_runtime_host::SqlValue::Text("kept".into())]]
+        );
+
+        let response = send(&app, "GET", "/modules/missing/sql", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sql_database_upload_limits() {
+        let state = sql_state(16 * 1024);
+        let app = admin_app(&state);
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/modules/copy/sql",
+                Some(TOKEN),
+                Body::from(vec![0; 16 * 1024 + 1]),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/modules/copy/sql",
+                Some(TOKEN),
+                Body::from("not a database"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/modules/copy/sql",
+                None,
+                Body::from("not a database"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_blob_endpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let blobs = edge_runtime_host::BlobStore::open(&edge_runtime_common::BlobConfig {
+            directory: Some(dir.path().to_string_lossy().into_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut writer = blobs.writer().await.unwrap();
+        writer.write(b"hello").await.unwrap();
+        let meta = blobs
+            .commit(
+                writer,
+                "files",
+                "docs/a.txt",
+                Some("text/plain".into()),
+                1024,
+            )
+            .await
+            .unwrap();
+        let state = test_state().with_blob_store(blobs);
+        let app = admin_app(&state);
+
+        let buckets = body_json(send(&app, "GET", "/blobs", serde_json::Value::Null).await).await;
+        assert_eq!(
+            buckets,
+            serde_json::json!({
+                "buckets": [{ "bucket": "files", "objects": 1, "bytes": 5 }],
+                "count": 1
+            })
+        );
+
+        let objects = body_json(
+            send(
+                &app,
+                "GET",
+                "/blobs/files?prefix=docs/",
+                serde_json::Value::Null,
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(objects["count"], 1);
+        assert_eq!(objects["objects"][0]["key"], "docs/a.txt");
+        let objects = body_json(
+            send(
+                &app,
+                "GET",
+                "/blobs/files?prefix=img/",
+                serde_json::Value::Null,
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(objects["count"], 0);
+
+        let response = send(
+            &app,
+            "GET",
+            "/blobs/files/docs/a.txt",
+            serde_json::Value::Null,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(
+            response.headers()[header::ETAG],
+            format!("\"{}\"", meta.digest).as_str()
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"hello");
+
+        let response = send(&app, "GET", "/blobs/files/missing", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        for uri in ["/blobs", "/blobs/files", "/blobs/files/docs/a.txt"] {
+            let response = app
+                .clone()
+                .oneshot(request("GET", uri, Some("wrong"), Body::empty()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blob_endpoints_without_storage() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        for uri in ["/blobs", "/blobs/files", "/blobs/files/a.txt"] {
+            let response = send(&app, "GET", uri, serde_json::Value::Null).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_config_endpoints_never_return_secret_values() {
+        use edge_runtime_host::config::encrypt_secrets;
+        use edge_runtime_host::{ConfigStore, SecretsKey};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.enc");
+        let key = SecretsKey::generate().unwrap();
+        let write_secrets = |document: &str| {
+            std::fs::write(&path, encrypt_secrets(&key, document).unwrap()).unwrap();
+        };
+        write_secrets("[hello]\nDB_PASSWORD = \"hunter2\"\n");
+        let config = ConfigStore::with_secrets_file(&path, key.clone()).unwrap();
+        let state = test_state().with_config_store(config);
+        let app = admin_app(&state);
+
+        // Read a response body, checking it holds no secret value.
+        async fn checked_body(response: axum::response::Response) -> serde_json::Value {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let text = String::from_utf8(body.to_vec()).unwrap();
+            assert!(!text.contains("hunter2"), "{text}");
+            assert!(!text.contains("rotated"), "{text}");
+            serde_json::from_str(&text).unwrap()
+        }
+
+        let info = checked_body(
+            send(
+                &app,
+                "GET",
+                "/modules/hello/config",
+                serde_json::Value::Null,
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(
+            info,
+            serde_json::json!({ "id": "hello", "values": {}, "secrets": ["DB_PASSWORD"] })
+        );
+
+        let info = checked_body(
+            send(
+                &app,
+                "PUT",
+                "/modules/hello/config",
+                serde_json::json!({ "values": { "region": "eu" } }),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(info["values"], serde_json::json!({ "region": "eu" }));
+        assert_eq!(info["secrets"], serde_json::json!(["DB_PASSWORD"]));
+
+        let modules =
+            checked_body(send(&app, "GET", "/modules", serde_json::Value::Null).await).await;
+        assert_eq!(modules["modules"][0]["id"], "hello");
+        assert!(modules["modules"][0].get("secrets").is_none());
+
+        write_secrets("[hello]\nDB_PASSWORD = \"rotated\"\nTOKEN = \"rotated\"\n");
+        let reloaded =
+            checked_body(send(&app, "POST", "/secrets/reload", serde_json::Value::Null).await)
+                .await;
+        assert_eq!(reloaded["secrets"], 2);
+        let info = checked_body(
+            send(
+                &app,
+                "GET",
+                "/modules/hello/config",
+                serde_json::Value::Null,
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(info["secrets"], serde_json::json!(["DB_PASSWORD", "TOKEN"]));
+
+        let response = send(
+            &app,
+            "GET",
+            "/modules/missing/config",
+            serde_json::Value::Null,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/modules/hello/config", None, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_reload_secrets_without_file() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        let response = send(&app, "POST", "/secrets/reload", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_logging_endpoints() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        let defaults = body_json(
+            send(
+                &app,
+                "GET",
+                "/modules/hello/logging",
+                serde_json::Value::Null,
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(defaults["id"], "hello");
+
+        let response = send(
+            &app,
+            "PUT",
+            "/modules/hello/logging",
+            serde_json::json!({ "min_level": "warn" }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let updated = body_json(response).await;
+        assert_eq!(updated["min_level"], "warn");
+        assert_eq!(updated["sample_rate"], defaults["sample_rate"]);
+
+        let updated = body_json(
+            send(
+                &app,
+                "PUT",
+                "/modules/hello/logging",
+                serde_json::json!({ "sample_rate": 0.25 }),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(updated["min_level"], "warn");
+        assert_eq!(updated["sample_rate"], 0.25);
+
+        let policy = state.log_policy_for("hello");
+        assert_eq!(policy.sample_rate, 0.25);
+        assert_eq!(GuestLogLevel::from(policy.min_level), GuestLogLevel::Warn);
+        assert_eq!(
+            body_json(
+                send(
+                    &app,
+                    "GET",
+                    "/modules/hello/logging",
+                    serde_json::Value::Null
+                )
+                .await
+            )
+            .await,
+            updated
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logging_rejects_invalid_settings() {
+        let state = test_state();
+        let app = admin_app(&state);
+
+        for (body, status) in [
+            (
+                serde_json::json!({ "min_level": "verbose" }),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                serde_json::json!({ "sample_rate": -0.1 }),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                serde_json::json!({ "sample_rate": 1.5 }),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                serde_json::json!({ "min_level": "error", "sample_rate": 2 }),
+                StatusCode::BAD_REQUEST,
+            ),
+        ] {
+            let response = send(&app, "PUT", "/modules/hello/logging", body.clone()).await;
+            assert_eq!(response.status(), status, "{body}");
+        }
+
+        // Rejected updates leave the settings unchanged.
+        let policy = state.log_policy_for("hello");
+        assert_eq!(policy.min_level, state.default_log_policy().min_level);
+        assert_eq!(policy.sample_rate, state.default_log_policy().sample_rate);
+
+        let response = send(
+            &app,
+            "PUT",
+            "/modules/missing/logging",
+            serde_json::json!({ "min_level": "warn" }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app
+            .clone()
+            .oneshot(request(
+                "PUT",
+                "/modules/hello/logging",
+                Some("wrong"),
+                Body::from(r#"{"min_level":"warn"}"#),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_module_stats_endpoints() {
+        let state = test_state();
+        state
+            .load_module_wat("trap", r#"(module (func (export "_start") unreachable))"#)
+            .unwrap();
+        for module in ["hello", "trap"] {
+            let request = crate::request::WasmHttpRequest::new("GET", "/functions/x");
+            crate::invocation::invoke_module(&state, module, "req-1".into(), &request)
+                .await
+                .unwrap();
+        }
+        let app = admin_app(&state);
+
+        let response = send(&app, "GET", "/modules/hello/stats", serde_json::Value::Null).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let stats = body_json(response).await;
+        assert_eq!(stats["invocations"], 1);
+        assert_eq!(stats["errors"]["trap"], 0);
+        for distribution in ["latency_ms", "fuel"] {
+            for key in ["p50", "p90", "p99", "max"] {
+                assert!(stats[distribution][key].is_number(), "{distribution}.{key}");
+            }
+        }
+        assert!(stats["fuel"]["max"].as_u64().unwrap() > 0);
+        assert!(stats["peak_memory_bytes"].is_u64());
+        assert!(stats["last_invocation"].is_string());
+        assert!(stats["loaded_at"].is_string());
+        assert!(stats["compile_ms"].is_number());
+
+        let stats =
+            body_json(send(&app, "GET", "/modules/trap/stats", serde_json::Value::Null).await)
+                .await;
+        assert_eq!(stats["invocations"], 1);
+        assert_eq!(stats["errors"]["trap"], 1);
+
+        let modules = body_json(send(&app, "GET", "/modules", serde_json::Value::Null).await).await;
+        assert_eq!(modules["count"], 2);
+        for module in modules["modules"].as_array().unwrap() {
+            assert_eq!(module["stats"]["invocations"], 1, "{module}");
+        }
+        let module =
+            body_json(send(&app, "GET", "/modules/hello", serde_json::Value::Null).await).await;
+        assert!(module.get("stats").is_none());
+
+        let response = send(
+            &app,
+            "GET",
+            "/modules/missing/stats",
+            serde_json::Value::Null,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/modules/hello/stats", None, Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 
     #[test]
     fn test_verify_token_valid() {
@@ -3,49 +3,54 @@
 //! This module provides HTTP handlers for executing WebAssembly functions
 //! and managing the runtime.
 
-use std::time::Instant;
-
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::body::to_bytes;
+use axum::extract::{Path, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::IntoResponse;
-use tracing::{error, info, instrument};
+use tracing::{Span, error, info, instrument};
 use uuid::Uuid;
 
 use edge_runtime_common::RuntimeError;
 use edge_runtime_core::ExecutionResult;
-use edge_runtime_core::store::create_store;
-
-use edge_runtime_core::store::LogEntry;
+use edge_runtime_host::otel::{self, TRACEPARENT, TRACESTATE, TraceContext};
 
+use crate::access_log::AccessRecord;
+use crate::invocation::{InvocationOutcome, invoke_module, logs_to_json};
+use crate::pipeline::{Pipeline, pipeline_logs_to_json, run_pipeline, stages_to_json};
+use crate::request::WasmHttpRequest;
 use crate::response::WasmHttpResponse;
 use crate::state::AppState;
 
-/// Convert log entries to JSON-serializable format.
-fn logs_to_json(logs: &[LogEntry]) -> Vec<serde_json::Value> {
-    logs.iter()
-        .map(|l| {
-            serde_json::json!({
-                "level": l.level.to_string(),
-                "message": l.message,
-            })
-        })
-        .collect()
-}
+/// Maximum request body size accepted for function invocations.
+pub const MAX_REQUEST_BODY_BYTES: usize = 16 * 1024 * 1024;
 
 /// Execute a Wasm function for an HTTP request.
 ///
 /// This handler:
-/// 1. Looks up the module by function_id
-/// 2. Creates a new execution store
+/// 1. Looks up a pipeline, then a module, by function_id
+/// 2. Creates a new execution store per module
 /// 3. Executes the module's `_start` entry point
-/// 4. Returns the execution result as an HTTP response
-#[instrument(skip(state), fields(function_id = %function_id))]
+/// 4. Returns the response written by the guest, or the execution result
+///    as a JSON summary if it wrote none
+///
+/// A caller's `traceparent` header makes the request's span, and the spans
+/// of the execution, part of the caller's trace.
+#[instrument(skip(state, request), fields(function_id = %function_id, otel.kind = "server"))]
 pub async fn handle_function(
     State(state): State<AppState>,
     Path(function_id): Path<String>,
+    request: Request,
 ) -> impl IntoResponse {
-    let start = Instant::now();
+    if let Some(context) = trace_context(request.headers()) {
+        otel::set_parent(&Span::current(), &context);
+    }
     let request_id = Uuid::new_v4().to_string();
+    let access = request
+        .extensions()
+        .get::<AccessRecord>()
+        .cloned()
+        .unwrap_or_default();
+    access.set_request_id(&request_id);
 
     info!(
         request_id = %request_id,
@@ -53,41 +58,47 @@ pub async fn handle_function(
         "Handling function request"
     );
 
-    // Get the module
-    let module = match state.get_module(&function_id) {
-        Some(m) => m,
-        None => {
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_REQUEST_BODY_BYTES).await else {
+        return WasmHttpResponse::error(413, "Request body too large").into_axum_response();
+    };
+    access.set_bytes_in(body.len());
+    let request = WasmHttpRequest::from_axum(&Request::from_parts(parts, ()), body);
+
+    if let Some(pipeline) = state.pipelines().get(&function_id) {
+        access.set_pipeline(&function_id);
+        return handle_pipeline(&state, &pipeline, request_id, &request, &access).await;
+    }
+
+    access.set_module(&function_id);
+    let invocation = match invoke_module(&state, &function_id, request_id.clone(), &request).await {
+        Ok(invocation) => invocation,
+        Err(e) if e.is_not_found() => {
+            access.set_result(InvocationOutcome::NotFound, 0);
             error!(function_id = %function_id, "Function not found");
             return WasmHttpResponse::error(404, &format!("Function '{}' not found", function_id))
                 .into_axum_response();
         }
-    };
-
-    // Create execution store
-    let mut store = match create_store(state.engine(), state.exec_config(), request_id.clone()) {
-        Ok(s) => s,
         Err(e) => {
+            access.set_result(InvocationOutcome::Error, 0);
             error!(error = %e, "Failed to create store");
             return WasmHttpResponse::error(500, "Internal server error").into_axum_response();
         }
     };
 
-    // Execute the function
-    let result = state
-        .runner()
-        .execute_core(&module, &mut store, "_start")
-        .await;
-
-    let duration = start.elapsed();
+    let duration = invocation.duration;
+    access.set_result(invocation.outcome(), invocation.metrics.fuel_consumed);
 
-    match result {
+    match invocation.result {
         Ok(exec_result) => {
-            let logs = &store.data().logs;
-            let fuel_consumed = store.data().metrics.fuel_consumed;
+            let logs = &invocation.logs;
+            let fuel_consumed = invocation.metrics.fuel_consumed;
+            let sleep_ms = invocation.metrics.sleep_duration.as_millis();
 
             info!(
                 request_id = %request_id,
                 duration_ms = duration.as_millis(),
+                sleep_ms = sleep_ms,
                 fuel_consumed = fuel_consumed,
                 log_count = logs.len(),
                 "Request completed"
@@ -95,12 +106,17 @@ pub async fn handle_function(
 
             match exec_result {
                 ExecutionResult::Success => {
+                    if let Some(response) = invocation.response {
+                        return response.into_axum_response();
+                    }
+
                     let response_body = serde_json::json!({
                         "success": true,
                         "logs": logs_to_json(logs),
                         "metrics": {
                             "fuel_consumed": fuel_consumed,
                             "duration_ms": duration.as_millis(),
+                            "sleep_ms": sleep_ms,
                         }
                     });
 
@@ -133,25 +149,127 @@ pub async fn handle_function(
     }
 }
 
-/// Convert RuntimeError to HTTP response.
-fn error_to_response(error: RuntimeError) -> WasmHttpResponse {
-    match error {
-        RuntimeError::ModuleNotFound { module_id } => {
-            WasmHttpResponse::error(404, &format!("Module not found: {module_id}"))
+/// Execute a pipeline for an HTTP request.
+///
+/// Returns the final response produced by the stages, or a JSON summary
+/// with per-stage results and merged logs and metrics if none was written.
+async fn handle_pipeline(
+    state: &AppState,
+    pipeline: &Pipeline,
+    request_id: String,
+    request: &WasmHttpRequest,
+    access: &AccessRecord,
+) -> axum::response::Response {
+    let mut run = match run_pipeline(state, pipeline, request_id, request).await {
+        Ok(run) => run,
+        Err(RuntimeError::ModuleNotFound { module_id }) => {
+            access.set_result(InvocationOutcome::NotFound, 0);
+            error!(pipeline_id = %pipeline.id, stage = %module_id, "Pipeline stage not found");
+            return WasmHttpResponse::error(
+                502,
+                &format!("Pipeline stage '{module_id}' not found"),
+            )
+            .into_axum_response();
+        }
+        Err(e) => {
+            access.set_result(InvocationOutcome::Error, 0);
+            error!(pipeline_id = %pipeline.id, error = %e, "Pipeline failed");
+            return error_to_response(e).into_axum_response();
+        }
+    };
+
+    let metrics = run.metrics();
+    access.set_result(
+        run.stages
+            .last()
+            .map_or(InvocationOutcome::Success, |stage| {
+                stage.invocation.outcome()
+            }),
+        metrics.fuel_consumed,
+    );
+    let stages = stages_to_json(&run);
+    let logs = pipeline_logs_to_json(&run);
+
+    if run.is_success() {
+        if let Some(response) = run.response {
+            return response.into_axum_response();
         }
-        RuntimeError::FuelExhausted => {
-            WasmHttpResponse::error(429, "Execution limit exceeded: fuel exhausted")
+
+        let response_body = serde_json::json!({
+            "success": true,
+            "pipeline": run.pipeline_id,
+            "stages": stages,
+            "logs": logs,
+            "metrics": {
+                "fuel_consumed": metrics.fuel_consumed,
+                "duration_ms": run.duration.as_millis(),
+                "sleep_ms": metrics.sleep_duration.as_millis(),
+            }
+        });
+
+        return WasmHttpResponse::json(200, &response_body.to_string()).into_axum_response();
+    }
+
+    // The pipeline stops at the first failed stage, so it is the last one.
+    let Some(stage) = run.stages.pop() else {
+        return WasmHttpResponse::error(500, "Internal server error").into_axum_response();
+    };
+
+    match stage.invocation.result {
+        Ok(ExecutionResult::Trap { message, code }) => {
+            let response_body = serde_json::json!({
+                "success": false,
+                "pipeline": run.pipeline_id,
+                "error": {
+                    "type": "trap",
+                    "stage": stage.module_id,
+                    "message": message,
+                    "code": code,
+                },
+                "stages": stages,
+                "logs": logs,
+            });
+
+            WasmHttpResponse::json(500, &response_body.to_string()).into_axum_response()
+        }
+        Ok(ExecutionResult::Success) => {
+            WasmHttpResponse::error(500, "Internal server error").into_axum_response()
         }
+        Err(e) => error_to_response(e).into_axum_response(),
+    }
+}
+
+/// Convert RuntimeError to HTTP response.
+fn error_to_response(error: RuntimeError) -> WasmHttpResponse {
+    let message = match &error {
+        RuntimeError::ModuleNotFound { module_id } => format!("Module not found: {module_id}"),
+        RuntimeError::FuelExhausted => "Execution limit exceeded: fuel exhausted".to_string(),
         RuntimeError::ExecutionTimeout { duration_ms } => {
-            WasmHttpResponse::error(504, &format!("Execution timeout after {duration_ms}ms"))
+            format!("Execution timeout after {duration_ms}ms")
         }
         RuntimeError::MemoryLimitExceeded { limit_mb } => {
-            WasmHttpResponse::error(507, &format!("Memory limit exceeded: {limit_mb}MB"))
-        }
-        RuntimeError::HostFunction(host_err) => {
-            WasmHttpResponse::error(500, &format!("Host function error: {host_err}"))
+            format!("Memory limit exceeded: {limit_mb}MB")
         }
-        _ => WasmHttpResponse::error(500, "Internal server error"),
+        RuntimeError::HostFunction(host_err) => format!("Host function error: {host_err}"),
+        _ => "Internal server error".to_string(),
+    };
+    WasmHttpResponse::error(error_status(&error), &message)
+}
+
+/// The trace context sent by the caller, if any.
+fn trace_context(headers: &HeaderMap) -> Option<TraceContext> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    TraceContext::parse(header(TRACEPARENT)?, header(TRACESTATE))
+}
+
+/// HTTP status of the error response for a runtime error.
+pub(crate) fn error_status(error: &RuntimeError) -> u16 {
+    match error {
+        RuntimeError::ModuleNotFound { .. } => 404,
+        RuntimeError::FuelExhausted => 429,
+        RuntimeError::ExecutionTimeout { .. } => 504,
+        RuntimeError::MemoryLimitExceeded { .. } => 507,
+        _ => 500,
     }
 }
 
@@ -177,6 +295,25 @@ pub async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse
     (StatusCode::OK, axum::Json(body))
 }
 
+/// Metrics handler.
+///
+/// Returns the runtime metrics, labelled by module ID, followed by the
+/// metrics recorded by modules, with metric names prefixed by the module
+/// ID, in the Prometheus text format.
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let engine_config = state.engine().config();
+    let pooling_capacity = engine_config
+        .pooling_allocator
+        .then_some(engine_config.max_instances);
+    let mut body = state.runtime_metrics().render(pooling_capacity);
+    body.push_str(&state.guest_metrics().render());
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+}
+
 /// List loaded modules.
 pub async fn list_modules(State(state): State<AppState>) -> impl IntoResponse {
     let modules = state.list_modules();
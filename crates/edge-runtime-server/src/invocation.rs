@@ -0,0 +1,995 @@
+//! Module invocation shared by the HTTP handlers and background subsystems.
+//!
+//! This module provides [`invoke_module`], which runs a loaded module's entry
+//! point in a fresh store and collects everything the caller needs to report
+//! on the run: the execution result, guest logs and metrics.
+//!
+//! Work registered by the guest via `wait_until` is run in a background task
+//! after the invocation returns, so the caller can send its response first.
+//!
+//! The guest logs of the entry point call, and those of deferred work once it
+//! finishes, are submitted to the [`LogSinks`](crate::log_sinks::LogSinks)
+//! and published to the [`LogTail`](crate::log_tail::LogTail).
+//!
+//! Each execution gets the module's [`Permissions`], the request/response
+//! [`Exchange`], a [`ServiceContext`], the [`KvStore`], the
+//! [`ResponseCache`], the [`SqlStore`] and, if enabled, the [`BlobStore`]
+//! attached to its store, so that the guest can read the request, write a
+//! response, call other modules and use storage.
+//!
+//! [`Permissions`]: edge_runtime_host::Permissions
+//! [`KvStore`]: edge_runtime_host::KvStore
+//! [`ResponseCache`]: edge_runtime_host::ResponseCache
+//! [`SqlStore`]: edge_runtime_host::SqlStore
+//! [`BlobStore`]: edge_runtime_host::BlobStore
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{Instrument, Span, debug, error, info_span, instrument};
+use wasmtime::{Instance, Store};
+
+use edge_runtime_common::{ExecutionConfig, RuntimeError};
+use edge_runtime_core::store::{LogEntry, WorkerContext, create_store, reset_for_deferred};
+use edge_runtime_core::{DeferredOutcome, ExecutionMetrics, ExecutionResult};
+use edge_runtime_host::{Exchange, GuestRequest, ServiceContext, SpanParent};
+
+use crate::handler::error_status;
+use crate::request::WasmHttpRequest;
+use crate::response::WasmHttpResponse;
+use crate::state::AppState;
+
+/// Entry point invoked for every module execution.
+pub const ENTRY_POINT: &str = "_start";
+
+/// Result of a single module invocation.
+#[derive(Debug)]
+pub struct Invocation {
+    /// Request ID the execution was tagged with.
+    pub request_id: String,
+    /// Outcome of the entry point call.
+    pub result: Result<ExecutionResult, RuntimeError>,
+    /// Logs emitted by the guest.
+    pub logs: Vec<LogEntry>,
+    /// Execution metrics collected by the store.
+    pub metrics: ExecutionMetrics,
+    /// Response written by the guest, if it wrote one.
+    pub response: Option<WasmHttpResponse>,
+    /// Wall-clock time including store creation and instantiation.
+    pub duration: Duration,
+    /// Background task running deferred work, if the guest registered any.
+    ///
+    /// Dropping the handle detaches the task; it keeps running.
+    pub deferred: Option<JoinHandle<Vec<DeferredOutcome>>>,
+}
+
+impl Invocation {
+    /// Returns `true` if the entry point completed without a trap or error.
+    pub fn is_success(&self) -> bool {
+        matches!(self.result, Ok(ExecutionResult::Success))
+    }
+
+    /// Classify the outcome of the entry point call.
+    pub fn outcome(&self) -> InvocationOutcome {
+        match &self.result {
+            Ok(ExecutionResult::Success) => InvocationOutcome::Success,
+            // Epoch deadlines surface as interrupt traps.
+            Ok(ExecutionResult::Trap { code, .. }) if code.as_deref() == Some("Interrupt") => {
+                InvocationOutcome::Timeout
+            }
+            Ok(ExecutionResult::Trap { code, .. }) if code.as_deref() == Some("OutOfFuel") => {
+                InvocationOutcome::FuelExhausted
+            }
+            Ok(ExecutionResult::Trap { .. }) => InvocationOutcome::Trap,
+            Err(RuntimeError::FuelExhausted) => InvocationOutcome::FuelExhausted,
+            Err(RuntimeError::ModuleNotFound { .. }) => InvocationOutcome::NotFound,
+            Err(RuntimeError::ExecutionTimeout { .. }) => InvocationOutcome::Timeout,
+            Err(_) => InvocationOutcome::Error,
+        }
+    }
+
+    /// HTTP status of the invocation: the status of the guest's response,
+    /// or the status the runtime responds with if the guest wrote none.
+    pub fn status(&self) -> u16 {
+        match &self.result {
+            Ok(ExecutionResult::Success) => self.response.as_ref().map_or(200, |r| r.status),
+            Ok(ExecutionResult::Trap { .. }) => 500,
+            Err(e) => error_status(e),
+        }
+    }
+}
+
+/// Outcome of an invocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvocationOutcome {
+    /// The entry point completed.
+    Success,
+    /// The guest trapped.
+    Trap,
+    /// The guest ran out of fuel.
+    FuelExhausted,
+    /// The guest exceeded its time limit.
+    Timeout,
+    /// The module or its entry point does not exist.
+    NotFound,
+    /// The invocation failed in the runtime.
+    Error,
+}
+
+impl InvocationOutcome {
+    /// The outcome as a `snake_case` string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Trap => "trap",
+            Self::FuelExhausted => "fuel_exhausted",
+            Self::Timeout => "timeout",
+            Self::NotFound => "not_found",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Invoke a loaded module's entry point.
+///
+/// # Arguments
+///
+/// * `state` - Application state holding the module cache and runner
+/// * `module_id` - ID of the module to run
+/// * `request_id` - Request ID used to tag logs and traces
+/// * `request` - The (possibly synthetic) request that triggered the run
+///
+/// # Errors
+///
+/// Returns an error if the module is not loaded or the store cannot be
+/// created. Failures of the guest itself are reported in
+/// [`Invocation::result`].
+pub async fn invoke_module(
+    state: &AppState,
+    module_id: &str,
+    request_id: String,
+    request: &WasmHttpRequest,
+) -> Result<Invocation, RuntimeError> {
+    invoke_module_with_config(state, module_id, request_id, request, state.exec_config()).await
+}
+
+/// Invoke a loaded module's entry point with explicit execution limits.
+///
+/// This is [`invoke_module`] with `exec_config` used in place of the
+/// server-wide execution configuration.
+///
+/// # Errors
+///
+/// Returns an error if the module is not loaded or the store cannot be
+/// created.
+pub async fn invoke_module_with_config(
+    state: &AppState,
+    module_id: &str,
+    request_id: String,
+    request: &WasmHttpRequest,
+    exec_config: &ExecutionConfig,
+) -> Result<Invocation, RuntimeError> {
+    invoke_module_at_depth(state, module_id, request_id, request, exec_config, 0).await
+}
+
+/// Invoke a module as part of a chain of service calls.
+///
+/// `depth` is the nesting depth of this execution; top-level requests run
+/// at depth 0.
+///
+/// # Errors
+///
+/// Returns an error if the module is not loaded or the store cannot be
+/// created.
+pub(crate) async fn invoke_module_at_depth(
+    state: &AppState,
+    module_id: &str,
+    request_id: String,
+    request: &WasmHttpRequest,
+    exec_config: &ExecutionConfig,
+    depth: u32,
+) -> Result<Invocation, RuntimeError> {
+    let exchange = Exchange::new(GuestRequest::from(request));
+    invoke_with_exchange(state, module_id, request_id, exchange, exec_config, depth)
+        .await
+        .map(|(invocation, _)| invocation)
+}
+
+/// Invoke a module with a prepared request/response exchange.
+///
+/// Returns the invocation together with the exchange as the guest left it,
+/// so callers such as pipelines can pass the (possibly modified) request
+/// on. The response is moved into [`Invocation::response`].
+///
+/// # Errors
+///
+/// Returns an error if the module is not loaded or the store cannot be
+/// created.
+#[instrument(
+    skip(state, exchange, exec_config),
+    fields(method = %exchange.request().method, uri = %exchange.request().uri)
+)]
+pub(crate) async fn invoke_with_exchange(
+    state: &AppState,
+    module_id: &str,
+    request_id: String,
+    exchange: Exchange,
+    exec_config: &ExecutionConfig,
+    depth: u32,
+) -> Result<(Invocation, Exchange), RuntimeError> {
+    let start = Instant::now();
+
+    let Some(module) = state.get_module(module_id) else {
+        state.runtime_metrics().record_not_found(module_id);
+        return Err(RuntimeError::module_not_found(module_id));
+    };
+    let stats = state.module_stats(module_id);
+
+    let mut store = create_store(state.engine(), exec_config, request_id.clone())?;
+
+    let context = store.data_mut();
+    context.module_id = Some(module_id.to_string());
+    let extensions = context.extensions_mut();
+    extensions.insert(state.permissions_for(module_id));
+    extensions.insert(state.log_policy_for(module_id));
+    extensions.insert(state.runtime_metrics().instance_slot(module_id));
+    extensions.insert(exchange);
+    extensions.insert(state.kv().clone());
+    extensions.insert(state.cache().clone());
+    extensions.insert(state.config().clone());
+    extensions.insert(state.keyring().clone());
+    extensions.insert(state.socket_connector().clone());
+    extensions.insert(state.guest_metrics().clone());
+    // Guest spans nest under this invocation's span, inside the caller's.
+    extensions.insert(SpanParent(Span::current()));
+    extensions.insert(state.sql().clone());
+    if let Some(blobs) = state.blobs() {
+        extensions.insert(blobs.clone());
+    }
+    extensions.insert(ServiceContext::new(
+        Arc::new(state.clone()),
+        depth,
+        exec_config.max_service_depth,
+    ));
+
+    let recorder = state
+        .profiler()
+        .attach(module_id, &module, &mut store, exec_config.timeout_ms);
+
+    debug!(request_id = %request_id, "Invoking module");
+
+    let (result, instance) = match state
+        .runner()
+        .execute_core_instance(&module, &mut store, ENTRY_POINT)
+        .await
+    {
+        Ok((result, instance)) => (Ok(result), Some(instance)),
+        Err(e) => (Err(e), None),
+    };
+    // Deferred work is not part of the invocation's profile.
+    if let Some(recorder) = recorder {
+        recorder.finish(&mut store);
+    }
+
+    let context = store.data_mut();
+    let logs = std::mem::take(&mut context.logs);
+    state.log_sinks().submit(module_id, &request_id, &logs);
+    let metrics = context.metrics.clone();
+    let mut exchange = context
+        .extensions_mut()
+        .remove::<Exchange>()
+        .unwrap_or_default();
+    let response = exchange.take_response().map(WasmHttpResponse::from);
+
+    // Deferred work only runs after a successful entry point call. It can
+    // still read the request, but no longer affects the response.
+    let deferred = match (&result, instance) {
+        (Ok(ExecutionResult::Success), Some(instance)) if store.data().has_deferred() => {
+            store
+                .data_mut()
+                .extensions_mut()
+                .insert(Exchange::new(exchange.request().clone()));
+            Some(spawn_deferred(
+                state.clone(),
+                store,
+                instance,
+                exec_config.clone(),
+            ))
+        }
+        _ => None,
+    };
+
+    let invocation = Invocation {
+        request_id,
+        result,
+        logs,
+        metrics,
+        response,
+        duration: start.elapsed(),
+        deferred,
+    };
+    state
+        .runtime_metrics()
+        .record_invocation(module_id, &invocation);
+    if let Some(stats) = stats {
+        stats.record(&invocation);
+    }
+    state.log_tail().publish(module_id, &invocation);
+
+    Ok((invocation, exchange))
+}
+
+/// Run deferred work in the background with the deferred budget.
+///
+/// The store is kept alive by the task so the same instance can be called.
+fn spawn_deferred(
+    state: AppState,
+    mut store: Store<WorkerContext>,
+    instance: Instance,
+    exec_config: ExecutionConfig,
+) -> JoinHandle<Vec<DeferredOutcome>> {
+    let request_id = store.data().request_id.clone();
+    let span = info_span!("deferred", request_id = %request_id);
+
+    tokio::spawn(
+        async move {
+            if let Err(e) = reset_for_deferred(&mut store, state.engine(), &exec_config) {
+                error!(request_id = %request_id, error = %e, "Failed to prepare deferred work");
+                return Vec::new();
+            }
+
+            let outcomes = state.runner().run_deferred(&instance, &mut store).await;
+
+            let context = store.data_mut();
+            let logs = std::mem::take(&mut context.logs);
+            let module_id = context.module_id.as_deref().unwrap_or_default();
+            state.log_sinks().submit(module_id, &request_id, &logs);
+            state.log_tail().publish_logs(module_id, &request_id, &logs);
+
+            debug!(
+                request_id = %request_id,
+                calls = outcomes.len(),
+                fuel_consumed = store.data().metrics.deferred_fuel_consumed,
+                "Deferred work finished"
+            );
+
+            outcomes
+        }
+        .instrument(span),
+    )
+}
+
+/// Convert log entries to JSON-serializable format.
+///
+/// Each entry has its level, message, fields as a JSON object and the
+/// wall-clock time it was recorded at (RFC 3339).
+pub fn logs_to_json(logs: &[LogEntry]) -> Vec<serde_json::Value> {
+    logs.iter()
+        .map(|l| {
+            serde_json::json!({
+                "timestamp": log_timestamp(l),
+                "level": l.level.to_string(),
+                "message": l.message,
+                "fields": log_fields(l),
+            })
+        })
+        .collect()
+}
+
+/// Wall-clock time of a log entry in RFC 3339 format.
+pub(crate) fn log_timestamp(entry: &LogEntry) -> String {
+    DateTime::<Utc>::from(entry.time).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Fields of a log entry as a JSON object.
+pub(crate) fn log_fields(entry: &LogEntry) -> serde_json::Map<String, serde_json::Value> {
+    entry
+        .fields
+        .iter()
+        .map(|(name, value)| (name.clone(), serde_json::Value::String(value.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::RuntimeConfig;
+
+    #[tokio::test]
+    async fn test_invoke_module_success() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat("ok", r#"(module (func (export "_start")))"#)
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/ok");
+        let invocation = invoke_module(&state, "ok", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.request_id, "req-1");
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_deferred() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "deferred",
+                r#"(module
+                    (import "env" "wait_until" (func $wait_until (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "flush")
+                    (func (export "_start")
+                        (drop (call $wait_until (i32.const 0) (i32.const 5))))
+                    (func (export "flush")))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/deferred");
+        let invocation = invoke_module(&state, "deferred", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        let outcomes = invocation.deferred.unwrap().await.unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(outcomes[0].result, Ok(ExecutionResult::Success)));
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_kv() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.set_module_permissions(
+            "kv",
+            edge_runtime_host::Permissions::builder()
+                .kv_namespace("app", 10, 1024, 4096)
+                .build(),
+        );
+        state
+            .load_module_wat(
+                "kv",
+                r#"(module
+                    (import "env" "kv_set" (func $set (param i32 i32 i32 i32 i64) (result i32)))
+                    (import "env" "kv_get" (func $get (param i32 i32 i32 i32) (result i32)))
+                    (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "greeting")
+                    (data (i32.const 16) "hello")
+                    (func (export "_start")
+                        (drop (call $set (i32.const 0) (i32.const 8) (i32.const 16) (i32.const 5) (i64.const 0)))
+                        (drop (call $write (i32.const 64)
+                            (call $get (i32.const 0) (i32.const 8) (i32.const 64) (i32.const 64))))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/kv");
+        let invocation = invoke_module(&state, "kv", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.kv_operations, 2);
+        assert_eq!(invocation.response.unwrap().body, b"hello");
+        assert_eq!(
+            state.kv().backend().get("app", "greeting").await.unwrap(),
+            Some(b"hello".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_cache() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "cached",
+                r#"(module
+                    (import "env" "cache_match" (func $match (param i32 i32 i32 i32) (result i32)))
+                    (import "env" "cache_put" (func $put (param i32 i32 i32 i32) (result i32)))
+                    (import "env" "response_set_header" (func $header (param i32 i32 i32 i32) (result i32)))
+                    (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "/page")
+                    (data (i32.const 16) "cache-control")
+                    (data (i32.const 32) "max-age=60")
+                    (data (i32.const 48) "fresh")
+                    (func (export "_start")
+                        (if (i32.eqz (call $match (i32.const 0) (i32.const 5) (i32.const 0) (i32.const 0)))
+                            (then
+                                (drop (call $header (i32.const 16) (i32.const 13) (i32.const 32) (i32.const 10)))
+                                (drop (call $write (i32.const 48) (i32.const 5)))
+                                (drop (call $put (i32.const 0) (i32.const 5) (i32.const 0) (i32.const 0)))))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/cached");
+        let first = invoke_module(&state, "cached", "req-1".into(), &request)
+            .await
+            .unwrap();
+        assert_eq!(first.metrics.cache_misses, 1);
+        assert_eq!(state.cache().usage("cached").entries, 1);
+
+        let second = invoke_module(&state, "cached", "req-2".into(), &request)
+            .await
+            .unwrap();
+        assert_eq!(second.metrics.cache_hits, 1);
+        assert_eq!(second.response.unwrap().body, b"fresh");
+
+        state.remove_module("cached");
+        assert_eq!(state.cache().usage("cached").entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_config() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "configured",
+                r#"(module
+                    (import "env" "config_get" (func $get (param i32 i32 i32 i32) (result i32)))
+                    (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "region")
+                    (func (export "_start")
+                        (drop (call $write (i32.const 64)
+                            (call $get (i32.const 0) (i32.const 6) (i32.const 64) (i32.const 64))))))"#,
+            )
+            .unwrap();
+        let set_region = |region: &str| {
+            state.config().set_values(
+                "configured",
+                [("region".to_string(), region.to_string())].into(),
+            );
+        };
+
+        let request = WasmHttpRequest::new("GET", "/functions/configured");
+        set_region("eu");
+        let first = invoke_module(&state, "configured", "req-1".into(), &request)
+            .await
+            .unwrap();
+        assert_eq!(first.response.unwrap().body, b"eu");
+
+        // Updates apply without reloading the module.
+        set_region("us-east");
+        let second = invoke_module(&state, "configured", "req-2".into(), &request)
+            .await
+            .unwrap();
+        assert_eq!(second.response.unwrap().body, b"us-east");
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_crypto() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "hash",
+                r#"(module
+                    (import "env" "crypto_digest" (func $digest (param i32 i32 i32 i32 i32) (result i32)))
+                    (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "abc")
+                    (func (export "_start")
+                        (drop (call $write (i32.const 64)
+                            (call $digest (i32.const 1) (i32.const 0) (i32.const 3) (i32.const 64) (i32.const 64))))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/hash");
+        let invocation = invoke_module(&state, "hash", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        // Host-side work is charged to the module's fuel.
+        assert!(invocation.metrics.fuel_consumed > 1_000);
+        let body = invocation.response.unwrap().body;
+        assert_eq!(body.len(), 32);
+        assert_eq!(body[..4], [0xba, 0x78, 0x16, 0xbf]);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_sleep() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "sleep",
+                r#"(module
+                    (import "env" "sleep" (func $sleep (param i32) (result i32)))
+                    (func (export "_start")
+                        (drop (call $sleep (i32.const 50)))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/sleep");
+        let invocation = invoke_module(&state, "sleep", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert!(invocation.metrics.sleep_duration >= Duration::from_millis(50));
+        // Sleeping consumes no fuel.
+        assert!(invocation.metrics.fuel_consumed < 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_socket_private_address_blocked() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.set_module_permissions(
+            "redis",
+            edge_runtime_host::Permissions::builder()
+                .allow_sockets(["*"], 4, 1024)
+                .build(),
+        );
+        state
+            .load_module_wat(
+                "redis",
+                r#"(module
+                    (import "env" "socket_connect" (func $connect (param i32 i32 i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "127.0.0.1")
+                    (func (export "_start")
+                        (if (i32.ne (call $connect (i32.const 0) (i32.const 9) (i32.const 6379) (i32.const 0))
+                                    (i32.const -2))
+                            (then unreachable))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/redis");
+        let invocation = invoke_module(&state, "redis", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.socket_connections, 0);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_websocket_private_address_blocked() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.set_module_permissions(
+            "chat",
+            edge_runtime_host::Permissions::builder()
+                .allow_http_hosts(["*"])
+                .websocket_limits(2, 100)
+                .build(),
+        );
+        state
+            .load_module_wat(
+                "chat",
+                r#"(module
+                    (import "env" "ws_connect" (func $connect (param i32 i32 i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "ws://127.0.0.1:9000/")
+                    (func (export "_start")
+                        (if (i32.ne (call $connect (i32.const 0) (i32.const 20) (i32.const 0) (i32.const 0))
+                                    (i32.const -2))
+                            (then unreachable))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/chat");
+        let invocation = invoke_module(&state, "chat", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.websocket_connections, 0);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_metrics() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "shop",
+                r#"(module
+                    (import "env" "metric_counter_add" (func $add (param i32 i32 i32 i32 f64) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "orders_total")
+                    (data (i32.const 16) "status: paid")
+                    (func (export "_start")
+                        (if (i32.ne (call $add (i32.const 0) (i32.const 12) (i32.const 16) (i32.const 12) (f64.const 2))
+                                    (i32.const 0))
+                            (then unreachable))
+                        (if (i32.ne (call $add (i32.const 0) (i32.const 12) (i32.const 0) (i32.const 0) (f64.const -1))
+                                    (i32.const -4))
+                            (then unreachable))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/shop");
+        for request_id in ["req-1", "req-2"] {
+            let invocation = invoke_module(&state, "shop", request_id.into(), &request)
+                .await
+                .unwrap();
+            assert!(invocation.is_success());
+        }
+
+        let text = state.guest_metrics().render();
+        assert!(text.contains("shop_orders_total{status=\"paid\"} 4\n"));
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_spans() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "traced",
+                r#"(module
+                    (import "env" "span_enter" (func $enter (param i32 i32 i32 i32) (result i32)))
+                    (import "env" "span_event" (func $event (param i32 i32 i32 i32 i32) (result i32)))
+                    (import "env" "span_exit" (func $exit (param i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "render")
+                    (data (i32.const 16) "template: home")
+                    (data (i32.const 32) "cache-miss")
+                    (func (export "_start")
+                        (local $span i32)
+                        (local.set $span (call $enter (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 14)))
+                        (if (i32.ne (local.get $span) (i32.const 1)) (then unreachable))
+                        (if (i32.ne (call $event (local.get $span) (i32.const 32) (i32.const 10) (i32.const 0) (i32.const 0))
+                                    (i32.const 0))
+                            (then unreachable))
+                        (if (i32.ne (call $exit (local.get $span)) (i32.const 0)) (then unreachable))
+                        (if (i32.ne (call $exit (local.get $span)) (i32.const -1)) (then unreachable))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/traced");
+        let invocation = invoke_module(&state, "traced", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_log_with_fields() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "logger",
+                r#"(module
+                    (import "env" "log_with_fields" (func $log (param i32 i32 i32 i32 i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "order placed")
+                    (data (i32.const 16) "order_id: 42\nstatus: paid")
+                    (func (export "_start")
+                        (call $log (i32.const 1) (i32.const 0) (i32.const 12) (i32.const 16) (i32.const 25))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/logger");
+        let invocation = invoke_module(&state, "logger", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        let logs = logs_to_json(&invocation.logs);
+        assert_eq!(logs[0]["message"], "order placed");
+        assert_eq!(logs[0]["fields"]["order_id"], "42");
+        assert_eq!(logs[0]["fields"]["status"], "paid");
+        assert!(logs[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_log_policy() {
+        use edge_runtime_core::store::LogLevel;
+        use edge_runtime_host::LogPolicy;
+
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.set_log_policy(
+            "logger",
+            LogPolicy {
+                min_level: LogLevel::Info,
+                max_entries: 3,
+                ..LogPolicy::default()
+            },
+        );
+        state
+            .load_module_wat(
+                "logger",
+                r#"(module
+                    (import "env" "log" (func $log (param i32 i32 i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "tick")
+                    (func (export "_start")
+                        (local $i i32)
+                        (call $log (i32.const 0) (i32.const 0) (i32.const 4))
+                        (loop $next
+                            (call $log (i32.const 1) (i32.const 0) (i32.const 4))
+                            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                            (br_if $next (i32.lt_u (local.get $i) (i32.const 10))))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/logger");
+        let invocation = invoke_module(&state, "logger", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        let logs = logs_to_json(&invocation.logs);
+        assert_eq!(logs.len(), 4);
+        assert_eq!(logs[0]["level"], "INFO");
+        assert_eq!(logs[3]["level"], "WARN");
+        assert_eq!(
+            logs[3]["message"],
+            "7 log entries dropped (per-request limit reached)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_sql() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.set_module_permissions(
+            "sql",
+            edge_runtime_host::Permissions::builder()
+                .sql_namespace("app", 10)
+                .build(),
+        );
+        state
+            .load_module_wat(
+                "sql",
+                r#"(module
+                    (import "env" "sql_execute" (func $execute (param i32 i32 i32 i32) (result i64)))
+                    (import "env" "sql_query" (func $query (param i32 i32 i32 i32 i32 i32) (result i32)))
+                    (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "CREATE TABLE t (x)")
+                    (data (i32.const 32) "INSERT INTO t VALUES (?1)")
+                    (data (i32.const 64) "[7]")
+                    (data (i32.const 80) "SELECT x FROM t")
+                    (func (export "_start")
+                        (drop (call $execute (i32.const 0) (i32.const 18) (i32.const 0) (i32.const 0)))
+                        (drop (call $execute (i32.const 32) (i32.const 25) (i32.const 64) (i32.const 3)))
+                        (drop (call $write (i32.const 256)
+                            (call $query (i32.const 80) (i32.const 15) (i32.const 0) (i32.const 0)
+                                (i32.const 256) (i32.const 256))))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/sql");
+        let invocation = invoke_module(&state, "sql", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.sql_statements, 3);
+        assert_eq!(
+            invocation.response.unwrap().body,
+            br#"{"columns":["x"],"rows":[[7]]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_blob() {
+        let dir = tempfile::tempdir().unwrap();
+        let blobs = edge_runtime_host::BlobStore::open(&edge_runtime_common::BlobConfig {
+            directory: Some(dir.path().to_string_lossy().into_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        let state = AppState::new(&RuntimeConfig::default())
+            .unwrap()
+            .with_blob_store(blobs);
+        state.set_module_permissions(
+            "blob",
+            edge_runtime_host::Permissions::builder()
+                .allow_blob_buckets(["files"], 10, 1024)
+                .build(),
+        );
+        state
+            .load_module_wat(
+                "blob",
+                r#"(module
+                    (import "env" "blob_put_start" (func $start (param i32 i32 i32 i32 i32 i32) (result i32)))
+                    (import "env" "blob_write" (func $write (param i32 i32 i32) (result i32)))
+                    (import "env" "blob_put_finish" (func $finish (param i32 i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "files")
+                    (data (i32.const 16) "note.txt")
+                    (data (i32.const 32) "hello ")
+                    (data (i32.const 48) "blob")
+                    (func (export "_start")
+                        (local $h i32)
+                        (local.set $h (call $start (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 8) (i32.const 0) (i32.const 0)))
+                        (drop (call $write (local.get $h) (i32.const 32) (i32.const 6)))
+                        (drop (call $write (local.get $h) (i32.const 48) (i32.const 4)))
+                        (drop (call $finish (local.get $h) (i32.const 0) (i32.const 0)))))"#,
+            )
+            .unwrap();
+
+        let request = WasmHttpRequest::new("POST", "/functions/blob");
+        let invocation = invoke_module(&state, "blob", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.blob_operations, 1);
+        let (meta, data) = state
+            .blobs()
+            .unwrap()
+            .read("files", "note.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(meta.size, 10);
+        assert_eq!(data, b"hello blob");
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_not_found() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/missing");
+        let result = invoke_module(&state, "missing", "req-1".into(), &request).await;
+
+        assert!(matches!(result, Err(e) if e.is_not_found()));
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_runtime_metrics() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat("ok", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        state
+            .load_module_wat("trap", r#"(module (func (export "_start") unreachable))"#)
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/ok");
+        for module_id in ["ok", "ok", "trap", "missing"] {
+            let _ = invoke_module(&state, module_id, "req-1".into(), &request).await;
+        }
+
+        let out = state.runtime_metrics().render(None);
+        assert!(out.contains("edge_requests_total{module=\"ok\",outcome=\"success\"} 2\n"));
+        assert!(out.contains("edge_requests_total{module=\"trap\",outcome=\"trap\"} 1\n"));
+        assert!(out.contains("edge_requests_total{module=\"missing\",outcome=\"not_found\"} 1\n"));
+        assert!(out.contains("edge_fuel_consumed_count{module=\"ok\"} 2\n"));
+        assert!(out.contains("edge_pooling_instances_in_use{module=\"ok\"} 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_invoke_module_stats() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat(
+                "api",
+                r#"(module (memory (export "memory") 2) (func (export "_start")))"#,
+            )
+            .unwrap();
+        state
+            .load_module_wat("trap", r#"(module (func (export "_start") unreachable))"#)
+            .unwrap();
+
+        let request = WasmHttpRequest::new("GET", "/functions/api");
+        for module_id in ["api", "api", "trap"] {
+            invoke_module(&state, module_id, "req-1".into(), &request)
+                .await
+                .unwrap();
+        }
+
+        let api = state.module_stats("api").unwrap().snapshot();
+        assert_eq!(api.invocations, 2);
+        assert!(api.errors.values().all(|count| *count == 0));
+        assert_eq!(api.peak_memory_bytes, 2 * 65536);
+        assert!(api.fuel.max > 0);
+        assert!(api.last_invocation.is_some());
+
+        let trap = state.module_stats("trap").unwrap().snapshot();
+        assert_eq!(trap.errors["trap"], 1);
+
+        // Reloading resets the statistics; removing drops them.
+        state
+            .load_module_wat("api", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        assert_eq!(state.module_stats("api").unwrap().invocations(), 0);
+        state.remove_module("api");
+        assert!(state.module_stats("api").is_none());
+    }
+}
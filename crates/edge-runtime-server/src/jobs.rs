@@ -0,0 +1,690 @@
+//! Asynchronous invocation queue.
+//!
+//! This module provides [`JobQueue`] and its HTTP handlers, allowing
+//! functions that outlive the HTTP request timeout to run in the background:
+//!
+//! - `POST /functions/:id/async` - Enqueue an invocation, returns `202` with a job ID
+//! - `GET /jobs/:job_id` - Get job status, logs, metrics and output
+//!
+//! # Execution Model
+//!
+//! Jobs are executed by a bounded worker pool. At most
+//! [`JobsConfig::workers`] jobs run concurrently and at most
+//! [`JobsConfig::max_queue_depth`] jobs wait for a worker; further
+//! submissions are rejected with `429`. Finished jobs are kept for
+//! [`JobsConfig::retention_secs`] and then discarded.
+//!
+//! Jobs that trap are retried up to [`JobsConfig::max_retries`] times with
+//! exponential backoff. Jobs that time out or run out of fuel are not
+//! retried, since another attempt would fail the same way.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::to_bytes;
+use axum::extract::{Path, Request, State};
+use axum::response::IntoResponse;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use edge_runtime_common::{ExecutionConfig, JobsConfig, RuntimeError};
+use edge_runtime_core::ExecutionResult;
+
+use crate::handler::MAX_REQUEST_BODY_BYTES;
+use crate::invocation::{Invocation, InvocationOutcome, invoke_module_with_config, logs_to_json};
+use crate::request::WasmHttpRequest;
+use crate::response::WasmHttpResponse;
+use crate::state::AppState;
+
+/// Maximum delay between retries.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Bounded queue of asynchronous invocations.
+///
+/// Cloning is cheap; all clones share the same queue.
+#[derive(Clone)]
+pub struct JobQueue {
+    inner: Arc<JobQueueInner>,
+}
+
+struct JobQueueInner {
+    /// Queue configuration.
+    config: JobsConfig,
+    /// Job records (job_id -> record).
+    jobs: DashMap<String, JobRecord>,
+    /// Permits for jobs waiting for a worker.
+    queue_slots: Arc<Semaphore>,
+    /// Permits for running jobs.
+    workers: Arc<Semaphore>,
+}
+
+/// Lifecycle state of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for a worker.
+    Queued,
+    /// Currently executing (including retry backoff).
+    Running,
+    /// The entry point completed successfully.
+    Succeeded,
+    /// The job trapped on its last attempt or failed with an error.
+    Failed,
+}
+
+/// Status record of a job.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRecord {
+    /// Job ID.
+    pub job_id: String,
+    /// Module the job invokes.
+    pub module_id: String,
+    /// Current status.
+    pub status: JobStatus,
+    /// Number of attempts started so far.
+    pub attempts: u32,
+    /// Request ID of the most recent attempt.
+    pub request_id: Option<String>,
+    /// When the job was submitted (RFC 3339).
+    pub created_at: String,
+    /// When the first attempt started (RFC 3339).
+    pub started_at: Option<String>,
+    /// When the job finished (RFC 3339).
+    pub finished_at: Option<String>,
+    /// Guest logs of the most recent attempt.
+    pub logs: Vec<serde_json::Value>,
+    /// Metrics of the most recent attempt.
+    pub metrics: Option<JobMetrics>,
+    /// Result of the most recent attempt, including the response the guest
+    /// wrote, if any.
+    pub output: Option<serde_json::Value>,
+
+    /// Instant the job finished, used for retention.
+    #[serde(skip)]
+    finished: Option<Instant>,
+}
+
+/// Metrics reported for a job attempt.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct JobMetrics {
+    /// Fuel consumed.
+    pub fuel_consumed: u64,
+    /// Execution duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// Reasons a job cannot be submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitError {
+    /// The module is not loaded.
+    ModuleNotFound,
+    /// The queue is at its maximum depth.
+    QueueFull,
+}
+
+impl JobQueue {
+    /// Create a new job queue.
+    pub fn new(config: JobsConfig) -> Self {
+        Self {
+            inner: Arc::new(JobQueueInner {
+                queue_slots: Arc::new(Semaphore::new(config.max_queue_depth)),
+                workers: Arc::new(Semaphore::new(config.workers.max(1))),
+                jobs: DashMap::new(),
+                config,
+            }),
+        }
+    }
+
+    /// Get the queue configuration.
+    pub fn config(&self) -> &JobsConfig {
+        &self.inner.config
+    }
+
+    /// Enqueue an invocation of `module_id`.
+    ///
+    /// Returns the job ID. The job starts once a worker is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the module is not loaded or the queue is full.
+    pub fn submit(
+        &self,
+        state: &AppState,
+        module_id: &str,
+        request: WasmHttpRequest,
+    ) -> Result<String, SubmitError> {
+        self.prune_expired();
+
+        if state.get_module(module_id).is_none() {
+            return Err(SubmitError::ModuleNotFound);
+        }
+
+        let Ok(slot) = self.inner.queue_slots.clone().try_acquire_owned() else {
+            warn!(
+                module_id = %module_id,
+                max_queue_depth = self.inner.config.max_queue_depth,
+                "Job queue full"
+            );
+            return Err(SubmitError::QueueFull);
+        };
+
+        let job_id = Uuid::new_v4().to_string();
+        self.inner.jobs.insert(
+            job_id.clone(),
+            JobRecord {
+                job_id: job_id.clone(),
+                module_id: module_id.to_string(),
+                status: JobStatus::Queued,
+                attempts: 0,
+                request_id: None,
+                created_at: Utc::now().to_rfc3339(),
+                started_at: None,
+                finished_at: None,
+                logs: Vec::new(),
+                metrics: None,
+                output: None,
+                finished: None,
+            },
+        );
+
+        info!(job_id = %job_id, module_id = %module_id, "Job queued");
+
+        let queue = self.clone();
+        let state = state.clone();
+        let module_id = module_id.to_string();
+        let id = job_id.clone();
+
+        tokio::spawn(async move {
+            let Ok(_worker) = queue.inner.workers.clone().acquire_owned().await else {
+                return;
+            };
+            drop(slot);
+            queue.run(&state, &id, &module_id, &request).await;
+        });
+
+        Ok(job_id)
+    }
+
+    /// Get a job record.
+    pub fn get(&self, job_id: &str) -> Option<JobRecord> {
+        self.prune_expired();
+        self.inner.jobs.get(job_id).map(|r| r.clone())
+    }
+
+    /// Number of jobs waiting for a worker.
+    pub fn queued_count(&self) -> usize {
+        self.inner.config.max_queue_depth - self.inner.queue_slots.available_permits()
+    }
+
+    /// Execute a job, retrying traps with exponential backoff.
+    async fn run(
+        &self,
+        state: &AppState,
+        job_id: &str,
+        module_id: &str,
+        request: &WasmHttpRequest,
+    ) {
+        let exec_config = self.exec_config(state);
+        let mut backoff = Duration::from_millis(self.inner.config.retry_backoff_ms);
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let request_id = Uuid::new_v4().to_string();
+
+            self.update(job_id, |record| {
+                record.status = JobStatus::Running;
+                record.attempts = attempt;
+                record.request_id = Some(request_id.clone());
+                record
+                    .started_at
+                    .get_or_insert_with(|| Utc::now().to_rfc3339());
+            });
+
+            info!(job_id = %job_id, request_id = %request_id, attempt, "Job started");
+
+            let result =
+                invoke_module_with_config(state, module_id, request_id, request, &exec_config)
+                    .await;
+
+            let retry = is_retryable(&result) && attempt <= self.inner.config.max_retries;
+
+            self.record_attempt(job_id, result, retry);
+
+            if !retry {
+                break;
+            }
+
+            warn!(
+                job_id = %job_id,
+                attempt,
+                backoff_ms = backoff.as_millis(),
+                "Job trapped, retrying"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        }
+    }
+
+    /// Store the outcome of an attempt.
+    fn record_attempt(
+        &self,
+        job_id: &str,
+        result: Result<Invocation, RuntimeError>,
+        retrying: bool,
+    ) {
+        self.update(job_id, |record| {
+            let status = match result {
+                Ok(invocation) => {
+                    record.logs = logs_to_json(&invocation.logs);
+                    record.metrics = Some(JobMetrics {
+                        fuel_consumed: invocation.metrics.fuel_consumed,
+                        duration_ms: u64::try_from(invocation.duration.as_millis())
+                            .unwrap_or(u64::MAX),
+                    });
+                    let (status, mut output) = result_output(invocation.result);
+                    if let Some(response) = &invocation.response {
+                        output["response"] = response_output(response);
+                    }
+                    record.output = Some(output);
+                    status
+                }
+                Err(e) => {
+                    record.output = Some(error_output(&e));
+                    JobStatus::Failed
+                }
+            };
+
+            if !retrying {
+                record.status = status;
+                record.finished_at = Some(Utc::now().to_rfc3339());
+                record.finished = Some(Instant::now());
+                info!(job_id = %record.job_id, status = ?status, "Job finished");
+            }
+        });
+    }
+
+    /// Apply `f` to a job record if it still exists.
+    fn update(&self, job_id: &str, f: impl FnOnce(&mut JobRecord)) {
+        if let Some(mut record) = self.inner.jobs.get_mut(job_id) {
+            f(&mut record);
+        }
+    }
+
+    /// Execution configuration for jobs.
+    fn exec_config(&self, state: &AppState) -> ExecutionConfig {
+        let mut config = state.exec_config().clone();
+        if let Some(timeout_ms) = self.inner.config.timeout_ms {
+            config.timeout_ms = timeout_ms;
+        }
+        config
+    }
+
+    /// Discard finished jobs older than the retention period.
+    fn prune_expired(&self) {
+        let retention = Duration::from_secs(self.inner.config.retention_secs);
+        self.inner
+            .jobs
+            .retain(|_, record| record.finished.is_none_or(|t| t.elapsed() < retention));
+    }
+}
+
+/// Whether an attempt trapped in a way another attempt might not.
+///
+/// Timeouts and fuel exhaustion are not retried: the next attempt runs with
+/// the same budget.
+fn is_retryable(result: &Result<Invocation, RuntimeError>) -> bool {
+    matches!(result, Ok(invocation) if invocation.outcome() == InvocationOutcome::Trap)
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new(JobsConfig::default())
+    }
+}
+
+impl std::fmt::Debug for JobQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobQueue")
+            .field("jobs_count", &self.inner.jobs.len())
+            .field("queued", &self.queued_count())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Convert an execution result to a job status and output value.
+fn result_output(result: Result<ExecutionResult, RuntimeError>) -> (JobStatus, serde_json::Value) {
+    match result {
+        Ok(ExecutionResult::Success) => {
+            (JobStatus::Succeeded, serde_json::json!({ "success": true }))
+        }
+        Ok(ExecutionResult::Trap { message, code }) => (
+            JobStatus::Failed,
+            serde_json::json!({
+                "success": false,
+                "error": {
+                    "type": "trap",
+                    "message": message,
+                    "code": code,
+                }
+            }),
+        ),
+        Err(e) => (JobStatus::Failed, error_output(&e)),
+    }
+}
+
+/// Convert the response written by the guest to a job output value.
+///
+/// Bodies that are not valid UTF-8 are stored base64-encoded under
+/// `body_base64` instead of `body`.
+fn response_output(response: &WasmHttpResponse) -> serde_json::Value {
+    let mut output = serde_json::json!({
+        "status": response.status,
+        "headers": response.headers,
+    });
+    match std::str::from_utf8(&response.body) {
+        Ok(body) => output["body"] = body.into(),
+        Err(_) => output["body_base64"] = BASE64.encode(&response.body).into(),
+    }
+    output
+}
+
+/// Convert a runtime error to a job output value.
+fn error_output(error: &RuntimeError) -> serde_json::Value {
+    serde_json::json!({
+        "success": false,
+        "error": {
+            "type": "error",
+            "message": error.to_string(),
+        }
+    })
+}
+
+/// Enqueue an asynchronous function invocation.
+///
+/// # Request
+///
+/// `POST /functions/:function_id/async`
+///
+/// # Response
+///
+/// `202 Accepted`
+///
+/// ```json
+/// {
+///   "job_id": "6f1c...",
+///   "status": "queued",
+///   "status_url": "/jobs/6f1c..."
+/// }
+/// ```
+///
+/// Returns `404` if the function is not loaded, `413` if the request body
+/// exceeds [`MAX_REQUEST_BODY_BYTES`] and `429` if the queue is full.
+#[instrument(skip(state, request), fields(function_id = %function_id))]
+pub async fn submit_async(
+    State(state): State<AppState>,
+    Path(function_id): Path<String>,
+    request: Request,
+) -> impl IntoResponse {
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_REQUEST_BODY_BYTES).await else {
+        return WasmHttpResponse::error(413, "Request body too large").into_axum_response();
+    };
+    let request = WasmHttpRequest::from_axum(&Request::from_parts(parts, ()), body);
+
+    match state.jobs().submit(&state, &function_id, request) {
+        Ok(job_id) => {
+            let body = serde_json::json!({
+                "job_id": job_id,
+                "status": JobStatus::Queued,
+                "status_url": format!("/jobs/{job_id}"),
+            });
+            WasmHttpResponse::json(202, &body.to_string()).into_axum_response()
+        }
+        Err(SubmitError::ModuleNotFound) => {
+            WasmHttpResponse::error(404, &format!("Function '{function_id}' not found"))
+                .into_axum_response()
+        }
+        Err(SubmitError::QueueFull) => {
+            WasmHttpResponse::error(429, "Job queue is full").into_axum_response()
+        }
+    }
+}
+
+/// Get the status of an asynchronous invocation.
+///
+/// # Request
+///
+/// `GET /jobs/:job_id`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "job_id": "6f1c...",
+///   "module_id": "report",
+///   "status": "succeeded",
+///   "attempts": 1,
+///   "request_id": "...",
+///   "created_at": "2025-01-01T00:00:00+00:00",
+///   "started_at": "2025-01-01T00:00:00+00:00",
+///   "finished_at": "2025-01-01T00:00:05+00:00",
+///   "logs": [],
+///   "metrics": { "fuel_consumed": 1200, "duration_ms": 5000 },
+///   "output": {
+///     "success": true,
+///     "response": { "status": 200, "headers": [["content-type", "text/plain"]], "body": "done" }
+///   }
+/// }
+/// ```
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+) -> impl IntoResponse {
+    match state.jobs().get(&job_id) {
+        Some(record) => axum::Json(record).into_response(),
+        None => {
+            WasmHttpResponse::error(404, &format!("Job '{job_id}' not found")).into_axum_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::RuntimeConfig;
+    use edge_runtime_core::ExecutionMetrics;
+
+    fn test_state(config: JobsConfig) -> AppState {
+        let state = AppState::new(&RuntimeConfig::default())
+            .unwrap()
+            .with_jobs_config(config);
+        state
+            .load_module_wat("ok", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        state
+            .load_module_wat("trap", r#"(module (func (export "_start") unreachable))"#)
+            .unwrap();
+        state
+            .load_module_wat(
+                "spin",
+                r#"(module (func (export "_start") (loop $l (br $l))))"#,
+            )
+            .unwrap();
+        state
+            .load_module_wat(
+                "echo",
+                r#"(module
+                    (import "env" "request_body" (func $body (param i32 i32) (result i32)))
+                    (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+                    (memory (export "memory") 1)
+                    (func (export "_start")
+                        (drop (call $write (i32.const 0)
+                            (call $body (i32.const 0) (i32.const 1024))))))"#,
+            )
+            .unwrap();
+        state
+    }
+
+    async fn wait_for_finish(queue: &JobQueue, job_id: &str) -> JobRecord {
+        for _ in 0..200 {
+            let record = queue.get(job_id).unwrap();
+            if record.finished_at.is_some() {
+                return record;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job did not finish");
+    }
+
+    #[tokio::test]
+    async fn test_submit_and_complete() {
+        let state = test_state(JobsConfig::default());
+        let request = WasmHttpRequest::new("POST", "/functions/ok/async");
+
+        let job_id = state.jobs().submit(&state, "ok", request).unwrap();
+        let record = wait_for_finish(state.jobs(), &job_id).await;
+
+        assert_eq!(record.status, JobStatus::Succeeded);
+        assert_eq!(record.attempts, 1);
+        assert!(record.metrics.is_some());
+        assert_eq!(record.output.unwrap()["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_submit_async_forwards_body() {
+        let state = test_state(JobsConfig::default());
+        let request = Request::post("/functions/echo/async")
+            .body(axum::body::Body::from("hello"))
+            .unwrap();
+
+        let response = submit_async(State(state.clone()), Path("echo".into()), request)
+            .await
+            .into_response();
+        assert_eq!(response.status(), 202);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let record = wait_for_finish(state.jobs(), body["job_id"].as_str().unwrap()).await;
+        let output = record.output.unwrap();
+        assert_eq!(output["success"], true);
+        assert_eq!(output["response"]["status"], 200);
+        assert_eq!(output["response"]["body"], "hello");
+    }
+
+    #[test]
+    fn test_response_output_binary_body() {
+        let response = WasmHttpResponse {
+            status: 200,
+            headers: vec![("content-type".into(), "application/octet-stream".into())],
+            body: vec![0xff, 0x00],
+        };
+
+        let output = response_output(&response);
+        assert_eq!(output["headers"][0][1], "application/octet-stream");
+        assert_eq!(output["body_base64"], "/wA=");
+        assert!(output.get("body").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_submit_unknown_module() {
+        let state = test_state(JobsConfig::default());
+        let request = WasmHttpRequest::new("POST", "/functions/missing/async");
+
+        let result = state.jobs().submit(&state, "missing", request);
+        assert_eq!(result.unwrap_err(), SubmitError::ModuleNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let state = test_state(JobsConfig {
+            max_queue_depth: 0,
+            ..Default::default()
+        });
+        let request = WasmHttpRequest::new("POST", "/functions/ok/async");
+
+        let result = state.jobs().submit(&state, "ok", request);
+        assert_eq!(result.unwrap_err(), SubmitError::QueueFull);
+    }
+
+    #[tokio::test]
+    async fn test_trap_retried() {
+        let state = test_state(JobsConfig {
+            max_retries: 2,
+            retry_backoff_ms: 1,
+            ..Default::default()
+        });
+        let request = WasmHttpRequest::new("POST", "/functions/trap/async");
+
+        let job_id = state.jobs().submit(&state, "trap", request).unwrap();
+        let record = wait_for_finish(state.jobs(), &job_id).await;
+
+        assert_eq!(record.status, JobStatus::Failed);
+        assert_eq!(record.attempts, 3);
+        assert_eq!(record.output.unwrap()["error"]["type"], "trap");
+    }
+
+    #[tokio::test]
+    async fn test_fuel_exhausted_not_retried() {
+        let state = test_state(JobsConfig {
+            max_retries: 2,
+            retry_backoff_ms: 1,
+            ..Default::default()
+        });
+        let request = WasmHttpRequest::new("POST", "/functions/spin/async");
+
+        let job_id = state.jobs().submit(&state, "spin", request).unwrap();
+        let record = wait_for_finish(state.jobs(), &job_id).await;
+
+        assert_eq!(record.status, JobStatus::Failed);
+        assert_eq!(record.attempts, 1);
+    }
+
+    #[test]
+    fn test_exhausted_traps_not_retryable() {
+        let trapped = |code: Option<&str>| {
+            Ok(Invocation {
+                request_id: "req-1".into(),
+                result: Ok(ExecutionResult::Trap {
+                    message: "trap".into(),
+                    code: code.map(Into::into),
+                }),
+                logs: Vec::new(),
+                metrics: ExecutionMetrics::default(),
+                response: None,
+                duration: Duration::ZERO,
+                deferred: None,
+            })
+        };
+
+        assert!(is_retryable(&trapped(Some("UnreachableCodeReached"))));
+        assert!(is_retryable(&trapped(None)));
+        assert!(!is_retryable(&trapped(Some("Interrupt"))));
+        assert!(!is_retryable(&trapped(Some("OutOfFuel"))));
+        assert!(!is_retryable(&Err(RuntimeError::FuelExhausted)));
+    }
+
+    #[tokio::test]
+    async fn test_retention_expires_finished_jobs() {
+        let state = test_state(JobsConfig {
+            retention_secs: 0,
+            ..Default::default()
+        });
+        let request = WasmHttpRequest::new("POST", "/functions/ok/async");
+
+        let job_id = state.jobs().submit(&state, "ok", request).unwrap();
+        for _ in 0..200 {
+            if state.jobs().get(&job_id).is_none() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("finished job was not discarded");
+    }
+}
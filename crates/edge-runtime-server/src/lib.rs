@@ -7,7 +7,17 @@
 //! - Request/response transformation
 //! - WebAssembly module execution
 //! - Health and readiness checks
+//! - Prometheus metrics endpoint for runtime and module-recorded metrics
 //! - Admin API for module management
+//! - Scheduled (cron) invocation of modules
+//! - Asynchronous invocation queue with job status API
+//! - In-process module-to-module calls (service bindings)
+//! - Middleware pipelines composed from multiple modules
+//! - Guest log sinks: rotating files, syslog and HTTP export
+//! - Live tail of guest logs and request summaries
+//! - Per-module invocation, latency, fuel and memory statistics
+//! - Access log of function invocations in JSON or Common/Combined Log Format
+//! - Guest CPU profiling in the Firefox Profiler format
 //!
 //! # Quick Start
 //!
@@ -27,15 +37,34 @@
 //! }
 //! ```
 
+pub mod access_log;
 pub mod admin;
 pub mod handler;
+pub mod invocation;
+pub mod jobs;
+pub mod log_sinks;
+pub mod log_tail;
+pub mod metrics;
+pub mod module_stats;
+pub mod pipeline;
+pub mod profiling;
 pub mod request;
 pub mod response;
 pub mod router;
+pub mod scheduler;
 pub mod server;
+mod service;
 pub mod state;
 
+pub use access_log::AccessLog;
 pub use admin::{AdminState, build_admin_router};
+pub use jobs::JobQueue;
+pub use log_sinks::{LogSink, LogSinks};
+pub use log_tail::LogTail;
+pub use module_stats::{ModuleStats, ModuleStatsSnapshot};
+pub use pipeline::{Pipeline, Pipelines};
+pub use profiling::Profiler;
 pub use router::{AdminRouterConfig, build_router_with_admin};
+pub use scheduler::Scheduler;
 pub use server::{EdgeServer, ServerConfig};
 pub use state::AppState;
@@ -0,0 +1,771 @@
+//! Guest log sinks.
+//!
+//! This module provides [`LogSinks`], which forwards the guest logs of each
+//! invocation to pluggable [`LogSink`]s after the invocation completes:
+//!
+//! - [`FileSink`]: rotating JSON-lines files, one per module
+//! - [`SyslogSink`]: RFC 5424 messages over a local datagram socket
+//! - [`HttpSink`]: batches of JSON lines posted to a collector
+//!
+//! # Backpressure
+//!
+//! Each sink runs in its own task behind a bounded queue of
+//! [`LoggingConfig::sink_buffer`] batches. Submitting never waits: if a
+//! sink's queue is full, the batch is dropped for that sink and counted in
+//! [`LogSinks::dropped`]. A slow or failing sink thereby loses logs but
+//! never delays request handling or the other sinks.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use edge_runtime_common::{
+    FileSinkConfig, HttpSinkConfig, LoggingConfig, RuntimeError, SyslogSinkConfig,
+};
+use edge_runtime_core::store::{LogEntry, LogLevel};
+
+use crate::invocation::{log_fields, log_timestamp};
+
+/// Guest logs of one invocation.
+#[derive(Debug, Clone)]
+pub struct LogBatch {
+    /// Module that emitted the logs.
+    pub module_id: String,
+    /// Request ID the invocation was tagged with.
+    pub request_id: String,
+    /// Log entries, in emission order.
+    pub entries: Vec<LogEntry>,
+}
+
+impl LogBatch {
+    /// Format an entry as a JSON line, without the trailing newline.
+    pub fn json_line(&self, entry: &LogEntry) -> String {
+        serde_json::json!({
+            "timestamp": log_timestamp(entry),
+            "module_id": self.module_id,
+            "request_id": self.request_id,
+            "level": entry.level.to_string(),
+            "message": entry.message,
+            "fields": log_fields(entry),
+        })
+        .to_string()
+    }
+}
+
+/// Destination of guest logs.
+///
+/// A sink is owned by its own task, which calls [`write`](Self::write) for
+/// each batch and [`flush`](Self::flush) periodically and on shutdown.
+#[async_trait]
+pub trait LogSink: Send + 'static {
+    /// Name of the sink, used in diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// Write the logs of one invocation.
+    async fn write(&mut self, batch: &LogBatch) -> io::Result<()>;
+
+    /// Write out buffered logs.
+    async fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Interval at which [`flush`](Self::flush) is called.
+    fn flush_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+}
+
+/// Queue of a running sink.
+struct SinkHandle {
+    name: &'static str,
+    sender: mpsc::Sender<Arc<LogBatch>>,
+    dropped: AtomicU64,
+}
+
+/// Set of running log sinks.
+///
+/// Cloning is cheap; all clones feed the same sinks. Without sinks,
+/// [`submit`](Self::submit) does nothing.
+#[derive(Clone, Default)]
+pub struct LogSinks {
+    sinks: Vec<Arc<SinkHandle>>,
+}
+
+impl LogSinks {
+    /// Create an empty set of sinks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the sinks enabled in `config`.
+    ///
+    /// Must be called from within a Tokio runtime if any sink is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file sink's directory cannot be created or
+    /// the HTTP client cannot be built.
+    pub fn open(config: &LoggingConfig) -> Result<Self, RuntimeError> {
+        let mut sinks = Self::new();
+        if let Some(file) = &config.file {
+            sinks = sinks.with_sink(FileSink::new(file)?, config.sink_buffer);
+        }
+        if let Some(syslog) = &config.syslog {
+            sinks = sinks.with_sink(SyslogSink::new(syslog), config.sink_buffer);
+        }
+        if let Some(http) = &config.http {
+            sinks = sinks.with_sink(HttpSink::new(http)?, config.sink_buffer);
+        }
+        Ok(sinks)
+    }
+
+    /// Start a sink in its own task, buffering up to `buffer` batches.
+    ///
+    /// Must be called from within a Tokio runtime.
+    #[must_use]
+    pub fn with_sink(mut self, sink: impl LogSink, buffer: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(buffer.max(1));
+        self.sinks.push(Arc::new(SinkHandle {
+            name: sink.name(),
+            sender,
+            dropped: AtomicU64::new(0),
+        }));
+        tokio::spawn(run_sink(sink, receiver));
+        self
+    }
+
+    /// Returns `true` if no sink is running.
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Queue the logs of an invocation for every sink.
+    ///
+    /// Never waits; sinks whose queue is full drop the batch.
+    pub fn submit(&self, module_id: &str, request_id: &str, entries: &[LogEntry]) {
+        if self.sinks.is_empty() || entries.is_empty() {
+            return;
+        }
+
+        let batch = Arc::new(LogBatch {
+            module_id: module_id.to_string(),
+            request_id: request_id.to_string(),
+            entries: entries.to_vec(),
+        });
+        for sink in &self.sinks {
+            if sink.sender.try_send(batch.clone()).is_err() {
+                let dropped = sink.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped.is_power_of_two() {
+                    warn!(
+                        sink = sink.name,
+                        dropped, "Log sink is falling behind, dropping logs"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Number of batches dropped so far, summed over all sinks.
+    pub fn dropped(&self) -> u64 {
+        self.sinks
+            .iter()
+            .map(|sink| sink.dropped.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+impl std::fmt::Debug for LogSinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self.sinks.iter().map(|sink| sink.name).collect();
+        f.debug_struct("LogSinks").field("sinks", &names).finish()
+    }
+}
+
+/// Feed a sink until all senders are dropped, then flush it.
+async fn run_sink(mut sink: impl LogSink, mut receiver: mpsc::Receiver<Arc<LogBatch>>) {
+    let mut ticker = tokio::time::interval(sink.flush_interval());
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            batch = receiver.recv() => {
+                let Some(batch) = batch else { break };
+                if let Err(e) = sink.write(&batch).await {
+                    warn!(sink = sink.name(), error = %e, "Failed to write guest logs");
+                }
+            }
+            _ = ticker.tick() => {
+                if let Err(e) = sink.flush().await {
+                    warn!(sink = sink.name(), error = %e, "Failed to flush guest logs");
+                }
+            }
+        }
+    }
+
+    if let Err(e) = sink.flush().await {
+        warn!(sink = sink.name(), error = %e, "Failed to flush guest logs");
+    }
+}
+
+/// Rotating JSON-lines files, one per module.
+///
+/// A module's logs are appended to `<directory>/<module>.jsonl`, rotated
+/// as a [`RotatingFile`] by [`FileSinkConfig::max_file_bytes`] and
+/// [`FileSinkConfig::max_files`].
+pub struct FileSink {
+    directory: PathBuf,
+    max_file_bytes: u64,
+    max_files: u32,
+    /// Files by file stem.
+    files: HashMap<String, RotatingFile>,
+}
+
+impl FileSink {
+    /// Create a file sink, creating its directory if missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn new(config: &FileSinkConfig) -> Result<Self, RuntimeError> {
+        std::fs::create_dir_all(&config.directory).map_err(|e| {
+            RuntimeError::invalid_config(format!(
+                "Failed to create log directory {}: {e}",
+                config.directory
+            ))
+        })?;
+        Ok(Self {
+            directory: PathBuf::from(&config.directory),
+            max_file_bytes: config.max_file_bytes,
+            max_files: config.max_files,
+            files: HashMap::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl LogSink for FileSink {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn write(&mut self, batch: &LogBatch) -> io::Result<()> {
+        let stem = file_stem(&batch.module_id);
+        let mut lines = String::new();
+        for entry in &batch.entries {
+            lines.push_str(&batch.json_line(entry));
+            lines.push('\n');
+        }
+
+        let file = self.files.entry(stem).or_insert_with_key(|stem| {
+            RotatingFile::new(
+                self.directory.join(format!("{stem}.jsonl")),
+                self.max_file_bytes,
+                self.max_files,
+            )
+        });
+        file.write(lines.as_bytes()).await
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        for file in self.files.values_mut() {
+            file.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Append-only file rotated by size.
+///
+/// When a write would grow the file beyond `max_bytes`, the file is
+/// renamed to `<path>.1`, shifting older files up to `<path>.<max_files>`
+/// and deleting the oldest, and a new file is started.
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    /// Open file and its size.
+    file: Option<(tokio::fs::File, u64)>,
+}
+
+impl RotatingFile {
+    /// Create a rotating file; it is opened on the first write.
+    pub(crate) fn new(path: PathBuf, max_bytes: u64, max_files: u32) -> Self {
+        Self {
+            path,
+            max_bytes,
+            max_files,
+            file: None,
+        }
+    }
+
+    /// Append bytes, rotating first if they would not fit.
+    pub(crate) async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let len = bytes.len() as u64;
+        let (_, size) = self.open().await?;
+        if *size > 0 && *size + len > self.max_bytes {
+            self.rotate().await?;
+        }
+        let (file, size) = self.open().await?;
+        file.write_all(bytes).await?;
+        *size += len;
+        Ok(())
+    }
+
+    /// Flush the open file.
+    pub(crate) async fn flush(&mut self) -> io::Result<()> {
+        if let Some((file, _)) = &mut self.file {
+            file.flush().await?;
+        }
+        Ok(())
+    }
+
+    fn generation(&self, generation: u32) -> PathBuf {
+        if generation == 0 {
+            return self.path.clone();
+        }
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{generation}"));
+        PathBuf::from(path)
+    }
+
+    /// Shift the rotated files; the next write starts a new file.
+    async fn rotate(&mut self) -> io::Result<()> {
+        if let Some((mut file, _)) = self.file.take() {
+            file.flush().await?;
+        }
+        if self.max_files == 0 {
+            return tokio::fs::remove_file(&self.path).await;
+        }
+        for generation in (0..self.max_files).rev() {
+            let from = self.generation(generation);
+            if tokio::fs::try_exists(&from).await? {
+                tokio::fs::rename(&from, self.generation(generation + 1)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The open file and its size, opening it if needed.
+    async fn open(&mut self) -> io::Result<&mut (tokio::fs::File, u64)> {
+        if self.file.is_none() {
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+            let size = file.metadata().await?.len();
+            self.file = Some((file, size));
+        }
+        Ok(self.file.as_mut().expect("file was just opened"))
+    }
+}
+
+/// File name stem of a module's logs; characters other than ASCII
+/// alphanumerics, `-` and `_` are replaced by `_`.
+fn file_stem(module_id: &str) -> String {
+    module_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// RFC 5424 syslog messages over a Unix datagram socket.
+///
+/// The module ID is the message ID and the request ID is sent as
+/// structured data; fields are appended to the message as a JSON object.
+pub struct SyslogSink {
+    socket: PathBuf,
+    facility: u8,
+    app_name: String,
+    #[cfg(unix)]
+    connection: Option<tokio::net::UnixDatagram>,
+}
+
+impl SyslogSink {
+    /// Create a syslog sink. The socket is connected on first use.
+    pub fn new(config: &SyslogSinkConfig) -> Self {
+        Self {
+            socket: PathBuf::from(&config.socket),
+            facility: config.facility,
+            app_name: config.app_name.clone(),
+            #[cfg(unix)]
+            connection: None,
+        }
+    }
+
+    /// Format an entry as an RFC 5424 message.
+    fn message(&self, batch: &LogBatch, entry: &LogEntry) -> String {
+        let severity = match entry.level {
+            LogLevel::Debug => 7,
+            LogLevel::Info => 6,
+            LogLevel::Warn => 4,
+            LogLevel::Error => 3,
+        };
+        let priority = u32::from(self.facility.min(23)) * 8 + severity;
+        let msg_id = syslog_name(&batch.module_id, 32);
+        let mut message = format!(
+            "<{priority}>1 {} - {} {} {msg_id} [edge request_id=\"{}\"] {}",
+            log_timestamp(entry),
+            syslog_name(&self.app_name, 48),
+            std::process::id(),
+            escape_param(&batch.request_id),
+            entry.message,
+        );
+        if !entry.fields.is_empty() {
+            message.push(' ');
+            message.push_str(&serde_json::Value::Object(log_fields(entry)).to_string());
+        }
+        message
+    }
+}
+
+#[async_trait]
+impl LogSink for SyslogSink {
+    fn name(&self) -> &'static str {
+        "syslog"
+    }
+
+    #[cfg(unix)]
+    async fn write(&mut self, batch: &LogBatch) -> io::Result<()> {
+        if self.connection.is_none() {
+            let socket = tokio::net::UnixDatagram::unbound()?;
+            socket.connect(&self.socket)?;
+            self.connection = Some(socket);
+        }
+        let messages: Vec<_> = batch
+            .entries
+            .iter()
+            .map(|entry| self.message(batch, entry))
+            .collect();
+
+        let connection = self
+            .connection
+            .as_ref()
+            .expect("syslog socket was just connected");
+        for message in messages {
+            if let Err(e) = connection.send(message.as_bytes()).await {
+                // Reconnect on the next batch, e.g. after a syslog restart.
+                self.connection = None;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    async fn write(&mut self, _batch: &LogBatch) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "syslog sockets are only supported on Unix",
+        ))
+    }
+}
+
+/// Printable ASCII name for a syslog header field, at most `max` bytes,
+/// or `-` if empty.
+fn syslog_name(name: &str, max: usize) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+    if name.is_empty() {
+        "-".to_string()
+    } else {
+        name
+    }
+}
+
+/// Escape a syslog structured data parameter value.
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Batches of JSON lines posted to an HTTP collector.
+///
+/// Entries are buffered until [`HttpSinkConfig::batch_size`] entries are
+/// pending or the flush interval elapses. A failed post is logged and its
+/// entries are dropped.
+pub struct HttpSink {
+    client: reqwest::Client,
+    url: String,
+    batch_size: usize,
+    flush_interval: Duration,
+    pending: String,
+    pending_entries: usize,
+}
+
+impl HttpSink {
+    /// Create an HTTP sink.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be built.
+    pub fn new(config: &HttpSinkConfig) -> Result<Self, RuntimeError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| {
+                RuntimeError::invalid_config(format!("Failed to create log exporter: {e}"))
+            })?;
+        Ok(Self {
+            client,
+            url: config.url.clone(),
+            batch_size: config.batch_size.max(1),
+            flush_interval: Duration::from_millis(config.flush_interval_ms.max(1)),
+            pending: String::new(),
+            pending_entries: 0,
+        })
+    }
+}
+
+#[async_trait]
+impl LogSink for HttpSink {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    async fn write(&mut self, batch: &LogBatch) -> io::Result<()> {
+        for entry in &batch.entries {
+            self.pending.push_str(&batch.json_line(entry));
+            self.pending.push('\n');
+            self.pending_entries += 1;
+        }
+        if self.pending_entries >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        if self.pending_entries == 0 {
+            return Ok(());
+        }
+        let body = std::mem::take(&mut self.pending);
+        let entries = std::mem::take(&mut self.pending_entries);
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()
+            .await
+            .map_err(io::Error::other)?;
+        if !response.status().is_success() {
+            return Err(io::Error::other(format!(
+                "collector returned {} for {entries} entries",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    fn flush_interval(&self) -> Duration {
+        self.flush_interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    fn batch(module_id: &str, messages: &[&str]) -> LogBatch {
+        LogBatch {
+            module_id: module_id.to_string(),
+            request_id: "req-1".to_string(),
+            entries: messages
+                .iter()
+                .map(|message| LogEntry::new(LogLevel::Info, (*message).to_string(), Vec::new()))
+                .collect(),
+        }
+    }
+
+    /// Records batches; blocks until released if `gate` is set.
+    struct Recorder {
+        batches: Arc<Mutex<Vec<String>>>,
+        gate: Option<Arc<tokio::sync::Notify>>,
+    }
+
+    #[async_trait]
+    impl LogSink for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn write(&mut self, batch: &LogBatch) -> io::Result<()> {
+            if let Some(gate) = &self.gate {
+                gate.notified().await;
+            }
+            self.batches.lock().unwrap().push(batch.request_id.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_delivers_batches() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let sinks = LogSinks::new().with_sink(
+            Recorder {
+                batches: batches.clone(),
+                gate: None,
+            },
+            8,
+        );
+
+        let entry = LogEntry::new(LogLevel::Info, "hello".into(), Vec::new());
+        sinks.submit("api", "req-1", std::slice::from_ref(&entry));
+        sinks.submit("api", "req-2", &[]);
+        sinks.submit("api", "req-3", &[entry]);
+
+        for _ in 0..100 {
+            if batches.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(*batches.lock().unwrap(), ["req-1", "req-3"]);
+        assert_eq!(sinks.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_batches() {
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let sinks = LogSinks::new().with_sink(
+            Recorder {
+                batches: Arc::new(Mutex::new(Vec::new())),
+                gate: Some(gate.clone()),
+            },
+            1,
+        );
+
+        let entry = LogEntry::new(LogLevel::Info, "hello".into(), Vec::new());
+        for i in 0..10 {
+            sinks.submit("api", &format!("req-{i}"), std::slice::from_ref(&entry));
+        }
+
+        // At most one batch is being written and one is queued.
+        assert!(sinks.dropped() >= 8);
+        gate.notify_waiters();
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = FileSink::new(&FileSinkConfig {
+            directory: dir.path().display().to_string(),
+            max_file_bytes: 300,
+            max_files: 2,
+        })
+        .unwrap();
+
+        for _ in 0..4 {
+            sink.write(&batch("my/api", &["first", "second"]))
+                .await
+                .unwrap();
+        }
+        sink.flush().await.unwrap();
+
+        let current = std::fs::read_to_string(dir.path().join("my_api.jsonl")).unwrap();
+        let line: serde_json::Value =
+            serde_json::from_str(current.lines().next().unwrap()).unwrap();
+        assert_eq!(line["module_id"], "my/api");
+        assert_eq!(line["request_id"], "req-1");
+        assert_eq!(line["message"], "first");
+        assert!(dir.path().join("my_api.jsonl.1").exists());
+        assert!(dir.path().join("my_api.jsonl.2").exists());
+        assert!(!dir.path().join("my_api.jsonl.3").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_syslog_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.sock");
+        let server = tokio::net::UnixDatagram::bind(&path).unwrap();
+
+        let mut sink = SyslogSink::new(&SyslogSinkConfig {
+            socket: path.display().to_string(),
+            facility: 16,
+            app_name: "edge".to_string(),
+        });
+        let mut batch = batch("api", &["hello"]);
+        batch.entries[0].fields = vec![("user".to_string(), "42".to_string())];
+        sink.write(&batch).await.unwrap();
+
+        let mut buf = vec![0; 1024];
+        let len = server.recv(&mut buf).await.unwrap();
+        let message = String::from_utf8_lossy(&buf[..len]);
+        assert!(message.starts_with("<134>1 "), "{message}");
+        assert!(
+            message.ends_with(r#" api [edge request_id="req-1"] hello {"user":"42"}"#),
+            "{message}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_sink_posts_batches() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/logs", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            while !String::from_utf8_lossy(&request).contains("\"third\"") {
+                let n = stream.read(&mut buf).await.unwrap();
+                assert!(n > 0);
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let mut sink = HttpSink::new(&HttpSinkConfig {
+            url,
+            batch_size: 3,
+            flush_interval_ms: 60_000,
+            timeout_ms: 5000,
+        })
+        .unwrap();
+        sink.write(&batch("api", &["first", "second"]))
+            .await
+            .unwrap();
+        assert_eq!(sink.pending_entries, 2);
+        sink.write(&batch("api", &["third"])).await.unwrap();
+        assert_eq!(sink.pending_entries, 0);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /logs "));
+        assert!(request.contains("application/x-ndjson"));
+        let body = request.split("\r\n\r\n").nth(1).unwrap();
+        assert_eq!(body.lines().count(), 3);
+    }
+}
@@ -0,0 +1,390 @@
+//! Live tail of guest logs.
+//!
+//! This module provides [`LogTail`], a broadcast of the guest logs and a
+//! summary of each invocation as it completes, and the Admin API handler
+//! streaming it as server-sent events:
+//!
+//! - `GET /admin/modules/:id/logs/tail` - Stream a module's logs and request summaries
+//!
+//! # Events
+//!
+//! - `log`: a guest log entry, in the format of the sinks' JSON lines
+//! - `request`: a request summary with status, outcome, fuel and duration
+//! - `lagged`: the number of invocations skipped because the client fell
+//!   behind
+//!
+//! Invocations are only captured while a client is connected, and a slow
+//! client never delays request handling: it skips invocations instead.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    Extension,
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tracing::{info, instrument};
+
+use edge_runtime_common::GuestLogLevel;
+use edge_runtime_core::store::{LogEntry, LogLevel};
+
+use crate::admin::{AdminState, verify_token};
+use crate::invocation::Invocation;
+use crate::log_sinks::LogBatch;
+
+/// Number of invocations buffered for each client.
+pub const TAIL_BUFFER: usize = 256;
+
+/// Summary of a completed invocation.
+#[derive(Debug, Clone)]
+pub struct RequestSummary {
+    /// HTTP status of the invocation.
+    pub status: u16,
+    /// Outcome of the entry point call.
+    pub outcome: &'static str,
+    /// Fuel consumed.
+    pub fuel_consumed: u64,
+    /// Wall-clock duration in milliseconds.
+    pub duration_ms: u128,
+}
+
+/// Logs of an invocation, with its summary unless they come from
+/// deferred work.
+#[derive(Debug)]
+struct TailEvent {
+    batch: LogBatch,
+    summary: Option<RequestSummary>,
+}
+
+/// Broadcast of completed invocations to tail clients.
+///
+/// Cloning is cheap; all clones share the same subscribers.
+#[derive(Clone)]
+pub struct LogTail {
+    sender: broadcast::Sender<Arc<TailEvent>>,
+}
+
+impl Default for LogTail {
+    fn default() -> Self {
+        Self::new(TAIL_BUFFER)
+    }
+}
+
+impl LogTail {
+    /// Create a tail buffering up to `capacity` invocations per client.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Number of connected clients.
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    /// Publish a completed invocation. Does nothing without clients.
+    pub fn publish(&self, module_id: &str, invocation: &Invocation) {
+        self.send(
+            module_id,
+            &invocation.request_id,
+            &invocation.logs,
+            Some(RequestSummary {
+                status: invocation.status(),
+                outcome: invocation.outcome().as_str(),
+                fuel_consumed: invocation.metrics.fuel_consumed,
+                duration_ms: invocation.duration.as_millis(),
+            }),
+        );
+    }
+
+    /// Publish the logs of deferred work. Does nothing without clients or
+    /// logs.
+    pub fn publish_logs(&self, module_id: &str, request_id: &str, logs: &[LogEntry]) {
+        if !logs.is_empty() {
+            self.send(module_id, request_id, logs, None);
+        }
+    }
+
+    fn send(
+        &self,
+        module_id: &str,
+        request_id: &str,
+        logs: &[LogEntry],
+        summary: Option<RequestSummary>,
+    ) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let event = TailEvent {
+            batch: LogBatch {
+                module_id: module_id.to_string(),
+                request_id: request_id.to_string(),
+                entries: logs.to_vec(),
+            },
+            summary,
+        };
+        // Fails only if the last client disconnected in the meantime.
+        let _ = self.sender.send(Arc::new(event));
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Arc<TailEvent>> {
+        self.sender.subscribe()
+    }
+}
+
+impl std::fmt::Debug for LogTail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogTail")
+            .field("subscribers", &self.subscribers())
+            .finish()
+    }
+}
+
+/// Query parameters for tailing logs.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TailQuery {
+    /// Only stream log entries at or above this level.
+    pub level: Option<GuestLogLevel>,
+    /// Only stream the logs and summary of this request.
+    pub request_id: Option<String>,
+}
+
+/// Filter of a tail client.
+#[derive(Debug, Clone)]
+struct TailFilter {
+    module_id: String,
+    min_level: LogLevel,
+    request_id: Option<String>,
+}
+
+impl TailFilter {
+    /// The SSE events of an invocation that pass the filter.
+    fn events(&self, event: &TailEvent) -> Vec<Event> {
+        let batch = &event.batch;
+        if batch.module_id != self.module_id
+            || self
+                .request_id
+                .as_ref()
+                .is_some_and(|id| *id != batch.request_id)
+        {
+            return Vec::new();
+        }
+
+        let mut events: Vec<Event> = batch
+            .entries
+            .iter()
+            .filter(|entry| entry.level >= self.min_level)
+            .map(|entry| Event::default().event("log").data(batch.json_line(entry)))
+            .collect();
+        if let Some(summary) = &event.summary {
+            let data = serde_json::json!({
+                "module_id": batch.module_id,
+                "request_id": batch.request_id,
+                "status": summary.status,
+                "outcome": summary.outcome,
+                "fuel_consumed": summary.fuel_consumed,
+                "duration_ms": summary.duration_ms,
+            });
+            events.push(Event::default().event("request").data(data.to_string()));
+        }
+        events
+    }
+}
+
+/// Stream a module's guest logs and request summaries as they complete.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/logs/tail?level=warn&request_id=...`
+///
+/// # Response
+///
+/// A `text/event-stream` of `log`, `request` and `lagged` events:
+///
+/// ```text
+/// event: log
+/// data: {"timestamp":"...","module_id":"api","request_id":"...","level":"WARN","message":"slow query","fields":{}}
+///
+/// event: request
+/// data: {"module_id":"api","request_id":"...","status":200,"outcome":"success","fuel_consumed":1200,"duration_ms":3}
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn tail_module_logs(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+    Query(query): Query<TailQuery>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.get_module(&module_id).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    }
+
+    info!(id = %module_id, "Log tail started");
+    let filter = TailFilter {
+        module_id,
+        min_level: query.level.unwrap_or_default().into(),
+        request_id: query.request_id,
+    };
+    let receiver = admin_state.app_state.log_tail().subscribe();
+
+    let stream = futures_util::stream::unfold(
+        (receiver, VecDeque::new()),
+        move |(mut receiver, mut pending)| {
+            let filter = filter.clone();
+            async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((Ok::<_, Infallible>(event), (receiver, pending)));
+                    }
+                    match receiver.recv().await {
+                        Ok(event) => pending.extend(filter.events(&event)),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            pending.push_back(
+                                Event::default().event("lagged").data(skipped.to_string()),
+                            );
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        },
+    );
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use edge_runtime_common::RuntimeConfig;
+    use edge_runtime_core::ExecutionMetrics;
+
+    use super::*;
+
+    fn invocation(request_id: &str, logs: Vec<LogEntry>) -> Invocation {
+        Invocation {
+            request_id: request_id.to_string(),
+            result: Ok(edge_runtime_core::ExecutionResult::Success),
+            logs,
+            metrics: ExecutionMetrics::default(),
+            response: None,
+            duration: Duration::from_millis(3),
+            deferred: None,
+        }
+    }
+
+    fn filter(request_id: Option<&str>, min_level: LogLevel) -> TailFilter {
+        TailFilter {
+            module_id: "api".to_string(),
+            min_level,
+            request_id: request_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_publish_without_subscribers() {
+        let tail = LogTail::default();
+        tail.publish("api", &invocation("req-1", Vec::new()));
+        assert_eq!(tail.subscribers(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_filters() {
+        let tail = LogTail::default();
+        let mut receiver = tail.subscribe();
+        let logs = vec![
+            LogEntry::new(LogLevel::Debug, "starting".into(), Vec::new()),
+            LogEntry::new(LogLevel::Warn, "slow".into(), Vec::new()),
+        ];
+        tail.publish("api", &invocation("req-1", logs));
+        let event = receiver.recv().await.unwrap();
+
+        assert_eq!(filter(None, LogLevel::Debug).events(&event).len(), 3);
+        assert_eq!(filter(None, LogLevel::Warn).events(&event).len(), 2);
+        assert!(
+            filter(Some("req-2"), LogLevel::Debug)
+                .events(&event)
+                .is_empty()
+        );
+
+        let mut other = filter(None, LogLevel::Debug);
+        other.module_id = "other".to_string();
+        assert!(other.events(&event).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tail_endpoint_streams_events() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use futures_util::StreamExt;
+        use tower::util::ServiceExt;
+
+        let state = crate::AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat("api", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        let app = crate::build_admin_router(AdminState {
+            app_state: state.clone(),
+            admin_token: "secret".to_string(),
+        })
+        .with_state(state.clone());
+
+        let unauthorized = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/modules/api/logs/tail")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/modules/api/logs/tail?level=info")
+                    .header("X-Admin-Token", "secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.log_tail().subscribers(), 1);
+
+        let logs = vec![LogEntry::new(LogLevel::Info, "hello".into(), Vec::new())];
+        state.log_tail().publish("api", &invocation("req-1", logs));
+
+        let mut body = response.into_body().into_data_stream();
+        let mut text = String::new();
+        while !text.contains("event: request") {
+            let chunk = body.next().await.unwrap().unwrap();
+            text.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert!(text.contains("event: log\ndata: {"));
+        assert!(text.contains(r#""message":"hello""#));
+        assert!(text.contains(r#""status":200"#));
+        assert!(text.contains(r#""outcome":"success""#));
+    }
+}
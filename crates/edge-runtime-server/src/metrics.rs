@@ -0,0 +1,498 @@
+//! Runtime metrics.
+//!
+//! This module provides [`RuntimeMetrics`], the registry behind the runtime
+//! part of `GET /metrics`. All series are labelled by module ID:
+//!
+//! - `edge_requests_total`: invocations by outcome (`success`, `trap`,
+//!   `fuel_exhausted`, `timeout`, `not_found`, `error`)
+//! - `edge_request_duration_seconds`: histogram of invocation durations
+//! - `edge_fuel_consumed`: histogram of fuel consumed per invocation
+//! - `edge_instantiation_duration_seconds`: histogram of instantiation times
+//! - `edge_module_compile_duration_seconds`: compile time of the loaded module
+//! - `edge_pooling_instances_in_use`: live instances, against the pooling
+//!   allocator's `edge_pooling_instances_capacity`, with the overall
+//!   `edge_pooling_utilization` ratio
+//!
+//! Metrics of loaded modules are kept by atomic counters, like
+//! [`ModuleStats`](crate::module_stats::ModuleStats): recording an
+//! invocation takes no lock, so concurrent requests never wait on each
+//! other. Metrics recorded by modules themselves are rendered after these by
+//! [`GuestMetrics`](edge_runtime_host::GuestMetrics).
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use dashmap::DashMap;
+
+use crate::invocation::{Invocation, InvocationOutcome};
+
+/// Upper bounds of the duration histogram buckets, in seconds.
+pub const DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Upper bounds of the fuel histogram buckets.
+pub const FUEL_BUCKETS: [f64; 8] = [1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+
+/// Maximum number of unknown module IDs counted separately as
+/// `not_found`; further IDs are counted as `_other`.
+const MAX_NOT_FOUND_MODULES: usize = 100;
+
+/// Outcomes counted per loaded module, in the order of
+/// [`ModuleMetrics::requests`]. `not_found` is counted by module ID instead.
+const OUTCOMES: [InvocationOutcome; 5] = [
+    InvocationOutcome::Error,
+    InvocationOutcome::FuelExhausted,
+    InvocationOutcome::Success,
+    InvocationOutcome::Timeout,
+    InvocationOutcome::Trap,
+];
+
+/// Bit pattern of [`ModuleMetrics::compile_seconds`] before a compile is
+/// recorded. A NaN that `Duration::as_secs_f64` never returns.
+const NO_COMPILE: u64 = u64::MAX;
+
+/// Registry of runtime metrics.
+///
+/// Cloning is cheap; all clones share the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeMetrics {
+    modules: Arc<DashMap<String, Arc<ModuleMetrics>>>,
+    not_found: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+/// Metrics of one module.
+#[derive(Debug)]
+struct ModuleMetrics {
+    requests: [AtomicU64; OUTCOMES.len()],
+    duration: Histogram,
+    fuel: Histogram,
+    instantiation: Histogram,
+    /// Compile time in seconds as `f64` bits, or [`NO_COMPILE`].
+    compile_seconds: AtomicU64,
+    /// Live instances, shared with their [`InstanceSlot`]s and kept when
+    /// the other metrics are cleared.
+    instances_in_use: Arc<AtomicU64>,
+}
+
+impl ModuleMetrics {
+    fn new(instances_in_use: Arc<AtomicU64>) -> Self {
+        Self {
+            requests: Default::default(),
+            duration: Histogram::new(&DURATION_BUCKETS),
+            fuel: Histogram::new(&FUEL_BUCKETS),
+            instantiation: Histogram::new(&DURATION_BUCKETS),
+            compile_seconds: AtomicU64::new(NO_COMPILE),
+            instances_in_use,
+        }
+    }
+
+    fn compile_seconds(&self) -> Option<f64> {
+        let bits = self.compile_seconds.load(Ordering::Relaxed);
+        (bits != NO_COMPILE).then(|| f64::from_bits(bits))
+    }
+
+    fn instances_in_use(&self) -> u64 {
+        self.instances_in_use.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for ModuleMetrics {
+    fn default() -> Self {
+        Self::new(Arc::default())
+    }
+}
+
+/// Cumulative histogram with fixed buckets and atomic counters.
+#[derive(Debug)]
+struct Histogram {
+    bounds: &'static [f64],
+    /// One bucket per bound, then one for values above the last bound.
+    buckets: Box<[AtomicU64]>,
+    /// Sum of the observed values as `f64` bits.
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    fn observe(&self, value: f64) {
+        let index = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        let _ = self
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+    }
+
+    fn render(&self, name: &str, module: &str, out: &mut String) {
+        let mut cumulative = 0;
+        for (bound, bucket) in self.bounds.iter().zip(self.buckets.iter()) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{module=\"{module}\",le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let count = cumulative + self.buckets[self.bounds.len()].load(Ordering::Relaxed);
+        let sum = f64::from_bits(self.sum.load(Ordering::Relaxed));
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{module=\"{module}\",le=\"+Inf\"}} {count}"
+        );
+        let _ = writeln!(out, "{name}_sum{{module=\"{module}\"}} {sum}");
+        let _ = writeln!(out, "{name}_count{{module=\"{module}\"}} {count}");
+    }
+
+    fn count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+/// A live instance of a module, counted in
+/// `edge_pooling_instances_in_use` until dropped.
+///
+/// Attached to the store's extensions so that it lives as long as the
+/// instance, including deferred work.
+#[derive(Debug)]
+pub struct InstanceSlot {
+    instances_in_use: Arc<AtomicU64>,
+}
+
+impl Drop for InstanceSlot {
+    fn drop(&mut self) {
+        let _ = self
+            .instances_in_use
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                Some(n.saturating_sub(1))
+            });
+    }
+}
+
+impl RuntimeMetrics {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed invocation.
+    pub fn record_invocation(&self, module_id: &str, invocation: &Invocation) {
+        let outcome = invocation.outcome();
+        if outcome == InvocationOutcome::NotFound {
+            self.record_not_found(module_id);
+            return;
+        }
+        self.with_module(module_id, |metrics| {
+            if let Some(index) = OUTCOMES.iter().position(|o| *o == outcome) {
+                metrics.requests[index].fetch_add(1, Ordering::Relaxed);
+            }
+            metrics.duration.observe(invocation.duration.as_secs_f64());
+            #[allow(clippy::cast_precision_loss)]
+            metrics
+                .fuel
+                .observe(invocation.metrics.fuel_consumed as f64);
+            if !invocation.metrics.instantiation_duration.is_zero() {
+                metrics
+                    .instantiation
+                    .observe(invocation.metrics.instantiation_duration.as_secs_f64());
+            }
+        });
+    }
+
+    /// Record an invocation of a module that is not loaded.
+    pub fn record_not_found(&self, module_id: &str) {
+        let Ok(mut not_found) = self.not_found.lock() else {
+            return;
+        };
+        let key = if not_found.contains_key(module_id) || not_found.len() < MAX_NOT_FOUND_MODULES {
+            module_id
+        } else {
+            "_other"
+        };
+        *not_found.entry(key.to_string()).or_default() += 1;
+    }
+
+    /// Record the compile time of a loaded module.
+    pub fn record_compile(&self, module_id: &str, duration: Duration) {
+        self.with_module(module_id, |metrics| {
+            metrics
+                .compile_seconds
+                .store(duration.as_secs_f64().to_bits(), Ordering::Relaxed);
+        });
+    }
+
+    /// Count a live instance of a module until the returned slot is dropped.
+    pub fn instance_slot(&self, module_id: &str) -> InstanceSlot {
+        let instances_in_use = self.with_module(module_id, |metrics| {
+            metrics.instances_in_use.fetch_add(1, Ordering::Relaxed);
+            metrics.instances_in_use.clone()
+        });
+        InstanceSlot { instances_in_use }
+    }
+
+    /// Remove the metrics of a module, except its live instances.
+    pub fn clear(&self, module_id: &str) {
+        // Counted under the shard lock, so a slot taken concurrently either
+        // keeps the entry or is counted in a new one.
+        if self
+            .modules
+            .remove_if(module_id, |_, metrics| metrics.instances_in_use() == 0)
+            .is_some()
+        {
+            return;
+        }
+        if let Some(mut metrics) = self.modules.get_mut(module_id) {
+            let instances_in_use = metrics.instances_in_use.clone();
+            *metrics = Arc::new(ModuleMetrics::new(instances_in_use));
+        }
+    }
+
+    /// Render the metrics in the Prometheus text format.
+    ///
+    /// `pooling_capacity` is the number of instance slots of the pooling
+    /// allocator, if enabled.
+    pub fn render(&self, pooling_capacity: Option<u32>) -> String {
+        let mut out = String::new();
+        let mut modules: Vec<(String, Arc<ModuleMetrics>)> = self
+            .modules
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        modules.sort_by(|a, b| a.0.cmp(&b.0));
+
+        out.push_str("# TYPE edge_requests_total counter\n");
+        for (id, metrics) in &modules {
+            for (outcome, count) in OUTCOMES.iter().zip(&metrics.requests) {
+                let count = count.load(Ordering::Relaxed);
+                if count > 0 {
+                    let _ = writeln!(
+                        out,
+                        "edge_requests_total{{module=\"{}\",outcome=\"{}\"}} {count}",
+                        escape_label(id),
+                        outcome.as_str()
+                    );
+                }
+            }
+        }
+        if let Ok(not_found) = self.not_found.lock() {
+            for (id, count) in not_found.iter() {
+                let _ = writeln!(
+                    out,
+                    "edge_requests_total{{module=\"{}\",outcome=\"not_found\"}} {count}",
+                    escape_label(id)
+                );
+            }
+        }
+
+        render_histograms(
+            &mut out,
+            "edge_request_duration_seconds",
+            &modules,
+            |metrics| &metrics.duration,
+        );
+        render_histograms(&mut out, "edge_fuel_consumed", &modules, |metrics| {
+            &metrics.fuel
+        });
+        render_histograms(
+            &mut out,
+            "edge_instantiation_duration_seconds",
+            &modules,
+            |metrics| &metrics.instantiation,
+        );
+
+        out.push_str("# TYPE edge_module_compile_duration_seconds gauge\n");
+        for (id, metrics) in &modules {
+            if let Some(seconds) = metrics.compile_seconds() {
+                let _ = writeln!(
+                    out,
+                    "edge_module_compile_duration_seconds{{module=\"{}\"}} {seconds}",
+                    escape_label(id)
+                );
+            }
+        }
+
+        out.push_str("# TYPE edge_pooling_instances_in_use gauge\n");
+        let mut in_use = 0;
+        for (id, metrics) in &modules {
+            let instances = metrics.instances_in_use();
+            in_use += instances;
+            let _ = writeln!(
+                out,
+                "edge_pooling_instances_in_use{{module=\"{}\"}} {instances}",
+                escape_label(id)
+            );
+        }
+        if let Some(capacity) = pooling_capacity {
+            out.push_str("# TYPE edge_pooling_instances_capacity gauge\n");
+            let _ = writeln!(out, "edge_pooling_instances_capacity {capacity}");
+            out.push_str("# TYPE edge_pooling_utilization gauge\n");
+            #[allow(clippy::cast_precision_loss)]
+            let utilization = in_use as f64 / f64::from(capacity.max(1));
+            let _ = writeln!(out, "edge_pooling_utilization {utilization}");
+        }
+
+        out
+    }
+
+    /// Run `f` on the metrics of a module, created if missing.
+    ///
+    /// Existing modules are looked up without allocating a key.
+    fn with_module<T>(&self, module_id: &str, f: impl FnOnce(&ModuleMetrics) -> T) -> T {
+        if let Some(metrics) = self.modules.get(module_id) {
+            return f(&metrics);
+        }
+        let metrics = self.modules.entry(module_id.to_string()).or_default();
+        f(&metrics)
+    }
+}
+
+/// Render one histogram per module.
+fn render_histograms(
+    out: &mut String,
+    name: &str,
+    modules: &[(String, Arc<ModuleMetrics>)],
+    select: fn(&ModuleMetrics) -> &Histogram,
+) {
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    for (id, metrics) in modules {
+        let histogram = select(metrics);
+        if histogram.count() > 0 {
+            histogram.render(name, &escape_label(id), out);
+        }
+    }
+}
+
+/// Escape a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use edge_runtime_common::RuntimeError;
+    use edge_runtime_core::{ExecutionMetrics, ExecutionResult};
+
+    use super::*;
+
+    fn invocation(result: Result<ExecutionResult, RuntimeError>, fuel: u64) -> Invocation {
+        Invocation {
+            request_id: "req-1".to_string(),
+            result,
+            logs: Vec::new(),
+            metrics: ExecutionMetrics {
+                fuel_consumed: fuel,
+                instantiation_duration: Duration::from_micros(300),
+                ..ExecutionMetrics::default()
+            },
+            response: None,
+            duration: Duration::from_millis(3),
+            deferred: None,
+        }
+    }
+
+    #[test]
+    fn test_render_outcomes_and_histograms() {
+        let metrics = RuntimeMetrics::new();
+        metrics.record_invocation("api", &invocation(Ok(ExecutionResult::Success), 5000));
+        metrics.record_invocation("api", &invocation(Err(RuntimeError::FuelExhausted), 0));
+        metrics.record_not_found("missing");
+        metrics.record_compile("api", Duration::from_millis(250));
+
+        let out = metrics.render(Some(1000));
+        assert!(out.contains("edge_requests_total{module=\"api\",outcome=\"success\"} 1\n"));
+        assert!(out.contains("edge_requests_total{module=\"api\",outcome=\"fuel_exhausted\"} 1\n"));
+        assert!(out.contains("edge_requests_total{module=\"missing\",outcome=\"not_found\"} 1\n"));
+        assert!(out.contains("edge_request_duration_seconds_count{module=\"api\"} 2\n"));
+        assert!(out.contains("edge_fuel_consumed_bucket{module=\"api\",le=\"10000\"} 2\n"));
+        assert!(out.contains("edge_fuel_consumed_bucket{module=\"api\",le=\"1000\"} 1\n"));
+        assert!(out.contains("edge_instantiation_duration_seconds_count{module=\"api\"} 2\n"));
+        assert!(out.contains("edge_module_compile_duration_seconds{module=\"api\"} 0.25\n"));
+        assert!(out.contains("edge_pooling_instances_capacity 1000\n"));
+        assert!(out.contains("edge_pooling_utilization 0\n"));
+    }
+
+    #[test]
+    fn test_instance_slots() {
+        let metrics = RuntimeMetrics::new();
+        let first = metrics.instance_slot("api");
+        let second = metrics.instance_slot("api");
+        assert!(
+            metrics
+                .render(None)
+                .contains("edge_pooling_instances_in_use{module=\"api\"} 2\n")
+        );
+
+        drop(first);
+        metrics.clear("api");
+        assert!(
+            metrics
+                .render(None)
+                .contains("edge_pooling_instances_in_use{module=\"api\"} 1\n")
+        );
+        drop(second);
+        assert!(
+            metrics
+                .render(None)
+                .contains("edge_pooling_instances_in_use{module=\"api\"} 0\n")
+        );
+    }
+
+    #[test]
+    fn test_concurrent_recording() {
+        let metrics = RuntimeMetrics::new();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..250 {
+                        let _slot = metrics.instance_slot("api");
+                        metrics.record_invocation(
+                            "api",
+                            &invocation(Ok(ExecutionResult::Success), 5000),
+                        );
+                    }
+                });
+            }
+        });
+
+        let out = metrics.render(None);
+        assert!(out.contains("edge_requests_total{module=\"api\",outcome=\"success\"} 1000\n"));
+        assert!(out.contains("edge_fuel_consumed_bucket{module=\"api\",le=\"10000\"} 1000\n"));
+        assert!(out.contains("edge_fuel_consumed_sum{module=\"api\"} 5000000\n"));
+        assert!(out.contains("edge_pooling_instances_in_use{module=\"api\"} 0\n"));
+    }
+
+    #[test]
+    fn test_not_found_cardinality() {
+        let metrics = RuntimeMetrics::new();
+        for i in 0..MAX_NOT_FOUND_MODULES + 5 {
+            metrics.record_not_found(&format!("m{i}"));
+        }
+        let out = metrics.render(None);
+        assert!(out.contains("edge_requests_total{module=\"_other\",outcome=\"not_found\"} 5\n"));
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+    }
+}
@@ -0,0 +1,333 @@
+//! Per-module statistics.
+//!
+//! This module provides [`ModuleStats`], the statistics of a loaded module
+//! exposed by the Admin API:
+//!
+//! - invocations, and errors by outcome (`trap`, `fuel_exhausted`,
+//!   `timeout`, `not_found`, `error`)
+//! - latency and fuel percentiles (p50, p90, p99) and maxima
+//! - peak linear memory of an instance
+//! - time of the last invocation
+//! - load time and compile duration
+//!
+//! Statistics are kept by atomic counters: recording an invocation takes no
+//! lock, so concurrent requests of a module never wait on each other.
+//! Percentiles come from log-linear histograms and are exact up to 12.5%.
+//! Replacing a module resets its statistics.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::invocation::{Invocation, InvocationOutcome};
+
+/// Outcomes counted as errors, in the order of [`ModuleStats::errors`].
+const ERROR_OUTCOMES: [InvocationOutcome; 5] = [
+    InvocationOutcome::Trap,
+    InvocationOutcome::FuelExhausted,
+    InvocationOutcome::Timeout,
+    InvocationOutcome::NotFound,
+    InvocationOutcome::Error,
+];
+
+/// Statistics of a loaded module.
+#[derive(Debug)]
+pub struct ModuleStats {
+    invocations: AtomicU64,
+    errors: [AtomicU64; ERROR_OUTCOMES.len()],
+    /// Invocation durations in microseconds.
+    latency: LogHistogram,
+    fuel: LogHistogram,
+    peak_memory_bytes: AtomicU64,
+    /// Time of the last invocation in milliseconds since the Unix epoch,
+    /// or `0` if never invoked.
+    last_invocation_ms: AtomicI64,
+    loaded_at: DateTime<Utc>,
+    compile_duration: Duration,
+}
+
+impl ModuleStats {
+    /// Create the statistics of a module loaded now.
+    pub fn new(compile_duration: Duration) -> Self {
+        Self {
+            invocations: AtomicU64::new(0),
+            errors: Default::default(),
+            latency: LogHistogram::new(),
+            fuel: LogHistogram::new(),
+            peak_memory_bytes: AtomicU64::new(0),
+            last_invocation_ms: AtomicI64::new(0),
+            loaded_at: Utc::now(),
+            compile_duration,
+        }
+    }
+
+    /// Record a completed invocation.
+    pub fn record(&self, invocation: &Invocation) {
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+        let outcome = invocation.outcome();
+        if let Some(index) = ERROR_OUTCOMES.iter().position(|o| *o == outcome) {
+            self.errors[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency
+            .record(u64::try_from(invocation.duration.as_micros()).unwrap_or(u64::MAX));
+        self.fuel.record(invocation.metrics.fuel_consumed);
+        self.peak_memory_bytes.fetch_max(
+            u64::try_from(invocation.metrics.memory_used_bytes).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+        self.last_invocation_ms
+            .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// Number of invocations recorded.
+    pub fn invocations(&self) -> u64 {
+        self.invocations.load(Ordering::Relaxed)
+    }
+
+    /// Number of failed invocations by outcome.
+    pub fn errors(&self) -> BTreeMap<&'static str, u64> {
+        ERROR_OUTCOMES
+            .iter()
+            .zip(&self.errors)
+            .map(|(outcome, count)| (outcome.as_str(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// A point-in-time copy of the statistics.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn snapshot(&self) -> ModuleStatsSnapshot {
+        let latency = self.latency.percentiles();
+        let last_invocation_ms = self.last_invocation_ms.load(Ordering::Relaxed);
+        ModuleStatsSnapshot {
+            invocations: self.invocations(),
+            errors: self.errors(),
+            latency_ms: Percentiles {
+                p50: latency.p50 as f64 / 1000.0,
+                p90: latency.p90 as f64 / 1000.0,
+                p99: latency.p99 as f64 / 1000.0,
+                max: latency.max as f64 / 1000.0,
+            },
+            fuel: self.fuel.percentiles(),
+            peak_memory_bytes: self.peak_memory_bytes.load(Ordering::Relaxed),
+            last_invocation: (last_invocation_ms != 0)
+                .then(|| DateTime::from_timestamp_millis(last_invocation_ms))
+                .flatten()
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            loaded_at: self.loaded_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+            compile_ms: self.compile_duration.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Statistics of a module, as returned by the Admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleStatsSnapshot {
+    /// Number of invocations.
+    pub invocations: u64,
+    /// Number of failed invocations by outcome.
+    pub errors: BTreeMap<&'static str, u64>,
+    /// Invocation latency in milliseconds.
+    pub latency_ms: Percentiles<f64>,
+    /// Fuel consumed per invocation.
+    pub fuel: Percentiles<u64>,
+    /// Largest linear memory of an instance, in bytes.
+    pub peak_memory_bytes: u64,
+    /// Time of the last invocation (RFC 3339), if any.
+    pub last_invocation: Option<String>,
+    /// Time the module was loaded (RFC 3339).
+    pub loaded_at: String,
+    /// Time spent compiling the module, in milliseconds.
+    pub compile_ms: f64,
+}
+
+/// Percentiles and maximum of a distribution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Percentiles<T> {
+    /// Median.
+    pub p50: T,
+    /// 90th percentile.
+    pub p90: T,
+    /// 99th percentile.
+    pub p99: T,
+    /// Largest value.
+    pub max: T,
+}
+
+/// Bits of a value below its leading one that select its sub-bucket.
+const SUB_BUCKET_BITS: u32 = 3;
+
+/// Sub-buckets per power of two.
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// Number of buckets covering all `u64` values.
+const BUCKETS: usize = ((64 - SUB_BUCKET_BITS + 1) as usize) * SUB_BUCKETS as usize;
+
+/// Histogram of `u64` values with atomic log-linear buckets.
+///
+/// Values below [`SUB_BUCKETS`] have their own bucket; larger values share
+/// a bucket with values of the same power of two and leading bits, which
+/// bounds the relative error of a percentile to 1/[`SUB_BUCKETS`].
+#[derive(Debug)]
+struct LogHistogram {
+    buckets: Box<[AtomicU64]>,
+    max: AtomicU64,
+}
+
+impl LogHistogram {
+    fn new() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, value: u64) {
+        self.buckets[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    fn percentiles(&self) -> Percentiles<u64> {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        let total: u64 = counts.iter().sum();
+        let max = self.max.load(Ordering::Relaxed);
+        let percentile = |per_mille: u64| {
+            if total == 0 {
+                return 0;
+            }
+            let rank = (total * per_mille).div_ceil(1000).max(1);
+            let mut seen = 0;
+            for (index, count) in counts.iter().enumerate() {
+                seen += count;
+                if seen >= rank {
+                    return bucket_upper_bound(index).min(max);
+                }
+            }
+            max
+        };
+        Percentiles {
+            p50: percentile(500),
+            p90: percentile(900),
+            p99: percentile(990),
+            max,
+        }
+    }
+}
+
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKETS {
+        return usize::try_from(value).unwrap_or_default();
+    }
+    let exponent = 63 - value.leading_zeros();
+    let shift = exponent - SUB_BUCKET_BITS;
+    let sub_bucket = (value >> shift) & (SUB_BUCKETS - 1);
+    usize::try_from(u64::from(shift + 1) * SUB_BUCKETS + sub_bucket).unwrap_or(BUCKETS - 1)
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let lower = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    lower + ((1 << shift) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use edge_runtime_common::RuntimeError;
+    use edge_runtime_core::{ExecutionMetrics, ExecutionResult};
+
+    use super::*;
+
+    fn invocation(result: Result<ExecutionResult, RuntimeError>, millis: u64) -> Invocation {
+        Invocation {
+            request_id: "req".to_string(),
+            result,
+            logs: Vec::new(),
+            metrics: ExecutionMetrics {
+                fuel_consumed: millis * 1000,
+                memory_used_bytes: 65536,
+                ..ExecutionMetrics::default()
+            },
+            response: None,
+            duration: Duration::from_millis(millis),
+            deferred: None,
+        }
+    }
+
+    #[test]
+    fn test_buckets() {
+        for value in [0, 1, 7, 8, 9, 15, 16, 17, 1000, 123_456_789, u64::MAX] {
+            let index = bucket_index(value);
+            assert!(index < BUCKETS, "{value}");
+            let upper = bucket_upper_bound(index);
+            assert!(upper >= value, "{value}");
+            assert!(upper - value <= value / 8, "{value}");
+            if index > 0 {
+                assert!(bucket_upper_bound(index - 1) < value, "{value}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = LogHistogram::new();
+        assert_eq!(histogram.percentiles(), Percentiles::default());
+
+        for value in 1..=1000 {
+            histogram.record(value);
+        }
+        let percentiles = histogram.percentiles();
+        assert_eq!(percentiles.max, 1000);
+        for (actual, expected) in [
+            (percentiles.p50, 500),
+            (percentiles.p90, 900),
+            (percentiles.p99, 990),
+        ] {
+            assert!(
+                actual >= expected && actual <= expected + expected / 8,
+                "{actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_record_invocations() {
+        let stats = ModuleStats::new(Duration::from_millis(12));
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.invocations, 0);
+        assert!(snapshot.last_invocation.is_none());
+        assert!((snapshot.compile_ms - 12.0).abs() < f64::EPSILON);
+
+        for millis in 1..=9 {
+            stats.record(&invocation(Ok(ExecutionResult::Success), millis));
+        }
+        stats.record(&invocation(Err(RuntimeError::FuelExhausted), 100));
+        stats.record(&invocation(
+            Ok(ExecutionResult::Trap {
+                message: "unreachable".to_string(),
+                code: None,
+            }),
+            1,
+        ));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.invocations, 11);
+        assert_eq!(snapshot.errors["fuel_exhausted"], 1);
+        assert_eq!(snapshot.errors["trap"], 1);
+        assert_eq!(snapshot.errors["timeout"], 0);
+        assert!((snapshot.latency_ms.max - 100.0).abs() < f64::EPSILON);
+        assert!(snapshot.latency_ms.p50 >= 5.0 && snapshot.latency_ms.p50 < 6.0);
+        assert_eq!(snapshot.fuel.max, 100_000);
+        assert_eq!(snapshot.peak_memory_bytes, 65536);
+        assert!(snapshot.last_invocation.is_some());
+    }
+}
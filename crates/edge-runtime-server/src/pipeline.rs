@@ -0,0 +1,439 @@
+//! Middleware pipelines composed from multiple modules.
+//!
+//! A pipeline chains loaded modules into a single route, e.g.
+//! `auth -> rate-limit -> app -> compress`. Stages run in order, each in
+//! its own store with the full execution limits:
+//!
+//! - Every stage sees the request as modified by the stages before it.
+//! - Every stage sees the response produced so far (if any) and can
+//!   replace or edit it.
+//! - A stage ends the pipeline early by sending its response
+//!   (`env::response_send`), e.g. `auth` answering `401`.
+//! - A stage that traps or fails ends the pipeline with that error.
+//!
+//! Logs and metrics of all stages are merged into one [`PipelineRun`].
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde::Serialize;
+use tracing::{debug, info, instrument};
+
+use edge_runtime_common::RuntimeError;
+use edge_runtime_core::store::LogEntry;
+use edge_runtime_core::{ExecutionMetrics, ExecutionResult};
+use edge_runtime_host::{Exchange, GuestRequest, GuestResponse};
+
+use crate::invocation::{Invocation, invoke_with_exchange, log_fields, log_timestamp};
+use crate::request::WasmHttpRequest;
+use crate::response::WasmHttpResponse;
+use crate::state::AppState;
+
+/// A named chain of modules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pipeline {
+    /// Pipeline ID, served at `/functions/:id`.
+    pub id: String,
+    /// Module IDs of the stages, in execution order.
+    pub stages: Vec<String>,
+}
+
+/// Registry of pipeline definitions.
+#[derive(Debug, Clone, Default)]
+pub struct Pipelines {
+    pipelines: Arc<DashMap<String, Pipeline>>,
+}
+
+impl Pipelines {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define or replace a pipeline.
+    ///
+    /// Stages are not required to be loaded yet; a missing stage fails the
+    /// request that reaches it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ID is empty or there are no stages.
+    pub fn set(&self, id: &str, stages: Vec<String>) -> Result<Pipeline, RuntimeError> {
+        if id.is_empty() {
+            return Err(RuntimeError::invalid_config(
+                "Pipeline ID must not be empty",
+            ));
+        }
+        if stages.is_empty() || stages.iter().any(String::is_empty) {
+            return Err(RuntimeError::invalid_config(format!(
+                "Pipeline '{id}' must have at least one stage and no empty stage IDs"
+            )));
+        }
+
+        let pipeline = Pipeline {
+            id: id.to_string(),
+            stages,
+        };
+        self.pipelines.insert(id.to_string(), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Get a pipeline definition.
+    pub fn get(&self, id: &str) -> Option<Pipeline> {
+        self.pipelines.get(id).map(|p| p.clone())
+    }
+
+    /// Remove a pipeline definition.
+    pub fn remove(&self, id: &str) -> Option<Pipeline> {
+        self.pipelines.remove(id).map(|(_, p)| p)
+    }
+
+    /// List all pipeline definitions, sorted by ID.
+    pub fn list(&self) -> Vec<Pipeline> {
+        let mut pipelines: Vec<Pipeline> = self.pipelines.iter().map(|p| p.clone()).collect();
+        pipelines.sort_by(|a, b| a.id.cmp(&b.id));
+        pipelines
+    }
+}
+
+/// A single executed stage.
+#[derive(Debug)]
+pub struct StageRun {
+    /// Module ID of the stage.
+    pub module_id: String,
+    /// The stage's invocation.
+    pub invocation: Invocation,
+}
+
+/// Result of running a pipeline.
+#[derive(Debug)]
+pub struct PipelineRun {
+    /// Pipeline ID.
+    pub pipeline_id: String,
+    /// Request ID shared by all stages.
+    pub request_id: String,
+    /// Stages that ran, in order.
+    pub stages: Vec<StageRun>,
+    /// Final response, if any stage produced one.
+    pub response: Option<WasmHttpResponse>,
+    /// Stage that ended the pipeline early by sending its response.
+    pub sent_by: Option<String>,
+    /// Wall-clock time for the whole pipeline.
+    pub duration: Duration,
+}
+
+impl PipelineRun {
+    /// Returns `true` if every stage that ran completed successfully.
+    pub fn is_success(&self) -> bool {
+        self.stages
+            .iter()
+            .all(|stage| stage.invocation.is_success())
+    }
+
+    /// The stage that trapped or failed, if any.
+    pub fn failed_stage(&self) -> Option<&StageRun> {
+        self.stages
+            .iter()
+            .find(|stage| !stage.invocation.is_success())
+    }
+
+    /// Metrics of all stages merged into one.
+    ///
+    /// See [`ExecutionMetrics::merge`].
+    pub fn metrics(&self) -> ExecutionMetrics {
+        let mut merged = ExecutionMetrics::default();
+        for stage in &self.stages {
+            merged.merge(&stage.invocation.metrics);
+        }
+        merged.duration = Some(merged.duration.unwrap_or_default());
+        merged
+    }
+
+    /// Logs of all stages in execution order, tagged with the stage.
+    pub fn logs(&self) -> impl Iterator<Item = (&str, &LogEntry)> {
+        self.stages.iter().flat_map(|stage| {
+            stage
+                .invocation
+                .logs
+                .iter()
+                .map(|log| (stage.module_id.as_str(), log))
+        })
+    }
+}
+
+/// Run a pipeline for a request.
+///
+/// # Errors
+///
+/// Returns an error if a stage's store cannot be created or a stage module
+/// is not loaded. Guest failures are reported per stage in the result.
+#[instrument(skip(state, pipeline, request), fields(pipeline_id = %pipeline.id))]
+pub async fn run_pipeline(
+    state: &AppState,
+    pipeline: &Pipeline,
+    request_id: String,
+    request: &WasmHttpRequest,
+) -> Result<PipelineRun, RuntimeError> {
+    let start = Instant::now();
+
+    let mut guest_request = GuestRequest::from(request);
+    let mut response: Option<GuestResponse> = None;
+    let mut stages = Vec::with_capacity(pipeline.stages.len());
+    let mut sent_by = None;
+
+    for module_id in &pipeline.stages {
+        let exchange = match response.take() {
+            Some(response) => Exchange::with_response(guest_request, response),
+            None => Exchange::new(guest_request),
+        };
+
+        let (mut invocation, exchange) = invoke_with_exchange(
+            state,
+            module_id,
+            request_id.clone(),
+            exchange,
+            state.exec_config(),
+            0,
+        )
+        .await?;
+
+        debug!(
+            request_id = %request_id,
+            stage = %module_id,
+            success = invocation.is_success(),
+            "Pipeline stage finished"
+        );
+
+        response = invocation.response.take().map(GuestResponse::from);
+        let sent = exchange.is_sent();
+        guest_request = exchange.into_request();
+
+        let success = matches!(invocation.result, Ok(ExecutionResult::Success));
+        stages.push(StageRun {
+            module_id: module_id.clone(),
+            invocation,
+        });
+
+        if !success {
+            break;
+        }
+        if sent {
+            sent_by = Some(module_id.clone());
+            break;
+        }
+    }
+
+    let run = PipelineRun {
+        pipeline_id: pipeline.id.clone(),
+        request_id,
+        stages,
+        response: response.map(WasmHttpResponse::from),
+        sent_by,
+        duration: start.elapsed(),
+    };
+
+    info!(
+        request_id = %run.request_id,
+        stages_run = run.stages.len(),
+        success = run.is_success(),
+        sent_by = ?run.sent_by,
+        duration_ms = run.duration.as_millis(),
+        "Pipeline completed"
+    );
+
+    Ok(run)
+}
+
+/// Summarize the stages of a pipeline run for JSON responses.
+pub fn stages_to_json(run: &PipelineRun) -> Vec<serde_json::Value> {
+    run.stages
+        .iter()
+        .map(|stage| {
+            serde_json::json!({
+                "module": stage.module_id,
+                "success": stage.invocation.is_success(),
+                "fuel_consumed": stage.invocation.metrics.fuel_consumed,
+                "duration_ms": stage.invocation.duration.as_millis(),
+                "log_count": stage.invocation.logs.len(),
+            })
+        })
+        .collect()
+}
+
+/// Convert merged pipeline logs to JSON, tagging each with its stage.
+pub fn pipeline_logs_to_json(run: &PipelineRun) -> Vec<serde_json::Value> {
+    run.logs()
+        .map(|(stage, log)| {
+            serde_json::json!({
+                "stage": stage,
+                "timestamp": log_timestamp(log),
+                "level": log.level.to_string(),
+                "message": log.message,
+                "fields": log_fields(log),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::RuntimeConfig;
+
+    /// Stage that adds a request header and passes the request on.
+    const TAG: &str = r#"(module
+        (import "env" "request_set_header" (func $set (param i32 i32 i32 i32) (result i32)))
+        (import "env" "log" (func $log (param i32 i32 i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "x-user")
+        (data (i32.const 16) "alice")
+        (func (export "_start")
+            (call $log (i32.const 1) (i32.const 16) (i32.const 5))
+            (drop (call $set (i32.const 0) (i32.const 6) (i32.const 16) (i32.const 5)))))"#;
+
+    /// Stage that echoes the `x-user` request header as the body.
+    const APP: &str = r#"(module
+        (import "env" "request_header" (func $hdr (param i32 i32 i32 i32) (result i32)))
+        (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "x-user")
+        (func (export "_start")
+            (drop (call $write (i32.const 64)
+                (call $hdr (i32.const 0) (i32.const 6) (i32.const 64) (i32.const 64))))))"#;
+
+    /// Stage that sets a header on the existing response.
+    const STAMP: &str = r#"(module
+        (import "env" "response_set_header" (func $set (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "x-stamped")
+        (data (i32.const 16) "yes")
+        (func (export "_start")
+            (drop (call $set (i32.const 0) (i32.const 9) (i32.const 16) (i32.const 3)))))"#;
+
+    /// Stage that rejects the request with 401.
+    const DENY: &str = r#"(module
+        (import "env" "response_set_status" (func $status (param i32) (result i32)))
+        (import "env" "response_send" (func $send (result i32)))
+        (func (export "_start")
+            (drop (call $status (i32.const 401)))
+            (drop (call $send))))"#;
+
+    fn state() -> AppState {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.load_module_wat("tag", TAG).unwrap();
+        state.load_module_wat("app", APP).unwrap();
+        state.load_module_wat("stamp", STAMP).unwrap();
+        state.load_module_wat("deny", DENY).unwrap();
+        state
+            .load_module_wat("trap", r#"(module (func (export "_start") unreachable))"#)
+            .unwrap();
+        state
+    }
+
+    fn pipeline(stages: &[&str]) -> Pipeline {
+        Pipeline {
+            id: "p".into(),
+            stages: stages.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_pipelines_registry() {
+        let pipelines = Pipelines::new();
+
+        assert!(pipelines.set("p", vec![]).is_err());
+        assert!(pipelines.set("", vec!["a".into()]).is_err());
+
+        pipelines.set("p", vec!["a".into(), "b".into()]).unwrap();
+        assert_eq!(pipelines.get("p").unwrap().stages, vec!["a", "b"]);
+        assert_eq!(pipelines.list().len(), 1);
+
+        assert!(pipelines.remove("p").is_some());
+        assert!(pipelines.get("p").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_passes_request_and_response() {
+        let state = state();
+        let request = WasmHttpRequest::new("GET", "/functions/p");
+
+        let run = run_pipeline(
+            &state,
+            &pipeline(&["tag", "app", "stamp"]),
+            "r".into(),
+            &request,
+        )
+        .await
+        .unwrap();
+
+        assert!(run.is_success());
+        assert_eq!(run.stages.len(), 3);
+        assert!(run.sent_by.is_none());
+
+        let response = run.response.as_ref().unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"alice");
+        assert!(
+            response
+                .headers
+                .contains(&("x-stamped".into(), "yes".into()))
+        );
+
+        let logs: Vec<_> = run.logs().collect();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].0, "tag");
+
+        let total: u64 = run
+            .stages
+            .iter()
+            .map(|s| s.invocation.metrics.fuel_consumed)
+            .sum();
+        assert_eq!(run.metrics().fuel_consumed, total);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_short_circuit() {
+        let state = state();
+        let request = WasmHttpRequest::new("GET", "/functions/p");
+
+        let run = run_pipeline(&state, &pipeline(&["deny", "app"]), "r".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(run.is_success());
+        assert_eq!(run.stages.len(), 1);
+        assert_eq!(run.sent_by.as_deref(), Some("deny"));
+        assert_eq!(run.response.unwrap().status, 401);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_stage_trap() {
+        let state = state();
+        let request = WasmHttpRequest::new("GET", "/functions/p");
+
+        let run = run_pipeline(
+            &state,
+            &pipeline(&["tag", "trap", "app"]),
+            "r".into(),
+            &request,
+        )
+        .await
+        .unwrap();
+
+        assert!(!run.is_success());
+        assert_eq!(run.stages.len(), 2);
+        assert_eq!(run.failed_stage().unwrap().module_id, "trap");
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_missing_stage() {
+        let state = state();
+        let request = WasmHttpRequest::new("GET", "/functions/p");
+
+        let result =
+            run_pipeline(&state, &pipeline(&["tag", "missing"]), "r".into(), &request).await;
+
+        assert!(matches!(result, Err(e) if e.is_not_found()));
+    }
+}
@@ -0,0 +1,689 @@
+//! Guest CPU profiling.
+//!
+//! This module provides [`Profiler`], which samples the WebAssembly stack of
+//! a module's next invocations with wasmtime's [`GuestProfiler`], and the
+//! Admin API handlers driving it:
+//!
+//! - `POST /admin/modules/:id/profile` - Profile a module's next invocations
+//! - `GET /admin/modules/:id/profile` - Get the progress of a module's profile
+//! - `GET /admin/modules/:id/profile/download` - Download a module's profile
+//! - `DELETE /admin/modules/:id/profile` - Stop and discard a module's profile
+//!
+//! A profiled store takes a sample each time its epoch deadline is reached,
+//! i.e. every millisecond with the server's epoch ticker, and moves the
+//! deadline one tick on. The ticks left of the execution timeout are
+//! counted down meanwhile, so the store still traps with
+//! [`Trap::Interrupt`] once they run out. Functions are named from the
+//! module's name section, or by index.
+//!
+//! Profiles are in the Firefox Profiler's processed format, with the
+//! samples of all profiled invocations in one thread; open them at
+//! <https://profiler.firefox.com>.
+
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    Extension, Json,
+    extract::Path,
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, instrument, warn};
+use wasmtime::{GuestProfiler, Store, Trap, UpdateDeadline};
+
+use edge_runtime_core::{CompiledModule, WorkerContext};
+
+use crate::admin::{AdminState, verify_token};
+
+/// Default number of invocations of a profile.
+pub const DEFAULT_PROFILED_INVOCATIONS: u32 = 10;
+
+/// Maximum number of invocations of a profile.
+pub const MAX_PROFILED_INVOCATIONS: u32 = 1000;
+
+/// Wall-clock time between epoch ticks of the server, and so between
+/// samples.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Guest CPU profiles of modules.
+///
+/// Cloning is cheap; all clones share the same profiles.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    profiles: Arc<DashMap<String, Arc<ModuleProfile>>>,
+}
+
+impl Profiler {
+    /// Create a profiler without profiles.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Profile the next `invocations` invocations of a core module,
+    /// discarding its previous profile.
+    ///
+    /// Only invocations of this compilation of the module are profiled.
+    pub fn start(
+        &self,
+        module_id: &str,
+        module: &CompiledModule,
+        invocations: u32,
+    ) -> ProfileStatus {
+        let profile = Arc::new(ModuleProfile::new(module_id, module, invocations));
+        let status = profile.status();
+        self.profiles.insert(module_id.to_string(), profile);
+        status
+    }
+
+    /// Get the progress of a module's profile.
+    pub fn status(&self, module_id: &str) -> Option<ProfileStatus> {
+        self.profiles.get(module_id).map(|profile| profile.status())
+    }
+
+    /// Export a module's profile as Firefox Profiler JSON.
+    ///
+    /// Exporting completes the profile: it includes the invocations
+    /// profiled so far, and no further invocations are profiled.
+    pub fn export(&self, module_id: &str) -> Option<Vec<u8>> {
+        let profile = self.profiles.get(module_id)?.clone();
+        Some(profile.export())
+    }
+
+    /// Stop and discard a module's profile. Returns `false` if there was
+    /// none.
+    pub fn stop(&self, module_id: &str) -> bool {
+        self.profiles.remove(module_id).is_some()
+    }
+
+    /// Sample an invocation of a module if it is being profiled.
+    ///
+    /// Installs the sampling epoch deadline callback on `store`, which traps
+    /// after `timeout_ticks` epoch ticks like the store's own deadline.
+    /// [`Recorder::finish`] restores the default deadline behavior, so later
+    /// executions in the store, such as deferred work, are not sampled.
+    pub fn attach(
+        &self,
+        module_id: &str,
+        module: &CompiledModule,
+        store: &mut Store<WorkerContext>,
+        timeout_ticks: u64,
+    ) -> Option<Recorder> {
+        let profile = self.profiles.get(module_id)?.clone();
+        if profile.content_hash != module.content_hash() || !profile.claim() {
+            return None;
+        }
+
+        let sampler = Arc::clone(&profile);
+        let mut remaining = timeout_ticks;
+        let mut last_sample = Instant::now();
+        store.epoch_deadline_callback(move |context| {
+            let now = Instant::now();
+            sampler.sample(&context, now.duration_since(last_sample));
+            last_sample = now;
+
+            remaining = remaining.saturating_sub(1);
+            if remaining == 0 {
+                return Err(Trap::Interrupt.into());
+            }
+            Ok(UpdateDeadline::Continue(1))
+        });
+        store.set_epoch_deadline(1);
+
+        Some(Recorder { profile })
+    }
+}
+
+/// Sampling of one invocation.
+///
+/// Counts the invocation as profiled when dropped, and completes the
+/// profile with the last one.
+#[derive(Debug)]
+pub struct Recorder {
+    profile: Arc<ModuleProfile>,
+}
+
+impl Recorder {
+    /// Stop sampling the store and restore its default epoch deadline
+    /// behavior, trapping once the deadline is reached.
+    pub fn finish(self, store: &mut Store<WorkerContext>) {
+        store.epoch_deadline_trap();
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let profiled = self.profile.profiled.fetch_add(1, Ordering::Relaxed) + 1;
+        if profiled >= self.profile.invocations {
+            self.profile.finish();
+        }
+    }
+}
+
+/// Progress of a module's profile.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileStatus {
+    /// Profiled module.
+    pub module_id: String,
+    /// Number of invocations to profile.
+    pub invocations: u32,
+    /// Number of invocations profiled so far.
+    pub profiled: u32,
+    /// Number of samples taken so far.
+    pub samples: usize,
+    /// Whether no further invocations are profiled.
+    pub complete: bool,
+    /// Time the profile was started (RFC 3339).
+    pub started_at: String,
+}
+
+/// Profile of a module's invocations.
+#[derive(Debug)]
+struct ModuleProfile {
+    module_id: String,
+    /// Hash of the profiled compilation of the module.
+    content_hash: String,
+    invocations: u32,
+    /// Number of invocations being or done being sampled.
+    claimed: AtomicU32,
+    /// Number of invocations done being sampled.
+    profiled: AtomicU32,
+    samples: AtomicUsize,
+    started_at: DateTime<Utc>,
+    state: Mutex<ProfileState>,
+}
+
+/// Profiler of a running profile, or the JSON of a completed one.
+#[derive(Debug)]
+enum ProfileState {
+    Running(Box<GuestProfiler>),
+    Complete(Vec<u8>),
+}
+
+impl ModuleProfile {
+    fn new(module_id: &str, module: &CompiledModule, invocations: u32) -> Self {
+        let profiler = GuestProfiler::new(
+            module_id,
+            SAMPLE_INTERVAL,
+            vec![(module_id.to_string(), module.as_core_module().clone())],
+        );
+        Self {
+            module_id: module_id.to_string(),
+            content_hash: module.content_hash().to_string(),
+            invocations,
+            claimed: AtomicU32::new(0),
+            profiled: AtomicU32::new(0),
+            samples: AtomicUsize::new(0),
+            started_at: Utc::now(),
+            state: Mutex::new(ProfileState::Running(Box::new(profiler))),
+        }
+    }
+
+    /// Claim an invocation to sample. Returns `false` once all are claimed
+    /// or the profile is complete.
+    fn claim(&self) -> bool {
+        !self.is_complete()
+            && self
+                .claimed
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |claimed| {
+                    (claimed < self.invocations).then_some(claimed + 1)
+                })
+                .is_ok()
+    }
+
+    /// Sample the guest stack of `store`, if the profile is still running.
+    fn sample(&self, store: impl wasmtime::AsContext, delta: Duration) {
+        let mut state = self.state.lock().expect("profile lock poisoned");
+        if let ProfileState::Running(profiler) = &mut *state {
+            profiler.sample(store, delta);
+            self.samples.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        matches!(
+            *self.state.lock().expect("profile lock poisoned"),
+            ProfileState::Complete(_)
+        )
+    }
+
+    /// Complete the profile, if it is still running.
+    fn finish(&self) {
+        let mut state = self.state.lock().expect("profile lock poisoned");
+        if matches!(*state, ProfileState::Complete(_)) {
+            return;
+        }
+        let ProfileState::Running(profiler) =
+            std::mem::replace(&mut *state, ProfileState::Complete(Vec::new()))
+        else {
+            unreachable!("profile checked to be running");
+        };
+
+        let mut json = Vec::new();
+        if let Err(e) = profiler.finish(&mut json) {
+            warn!(id = %self.module_id, error = %e, "Failed to write profile");
+        }
+        *state = ProfileState::Complete(json);
+    }
+
+    fn status(&self) -> ProfileStatus {
+        ProfileStatus {
+            module_id: self.module_id.clone(),
+            invocations: self.invocations,
+            profiled: self.profiled.load(Ordering::Relaxed),
+            samples: self.samples.load(Ordering::Relaxed),
+            complete: self.is_complete(),
+            started_at: self.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+
+    fn export(&self) -> Vec<u8> {
+        self.finish();
+        match &*self.state.lock().expect("profile lock poisoned") {
+            ProfileState::Complete(json) => json.clone(),
+            ProfileState::Running(_) => Vec::new(),
+        }
+    }
+}
+
+/// Request body for starting a profile.
+#[derive(Debug, Deserialize)]
+pub struct ProfileRequest {
+    /// Number of invocations to profile, from 1 to
+    /// [`MAX_PROFILED_INVOCATIONS`].
+    #[serde(default = "default_invocations")]
+    pub invocations: u32,
+}
+
+fn default_invocations() -> u32 {
+    DEFAULT_PROFILED_INVOCATIONS
+}
+
+/// Profile a module's next invocations.
+///
+/// Replaces the module's previous profile. Requires epoch interruption and
+/// a core module.
+///
+/// # Request
+///
+/// `POST /admin/modules/:id/profile`
+///
+/// ```json
+/// { "invocations": 10 }
+/// ```
+///
+/// # Response
+///
+/// `202 Accepted` with the progress of the profile, in the same format as
+/// `GET`.
+#[instrument(skip(admin_state, headers, body))]
+pub async fn start_profile(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+    Json(body): Json<ProfileRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let state = &admin_state.app_state;
+    let Some(module) = state.get_module(&module_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("Module not found: {module_id}"),
+        )
+            .into_response();
+    };
+    if module.is_component() {
+        return (
+            StatusCode::CONFLICT,
+            "Profiling is not supported for components",
+        )
+            .into_response();
+    }
+    if !state.engine().config().epoch_interruption {
+        return (
+            StatusCode::CONFLICT,
+            "Profiling requires epoch interruption",
+        )
+            .into_response();
+    }
+    if !(1..=MAX_PROFILED_INVOCATIONS).contains(&body.invocations) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("invocations must be between 1 and {MAX_PROFILED_INVOCATIONS}"),
+        )
+            .into_response();
+    }
+
+    info!(id = %module_id, invocations = body.invocations, "Profile started");
+    let status = state
+        .profiler()
+        .start(&module_id, &module, body.invocations);
+    (StatusCode::ACCEPTED, Json(status)).into_response()
+}
+
+/// Get the progress of a module's profile.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/profile`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "module_id": "api",
+///   "invocations": 10,
+///   "profiled": 4,
+///   "samples": 212,
+///   "complete": false,
+///   "started_at": "2025-01-01T00:00:00.000Z"
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn get_profile(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    match admin_state.app_state.profiler().status(&module_id) {
+        Some(status) => Json(status).into_response(),
+        None => no_profile(&module_id),
+    }
+}
+
+/// Download a module's profile.
+///
+/// The profile includes the invocations profiled so far.
+///
+/// # Request
+///
+/// `GET /admin/modules/:id/profile/download`
+///
+/// # Response
+///
+/// The profile in the Firefox Profiler's processed format, as an
+/// attachment named `<id>.profile.json`.
+#[instrument(skip(admin_state, headers))]
+pub async fn download_profile(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    let Some(profile) = admin_state.app_state.profiler().export(&module_id) else {
+        return no_profile(&module_id);
+    };
+    let file_name: String = module_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    (
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}.profile.json\""),
+            ),
+        ],
+        profile,
+    )
+        .into_response()
+}
+
+/// Stop and discard a module's profile.
+///
+/// # Request
+///
+/// `DELETE /admin/modules/:id/profile`
+///
+/// # Response
+///
+/// ```json
+/// {
+///   "id": "api",
+///   "message": "Profile deleted successfully"
+/// }
+/// ```
+#[instrument(skip(admin_state, headers))]
+pub async fn delete_profile(
+    Extension(admin_state): Extension<AdminState>,
+    headers: HeaderMap,
+    Path(module_id): Path<String>,
+) -> impl IntoResponse {
+    if let Err(e) = verify_token(&headers, &admin_state.admin_token) {
+        return e.into_response();
+    }
+
+    if admin_state.app_state.profiler().stop(&module_id) {
+        info!(id = %module_id, "Profile deleted");
+        Json(json!({
+            "id": module_id,
+            "message": "Profile deleted successfully"
+        }))
+        .into_response()
+    } else {
+        no_profile(&module_id)
+    }
+}
+
+fn no_profile(module_id: &str) -> axum::response::Response {
+    (
+        StatusCode::NOT_FOUND,
+        format!("No profile for module: {module_id}"),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::util::ServiceExt;
+
+    use edge_runtime_common::RuntimeConfig;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::invocation::{InvocationOutcome, invoke_module};
+    use crate::request::WasmHttpRequest;
+    use crate::state::AppState;
+
+    /// Spins until interrupted by the execution timeout.
+    const SPIN_WAT: &str = r#"(module
+        (func $spin (loop $forever (br $forever)))
+        (func $handle (call $spin))
+        (func (export "_start") (call $handle)))"#;
+
+    fn spin_state() -> AppState {
+        let mut config = RuntimeConfig::default();
+        config.execution.timeout_ms = 30;
+        config.execution.max_fuel = u64::MAX;
+        let state = AppState::new(&config).unwrap();
+        state.load_module_wat("spin", SPIN_WAT).unwrap();
+        state
+    }
+
+    /// Increment the epoch every millisecond until the guard is dropped.
+    struct EpochTicker(Arc<AtomicBool>);
+
+    impl EpochTicker {
+        fn start(state: &AppState) -> Self {
+            let stop = Arc::new(AtomicBool::new(false));
+            let engine = state.engine().inner().clone();
+            let flag = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                while !flag.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(1));
+                    engine.increment_epoch();
+                }
+            });
+            Self(stop)
+        }
+    }
+
+    impl Drop for EpochTicker {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_profiles_next_invocations() {
+        let state = spin_state();
+        let _ticker = EpochTicker::start(&state);
+        let module = state.get_module("spin").unwrap();
+        let status = state.profiler().start("spin", &module, 2);
+        assert_eq!(status.profiled, 0);
+        assert!(!status.complete);
+
+        let request = WasmHttpRequest::new("GET", "/functions/spin");
+        for request_id in ["req-1", "req-2", "req-3"] {
+            let invocation = invoke_module(&state, "spin", request_id.into(), &request)
+                .await
+                .unwrap();
+            assert_eq!(invocation.outcome(), InvocationOutcome::Timeout);
+        }
+
+        let status = state.profiler().status("spin").unwrap();
+        assert_eq!(status.profiled, 2);
+        assert!(status.complete);
+        assert!(status.samples > 0);
+
+        let profile: Value =
+            serde_json::from_slice(&state.profiler().export("spin").unwrap()).unwrap();
+        assert!(profile["meta"]["interval"].is_number());
+        let threads = profile["threads"].as_array().unwrap();
+        assert_eq!(threads.len(), 1);
+
+        // Functions are named from the module's name section.
+        let names: Vec<&str> = threads[0]["stringArray"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        assert!(names.iter().any(|name| name.contains("spin")), "{names:?}");
+        assert!(
+            names.iter().any(|name| name.contains("handle")),
+            "{names:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_endpoints() {
+        let state = spin_state();
+        let app = crate::build_admin_router(AdminState {
+            app_state: state.clone(),
+            admin_token: "secret".to_string(),
+        })
+        .with_state(state.clone());
+        let request = |method: &str, uri: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("X-Admin-Token", "secret")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/modules/spin/profile", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        for (uri, body, status) in [
+            ("/modules/missing/profile", "{}", StatusCode::NOT_FOUND),
+            (
+                "/modules/spin/profile",
+                r#"{"invocations":0}"#,
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/modules/spin/profile",
+                r#"{"invocations":1}"#,
+                StatusCode::ACCEPTED,
+            ),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(request("POST", uri, body))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{uri} {body}");
+        }
+
+        let _ticker = EpochTicker::start(&state);
+        invoke_module(
+            &state,
+            "spin",
+            "req-1".into(),
+            &WasmHttpRequest::new("GET", "/"),
+        )
+        .await
+        .unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/modules/spin/profile", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["profiled"], 1);
+        assert_eq!(status["complete"], true);
+
+        let response = app
+            .clone()
+            .oneshot(request("GET", "/modules/spin/profile/download", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"spin.profile.json\""
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let profile: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(profile["threads"].as_array().unwrap().len(), 1);
+
+        let response = app
+            .clone()
+            .oneshot(request("DELETE", "/modules/spin/profile", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.profiler().status("spin").is_none());
+    }
+}
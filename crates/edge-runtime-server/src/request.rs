@@ -5,6 +5,7 @@
 
 use axum::http::Request;
 use bytes::Bytes;
+use edge_runtime_host::GuestRequest;
 
 /// Wasm-compatible HTTP request structure.
 ///
@@ -88,6 +89,32 @@ impl WasmHttpRequest {
     }
 }
 
+impl From<&WasmHttpRequest> for GuestRequest {
+    fn from(req: &WasmHttpRequest) -> Self {
+        Self {
+            method: req.method.clone(),
+            uri: req.uri.clone(),
+            headers: req.headers.clone(),
+            body: req.body.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<GuestRequest> for WasmHttpRequest {
+    fn from(req: GuestRequest) -> Self {
+        Self {
+            method: req.method,
+            uri: req.uri,
+            headers: req.headers,
+            body: if req.body.is_empty() {
+                None
+            } else {
+                Some(req.body)
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,6 +159,24 @@ mod tests {
         assert!(req.get_header("X-Missing").is_none());
     }
 
+    #[test]
+    fn test_guest_request_roundtrip() {
+        let mut req = WasmHttpRequest::new("POST", "/orders");
+        req.headers.push(("X-Id".to_string(), "1".to_string()));
+        req.body = Some(b"{}".to_vec());
+
+        let guest = GuestRequest::from(&req);
+        assert_eq!(guest.header("x-id"), Some("1"));
+
+        let back = WasmHttpRequest::from(guest);
+        assert_eq!(back.body.as_deref(), Some(b"{}".as_slice()));
+        assert!(
+            WasmHttpRequest::from(GuestRequest::new("GET", "/"))
+                .body
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_is_json() {
         let mut req = WasmHttpRequest::new("POST", "/");
@@ -5,6 +5,7 @@
 
 use axum::body::Body;
 use axum::http::{HeaderName, HeaderValue, Response, StatusCode};
+use edge_runtime_host::GuestResponse;
 
 /// Wasm-compatible HTTP response structure.
 ///
@@ -95,6 +96,26 @@ impl Default for WasmHttpResponse {
     }
 }
 
+impl From<GuestResponse> for WasmHttpResponse {
+    fn from(resp: GuestResponse) -> Self {
+        Self {
+            status: resp.status,
+            headers: resp.headers,
+            body: resp.body,
+        }
+    }
+}
+
+impl From<WasmHttpResponse> for GuestResponse {
+    fn from(resp: WasmHttpResponse) -> Self {
+        Self {
+            status: resp.status,
+            headers: resp.headers,
+            body: resp.body,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -6,13 +6,16 @@
 use std::time::Duration;
 
 use axum::Router;
+use axum::middleware;
 use axum::routing::{any, get, post};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
+use crate::access_log::access_log;
 use crate::admin::{AdminState, build_admin_router};
-use crate::handler::{handle_function, health_check, list_modules, readiness_check};
+use crate::handler::{handle_function, health_check, list_modules, metrics, readiness_check};
+use crate::jobs::{get_job, submit_async};
 use crate::state::AppState;
 
 /// Admin API configuration for router.
@@ -28,9 +31,14 @@ pub struct AdminRouterConfig {
 /// Routes:
 /// - `POST /functions/:function_id` - Execute a function with request body
 /// - `GET /functions/:function_id` - Execute a function without body
+/// - `POST /functions/:function_id/async` - Enqueue an asynchronous invocation
+/// - `GET /jobs/:job_id` - Get asynchronous invocation status
 /// - `GET /health` - Health check
 /// - `GET /ready` - Readiness check
 /// - `GET /modules` - List loaded modules
+/// - `GET /metrics` - Runtime and module-recorded metrics (Prometheus text format)
+///
+/// Synchronous invocations are written to the access log, if enabled.
 pub fn build_router(state: AppState, request_timeout: Duration) -> Router {
     build_router_with_admin(state, request_timeout, None)
 }
@@ -54,13 +62,20 @@ pub fn build_router_with_admin(
         // GET /functions/:function_id - Execute without body
         .route("/functions/:function_id", get(handle_function))
         // ANY /invoke/:function_id - Simplified invoke endpoint
-        .route("/invoke/:function_id", any(handle_function));
+        .route("/invoke/:function_id", any(handle_function))
+        // Log the synchronous invocations above to the access log
+        .route_layer(middleware::from_fn_with_state(state.clone(), access_log))
+        // POST /functions/:function_id/async - Enqueue for background execution
+        .route("/functions/:function_id/async", post(submit_async))
+        // GET /jobs/:job_id - Asynchronous invocation status
+        .route("/jobs/:job_id", get(get_job));
 
     // Health and monitoring routes
     let health_routes = Router::new()
         .route("/health", get(health_check))
         .route("/ready", get(readiness_check))
-        .route("/modules", get(list_modules));
+        .route("/modules", get(list_modules))
+        .route("/metrics", get(metrics));
 
     // Start building the router
     let mut router = Router::new().merge(function_routes).merge(health_routes);
@@ -94,6 +109,7 @@ mod tests {
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use edge_runtime_common::RuntimeConfig;
+    use edge_runtime_host::MetricKind;
     use tower::util::ServiceExt;
 
     async fn setup_router() -> Router {
@@ -153,6 +169,39 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_metrics() {
+        let config = RuntimeConfig::default();
+        let state = AppState::new(&config).unwrap();
+        state
+            .guest_metrics()
+            .record("shop", MetricKind::Counter, "orders_total", &[], 2.0)
+            .unwrap();
+        state
+            .load_module_wat("shop", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        let app = build_router(state, Duration::from_secs(30));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("shop_orders_total 2\n"));
+        assert!(body.contains("edge_module_compile_duration_seconds{module=\"shop\"}"));
+        assert!(body.contains("edge_pooling_instances_in_use{module=\"shop\"} 0\n"));
+    }
+
     #[tokio::test]
     async fn test_function_not_found() {
         let app = setup_router().await;
@@ -169,4 +218,165 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
+
+    #[tokio::test]
+    async fn test_async_invocation() {
+        let config = RuntimeConfig::default();
+        let state = AppState::new(&config).unwrap();
+        state
+            .load_module_wat("hello", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        let app = build_router(state, Duration::from_secs(30));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/functions/hello/async")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let status_url = body["status_url"].as_str().unwrap().to_string();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(status_url)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_job_not_found() {
+        let app = setup_router().await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/jobs/nonexistent")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    /// Read an HTTP/1.1 request, returning its head and body.
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> (String, Vec<u8>) {
+        use tokio::io::AsyncReadExt;
+
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            let mut byte = [0; 1];
+            stream.read_exact(&mut byte).await.unwrap();
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).unwrap().to_ascii_lowercase();
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: "))
+            .map_or(0, |length| length.parse().unwrap());
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+        (head, body)
+    }
+
+    #[tokio::test]
+    async fn test_trace_export() {
+        use tokio::io::AsyncWriteExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        // Collector stand-in forwarding exported span batches.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (batches, mut received) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let (_, body) = read_request(&mut stream).await;
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                    .await
+                    .unwrap();
+                let _ = batches.send(serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+            }
+        });
+
+        let layer = edge_runtime_host::OtelLayer::new();
+        layer
+            .start_export(&edge_runtime_common::TracingConfig {
+                otlp_endpoint: Some(endpoint),
+                export_interval_ms: 10,
+                ..Default::default()
+            })
+            .unwrap();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat("hello", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        let response = build_router(state, Duration::from_secs(30))
+            .oneshot(
+                Request::builder()
+                    .uri("/functions/hello")
+                    .header("traceparent", TRACEPARENT)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut spans = Vec::new();
+        while !["handle_function", "execute_core"]
+            .iter()
+            .all(|name| spans.iter().any(|s: &serde_json::Value| s["name"] == *name))
+        {
+            let batch = tokio::time::timeout(Duration::from_secs(5), received.recv())
+                .await
+                .expect("spans were not exported")
+                .unwrap();
+            for resource in batch["resourceSpans"].as_array().unwrap() {
+                for scope in resource["scopeSpans"].as_array().unwrap() {
+                    spans.extend(scope["spans"].as_array().unwrap().iter().cloned());
+                }
+            }
+        }
+        let find = |name: &str| spans.iter().find(|s| s["name"] == name).unwrap();
+
+        let request = find("handle_function");
+        assert_eq!(request["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(request["parentSpanId"], "00f067aa0ba902b7");
+
+        // `execute_core` runs within `handle_function`, possibly through
+        // intermediate spans.
+        let mut span = find("execute_core");
+        assert_eq!(span["traceId"], request["traceId"]);
+        while span["parentSpanId"] != request["spanId"] {
+            span = spans
+                .iter()
+                .find(|s| s["spanId"] == span["parentSpanId"])
+                .expect("execute_core is not a descendant of handle_function");
+        }
+    }
 }
@@ -0,0 +1,547 @@
+//! Scheduled (cron) invocation of modules.
+//!
+//! This module provides the [`Scheduler`], a registry of cron schedules keyed
+//! by module ID. A background driver task checks the registry once per second
+//! and invokes every module whose schedule is due with a synthetic request.
+//!
+//! Schedules come from [`ModuleEntry::schedule`] in the configuration file or
+//! from the Admin API. The outcome of the most recent run (status, duration,
+//! fuel and guest logs) is kept for inspection through the Admin API.
+//!
+//! [`ModuleEntry::schedule`]: edge_runtime_common::ModuleEntry::schedule
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::OwnedMutexGuard;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use edge_runtime_common::{OverlapPolicy, RuntimeError};
+use edge_runtime_core::ExecutionResult;
+
+use crate::invocation::{invoke_module, logs_to_json};
+use crate::request::WasmHttpRequest;
+use crate::state::AppState;
+
+/// Header added to synthetic requests, carrying the cron expression.
+pub const SCHEDULE_HEADER: &str = "x-edge-schedule";
+
+/// Interval at which the driver task checks for due schedules.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Registry of cron schedules.
+///
+/// Cloning is cheap; all clones share the same registry.
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    /// Scheduled jobs (module_id -> job).
+    jobs: Arc<DashMap<String, Arc<ScheduledJob>>>,
+}
+
+/// A single module's schedule and run bookkeeping.
+struct ScheduledJob {
+    /// Module to invoke.
+    module_id: String,
+    /// Cron expression as provided by the user.
+    expression: String,
+    /// Parsed schedule.
+    schedule: Schedule,
+    /// Overlap policy.
+    policy: OverlapPolicy,
+    /// Next time the job is due.
+    next_run: Mutex<Option<DateTime<Utc>>>,
+    /// Run bookkeeping, kept when the module is re-scheduled.
+    runs: Arc<RunState>,
+}
+
+/// Runs of a module's schedule, shared by its successive schedules.
+#[derive(Default)]
+struct RunState {
+    /// Held for the duration of a run.
+    run_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Whether a run is queued behind the current one.
+    queued: AtomicBool,
+    /// Number of completed runs.
+    total_runs: AtomicU64,
+    /// Number of runs dropped by the overlap policy.
+    skipped_runs: AtomicU64,
+    /// Record of the most recent run.
+    last_run: Mutex<Option<RunRecord>>,
+}
+
+/// Outcome of a scheduled run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// The entry point completed successfully.
+    Success,
+    /// The guest trapped.
+    Trap,
+    /// The run failed (fuel exhausted, module missing, etc.).
+    Error,
+}
+
+/// Record of a completed scheduled run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunRecord {
+    /// Request ID of the run.
+    pub request_id: String,
+    /// When the run started (RFC 3339).
+    pub started_at: String,
+    /// Run duration in milliseconds.
+    pub duration_ms: u64,
+    /// Run outcome.
+    pub status: RunStatus,
+    /// Error or trap message, if any.
+    pub error: Option<String>,
+    /// Fuel consumed by the run.
+    pub fuel_consumed: u64,
+    /// Guest logs emitted during the run.
+    pub logs: Vec<serde_json::Value>,
+}
+
+/// Schedule information for API responses.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleInfo {
+    /// Module ID.
+    pub module_id: String,
+    /// Cron expression.
+    pub cron: String,
+    /// Overlap policy.
+    pub overlap_policy: OverlapPolicy,
+    /// Next time the job is due (RFC 3339).
+    pub next_run: Option<String>,
+    /// Whether a run is currently executing.
+    pub running: bool,
+    /// Number of completed runs.
+    pub total_runs: u64,
+    /// Number of runs dropped by the overlap policy.
+    pub skipped_runs: u64,
+    /// Record of the most recent run.
+    pub last_run: Option<RunRecord>,
+}
+
+impl Scheduler {
+    /// Create an empty scheduler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule a module, replacing any existing schedule for it.
+    ///
+    /// Replacing a schedule keeps its run history, and a run in progress
+    /// still counts against the new schedule's overlap policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `module_id` - Module to invoke
+    /// * `expression` - Cron expression (five fields, or six/seven with seconds)
+    /// * `policy` - Overlap policy
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cron expression is invalid.
+    pub fn schedule(
+        &self,
+        module_id: &str,
+        expression: &str,
+        policy: OverlapPolicy,
+    ) -> Result<(), RuntimeError> {
+        let schedule = parse_cron(expression)?;
+        let next_run = schedule.upcoming(Utc).next();
+
+        let job = ScheduledJob {
+            module_id: module_id.to_string(),
+            expression: expression.to_string(),
+            schedule,
+            policy,
+            next_run: Mutex::new(next_run),
+            runs: self
+                .jobs
+                .get(module_id)
+                .map(|job| Arc::clone(&job.runs))
+                .unwrap_or_default(),
+        };
+
+        info!(module_id = %module_id, cron = %expression, ?policy, "Module scheduled");
+        self.jobs.insert(module_id.to_string(), Arc::new(job));
+        Ok(())
+    }
+
+    /// Remove a module's schedule.
+    ///
+    /// Returns `true` if a schedule existed. A run already in progress is
+    /// allowed to finish.
+    pub fn unschedule(&self, module_id: &str) -> bool {
+        self.jobs.remove(module_id).is_some()
+    }
+
+    /// Get schedule information for a module.
+    pub fn info(&self, module_id: &str) -> Option<ScheduleInfo> {
+        self.jobs.get(module_id).map(|job| job.info())
+    }
+
+    /// List schedule information for all scheduled modules.
+    pub fn list(&self) -> Vec<ScheduleInfo> {
+        self.jobs.iter().map(|job| job.info()).collect()
+    }
+
+    /// Spawn the background driver task.
+    ///
+    /// The task checks for due schedules once per second until aborted.
+    pub fn spawn(&self, state: AppState) -> JoinHandle<()> {
+        let scheduler = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                scheduler.tick(&state, Utc::now());
+            }
+        })
+    }
+
+    /// Fire every job that is due at `now`.
+    fn tick(&self, state: &AppState, now: DateTime<Utc>) {
+        let due: Vec<Arc<ScheduledJob>> = self
+            .jobs
+            .iter()
+            .filter(|job| job.take_due(now))
+            .map(|job| job.value().clone())
+            .collect();
+
+        for job in due {
+            fire(state.clone(), job);
+        }
+    }
+
+    /// Run a module's schedule immediately, applying its overlap policy.
+    ///
+    /// Returns `false` if the module is not scheduled.
+    pub fn trigger(&self, state: &AppState, module_id: &str) -> bool {
+        let Some(job) = self.jobs.get(module_id).map(|j| j.value().clone()) else {
+            return false;
+        };
+        fire(state.clone(), job);
+        true
+    }
+}
+
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("jobs_count", &self.jobs.len())
+            .finish()
+    }
+}
+
+impl ScheduledJob {
+    /// If the job is due, advance its next run time and return `true`.
+    fn take_due(&self, now: DateTime<Utc>) -> bool {
+        let mut next_run = self.next_run.lock().unwrap_or_else(|e| e.into_inner());
+        match *next_run {
+            Some(due) if due <= now => {
+                *next_run = self.schedule.after(&now).next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn info(&self) -> ScheduleInfo {
+        let next_run = *self.next_run.lock().unwrap_or_else(|e| e.into_inner());
+        let last_run = self
+            .runs
+            .last_run
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+
+        ScheduleInfo {
+            module_id: self.module_id.clone(),
+            cron: self.expression.clone(),
+            overlap_policy: self.policy,
+            next_run: next_run.map(|t| t.to_rfc3339()),
+            running: self.runs.run_lock.try_lock().is_err(),
+            total_runs: self.runs.total_runs.load(Ordering::Relaxed),
+            skipped_runs: self.runs.skipped_runs.load(Ordering::Relaxed),
+            last_run,
+        }
+    }
+
+    fn skip(&self) {
+        self.runs.skipped_runs.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            module_id = %self.module_id,
+            policy = ?self.policy,
+            "Scheduled run skipped: previous run still in progress"
+        );
+    }
+}
+
+/// Start a run of `job`, honoring its overlap policy.
+fn fire(state: AppState, job: Arc<ScheduledJob>) {
+    if let Ok(guard) = job.runs.run_lock.clone().try_lock_owned() {
+        tokio::spawn(run_job(state, job, guard));
+        return;
+    }
+
+    match job.policy {
+        OverlapPolicy::Skip => job.skip(),
+        OverlapPolicy::Queue => {
+            if job.runs.queued.swap(true, Ordering::AcqRel) {
+                job.skip();
+                return;
+            }
+            debug!(module_id = %job.module_id, "Scheduled run queued");
+            tokio::spawn(async move {
+                let guard = job.runs.run_lock.clone().lock_owned().await;
+                job.runs.queued.store(false, Ordering::Release);
+                run_job(state, job, guard).await;
+            });
+        }
+    }
+}
+
+/// Invoke the job's module and record the outcome.
+async fn run_job(state: AppState, job: Arc<ScheduledJob>, _guard: OwnedMutexGuard<()>) {
+    let request_id = Uuid::new_v4().to_string();
+    let started_at = Utc::now();
+
+    let mut request = WasmHttpRequest::new("POST", &format!("/functions/{}", job.module_id));
+    request
+        .headers
+        .push((SCHEDULE_HEADER.to_string(), job.expression.clone()));
+
+    info!(
+        module_id = %job.module_id,
+        request_id = %request_id,
+        "Running scheduled invocation"
+    );
+
+    let record = match invoke_module(&state, &job.module_id, request_id.clone(), &request).await {
+        Ok(invocation) => {
+            let (status, error) = match &invocation.result {
+                Ok(ExecutionResult::Success) => (RunStatus::Success, None),
+                Ok(ExecutionResult::Trap { message, .. }) => {
+                    (RunStatus::Trap, Some(message.clone()))
+                }
+                Err(e) => (RunStatus::Error, Some(e.to_string())),
+            };
+            RunRecord {
+                request_id,
+                started_at: started_at.to_rfc3339(),
+                duration_ms: duration_ms(invocation.duration),
+                status,
+                error,
+                fuel_consumed: invocation.metrics.fuel_consumed,
+                logs: logs_to_json(&invocation.logs),
+            }
+        }
+        Err(e) => RunRecord {
+            request_id,
+            started_at: started_at.to_rfc3339(),
+            duration_ms: 0,
+            status: RunStatus::Error,
+            error: Some(e.to_string()),
+            fuel_consumed: 0,
+            logs: Vec::new(),
+        },
+    };
+
+    info!(
+        module_id = %job.module_id,
+        request_id = %record.request_id,
+        status = ?record.status,
+        duration_ms = record.duration_ms,
+        "Scheduled invocation finished"
+    );
+
+    job.runs.total_runs.fetch_add(1, Ordering::Relaxed);
+    *job.runs.last_run.lock().unwrap_or_else(|e| e.into_inner()) = Some(record);
+}
+
+/// Parse a cron expression.
+///
+/// Five-field expressions (minute precision) are accepted by prepending a
+/// seconds field of `0`.
+fn parse_cron(expression: &str) -> Result<Schedule, RuntimeError> {
+    let expression = expression.trim();
+    let normalized = if expression.split_whitespace().count() == 5 {
+        format!("0 {expression}")
+    } else {
+        expression.to_string()
+    };
+
+    Schedule::from_str(&normalized).map_err(|e| {
+        RuntimeError::invalid_config(format!("Invalid cron expression '{expression}': {e}"))
+    })
+}
+
+/// Convert a duration to whole milliseconds, saturating on overflow.
+fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edge_runtime_common::RuntimeConfig;
+
+    fn test_state() -> AppState {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state
+            .load_module_wat("job", r#"(module (func (export "_start")))"#)
+            .unwrap();
+        state
+    }
+
+    async fn wait_for_run(scheduler: &Scheduler, module_id: &str, runs: u64) -> ScheduleInfo {
+        for _ in 0..200 {
+            let info = scheduler.info(module_id).unwrap();
+            if info.total_runs >= runs {
+                return info;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("scheduled run did not complete");
+    }
+
+    #[test]
+    fn test_parse_cron_five_fields() {
+        assert!(parse_cron("*/5 * * * *").is_ok());
+        assert!(parse_cron("0 */5 * * * *").is_ok());
+        assert!(parse_cron("not a cron").is_err());
+    }
+
+    #[test]
+    fn test_schedule_and_unschedule() {
+        let scheduler = Scheduler::new();
+        scheduler
+            .schedule("job", "* * * * *", OverlapPolicy::Skip)
+            .unwrap();
+
+        let info = scheduler.info("job").unwrap();
+        assert_eq!(info.cron, "* * * * *");
+        assert!(info.next_run.is_some());
+        assert!(info.last_run.is_none());
+
+        assert!(scheduler.unschedule("job"));
+        assert!(scheduler.info("job").is_none());
+    }
+
+    #[test]
+    fn test_schedule_invalid_expression() {
+        let scheduler = Scheduler::new();
+        let result = scheduler.schedule("job", "every minute", OverlapPolicy::Skip);
+        assert!(result.is_err());
+        assert!(scheduler.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tick_runs_due_job() {
+        let state = test_state();
+        let scheduler = Scheduler::new();
+        scheduler
+            .schedule("job", "* * * * * *", OverlapPolicy::Skip)
+            .unwrap();
+
+        scheduler.tick(&state, Utc::now() + chrono::Duration::seconds(2));
+
+        let info = wait_for_run(&scheduler, "job", 1).await;
+        let last_run = info.last_run.unwrap();
+        assert_eq!(last_run.status, RunStatus::Success);
+    }
+
+    #[tokio::test]
+    async fn test_missing_module_records_error() {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        let scheduler = Scheduler::new();
+        scheduler
+            .schedule("gone", "* * * * *", OverlapPolicy::Skip)
+            .unwrap();
+
+        assert!(scheduler.trigger(&state, "gone"));
+
+        let info = wait_for_run(&scheduler, "gone", 1).await;
+        assert_eq!(info.last_run.unwrap().status, RunStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_skip_policy_drops_overlapping_run() {
+        let state = test_state();
+        let scheduler = Scheduler::new();
+        scheduler
+            .schedule("job", "* * * * *", OverlapPolicy::Skip)
+            .unwrap();
+
+        let job = scheduler.jobs.get("job").unwrap().value().clone();
+        let guard = job.runs.run_lock.clone().try_lock_owned().unwrap();
+
+        scheduler.trigger(&state, "job");
+        assert_eq!(scheduler.info("job").unwrap().skipped_runs, 1);
+
+        drop(guard);
+    }
+
+    #[tokio::test]
+    async fn test_queue_policy_runs_after_current() {
+        let state = test_state();
+        let scheduler = Scheduler::new();
+        scheduler
+            .schedule("job", "* * * * *", OverlapPolicy::Queue)
+            .unwrap();
+
+        let job = scheduler.jobs.get("job").unwrap().value().clone();
+        let guard = job.runs.run_lock.clone().try_lock_owned().unwrap();
+
+        scheduler.trigger(&state, "job");
+        scheduler.trigger(&state, "job");
+        assert_eq!(scheduler.info("job").unwrap().skipped_runs, 1);
+
+        drop(guard);
+
+        let info = wait_for_run(&scheduler, "job", 1).await;
+        assert_eq!(info.total_runs, 1);
+        assert_eq!(info.last_run.unwrap().status, RunStatus::Success);
+    }
+
+    #[tokio::test]
+    async fn test_reschedule_keeps_runs() {
+        let state = test_state();
+        let scheduler = Scheduler::new();
+        scheduler
+            .schedule("job", "* * * * *", OverlapPolicy::Skip)
+            .unwrap();
+        scheduler.trigger(&state, "job");
+        wait_for_run(&scheduler, "job", 1).await;
+
+        let job = scheduler.jobs.get("job").unwrap().value().clone();
+        let guard = job.runs.run_lock.clone().try_lock_owned().unwrap();
+
+        scheduler
+            .schedule("job", "*/5 * * * *", OverlapPolicy::Skip)
+            .unwrap();
+        let info = scheduler.info("job").unwrap();
+        assert_eq!(info.cron, "*/5 * * * *");
+        assert!(info.running);
+        assert_eq!(info.total_runs, 1);
+        assert!(info.last_run.is_some());
+
+        // The run in progress still blocks the new schedule.
+        scheduler.trigger(&state, "job");
+        assert_eq!(scheduler.info("job").unwrap().skipped_runs, 1);
+
+        drop(guard);
+    }
+}
@@ -9,8 +9,18 @@ use std::time::Duration;
 use tokio::net::TcpListener;
 use tracing::info;
 
-use edge_runtime_common::{RuntimeConfig, RuntimeError};
-
+use edge_runtime_common::{
+    AccessLogConfig, BlobConfig, CacheConfig, CryptoConfig, JobsConfig, KvConfig, LoggingConfig,
+    MetricsConfig, RuntimeConfig, RuntimeError, SecretsConfig, SocketsConfig, SqlConfig,
+    TracingConfig, WebSocketConfig,
+};
+use edge_runtime_host::{
+    BlobStore, ConfigStore, GuestMetrics, Keyring, KvStore, LogPolicy, SecretsKey, SocketConnector,
+    SqlStore,
+};
+
+use crate::access_log::AccessLog;
+use crate::log_sinks::LogSinks;
 use crate::router::{AdminRouterConfig, build_router_with_admin};
 use crate::state::AppState;
 
@@ -23,6 +33,33 @@ pub struct ServerConfig {
     pub request_timeout_secs: u64,
     /// Enable graceful shutdown on SIGTERM/SIGINT.
     pub graceful_shutdown: bool,
+    /// Asynchronous invocation queue settings.
+    pub jobs: JobsConfig,
+    /// Key-value store settings.
+    pub kv: KvConfig,
+    /// Response cache settings.
+    pub cache: CacheConfig,
+    /// Embedded SQL database settings.
+    pub sql: SqlConfig,
+    /// Blob storage settings.
+    pub blob: BlobConfig,
+    /// Module secrets settings.
+    pub secrets: SecretsConfig,
+    /// Crypto host function settings.
+    pub crypto: CryptoConfig,
+    /// Outbound TCP socket settings.
+    pub sockets: SocketsConfig,
+    /// Outbound WebSocket settings.
+    pub websocket: WebSocketConfig,
+    /// Guest metrics settings.
+    pub metrics: MetricsConfig,
+
+    /// Guest log settings.
+    pub logging: LoggingConfig,
+    /// Access log settings.
+    pub access_log: AccessLogConfig,
+    /// Trace export settings.
+    pub tracing: TracingConfig,
 }
 
 impl Default for ServerConfig {
@@ -31,6 +68,19 @@ impl Default for ServerConfig {
             bind_addr: "0.0.0.0:8080".parse().unwrap(),
             request_timeout_secs: 30,
             graceful_shutdown: true,
+            jobs: JobsConfig::default(),
+            kv: KvConfig::default(),
+            cache: CacheConfig::default(),
+            sql: SqlConfig::default(),
+            blob: BlobConfig::default(),
+            secrets: SecretsConfig::default(),
+            crypto: CryptoConfig::default(),
+            sockets: SocketsConfig::default(),
+            websocket: WebSocketConfig::default(),
+            metrics: MetricsConfig::default(),
+            logging: LoggingConfig::default(),
+            access_log: AccessLogConfig::default(),
+            tracing: TracingConfig::default(),
         }
     }
 }
@@ -48,6 +98,84 @@ impl ServerConfig {
         self
     }
 
+    /// Create a new server config with custom job queue settings.
+    pub fn with_jobs(mut self, jobs: JobsConfig) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Create a new server config with custom key-value store settings.
+    pub fn with_kv(mut self, kv: KvConfig) -> Self {
+        self.kv = kv;
+        self
+    }
+
+    /// Create a new server config with custom response cache settings.
+    pub fn with_cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Create a new server config with custom SQL database settings.
+    pub fn with_sql(mut self, sql: SqlConfig) -> Self {
+        self.sql = sql;
+        self
+    }
+
+    /// Create a new server config with custom blob storage settings.
+    pub fn with_blob(mut self, blob: BlobConfig) -> Self {
+        self.blob = blob;
+        self
+    }
+
+    /// Create a new server config with custom module secrets settings.
+    pub fn with_secrets(mut self, secrets: SecretsConfig) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Create a new server config with custom crypto settings.
+    pub fn with_crypto(mut self, crypto: CryptoConfig) -> Self {
+        self.crypto = crypto;
+        self
+    }
+
+    /// Create a new server config with custom outbound socket settings.
+    pub fn with_sockets(mut self, sockets: SocketsConfig) -> Self {
+        self.sockets = sockets;
+        self
+    }
+
+    /// Create a new server config with custom outbound WebSocket settings.
+    pub fn with_websocket(mut self, websocket: WebSocketConfig) -> Self {
+        self.websocket = websocket;
+        self
+    }
+
+    /// Create a new server config with custom guest metrics settings.
+    pub fn with_metrics(mut self, metrics: MetricsConfig) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Create a new server config with custom guest log settings.
+    pub fn with_logging(mut self, logging: LoggingConfig) -> Self {
+        self.logging = logging;
+        self
+    }
+
+    /// Create a new server config with custom access log settings.
+    pub fn with_access_log(mut self, access_log: AccessLogConfig) -> Self {
+        self.access_log = access_log;
+        self
+    }
+
+    /// Create a new server config with custom trace export settings.
+    pub fn with_tracing(mut self, tracing: TracingConfig) -> Self {
+        self.tracing = tracing;
+        self
+    }
+
     /// Get the request timeout as Duration.
     pub fn request_timeout(&self) -> Duration {
         Duration::from_secs(self.request_timeout_secs)
@@ -94,12 +222,29 @@ impl EdgeServer {
     ///
     /// # Errors
     ///
-    /// Returns an error if the runtime cannot be initialized.
+    /// Returns an error if the runtime or one of the storage backends cannot
+    /// be initialized.
     pub fn new(
         runtime_config: &RuntimeConfig,
         server_config: ServerConfig,
     ) -> Result<Self, RuntimeError> {
-        let state = AppState::new(runtime_config)?;
+        let mut state = AppState::new(runtime_config)?
+            .with_jobs_config(server_config.jobs.clone())
+            .with_kv_store(KvStore::open(&server_config.kv)?)
+            .with_cache_config(&server_config.cache)
+            .with_sql_store(SqlStore::open(&server_config.sql)?)
+            .with_config_store(open_config_store(&server_config.secrets)?)
+            .with_keyring(Keyring::load(&server_config.crypto)?)
+            .with_socket_connector(SocketConnector::new(&server_config.sockets))
+            .with_guest_metrics(GuestMetrics::new(
+                server_config.metrics.max_series_per_module,
+            ))
+            .with_log_policy(LogPolicy::new(&server_config.logging))
+            .with_log_sinks(LogSinks::open(&server_config.logging)?)
+            .with_access_log(AccessLog::open(&server_config.access_log)?);
+        if server_config.blob.directory.is_some() {
+            state = state.with_blob_store(BlobStore::open(&server_config.blob)?);
+        }
 
         Ok(Self {
             state,
@@ -144,6 +289,9 @@ impl EdgeServer {
         // This is needed for the epoch increment background task.
         let epoch_engine = self.state.engine().clone();
 
+        // Start the cron scheduler driver.
+        let scheduler_task = self.state.scheduler().spawn(self.state.clone());
+
         let app =
             build_router_with_admin(self.state, self.config.request_timeout(), self.admin_config);
 
@@ -164,16 +312,24 @@ impl EdgeServer {
         });
 
         if self.config.graceful_shutdown {
-            axum::serve(listener, app)
-                .with_graceful_shutdown(shutdown_signal())
-                .await
-                .map_err(|e| RuntimeError::invalid_config(format!("Server error: {e}")))?;
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown_signal())
+            .await
+            .map_err(|e| RuntimeError::invalid_config(format!("Server error: {e}")))?;
         } else {
-            axum::serve(listener, app)
-                .await
-                .map_err(|e| RuntimeError::invalid_config(format!("Server error: {e}")))?;
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .map_err(|e| RuntimeError::invalid_config(format!("Server error: {e}")))?;
         }
 
+        scheduler_task.abort();
+
         info!("Server shutdown complete");
         Ok(())
     }
@@ -198,11 +354,14 @@ impl EdgeServer {
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
 
         let handle = tokio::spawn(async move {
-            axum::serve(listener, app)
-                .with_graceful_shutdown(async {
-                    let _ = shutdown_rx.await;
-                })
-                .await
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await
         });
 
         Ok(TestHandle {
@@ -253,6 +412,25 @@ impl TestHandle {
     }
 }
 
+/// Open the configuration store, loading secrets if a secrets file is set.
+///
+/// The decryption key is read from the environment variable named by
+/// `key_env`.
+fn open_config_store(secrets: &SecretsConfig) -> Result<ConfigStore, RuntimeError> {
+    let Some(path) = &secrets.path else {
+        return Ok(ConfigStore::new());
+    };
+    let key = std::env::var(&secrets.key_env).map_err(|_| {
+        RuntimeError::invalid_config(format!(
+            "Secrets file '{path}' requires the key in environment variable {}",
+            secrets.key_env
+        ))
+    })?;
+    let store = ConfigStore::with_secrets_file(path, SecretsKey::from_hex(&key)?)?;
+    info!(path = %path, "Secrets loaded");
+    Ok(store)
+}
+
 /// Wait for shutdown signal (SIGTERM or SIGINT).
 async fn shutdown_signal() {
     let ctrl_c = async {
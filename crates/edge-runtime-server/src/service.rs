@@ -0,0 +1,276 @@
+//! Service bindings backed by the module registry.
+//!
+//! This module implements [`ServiceInvoker`] for [`AppState`], so that a
+//! guest calling `invoke(module-id, request)` runs the target module
+//! in-process through the same invocation path as HTTP requests.
+
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use edge_runtime_common::RuntimeError;
+use edge_runtime_core::ExecutionResult;
+use edge_runtime_host::GuestResponse;
+use edge_runtime_host::service::{ServiceCall, ServiceError, ServiceInvoker, ServiceOutcome};
+
+use crate::invocation::invoke_module_at_depth;
+use crate::request::WasmHttpRequest;
+use crate::state::AppState;
+
+#[async_trait]
+impl ServiceInvoker for AppState {
+    async fn invoke(&self, call: ServiceCall) -> ServiceOutcome {
+        // The callee runs on whatever fuel and time the caller has left.
+        let mut exec_config = self.exec_config().clone();
+        if let Some(fuel) = call.fuel {
+            exec_config.max_fuel = fuel;
+        }
+        if let Some(deadline) = call.deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let remaining = u64::try_from(remaining.as_millis()).unwrap_or(u64::MAX);
+            exec_config.timeout_ms = exec_config.timeout_ms.min(remaining);
+        }
+
+        let request = WasmHttpRequest::from(call.request);
+        let invocation = invoke_module_at_depth(
+            self,
+            &call.target,
+            call.request_id,
+            &request,
+            &exec_config,
+            call.depth,
+        )
+        .await;
+
+        let invocation = match invocation {
+            Ok(invocation) => invocation,
+            Err(e) if e.is_not_found() => {
+                return ServiceOutcome {
+                    result: Err(ServiceError::NotFound {
+                        target: call.target,
+                    }),
+                    fuel_consumed: 0,
+                };
+            }
+            Err(e) => {
+                return ServiceOutcome {
+                    result: Err(ServiceError::Failed {
+                        message: e.to_string(),
+                    }),
+                    fuel_consumed: 0,
+                };
+            }
+        };
+
+        debug!(
+            caller = ?call.caller,
+            target = %call.target,
+            depth = call.depth,
+            fuel_consumed = invocation.metrics.fuel_consumed,
+            "Service call finished"
+        );
+
+        let result = match invocation.result {
+            Ok(ExecutionResult::Success) => Ok(invocation
+                .response
+                .map(GuestResponse::from)
+                .unwrap_or_default()),
+            Ok(ExecutionResult::Trap { message, .. }) => Err(ServiceError::Failed { message }),
+            Err(RuntimeError::FuelExhausted) => Err(ServiceError::FuelExhausted),
+            Err(e) => Err(ServiceError::Failed {
+                message: e.to_string(),
+            }),
+        };
+
+        ServiceOutcome {
+            result,
+            fuel_consumed: invocation.metrics.fuel_consumed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    use edge_runtime_common::{ExecutionConfig, RuntimeConfig};
+    use edge_runtime_host::service::{ServiceCall, ServiceError, ServiceInvoker};
+    use edge_runtime_host::{GuestRequest, Permissions};
+
+    use crate::invocation::invoke_module;
+    use crate::request::WasmHttpRequest;
+    use crate::state::AppState;
+
+    /// Module that answers with status 201 and body "pong".
+    const CALLEE: &str = r#"(module
+        (import "env" "response_set_status" (func $status (param i32) (result i32)))
+        (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "pong")
+        (func (export "_start")
+            (drop (call $status (i32.const 201)))
+            (drop (call $write (i32.const 0) (i32.const 4)))))"#;
+
+    /// Module that calls `callee` and relays its status and body.
+    const CALLER: &str = r#"(module
+        (import "env" "service_invoke"
+            (func $invoke (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "env" "service_response_status" (func $rstatus (param i32) (result i32)))
+        (import "env" "service_response_body" (func $rbody (param i32 i32 i32) (result i32)))
+        (import "env" "response_set_status" (func $status (param i32) (result i32)))
+        (import "env" "response_write" (func $write (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "callee")
+        (data (i32.const 16) "/ping")
+        (func (export "_start")
+            (local $h i32)
+            (local.set $h (call $invoke
+                (i32.const 0) (i32.const 6)
+                (i32.const 0) (i32.const 0)
+                (i32.const 16) (i32.const 5)
+                (i32.const 0) (i32.const 0)
+                (i32.const 0) (i32.const 0)))
+            (if (i32.lt_s (local.get $h) (i32.const 0))
+                (then
+                    (drop (call $status (i32.sub (i32.const 500) (local.get $h))))
+                    (return)))
+            (drop (call $status (call $rstatus (local.get $h))))
+            (drop (call $write (i32.const 64)
+                (call $rbody (local.get $h) (i32.const 64) (i32.const 64))))))"#;
+
+    /// Module that calls itself until the depth limit stops it.
+    const RECURSIVE: &str = r#"(module
+        (import "env" "service_invoke"
+            (func $invoke (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32) (result i32)))
+        (import "env" "response_set_status" (func $status (param i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "loop")
+        (func (export "_start")
+            (local $h i32)
+            (local.set $h (call $invoke
+                (i32.const 0) (i32.const 4)
+                (i32.const 0) (i32.const 0)
+                (i32.const 0) (i32.const 0)
+                (i32.const 0) (i32.const 0)
+                (i32.const 0) (i32.const 0)))
+            (if (i32.lt_s (local.get $h) (i32.const 0))
+                (then (drop (call $status (i32.sub (i32.const 500) (local.get $h))))))))"#;
+
+    fn state() -> AppState {
+        let state = AppState::new(&RuntimeConfig::default()).unwrap();
+        state.load_module_wat("callee", CALLEE).unwrap();
+        state.load_module_wat("caller", CALLER).unwrap();
+        state.load_module_wat("loop", RECURSIVE).unwrap();
+        state
+    }
+
+    #[tokio::test]
+    async fn test_service_call() {
+        let state = state();
+        state.set_module_permissions(
+            "caller",
+            Permissions::builder().allow_services(["callee"]).build(),
+        );
+
+        let request = WasmHttpRequest::new("GET", "/functions/caller");
+        let invocation = invoke_module(&state, "caller", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.service_calls, 1);
+        let response = invocation.response.unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_service_call_denied() {
+        let state = state();
+
+        let request = WasmHttpRequest::new("GET", "/functions/caller");
+        let invocation = invoke_module(&state, "caller", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        // 500 - (-2): permission denied
+        assert_eq!(invocation.response.unwrap().status, 502);
+    }
+
+    #[tokio::test]
+    async fn test_service_call_depth_limit() {
+        let state = state();
+        state.set_module_permissions(
+            "loop",
+            Permissions::builder().allow_services(["loop"]).build(),
+        );
+
+        let request = WasmHttpRequest::new("GET", "/functions/loop");
+        let invocation = invoke_module(&state, "loop", "req-1".into(), &request)
+            .await
+            .unwrap();
+
+        // The innermost call fails with depth exceeded (-4); each outer
+        // level sees a successful call without a response.
+        assert!(invocation.is_success());
+        assert_eq!(invocation.metrics.service_calls, 1);
+        assert!(invocation.response.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_service_call_bounded_by_caller_deadline() {
+        let state = AppState::new(&RuntimeConfig {
+            execution: ExecutionConfig {
+                timeout_ms: 10_000,
+                max_fuel: u64::MAX,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        state
+            .load_module_wat(
+                "spin",
+                r#"(module (func (export "_start") (loop $l (br $l))))"#,
+            )
+            .unwrap();
+
+        // Tick the epoch like the server does, so the deadline can fire.
+        let stop = Arc::new(AtomicBool::new(false));
+        let ticker = {
+            let engine = state.engine().inner().clone();
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(1));
+                    engine.increment_epoch();
+                }
+            })
+        };
+
+        let start = Instant::now();
+        let outcome = state
+            .invoke(ServiceCall {
+                request_id: "req-1".into(),
+                caller: Some("caller".into()),
+                target: "spin".into(),
+                request: GuestRequest::default(),
+                depth: 1,
+                fuel: None,
+                deadline: Some(start + Duration::from_millis(50)),
+            })
+            .await;
+        stop.store(true, Ordering::Relaxed);
+        ticker.join().unwrap();
+
+        assert!(
+            matches!(outcome.result, Err(ServiceError::Failed { .. })),
+            "{:?}",
+            outcome.result
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -4,12 +4,26 @@
 //! across all HTTP request handlers.
 
 use std::sync::Arc;
+use std::time::Instant;
 
 use dashmap::DashMap;
 
-use edge_runtime_common::{ExecutionConfig, RuntimeConfig, RuntimeError};
+use edge_runtime_common::{CacheConfig, ExecutionConfig, JobsConfig, RuntimeConfig, RuntimeError};
 use edge_runtime_core::{CompiledModule, InstanceRunner, WasmEngine};
-use edge_runtime_host::{Permissions, create_instance_runner};
+use edge_runtime_host::{
+    BlobStore, ConfigStore, GuestMetrics, Keyring, KvStore, LogPolicy, Permissions, ResponseCache,
+    SocketConnector, SqlStore, create_instance_runner,
+};
+
+use crate::access_log::AccessLog;
+use crate::jobs::JobQueue;
+use crate::log_sinks::LogSinks;
+use crate::log_tail::LogTail;
+use crate::metrics::RuntimeMetrics;
+use crate::module_stats::ModuleStats;
+use crate::pipeline::Pipelines;
+use crate::profiling::Profiler;
+use crate::scheduler::Scheduler;
 
 /// Shared state across all request handlers.
 ///
@@ -25,11 +39,71 @@ pub struct AppState {
     /// Compiled module cache (module_id -> CompiledModule).
     modules: Arc<DashMap<String, Arc<CompiledModule>>>,
 
+    /// Statistics of the loaded modules.
+    module_stats: Arc<DashMap<String, Arc<ModuleStats>>>,
+
     /// Execution configuration.
     exec_config: ExecutionConfig,
 
     /// Default permissions for functions.
     default_permissions: Permissions,
+
+    /// Per-module permissions overriding the defaults.
+    module_permissions: Arc<DashMap<String, Permissions>>,
+
+    /// Cron schedules for modules.
+    scheduler: Scheduler,
+
+    /// Asynchronous invocation queue.
+    jobs: JobQueue,
+
+    /// Middleware pipelines composed from modules.
+    pipelines: Pipelines,
+
+    /// Key-value store shared by all modules.
+    kv: KvStore,
+
+    /// Response cache, partitioned by module.
+    cache: ResponseCache,
+
+    /// Embedded SQL databases, one per namespace.
+    sql: SqlStore,
+
+    /// Blob storage, if configured.
+    blobs: Option<BlobStore>,
+
+    /// Per-module configuration values and secrets.
+    config: ConfigStore,
+
+    /// Host-held crypto keys.
+    keyring: Keyring,
+
+    /// Settings for outbound TCP connections.
+    sockets: SocketConnector,
+
+    /// Metrics recorded by modules.
+    metrics: GuestMetrics,
+
+    /// Metrics recorded by the runtime.
+    runtime_metrics: RuntimeMetrics,
+
+    /// Default guest log policy.
+    default_log_policy: LogPolicy,
+
+    /// Per-module log policies overriding the default.
+    log_policies: Arc<DashMap<String, LogPolicy>>,
+
+    /// Destinations of guest logs.
+    log_sinks: LogSinks,
+
+    /// Live tail of guest logs for Admin API clients.
+    log_tail: LogTail,
+
+    /// Access log of function invocations.
+    access_log: AccessLog,
+
+    /// Guest CPU profiles requested through the Admin API.
+    profiler: Profiler,
 }
 
 impl AppState {
@@ -50,11 +124,115 @@ impl AppState {
             engine,
             runner,
             modules: Arc::new(DashMap::new()),
+            module_stats: Arc::new(DashMap::new()),
             exec_config: config.execution.clone(),
             default_permissions: Permissions::builder().enable_logging().build(),
+            module_permissions: Arc::new(DashMap::new()),
+            scheduler: Scheduler::new(),
+            jobs: JobQueue::default(),
+            pipelines: Pipelines::new(),
+            kv: KvStore::memory(),
+            cache: ResponseCache::new(CacheConfig::default().max_bytes_per_module),
+            sql: SqlStore::memory(),
+            blobs: None,
+            config: ConfigStore::new(),
+            keyring: Keyring::default(),
+            sockets: SocketConnector::default(),
+            metrics: GuestMetrics::default(),
+            runtime_metrics: RuntimeMetrics::new(),
+            default_log_policy: LogPolicy::default(),
+            log_policies: Arc::new(DashMap::new()),
+            log_sinks: LogSinks::new(),
+            log_tail: LogTail::default(),
+            access_log: AccessLog::default(),
+            profiler: Profiler::new(),
         })
     }
 
+    /// Replace the asynchronous invocation queue with one using `config`.
+    #[must_use]
+    pub fn with_jobs_config(mut self, config: JobsConfig) -> Self {
+        self.jobs = JobQueue::new(config);
+        self
+    }
+
+    /// Replace the key-value store.
+    #[must_use]
+    pub fn with_kv_store(mut self, kv: KvStore) -> Self {
+        self.kv = kv;
+        self
+    }
+
+    /// Replace the response cache with one using `config`.
+    #[must_use]
+    pub fn with_cache_config(mut self, config: &CacheConfig) -> Self {
+        self.cache = ResponseCache::new(config.max_bytes_per_module);
+        self
+    }
+
+    /// Replace the SQL store.
+    #[must_use]
+    pub fn with_sql_store(mut self, sql: SqlStore) -> Self {
+        self.sql = sql;
+        self
+    }
+
+    /// Enable blob storage.
+    #[must_use]
+    pub fn with_blob_store(mut self, blobs: BlobStore) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    /// Replace the configuration store.
+    #[must_use]
+    pub fn with_config_store(mut self, config: ConfigStore) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Replace the crypto keyring.
+    #[must_use]
+    pub fn with_keyring(mut self, keyring: Keyring) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    /// Replace the outbound TCP connection settings.
+    #[must_use]
+    pub fn with_socket_connector(mut self, sockets: SocketConnector) -> Self {
+        self.sockets = sockets;
+        self
+    }
+
+    /// Replace the guest metrics registry.
+    #[must_use]
+    pub fn with_guest_metrics(mut self, metrics: GuestMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Replace the default guest log policy.
+    #[must_use]
+    pub fn with_log_policy(mut self, policy: LogPolicy) -> Self {
+        self.default_log_policy = policy;
+        self
+    }
+
+    /// Replace the guest log sinks.
+    #[must_use]
+    pub fn with_log_sinks(mut self, sinks: LogSinks) -> Self {
+        self.log_sinks = sinks;
+        self
+    }
+
+    /// Replace the access log.
+    #[must_use]
+    pub fn with_access_log(mut self, access_log: AccessLog) -> Self {
+        self.access_log = access_log;
+        self
+    }
+
     /// Get the Wasmtime engine.
     pub fn engine(&self) -> &WasmEngine {
         &self.engine
@@ -75,8 +253,136 @@ impl AppState {
         &self.default_permissions
     }
 
+    /// Get the permissions a module executes with.
+    ///
+    /// Returns the module's own permissions if set, or the defaults.
+    pub fn permissions_for(&self, module_id: &str) -> Permissions {
+        self.module_permissions
+            .get(module_id)
+            .map_or_else(|| self.default_permissions.clone(), |p| p.clone())
+    }
+
+    /// Set the permissions a module executes with.
+    ///
+    /// Permissions are kept when the module is removed or replaced.
+    pub fn set_module_permissions(&self, module_id: &str, permissions: Permissions) {
+        self.module_permissions
+            .insert(module_id.to_string(), permissions);
+    }
+
+    /// Get the default guest log policy.
+    pub fn default_log_policy(&self) -> &LogPolicy {
+        &self.default_log_policy
+    }
+
+    /// Get the log policy a module executes with.
+    ///
+    /// Returns the module's own policy if set, or the default.
+    pub fn log_policy_for(&self, module_id: &str) -> LogPolicy {
+        self.log_policies
+            .get(module_id)
+            .map_or(self.default_log_policy, |p| *p)
+    }
+
+    /// Set the log policy a module executes with.
+    ///
+    /// The policy is kept when the module is removed or replaced.
+    pub fn set_log_policy(&self, module_id: &str, policy: LogPolicy) {
+        self.log_policies.insert(module_id.to_string(), policy);
+    }
+
+    /// Get the guest log sinks.
+    pub fn log_sinks(&self) -> &LogSinks {
+        &self.log_sinks
+    }
+
+    /// Get the live tail of guest logs.
+    pub fn log_tail(&self) -> &LogTail {
+        &self.log_tail
+    }
+
+    /// Get the access log.
+    pub fn access_log(&self) -> &AccessLog {
+        &self.access_log
+    }
+
+    /// Get the guest CPU profiler.
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    /// Get the module scheduler.
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Get the asynchronous invocation queue.
+    pub fn jobs(&self) -> &JobQueue {
+        &self.jobs
+    }
+
+    /// Get the key-value store.
+    pub fn kv(&self) -> &KvStore {
+        &self.kv
+    }
+
+    /// Get the response cache.
+    pub fn cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
+    /// Get the SQL store.
+    pub fn sql(&self) -> &SqlStore {
+        &self.sql
+    }
+
+    /// Get the blob store, if blob storage is enabled.
+    pub fn blobs(&self) -> Option<&BlobStore> {
+        self.blobs.as_ref()
+    }
+
+    /// Get the configuration store.
+    ///
+    /// Configuration is kept when a module is removed or replaced.
+    pub fn config(&self) -> &ConfigStore {
+        &self.config
+    }
+
+    /// Get the crypto keyring.
+    pub fn keyring(&self) -> &Keyring {
+        &self.keyring
+    }
+
+    /// Get the outbound TCP connection settings.
+    pub fn socket_connector(&self) -> &SocketConnector {
+        &self.sockets
+    }
+
+    /// Get the guest metrics registry.
+    pub fn guest_metrics(&self) -> &GuestMetrics {
+        &self.metrics
+    }
+
+    /// Get the runtime metrics registry.
+    pub fn runtime_metrics(&self) -> &RuntimeMetrics {
+        &self.runtime_metrics
+    }
+
+    /// Get the statistics of a loaded module.
+    pub fn module_stats(&self, module_id: &str) -> Option<Arc<ModuleStats>> {
+        self.module_stats.get(module_id).map(|v| v.clone())
+    }
+
+    /// Get the pipeline registry.
+    pub fn pipelines(&self) -> &Pipelines {
+        &self.pipelines
+    }
+
     /// Load and cache a module from bytes.
     ///
+    /// Replacing a module drops the responses it cached and resets its
+    /// statistics.
+    ///
     /// # Arguments
     ///
     /// * `module_id` - Unique identifier for the module
@@ -90,14 +396,26 @@ impl AppState {
         module_id: &str,
         wasm_bytes: &[u8],
     ) -> Result<Arc<CompiledModule>, RuntimeError> {
+        let start = Instant::now();
         let compiled = CompiledModule::from_bytes(self.engine.inner(), wasm_bytes)?;
+        let compile_duration = start.elapsed();
+        self.runtime_metrics
+            .record_compile(module_id, compile_duration);
         let compiled = Arc::new(compiled);
         self.modules.insert(module_id.to_string(), compiled.clone());
+        self.module_stats.insert(
+            module_id.to_string(),
+            Arc::new(ModuleStats::new(compile_duration)),
+        );
+        self.cache.clear(module_id);
         Ok(compiled)
     }
 
     /// Load and cache a module from WAT text.
     ///
+    /// Replacing a module drops the responses it cached and resets its
+    /// statistics.
+    ///
     /// # Arguments
     ///
     /// * `module_id` - Unique identifier for the module
@@ -111,9 +429,18 @@ impl AppState {
         module_id: &str,
         wat: &str,
     ) -> Result<Arc<CompiledModule>, RuntimeError> {
+        let start = Instant::now();
         let compiled = CompiledModule::from_wat(self.engine.inner(), wat)?;
+        let compile_duration = start.elapsed();
+        self.runtime_metrics
+            .record_compile(module_id, compile_duration);
         let compiled = Arc::new(compiled);
         self.modules.insert(module_id.to_string(), compiled.clone());
+        self.module_stats.insert(
+            module_id.to_string(),
+            Arc::new(ModuleStats::new(compile_duration)),
+        );
+        self.cache.clear(module_id);
         Ok(compiled)
     }
 
@@ -132,6 +459,9 @@ impl AppState {
 
     /// Remove a module from the cache.
     ///
+    /// The responses the module cached, the metrics it recorded and its
+    /// statistics are dropped as well.
+    ///
     /// # Arguments
     ///
     /// * `module_id` - Module identifier
@@ -140,6 +470,11 @@ impl AppState {
     ///
     /// The removed module if it existed.
     pub fn remove_module(&self, module_id: &str) -> Option<Arc<CompiledModule>> {
+        self.cache.clear(module_id);
+        self.metrics.clear(module_id);
+        self.runtime_metrics.clear(module_id);
+        self.module_stats.remove(module_id);
+        self.profiler.stop(module_id);
         self.modules.remove(module_id).map(|(_, v)| v)
     }
 
@@ -181,6 +516,40 @@ mod tests {
         assert_eq!(state.list_modules(), vec!["test"]);
     }
 
+    #[test]
+    fn test_module_permissions() {
+        let config = RuntimeConfig::default();
+        let state = AppState::new(&config).unwrap();
+
+        assert!(!state.permissions_for("api").is_service_allowed("auth"));
+
+        state.set_module_permissions(
+            "api",
+            Permissions::builder().allow_services(["auth"]).build(),
+        );
+        assert!(state.permissions_for("api").is_service_allowed("auth"));
+        assert!(!state.permissions_for("other").is_service_allowed("auth"));
+    }
+
+    #[test]
+    fn test_log_policies() {
+        use edge_runtime_core::store::LogLevel;
+
+        let config = RuntimeConfig::default();
+        let state = AppState::new(&config).unwrap();
+        assert_eq!(state.log_policy_for("api").min_level, LogLevel::Debug);
+
+        state.set_log_policy(
+            "api",
+            LogPolicy {
+                min_level: LogLevel::Warn,
+                ..*state.default_log_policy()
+            },
+        );
+        assert_eq!(state.log_policy_for("api").min_level, LogLevel::Warn);
+        assert_eq!(state.log_policy_for("other").min_level, LogLevel::Debug);
+    }
+
     #[test]
     fn test_remove_module() {
         let config = RuntimeConfig::default();
@@ -19,6 +19,10 @@
 //!
 //! # Enable Admin API
 //! edge-runtime --enable-admin --admin-token secret
+//!
+//! # Create a secrets key and encrypt secrets to `server.secrets.path`
+//! export EDGE_SECRETS_KEY=$(edge-runtime --generate-secrets-key)
+//! edge-runtime --config ./edge-runtime.toml --encrypt-secrets ./secrets.toml
 //! ```
 
 use std::net::SocketAddr;
@@ -29,7 +33,11 @@ use clap::Parser;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use edge_runtime_common::{AdminConfig, ConfigFile, RuntimeConfig, ServerConfigFile};
+use edge_runtime_common::{
+    AdminConfig, ConfigFile, ModuleEntry, PipelineEntry, RuntimeConfig, SecretsConfig,
+    ServerConfigFile,
+};
+use edge_runtime_host::{LogPolicy, OtelLayer, Permissions, SecretsKey, config::encrypt_secrets};
 use edge_runtime_server::{EdgeServer, ServerConfig};
 
 /// Edge Runtime - High-density serverless edge runtime
@@ -64,17 +72,27 @@ pub struct Cli {
     /// Enable admin API
     #[arg(long)]
     enable_admin: bool,
+
+    /// Print a new random secrets key and exit
+    #[arg(long)]
+    generate_secrets_key: bool,
+
+    /// Encrypt a plaintext secrets file to the configured secrets path and exit
+    #[arg(long, value_name = "FILE")]
+    encrypt_secrets: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
+    // Initialize tracing; spans are exported once the config is loaded
+    let otel = OtelLayer::new();
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "info,edge_runtime=debug".into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(otel.clone())
         .init();
 
     info!("Starting Edge Runtime");
@@ -82,11 +100,25 @@ async fn main() -> anyhow::Result<()> {
     // Parse CLI arguments
     let cli = Cli::parse();
 
+    if cli.generate_secrets_key {
+        println!("{}", SecretsKey::generate()?.to_hex());
+        return Ok(());
+    }
+
     // Build configuration from CLI, config file, and defaults
-    let (runtime_config, server_config, admin_config) = build_config(&cli)?;
+    let (runtime_config, server_config, admin_config, modules, pipelines) = build_config(&cli)?;
+
+    if let Some(plaintext) = &cli.encrypt_secrets {
+        return encrypt_secrets_file(plaintext, &server_config.secrets);
+    }
 
     info!(bind_addr = %server_config.bind_addr, "Configuration loaded");
 
+    otel.start_export(&server_config.tracing)?;
+    if let Some(endpoint) = &server_config.tracing.otlp_endpoint {
+        info!(endpoint = %endpoint, "Exporting traces over OTLP");
+    }
+
     // Create server
     let mut server = EdgeServer::new(&runtime_config, server_config.clone())?;
 
@@ -98,8 +130,10 @@ async fn main() -> anyhow::Result<()> {
         );
     }
 
-    // Load modules from CLI options
+    // Load modules from the config file, then CLI options
+    load_modules_from_config(&modules, &server_config, server.state())?;
     load_modules_from_cli(&cli, server.state())?;
+    register_pipelines(&pipelines, server.state())?;
 
     // Log admin API status
     if admin_config.is_configured() {
@@ -112,6 +146,8 @@ async fn main() -> anyhow::Result<()> {
     info!("  GET  /modules             - List loaded modules");
     info!("  GET  /functions/:id       - Execute function (no body)");
     info!("  POST /functions/:id       - Execute function (with body)");
+    info!("  POST /functions/:id/async - Enqueue asynchronous invocation");
+    info!("  GET  /jobs/:job_id        - Asynchronous invocation status");
 
     if admin_config.is_configured() {
         info!("Admin API endpoints (requires X-Admin-Token header):");
@@ -131,6 +167,22 @@ async fn main() -> anyhow::Result<()> {
             "  DELETE {}/modules/:id  - Delete module",
             admin_config.prefix
         );
+        info!(
+            "  GET    {}/schedules    - List module schedules",
+            admin_config.prefix
+        );
+        info!(
+            "  PUT    {}/modules/:id/schedule - Set module schedule",
+            admin_config.prefix
+        );
+        info!(
+            "  GET    {}/pipelines    - List pipelines",
+            admin_config.prefix
+        );
+        info!(
+            "  PUT    {}/pipelines/:id - Define pipeline",
+            admin_config.prefix
+        );
     }
 
     server.run().await?;
@@ -141,7 +193,15 @@ async fn main() -> anyhow::Result<()> {
 /// Build configuration from CLI arguments, config file, and defaults.
 ///
 /// Priority: CLI > Environment Variables > Config File > Defaults
-fn build_config(cli: &Cli) -> anyhow::Result<(RuntimeConfig, ServerConfig, AdminConfig)> {
+fn build_config(
+    cli: &Cli,
+) -> anyhow::Result<(
+    RuntimeConfig,
+    ServerConfig,
+    AdminConfig,
+    Vec<ModuleEntry>,
+    Vec<PipelineEntry>,
+)> {
     // 1. Load config file if specified
     let config_file = if let Some(path) = &cli.config {
         info!(path = ?path, "Loading configuration file");
@@ -157,7 +217,20 @@ fn build_config(cli: &Cli) -> anyhow::Result<(RuntimeConfig, ServerConfig, Admin
     let bind_addr = resolve_bind_addr(cli, &config_file.server)?;
     let server_config = ServerConfig::default()
         .with_bind_addr(bind_addr)
-        .with_timeout(config_file.server.request_timeout_secs);
+        .with_timeout(config_file.server.request_timeout_secs)
+        .with_jobs(config_file.server.jobs.clone())
+        .with_kv(config_file.server.kv.clone())
+        .with_cache(config_file.server.cache.clone())
+        .with_sql(config_file.server.sql.clone())
+        .with_blob(config_file.server.blob.clone())
+        .with_secrets(config_file.server.secrets.clone())
+        .with_crypto(config_file.server.crypto.clone())
+        .with_sockets(config_file.server.sockets.clone())
+        .with_websocket(config_file.server.websocket.clone())
+        .with_metrics(config_file.server.metrics.clone())
+        .with_logging(config_file.server.logging.clone())
+        .with_access_log(config_file.server.access_log.clone())
+        .with_tracing(config_file.server.tracing.clone());
 
     // 4. AdminConfig: CLI > config file
     let admin_config = AdminConfig {
@@ -166,7 +239,13 @@ fn build_config(cli: &Cli) -> anyhow::Result<(RuntimeConfig, ServerConfig, Admin
         prefix: config_file.admin.prefix,
     };
 
-    Ok((runtime_config, server_config, admin_config))
+    Ok((
+        runtime_config,
+        server_config,
+        admin_config,
+        config_file.modules,
+        config_file.pipelines,
+    ))
 }
 
 /// Resolve bind address from CLI, environment, or config file.
@@ -188,11 +267,118 @@ fn resolve_bind_addr(cli: &Cli, server_config: &ServerConfigFile) -> anyhow::Res
         .context("Invalid bind_addr in config")
 }
 
+/// Load modules listed in the config file and register their schedules.
+fn load_modules_from_config(
+    modules: &[ModuleEntry],
+    server_config: &ServerConfig,
+    state: &edge_runtime_server::AppState,
+) -> anyhow::Result<()> {
+    for entry in modules {
+        let bytes = std::fs::read(&entry.path)
+            .with_context(|| format!("Failed to read module: {}", entry.path))?;
+        state.load_module(&entry.id, &bytes)?;
+        info!(id = %entry.id, path = %entry.path, "Loaded module from config");
+
+        if !entry.services.is_empty()
+            || !entry.http_hosts.is_empty()
+            || entry.kv_namespace.is_some()
+            || entry.sql_namespace.is_some()
+            || !entry.blob_buckets.is_empty()
+            || !entry.crypto_keys.is_empty()
+            || !entry.sockets.is_empty()
+        {
+            let permissions = Permissions {
+                allowed_services: entry.services.iter().cloned().collect(),
+                allowed_http_hosts: entry.http_hosts.iter().cloned().collect(),
+                http_enabled: !entry.http_hosts.is_empty(),
+                max_websocket_connections: server_config.websocket.max_connections,
+                max_websocket_messages: server_config.websocket.max_messages,
+                kv_namespace: entry.kv_namespace.clone(),
+                max_kv_operations: server_config.kv.max_operations,
+                max_kv_value_bytes: server_config.kv.max_value_bytes,
+                max_kv_bytes_written: server_config.kv.max_bytes_per_request,
+                sql_namespace: entry.sql_namespace.clone(),
+                max_sql_statements: server_config.sql.max_statements,
+                sql_read_only: entry.sql_read_only,
+                blob_buckets: entry.blob_buckets.iter().cloned().collect(),
+                max_blob_operations: server_config.blob.max_operations,
+                max_blob_bucket_bytes: entry
+                    .blob_quota_bytes
+                    .unwrap_or(server_config.blob.default_quota_bytes),
+                crypto_keys: entry.crypto_keys.iter().cloned().collect(),
+                allowed_socket_addresses: entry.sockets.iter().cloned().collect(),
+                max_socket_connections: server_config.sockets.max_connections,
+                max_socket_bytes: server_config.sockets.max_bytes,
+                ..state.default_permissions().clone()
+            };
+            state.set_module_permissions(&entry.id, permissions);
+        }
+
+        if !entry.config.is_empty() {
+            state.config().set_values(&entry.id, entry.config.clone());
+        }
+
+        if entry.log_level.is_some() || entry.log_sample_rate.is_some() {
+            let defaults = *state.default_log_policy();
+            let policy = LogPolicy {
+                min_level: entry.log_level.map_or(defaults.min_level, Into::into),
+                sample_rate: entry.log_sample_rate.unwrap_or(defaults.sample_rate),
+                ..defaults
+            };
+            state.set_log_policy(&entry.id, policy);
+        }
+
+        if let Some(schedule) = &entry.schedule {
+            state
+                .scheduler()
+                .schedule(&entry.id, schedule, entry.overlap_policy)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encrypt a plaintext secrets file to the configured secrets path.
+///
+/// The key is read from the environment variable named by `key_env`.
+fn encrypt_secrets_file(
+    plaintext: &std::path::Path,
+    secrets: &SecretsConfig,
+) -> anyhow::Result<()> {
+    let Some(path) = &secrets.path else {
+        anyhow::bail!("server.secrets.path must be set to encrypt secrets");
+    };
+    let key = std::env::var(&secrets.key_env).with_context(|| {
+        format!(
+            "Secrets key not set in environment variable {}",
+            secrets.key_env
+        )
+    })?;
+    let document = std::fs::read_to_string(plaintext)
+        .with_context(|| format!("Failed to read secrets: {}", plaintext.display()))?;
+
+    let data = encrypt_secrets(&SecretsKey::from_hex(&key)?, &document)?;
+    std::fs::write(path, data).with_context(|| format!("Failed to write secrets: {path}"))?;
+    info!(path = %path, "Secrets encrypted");
+
+    Ok(())
+}
+
+/// Register pipelines listed in the config file.
+fn register_pipelines(
+    pipelines: &[PipelineEntry],
+    state: &edge_runtime_server::AppState,
+) -> anyhow::Result<()> {
+    for entry in pipelines {
+        state.pipelines().set(&entry.id, entry.stages.clone())?;
+        info!(id = %entry.id, stages = ?entry.stages, "Registered pipeline from config");
+    }
+
+    Ok(())
+}
+
 /// Load modules from CLI options.
 fn load_modules_from_cli(cli: &Cli, state: &edge_runtime_server::AppState) -> anyhow::Result<()> {
-    // Load from config file modules (already loaded in build_config)
-    // This will be handled when we integrate with the full config loading
-
     // Load from --wasm option
     if let Some(wasm_path) = &cli.wasm {
         let id = wasm_path